@@ -0,0 +1,193 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Read and write throughput against a `MiniCluster`, for catching regressions in the wrapper layer, like extra
+//! copies or JNI calls. Run with `cargo bench --features integration`; the cluster needs the same setup as the
+//! integration tests.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hdfs::{HdfsConnection, MiniCluster};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::sync::OnceLock;
+use std::thread;
+
+/// Size of the file that the read benchmarks read.
+const FILE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Size of the file written by the write benchmarks.
+const WRITE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Sizes of the buffers passed to each `read` or `write` call.
+const CHUNK_SIZES: &[usize] = &[4 * 1024, 64 * 1024, 1024 * 1024, 4 * 1024 * 1024];
+
+/// Size of each random read in the positioned read benchmarks.
+const RANDOM_READ_SIZE: usize = 4 * 1024;
+
+/// Number of concurrent streams in the parallel read benchmarks.
+const STREAMS: &[usize] = &[1, 2, 4, 8];
+
+const DATA_PATH: &str = "/bench/data";
+
+/// Starts the cluster shared by every benchmark, and writes the file that they read.
+fn cluster() -> &'static MiniCluster {
+	static CLUSTER: OnceLock<MiniCluster> = OnceLock::new();
+	CLUSTER.get_or_init(|| {
+		let cluster = MiniCluster::start().expect("Could not start the minicluster");
+		let fs = cluster.connect().expect("Could not connect to the cluster");
+		let mut file = fs.open_create(DATA_PATH).unwrap();
+		let block: Vec<u8> = (0..1024 * 1024).map(|i| i as u8).collect();
+		for _ in 0..FILE_SIZE / block.len() {
+			file.write_all(&block).unwrap();
+		}
+		drop(file);
+		cluster
+	})
+}
+
+fn connect() -> HdfsConnection {
+	cluster().connect().expect("Could not connect to the cluster")
+}
+
+/// Reads the whole file with `read` calls of different sizes.
+fn read_chunk_size(c: &mut Criterion) {
+	let fs = connect();
+	let mut group = c.benchmark_group("read_chunk_size");
+	group.sample_size(10);
+	group.throughput(Throughput::Bytes(FILE_SIZE as u64));
+	for &size in CHUNK_SIZES {
+		group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+			let mut buf = vec![0; size];
+			b.iter(|| {
+				let mut file = fs.open_read(DATA_PATH).unwrap();
+				while file.read(&mut buf).unwrap() > 0 {}
+			});
+		});
+	}
+	group.finish();
+}
+
+/// Writes a file with `write` calls of different sizes.
+fn write_chunk_size(c: &mut Criterion) {
+	let fs = connect();
+	let mut group = c.benchmark_group("write_chunk_size");
+	group.sample_size(10);
+	group.throughput(Throughput::Bytes(WRITE_SIZE as u64));
+	for &size in CHUNK_SIZES {
+		group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+			let buf = vec![0x5a; size];
+			let path = format!("/bench/write-{}", size);
+			b.iter(|| {
+				let mut file = fs.open_create(&path).unwrap();
+				for _ in 0..WRITE_SIZE / size {
+					file.write_all(&buf).unwrap();
+				}
+			});
+		});
+	}
+	group.finish();
+}
+
+/// Reads the whole file in small `read` calls, directly and through a `BufReader`.
+fn buffered_read(c: &mut Criterion) {
+	let fs = connect();
+	let mut group = c.benchmark_group("buffered_read");
+	group.sample_size(10);
+	group.throughput(Throughput::Bytes(FILE_SIZE as u64));
+	group.bench_function("unbuffered", |b| {
+		let mut buf = [0; 512];
+		b.iter(|| {
+			let mut file = fs.open_read(DATA_PATH).unwrap();
+			while file.read(&mut buf).unwrap() > 0 {}
+		});
+	});
+	group.bench_function("buffered", |b| {
+		let mut buf = [0; 512];
+		b.iter(|| {
+			let mut file = BufReader::with_capacity(1024 * 1024, fs.open_read(DATA_PATH).unwrap());
+			while file.read(&mut buf).unwrap() > 0 {}
+		});
+	});
+	group.finish();
+}
+
+/// Small reads at random offsets, with `read_exact_at` and with `seek` followed by `read_exact`.
+fn random_reads(c: &mut Criterion) {
+	let fs = connect();
+	let mut group = c.benchmark_group("random_reads");
+	group.throughput(Throughput::Bytes(RANDOM_READ_SIZE as u64));
+	let mut offsets = random_offsets();
+	
+	let file = fs.open_read(DATA_PATH).unwrap();
+	let mut buf = vec![0; RANDOM_READ_SIZE];
+	group.bench_function("pread", |b| {
+		b.iter(|| file.read_exact_at(offsets.next().unwrap(), &mut buf).unwrap());
+	});
+	let mut file = fs.open_read(DATA_PATH).unwrap();
+	group.bench_function("seek_read", |b| {
+		b.iter(|| {
+			file.seek(SeekFrom::Start(offsets.next().unwrap())).unwrap();
+			file.read_exact(&mut buf).unwrap();
+		});
+	});
+	group.finish();
+}
+
+/// Reads the whole file split into ranges read by concurrent streams, each with its own connection.
+fn parallel_streams(c: &mut Criterion) {
+	let mut group = c.benchmark_group("parallel_streams");
+	group.sample_size(10);
+	group.throughput(Throughput::Bytes(FILE_SIZE as u64));
+	for &streams in STREAMS {
+		let mut connections: Vec<HdfsConnection> = (0..streams).map(|_| connect()).collect();
+		group.bench_with_input(BenchmarkId::from_parameter(streams), &streams, |b, &streams| {
+			let range = FILE_SIZE / streams;
+			b.iter(|| thread::scope(|scope| {
+				for (i, fs) in connections.iter_mut().enumerate() {
+					scope.spawn(move || {
+						let file = fs.open_read(DATA_PATH).unwrap();
+						let mut buf = vec![0; 1024 * 1024];
+						let mut pos = i * range;
+						while pos < (i + 1) * range {
+							let len = buf.len().min((i + 1) * range - pos);
+							file.read_exact_at(pos as u64, &mut buf[..len]).unwrap();
+							pos += len;
+						}
+					});
+				}
+			}));
+		});
+	}
+	group.finish();
+}
+
+/// Endless sequence of offsets where a random read fits in the file, from a fixed-seed xorshift generator so
+/// every run reads the same places.
+fn random_offsets() -> impl Iterator<Item = u64> {
+	let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+	std::iter::repeat_with(move || {
+		state ^= state << 13;
+		state ^= state >> 7;
+		state ^= state << 17;
+		state % (FILE_SIZE - RANDOM_READ_SIZE) as u64
+	})
+}
+
+criterion_group!(benches, read_chunk_size, write_chunk_size, buffered_read, random_reads, parallel_streams);
+criterion_main!(benches);
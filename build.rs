@@ -0,0 +1,49 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Sets the rpath of the `dfs` binary, tests, and examples on macOS, so that they find `libhdfs.dylib` and
+//! `libjvm.dylib` without `DYLD_LIBRARY_PATH`, which System Integrity Protection strips from child processes.
+//! 
+//! When `libhdfs` is linked statically on Linux, the rpath is set to `libjvm.so`'s directory, or
+//! `RSHDFS_JVM_RPATH` if it's set, so that the only other file needed is the JVM. They're also linked without
+//! PIE, since `libhdfs.a` is usually built without `-fPIC`.
+//! 
+//! `libhdfs-sys` can't do this itself, since link arguments only apply to the package that sets them.
+//! Applications can do the same from their own build script by also depending on `libhdfs-sys` directly, which
+//! gives them the `DEP_HDFS_LIB_DIR` and `DEP_HDFS_JVM_DIR` variables it sets.
+
+use std::env;
+
+fn main() {
+	println!("cargo:rerun-if-env-changed=RSHDFS_JVM_RPATH");
+	let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+	if target_os == "macos" {
+		for var in ["DEP_HDFS_LIB_DIR", "DEP_HDFS_JVM_DIR"].iter() {
+			if let Some(dir) = env::var_os(var) {
+				println!("cargo:rustc-link-arg=-Wl,-rpath,{}", dir.to_string_lossy());
+			}
+		}
+	} else if target_os == "linux" && env::var_os("DEP_HDFS_STATIC").is_some() {
+		if let Some(dir) = env::var_os("RSHDFS_JVM_RPATH").or_else(|| env::var_os("DEP_HDFS_JVM_DIR")) {
+			println!("cargo:rustc-link-arg=-Wl,-rpath,{}", dir.to_string_lossy());
+		}
+		println!("cargo:rustc-link-arg=-no-pie");
+	}
+}
@@ -0,0 +1,471 @@
+// Pre-generated bindings for `include/hadoop-3.3/hdfs.h`, used by the `pregenerated` feature. Regenerate with
+// `bindgen` 0.42, using the allowlists in `build.rs`:
+// 
+//     bindgen include/hadoop-3.3/hdfs.h --no-layout-tests --opaque-type hdfs_internal \
+//         --whitelist-function '(hdfs|hadoop).*' --whitelist-type '(hdfs|hadoop|t[A-Z]).*' \
+//         --whitelist-var '(O_.*|EINTERNAL|ELASTIC_BYTE_BUFFER_POOL_CLASS)' > bindings.rs
+
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1;
+pub const O_RDWR: u32 = 2;
+pub const O_CREAT: u32 = 64;
+pub const O_EXCL: u32 = 128;
+pub const O_NOCTTY: u32 = 256;
+pub const O_TRUNC: u32 = 512;
+pub const O_APPEND: u32 = 1024;
+pub const O_NONBLOCK: u32 = 2048;
+pub const O_NDELAY: u32 = 2048;
+pub const O_SYNC: u32 = 1052672;
+pub const O_FSYNC: u32 = 1052672;
+pub const O_ASYNC: u32 = 8192;
+pub const O_DIRECTORY: u32 = 65536;
+pub const O_NOFOLLOW: u32 = 131072;
+pub const O_CLOEXEC: u32 = 524288;
+pub const O_DSYNC: u32 = 4096;
+pub const O_RSYNC: u32 = 1052672;
+pub const O_ACCMODE: u32 = 3;
+pub const EINTERNAL: u32 = 255;
+pub const ELASTIC_BYTE_BUFFER_POOL_CLASS: &'static [u8; 43usize] =
+    b"org/apache/hadoop/io/ElasticByteBufferPool\0";
+pub type __time_t = ::std::os::raw::c_long;
+pub type time_t = __time_t;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfsBuilder {
+    _unused: [u8; 0],
+}
+pub type tSize = i32;
+pub type tTime = time_t;
+pub type tOffset = i64;
+pub type tPort = u16;
+pub const tObjectKind_kObjectKindFile: tObjectKind = 70;
+pub const tObjectKind_kObjectKindDirectory: tObjectKind = 68;
+pub type tObjectKind = u32;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfsStreamBuilder {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfs_internal {
+    _unused: [u8; 0],
+}
+pub type hdfsFS = *mut hdfs_internal;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfsFile_internal {
+    _unused: [u8; 0],
+}
+pub type hdfsFile = *mut hdfsFile_internal;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hadoopRzOptions {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hadoopRzBuffer {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn hdfsFileIsOpenForRead(file: hdfsFile) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsFileIsOpenForWrite(file: hdfsFile) -> ::std::os::raw::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfsReadStatistics {
+    pub totalBytesRead: u64,
+    pub totalLocalBytesRead: u64,
+    pub totalShortCircuitBytesRead: u64,
+    pub totalZeroCopyBytesRead: u64,
+}
+extern "C" {
+    pub fn hdfsFileGetReadStatistics(
+        file: hdfsFile,
+        stats: *mut *mut hdfsReadStatistics,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsReadStatisticsGetRemoteBytesRead(stats: *const hdfsReadStatistics) -> i64;
+}
+extern "C" {
+    pub fn hdfsFileClearReadStatistics(file: hdfsFile) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsFileFreeReadStatistics(stats: *mut hdfsReadStatistics);
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfsHedgedReadMetrics {
+    pub hedgedReadOps: u64,
+    pub hedgedReadOpsWin: u64,
+    pub hedgedReadOpsInCurThread: u64,
+}
+extern "C" {
+    pub fn hdfsGetHedgedReadMetrics(
+        fs: hdfsFS,
+        metrics: *mut *mut hdfsHedgedReadMetrics,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsFreeHedgedReadMetrics(metrics: *mut hdfsHedgedReadMetrics);
+}
+extern "C" {
+    pub fn hdfsConnectAsUser(
+        nn: *const ::std::os::raw::c_char,
+        port: tPort,
+        user: *const ::std::os::raw::c_char,
+    ) -> hdfsFS;
+}
+extern "C" {
+    pub fn hdfsConnect(nn: *const ::std::os::raw::c_char, port: tPort) -> hdfsFS;
+}
+extern "C" {
+    pub fn hdfsConnectAsUserNewInstance(
+        nn: *const ::std::os::raw::c_char,
+        port: tPort,
+        user: *const ::std::os::raw::c_char,
+    ) -> hdfsFS;
+}
+extern "C" {
+    pub fn hdfsConnectNewInstance(nn: *const ::std::os::raw::c_char, port: tPort) -> hdfsFS;
+}
+extern "C" {
+    pub fn hdfsBuilderConnect(bld: *mut hdfsBuilder) -> hdfsFS;
+}
+extern "C" {
+    pub fn hdfsNewBuilder() -> *mut hdfsBuilder;
+}
+extern "C" {
+    pub fn hdfsBuilderSetForceNewInstance(bld: *mut hdfsBuilder);
+}
+extern "C" {
+    pub fn hdfsBuilderSetNameNode(bld: *mut hdfsBuilder, nn: *const ::std::os::raw::c_char);
+}
+extern "C" {
+    pub fn hdfsBuilderSetNameNodePort(bld: *mut hdfsBuilder, port: tPort);
+}
+extern "C" {
+    pub fn hdfsBuilderSetUserName(bld: *mut hdfsBuilder, userName: *const ::std::os::raw::c_char);
+}
+extern "C" {
+    pub fn hdfsBuilderSetKerbTicketCachePath(
+        bld: *mut hdfsBuilder,
+        kerbTicketCachePath: *const ::std::os::raw::c_char,
+    );
+}
+extern "C" {
+    pub fn hdfsFreeBuilder(bld: *mut hdfsBuilder);
+}
+extern "C" {
+    pub fn hdfsBuilderConfSetStr(
+        bld: *mut hdfsBuilder,
+        key: *const ::std::os::raw::c_char,
+        val: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsConfGetStr(
+        key: *const ::std::os::raw::c_char,
+        val: *mut *mut ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsConfGetInt(
+        key: *const ::std::os::raw::c_char,
+        val: *mut i32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsConfStrFree(val: *mut ::std::os::raw::c_char);
+}
+extern "C" {
+    pub fn hdfsDisconnect(fs: hdfsFS) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsOpenFile(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        flags: ::std::os::raw::c_int,
+        bufferSize: ::std::os::raw::c_int,
+        replication: ::std::os::raw::c_short,
+        blocksize: tSize,
+    ) -> hdfsFile;
+}
+extern "C" {
+    pub fn hdfsStreamBuilderAlloc(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        flags: ::std::os::raw::c_int,
+    ) -> *mut hdfsStreamBuilder;
+}
+extern "C" {
+    pub fn hdfsStreamBuilderFree(bld: *mut hdfsStreamBuilder);
+}
+extern "C" {
+    pub fn hdfsStreamBuilderSetBufferSize(
+        bld: *mut hdfsStreamBuilder,
+        bufferSize: i32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsStreamBuilderSetReplication(
+        bld: *mut hdfsStreamBuilder,
+        replication: i16,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsStreamBuilderSetDefaultBlockSize(
+        bld: *mut hdfsStreamBuilder,
+        defaultBlockSize: i64,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsStreamBuilderBuild(bld: *mut hdfsStreamBuilder) -> hdfsFile;
+}
+extern "C" {
+    pub fn hdfsTruncateFile(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        newlength: tOffset,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsUnbufferFile(file: hdfsFile) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsCloseFile(fs: hdfsFS, file: hdfsFile) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsExists(fs: hdfsFS, path: *const ::std::os::raw::c_char) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsSeek(fs: hdfsFS, file: hdfsFile, desiredPos: tOffset) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsTell(fs: hdfsFS, file: hdfsFile) -> tOffset;
+}
+extern "C" {
+    pub fn hdfsRead(
+        fs: hdfsFS,
+        file: hdfsFile,
+        buffer: *mut ::std::os::raw::c_void,
+        length: tSize,
+    ) -> tSize;
+}
+extern "C" {
+    pub fn hdfsPread(
+        fs: hdfsFS,
+        file: hdfsFile,
+        position: tOffset,
+        buffer: *mut ::std::os::raw::c_void,
+        length: tSize,
+    ) -> tSize;
+}
+extern "C" {
+    pub fn hdfsPreadFully(
+        fs: hdfsFS,
+        file: hdfsFile,
+        position: tOffset,
+        buffer: *mut ::std::os::raw::c_void,
+        length: tSize,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsWrite(
+        fs: hdfsFS,
+        file: hdfsFile,
+        buffer: *const ::std::os::raw::c_void,
+        length: tSize,
+    ) -> tSize;
+}
+extern "C" {
+    pub fn hdfsFlush(fs: hdfsFS, file: hdfsFile) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsHFlush(fs: hdfsFS, file: hdfsFile) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsHSync(fs: hdfsFS, file: hdfsFile) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsAvailable(fs: hdfsFS, file: hdfsFile) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsCopy(
+        srcFS: hdfsFS,
+        src: *const ::std::os::raw::c_char,
+        dstFS: hdfsFS,
+        dst: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsMove(
+        srcFS: hdfsFS,
+        src: *const ::std::os::raw::c_char,
+        dstFS: hdfsFS,
+        dst: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsDelete(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        recursive: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsRename(
+        fs: hdfsFS,
+        oldPath: *const ::std::os::raw::c_char,
+        newPath: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsGetWorkingDirectory(
+        fs: hdfsFS,
+        buffer: *mut ::std::os::raw::c_char,
+        bufferSize: usize,
+    ) -> *mut ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn hdfsSetWorkingDirectory(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsCreateDirectory(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsSetReplication(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        replication: i16,
+    ) -> ::std::os::raw::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfsFileInfo {
+    pub mKind: tObjectKind,
+    pub mName: *mut ::std::os::raw::c_char,
+    pub mLastMod: tTime,
+    pub mSize: tOffset,
+    pub mReplication: ::std::os::raw::c_short,
+    pub mBlockSize: tOffset,
+    pub mOwner: *mut ::std::os::raw::c_char,
+    pub mGroup: *mut ::std::os::raw::c_char,
+    pub mPermissions: ::std::os::raw::c_short,
+    pub mLastAccess: tTime,
+}
+extern "C" {
+    pub fn hdfsListDirectory(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        numEntries: *mut ::std::os::raw::c_int,
+    ) -> *mut hdfsFileInfo;
+}
+extern "C" {
+    pub fn hdfsGetPathInfo(fs: hdfsFS, path: *const ::std::os::raw::c_char) -> *mut hdfsFileInfo;
+}
+extern "C" {
+    pub fn hdfsFreeFileInfo(hdfsFileInfo: *mut hdfsFileInfo, numEntries: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn hdfsFileIsEncrypted(hdfsFileInfo: *mut hdfsFileInfo) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsGetHosts(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        start: tOffset,
+        length: tOffset,
+    ) -> *mut *mut *mut ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn hdfsFreeHosts(blockHosts: *mut *mut *mut ::std::os::raw::c_char);
+}
+extern "C" {
+    pub fn hdfsGetDefaultBlockSize(fs: hdfsFS) -> tOffset;
+}
+extern "C" {
+    pub fn hdfsGetDefaultBlockSizeAtPath(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+    ) -> tOffset;
+}
+extern "C" {
+    pub fn hdfsGetCapacity(fs: hdfsFS) -> tOffset;
+}
+extern "C" {
+    pub fn hdfsGetUsed(fs: hdfsFS) -> tOffset;
+}
+extern "C" {
+    pub fn hdfsChown(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        owner: *const ::std::os::raw::c_char,
+        group: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsChmod(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        mode: ::std::os::raw::c_short,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsUtime(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        mtime: tTime,
+        atime: tTime,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hadoopRzOptionsAlloc() -> *mut hadoopRzOptions;
+}
+extern "C" {
+    pub fn hadoopRzOptionsSetSkipChecksum(
+        opts: *mut hadoopRzOptions,
+        skip: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hadoopRzOptionsSetByteBufferPool(
+        opts: *mut hadoopRzOptions,
+        className: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hadoopRzOptionsFree(opts: *mut hadoopRzOptions);
+}
+extern "C" {
+    pub fn hadoopReadZero(
+        file: hdfsFile,
+        opts: *mut hadoopRzOptions,
+        maxLength: i32,
+    ) -> *mut hadoopRzBuffer;
+}
+extern "C" {
+    pub fn hadoopRzBufferLength(buffer: *const hadoopRzBuffer) -> i32;
+}
+extern "C" {
+    pub fn hadoopRzBufferGet(buffer: *const hadoopRzBuffer) -> *const ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn hadoopRzBufferFree(file: hdfsFile, buffer: *mut hadoopRzBuffer);
+}
+extern "C" {
+    pub fn hdfsGetLastExceptionRootCause() -> *mut ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn hdfsGetLastExceptionStackTrace() -> *mut ::std::os::raw::c_char;
+}
@@ -0,0 +1,124 @@
+// Pre-generated bindings for `include/hadoop-3.3/hdfs_ext.h`, appended to `bindings.rs` by the `ext` feature.
+// Regenerate the same way, with `hdfs_ext.h` as the header, keeping only what isn't in `bindings.rs`.
+
+extern "C" {
+    pub fn hdfsGetLastError(
+        buf: *mut ::std::os::raw::c_char,
+        len: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsCancel(fs: hdfsFS, file: hdfsFile) -> ::std::os::raw::c_int;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfsDNInfo {
+    pub ip_address: *const ::std::os::raw::c_char,
+    pub hostname: *const ::std::os::raw::c_char,
+    pub network_location: *const ::std::os::raw::c_char,
+    pub xfer_port: ::std::os::raw::c_int,
+    pub info_port: ::std::os::raw::c_int,
+    pub IPC_port: ::std::os::raw::c_int,
+    pub info_secure_port: ::std::os::raw::c_int,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfsBlockInfo {
+    pub start_offset: u64,
+    pub num_bytes: u64,
+    pub num_locations: usize,
+    pub locations: *mut hdfsDNInfo,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct hdfsBlockLocations {
+    pub fileLength: u64,
+    pub isLastBlockComplete: ::std::os::raw::c_int,
+    pub isUnderConstruction: ::std::os::raw::c_int,
+    pub num_blocks: usize,
+    pub blocks: *mut hdfsBlockInfo,
+}
+extern "C" {
+    pub fn hdfsGetBlockLocations(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        locations: *mut *mut hdfsBlockLocations,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsFreeBlockLocations(locations: *mut hdfsBlockLocations) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsFind(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        name: *const ::std::os::raw::c_char,
+        numEntries: *mut u32,
+    ) -> *mut hdfsFileInfo;
+}
+extern "C" {
+    pub fn hdfsCreateSnapshot(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        name: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsDeleteSnapshot(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        name: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsRenameSnapshot(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+        old_name: *const ::std::os::raw::c_char,
+        new_name: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsAllowSnapshot(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsDisallowSnapshot(
+        fs: hdfsFS,
+        path: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsAllocateFileSystem(bld: *mut hdfsBuilder) -> hdfsFS;
+}
+extern "C" {
+    pub fn hdfsConnectAllocated(fs: hdfsFS, bld: *mut hdfsBuilder) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsNewBuilderFromDirectory(
+        configDirectory: *const ::std::os::raw::c_char,
+    ) -> *mut hdfsBuilder;
+}
+extern "C" {
+    pub fn hdfsBuilderConfGetStr(
+        bld: *mut hdfsBuilder,
+        key: *const ::std::os::raw::c_char,
+        val: *mut *mut ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsBuilderConfGetInt(
+        bld: *mut hdfsBuilder,
+        key: *const ::std::os::raw::c_char,
+        val: *mut i32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn hdfsBuilderConfGetLong(
+        bld: *mut hdfsBuilder,
+        key: *const ::std::os::raw::c_char,
+        val: *mut i64,
+    ) -> ::std::os::raw::c_int;
+}
@@ -19,49 +19,451 @@
 
 
 
+#[cfg(feature = "bindgen")]
 use bindgen;
 use java_locator;
 use std::env;
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 fn main() {
-	println!("cargo:rerun-if-env-changed=RSHDFS_HEADER_DIR");
-	println!("cargo:rerun-if-env-changed=RSHDFS_LIB_DIR");
-	println!("cargo:rerun-if-env-changed=RSHDFS_STATIC");
+	let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
+	let pkg = probe_pkg_config();
+	let mut header_search = Search::default();
 	
-	let libjvm_path = java_locator::locate_jvm_dyn_library()
-		.unwrap();
-	println!("cargo:rustc-link-search=native={}", libjvm_path);
-	
-	let header_path = if let Some(dir) = env::var_os("RSHDFS_HEADER_DIR") {
+	let header_path = if let Some(dir) = target_var("RSHDFS_HEADER_DIR") {
 		let mut path = PathBuf::from(dir);
 		path.push("hdfs.h");
 		path
+	} else if let Some(version) = vendored_version() {
+		PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap())
+			.join("include")
+			.join(version)
+			.join("hdfs.h")
+	} else if let Some(dir) = header_search.find(&include_dirs(pkg.as_ref()), "hdfs.h") {
+		dir.join("hdfs.h")
 	} else {
+		// Maybe it's on the default include path
 		PathBuf::from("hdfs.h")
 	};
+	// `hdfs_ext.h` is usually next to `hdfs.h` if it's available, but Hadoop distributions don't ship it
+	let ext = env::var_os("CARGO_FEATURE_EXT").is_some();
+	let ext_header_path = if ext {
+		let path = header_path.with_file_name("hdfs_ext.h");
+		if path.exists() {
+			Some(path)
+		} else {
+			Some(PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("include/hadoop-3.3/hdfs_ext.h"))
+		}
+	} else {
+		None
+	};
 	let header_path = header_path.into_os_string().into_string().expect("Could not convert RSHDFS_HEADER_DIR to a string");
 	
-	if let Ok(dir) = env::var("RSHDFS_LIB_DIR") {
-		println!("cargo:rustc-link-search=native={}", dir);
+	// With `dlopen`, the library is loaded at runtime, and the functions are declared in `dynamic.rs`
+	let dlopen = env::var_os("CARGO_FEATURE_DLOPEN").is_some();
+	if !dlopen {
+		if target_os == "windows" {
+			// MSVC links against `jvm.lib`, which is in `lib` rather than next to `jvm.dll`
+			let java_home = windows_java_home()
+				.expect("Could not find Java. Set JAVA_HOME to the JDK's directory.");
+			println!("cargo:rustc-link-search=native={}", java_home.join("lib").display());
+		} else if target_os == "macos" {
+			let libjvm_path = macos_jvm_dir()
+				.expect("Could not find libjvm.dylib. Set JAVA_HOME to the JDK's directory.");
+			println!("cargo:rustc-link-search=native={}", libjvm_path.display());
+			println!("cargo:jvm_dir={}", libjvm_path.display());
+		} else if is_cross() {
+			// `java_locator` runs the host's `java`, which would find the host's JVM
+			let libjvm_path = target_var("JAVA_HOME")
+				.and_then(|home| jvm_dir(&PathBuf::from(home)))
+				.expect("Could not find libjvm.so for the target. Set JAVA_HOME_<target> to a JDK for the target.");
+			println!("cargo:rustc-link-search=native={}", libjvm_path.display());
+			println!("cargo:jvm_dir={}", libjvm_path.display());
+		} else {
+			let libjvm_path = java_locator::locate_jvm_dyn_library()
+				.unwrap();
+			println!("cargo:rustc-link-search=native={}", libjvm_path);
+			println!("cargo:jvm_dir={}", libjvm_path);
+		}
+		
+		// `JNI_GetCreatedJavaVMs` is called directly, and linkers don't resolve symbols through `libhdfs`'s own
+		// dependency on `libjvm`
+		println!("cargo:rustc-link-lib=dylib=jvm");
+		
+		let is_static = env::var_os("CARGO_FEATURE_STATIC").is_some()
+			|| target_var("RSHDFS_STATIC").is_some_and(|value| !value.is_empty());
+		let kind = if is_static {
+			// As `DEP_HDFS_STATIC`
+			println!("cargo:static=1");
+			"static"
+		} else {
+			"dylib"
+		};
+		let name = target_var("RSHDFS_LIB_NAME")
+			.map(|name| name.into_string().expect("Could not convert RSHDFS_LIB_NAME to a string"))
+			.unwrap_or("hdfs".into());
+		let mut lib_search = Search::default();
+		let file_name = lib_file_name(&target_os, &name, kind == "static");
+		
+		let lib_dir = match target_var("RSHDFS_LIB_DIR") {
+			Some(dir) => Some(PathBuf::from(dir)),
+			None => lib_search.find(&lib_dirs(&target_os, pkg.as_ref()), &file_name),
+		};
+		if let Some(dir) = lib_dir {
+			println!("cargo:rustc-link-search=native={}", dir.display());
+			// For dependents' build scripts, as `DEP_HDFS_LIB_DIR`
+			println!("cargo:lib_dir={}", dir.display());
+		} else {
+			println!("cargo:warning=Could not find {}, so linking will only work if it's on the default library path. \
+				Set RSHDFS_LIB_DIR to the directory containing it. Searched: {}", file_name, lib_search);
+		}
+		println!("cargo:rustc-link-lib={}={}", kind, name);
+		// For `is_libhdfspp`
+		println!("cargo:rustc-env=RSHDFS_LINKED_LIB={}", name);
 	}
 	
-	let kind = if env::var("RSHDFS_STATIC").unwrap_or("".into()) != "" {
-		println!("cargo:rustc-link-lib=dylib=jvm");
-		"static"
+	let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+	let out_path = out_dir.join("bindings.rs");
+	if env::var_os("CARGO_FEATURE_PREGENERATED").is_some() {
+		if target_os != "linux" {
+			panic!("The pre-generated bindings are for Linux only; use the `bindgen` feature instead");
+		}
+		copy_pregenerated(&out_path, ext, dlopen);
+		// Generated from the Hadoop 3.3 header, which has everything
+		write_missing(None, dlopen, &out_dir.join("missing.rs"));
 	} else {
-		"dylib"
-	};
-	println!("cargo:rustc-link-lib={}=hdfs", kind);
+		// If the header isn't at a known path, assume it's new enough
+		let header = fs::read_to_string(&header_path).ok();
+		write_missing(header.as_deref(), dlopen, &out_dir.join("missing.rs"));
+		generate(header_path, ext_header_path, &out_path, dlopen, &header_search);
+	}
+}
+
+/// Functions added in later Hadoop versions than the oldest `hdfs.h` this builds against, which `compat.rs` has
+/// stand-ins for.
+const NEWER_FUNCTIONS: &[&str] = &[
+	"hdfsFileGetReadStatistics",
+	"hdfsReadStatisticsGetRemoteBytesRead",
+	"hdfsFileClearReadStatistics",
+	"hdfsFileFreeReadStatistics",
+	"hdfsGetHedgedReadMetrics",
+	"hdfsFreeHedgedReadMetrics",
+	"hdfsStreamBuilderAlloc",
+	"hdfsStreamBuilderFree",
+	"hdfsStreamBuilderSetBufferSize",
+	"hdfsStreamBuilderSetReplication",
+	"hdfsStreamBuilderSetDefaultBlockSize",
+	"hdfsStreamBuilderBuild",
+	"hdfsTruncateFile",
+	"hdfsUnbufferFile",
+	"hdfsPreadFully",
+	"hdfsFileIsEncrypted",
+	"hdfsGetDefaultBlockSizeAtPath",
+	"hdfsGetLastExceptionRootCause",
+	"hdfsGetLastExceptionStackTrace",
+];
+
+/// Types those functions use, with what `bindgen` would generate for them.
+const NEWER_TYPES: &[(&str, &str)] = &[
+	("hdfsStreamBuilder", "pub struct hdfsStreamBuilder {\n\t_unused: [u8; 0],\n}"),
+	("hdfsReadStatistics", "pub struct hdfsReadStatistics {\n\tpub totalBytesRead: u64,\n\tpub totalLocalBytesRead: u64,\n\
+		\tpub totalShortCircuitBytesRead: u64,\n\tpub totalZeroCopyBytesRead: u64,\n}"),
+	("hdfsHedgedReadMetrics", "pub struct hdfsHedgedReadMetrics {\n\tpub hedgedReadOps: u64,\n\tpub hedgedReadOpsWin: u64,\n\
+		\tpub hedgedReadOpsInCurThread: u64,\n}"),
+];
+
+/// Writes `missing.rs`, which defines the types `hdfs.h` doesn't declare, and, when linking, re-exports the
+/// stand-ins for the functions it doesn't declare and lists them for `has_function`. With `dlopen`, which
+/// functions are missing is only known at runtime.
+fn write_missing(header: Option<&str>, dlopen: bool, out_path: &Path) {
+	let mut out = String::new();
+	let mut missing: Vec<&str> = vec![];
+	if let Some(header) = header {
+		for (name, definition) in NEWER_TYPES.iter() {
+			if !header.contains(&format!("struct {}", name)) {
+				out += &format!("#[repr(C)]\n#[derive(Debug, Copy, Clone)]\n{}\n", definition);
+			}
+		}
+		missing.extend(NEWER_FUNCTIONS.iter().copied().filter(|name| !declares(header, name)));
+	}
+	if !dlopen {
+		if !missing.is_empty() {
+			out += &format!("pub use crate::compat::{{{}}};\n", missing.join(", "));
+		}
+		out += &format!("const MISSING_FUNCTIONS: &[&str] = &{:?};\n", missing);
+		// For dependents' build scripts, as `DEP_HDFS_MISSING_FUNCTIONS`
+		println!("cargo:missing_functions={}", missing.join(","));
+	}
+	fs::write(out_path, out).expect("Could not write missing.rs");
+}
+
+/// Checks whether a header declares a function, by looking for its name followed by an opening parenthesis.
+fn declares(header: &str, name: &str) -> bool {
+	header.match_indices(name).any(|(i, _)| {
+		let before = header[..i].chars().next_back();
+		let after = header[i + name.len()..].trim_start().chars().next();
+		!matches!(before, Some(c) if c.is_alphanumeric() || c == '_') && after == Some('(')
+	})
+}
+
+#[cfg(feature = "bindgen")]
+fn generate(header_path: String, ext_header_path: Option<PathBuf>, out_path: &Path, dlopen: bool, search: &Search) {
+	// What's generated from `hdfs.h`, leaving out everything from the system headers it includes except the flags
+	// for `hdfsOpenFile`. Keep `bindings.rs` in sync when changing these.
+	const FUNCTIONS: &str = "(hdfs|hadoop).*";
+	const TYPES: &str = "(hdfs|hadoop|t[A-Z]).*";
+	const VARS: &str = "(O_.*|EINTERNAL|ELASTIC_BYTE_BUFFER_POOL_CLASS)";
 	
-	let bindings = bindgen::Builder::default()
+	let mut builder = bindgen::Builder::default()
 		.header(header_path)
 		.opaque_type("hdfs_internal")
+		.whitelist_function(FUNCTIONS)
+		.whitelist_type(TYPES)
+		.whitelist_var(VARS);
+	if let Some(path) = ext_header_path {
+		builder = builder.header(path.to_str().expect("Could not convert the hdfs_ext.h path to a string"));
+	}
+	if dlopen {
+		builder = builder.ignore_functions();
+	}
+	if is_cross() {
+		builder = builder.clang_arg(format!("--target={}", env::var("TARGET").unwrap()));
+	}
+	let bindings = builder
 		.generate()
-		.expect("Could not generate bindings");
+		.unwrap_or_else(|_| panic!("Could not generate bindings. If hdfs.h wasn't found, set RSHDFS_HEADER_DIR to the \
+			directory containing it, or enable a `hadoop-*` feature to use a bundled copy. Searched: {}", search));
 	
-	let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
 	bindings
-		.write_to_file(out_path.join("bindings.rs"))
+		.write_to_file(out_path)
 		.expect("Could not write bindings");
 }
+
+#[cfg(not(feature = "bindgen"))]
+fn generate(_header_path: String, _ext_header_path: Option<PathBuf>, _out_path: &Path, _dlopen: bool, _search: &Search) {
+	panic!("Either the `bindgen` or `pregenerated` feature of libhdfs-sys must be enabled");
+}
+
+/// Uses the checked in `bindings.rs`, and `bindings_ext.rs` with `ext`. With `dlopen`, the `extern` blocks are
+/// dropped, like `ignore_functions` does.
+fn copy_pregenerated(out_path: &Path, ext: bool, dlopen: bool) {
+	let dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+	let mut bindings = String::new();
+	for name in ["bindings.rs", "bindings_ext.rs"].iter().take(if ext { 2 } else { 1 }) {
+		let src_path = dir.join(name);
+		println!("cargo:rerun-if-changed={}", src_path.display());
+		bindings += &fs::read_to_string(&src_path).expect("Could not read pre-generated bindings");
+	}
+	
+	let mut out = String::with_capacity(bindings.len());
+	let mut in_extern = false;
+	for line in bindings.lines() {
+		if dlopen && line == "extern \"C\" {" {
+			in_extern = true;
+		} else if in_extern {
+			in_extern = line != "}";
+		} else {
+			out.push_str(line);
+			out.push('\n');
+		}
+	}
+	fs::write(out_path, out).expect("Could not write bindings");
+}
+
+/// Gets the directory under `include` of the vendored headers selected by a `hadoop-*` feature, preferring the
+/// newest if more than one is enabled.
+fn vendored_version() -> Option<&'static str> {
+	[("CARGO_FEATURE_HADOOP_3_3", "hadoop-3.3"), ("CARGO_FEATURE_HADOOP_2_10", "hadoop-2.10")].iter()
+		.find(|(feature, _)| env::var_os(feature).is_some())
+		.map(|(_, dir)| *dir)
+}
+
+/// Whether the target differs from the host, in which case nothing installed on the host is used for it.
+fn is_cross() -> bool {
+	env::var("TARGET").unwrap() != env::var("HOST").unwrap()
+}
+
+/// Gets an environment variable for the target, preferring one suffixed with its triple, like
+/// `RSHDFS_LIB_DIR_aarch64-unknown-linux-gnu` or `RSHDFS_LIB_DIR_aarch64_unknown_linux_gnu`, so cross builds can
+/// be configured separately from the host.
+fn target_var(name: &str) -> Option<OsString> {
+	let target = env::var("TARGET").unwrap();
+	let names = [format!("{}_{}", name, target), format!("{}_{}", name, target.replace('-', "_")), name.to_string()];
+	for name in names.iter() {
+		println!("cargo:rerun-if-env-changed={}", name);
+	}
+	names.iter().find_map(env::var_os)
+}
+
+/// Installation prefixes of Hadoop distributions, from `HADOOP_HOME` and `HADOOP_PREFIX`.
+fn hadoop_homes() -> Vec<PathBuf> {
+	["HADOOP_HOME", "HADOOP_PREFIX"].iter()
+		.filter_map(|name| target_var(name))
+		.filter(|home| !home.is_empty())
+		.map(PathBuf::from)
+		.collect()
+}
+
+/// Looks for a `libhdfs` pkg-config file. Hadoop doesn't ship one, but some packagers add it.
+fn probe_pkg_config() -> Option<pkg_config::Library> {
+	["libhdfs", "hdfs"].iter()
+		.find_map(|name| pkg_config::Config::new().cargo_metadata(false).probe(name).ok())
+}
+
+// Where distributions install `libhdfs` and `hdfs.h`: Cloudera parcels, HDP, Bigtop packages, tarballs
+// extracted to the usual places, and finally the system directories.
+const LIB_DIRS: &[&str] = &[
+	"/opt/cloudera/parcels/CDH/lib64",
+	"/opt/cloudera/parcels/CDH/lib/hadoop/lib/native",
+	"/usr/hdp/current/hadoop-client/lib/native",
+	"/usr/lib/hadoop/lib/native",
+	"/usr/local/hadoop/lib/native",
+	"/opt/hadoop/lib/native",
+	"/usr/local/lib",
+	"/usr/lib64",
+	"/usr/lib",
+];
+const INCLUDE_DIRS: &[&str] = &[
+	"/opt/cloudera/parcels/CDH/include",
+	"/usr/hdp/current/hadoop-client/include",
+	"/usr/lib/hadoop/include",
+	"/usr/local/hadoop/include",
+	"/opt/hadoop/include",
+	"/usr/local/include",
+	"/usr/include",
+];
+
+/// Directories to look for `libhdfs` in, most specific first.
+fn lib_dirs(target_os: &str, pkg: Option<&pkg_config::Library>) -> Vec<PathBuf> {
+	if target_os == "windows" {
+		// Windows builds of Hadoop put the DLLs and import libraries in `bin`
+		return hadoop_homes().into_iter()
+			.flat_map(|home| vec![home.join("bin"), home.join("lib").join("native")])
+			.collect();
+	}
+	let mut dirs: Vec<PathBuf> = hadoop_homes().into_iter().map(|home| home.join("lib").join("native")).collect();
+	dirs.extend(pkg.iter().flat_map(|pkg| pkg.link_paths.iter().cloned()));
+	// The host's libraries are for the wrong architecture
+	if !is_cross() {
+		dirs.extend(LIB_DIRS.iter().map(PathBuf::from));
+	}
+	dirs
+}
+
+/// Gets the file name of a library for the target, ex. `libhdfs.so` or `hdfs.lib`.
+fn lib_file_name(target_os: &str, name: &str, is_static: bool) -> String {
+	match (target_os, is_static) {
+		("windows", _) => format!("{}.lib", name),
+		(_, true) => format!("lib{}.a", name),
+		("macos", false) => format!("lib{}.dylib", name),
+		(_, false) => format!("lib{}.so", name),
+	}
+}
+
+/// Finds the JDK on Windows, from `JAVA_HOME` or the registry keys written by the Oracle and OpenJDK installers.
+fn windows_java_home() -> Option<PathBuf> {
+	if let Some(home) = target_var("JAVA_HOME").filter(|home| !home.is_empty()) {
+		return Some(PathBuf::from(home));
+	}
+	if is_cross() {
+		return None;
+	}
+	for key in [r"HKLM\SOFTWARE\JavaSoft\JDK", r"HKLM\SOFTWARE\JavaSoft\Java Development Kit"].iter() {
+		let version = match reg_query(key, "CurrentVersion") {
+			Some(version) => version,
+			None => { continue; },
+		};
+		if let Some(home) = reg_query(&format!(r"{}\{}", key, version), "JavaHome") {
+			return Some(PathBuf::from(home));
+		}
+	}
+	None
+}
+
+/// Finds the directory containing `libjvm.dylib` on macOS, under `JAVA_HOME` or the JDK picked by
+/// `/usr/libexec/java_home`.
+fn macos_jvm_dir() -> Option<PathBuf> {
+	let home = match target_var("JAVA_HOME").filter(|home| !home.is_empty()) {
+		Some(home) => PathBuf::from(home),
+		None if is_cross() => { return None; },
+		None => {
+			let output = Command::new("/usr/libexec/java_home").output().ok()?;
+			if !output.status.success() {
+				return None;
+			}
+			PathBuf::from(String::from_utf8_lossy(&output.stdout).trim())
+		},
+	};
+	// JDK 9+, then JDK 8
+	["lib/server", "jre/lib/server"].iter()
+		.map(|dir| home.join(dir))
+		.find(|dir| dir.join("libjvm.dylib").exists())
+}
+
+/// Finds the directory containing `libjvm.so` in a JDK for the target, for JDK 9+ and JDK 8 layouts.
+fn jvm_dir(home: &Path) -> Option<PathBuf> {
+	let arch = match env::var("CARGO_CFG_TARGET_ARCH").unwrap().as_str() {
+		"x86_64" => "amd64".to_string(),
+		"x86" => "i386".to_string(),
+		arch => arch.to_string(),
+	};
+	["lib/server".to_string(), "jre/lib/server".to_string(), format!("jre/lib/{}/server", arch), format!("lib/{}/server", arch)].iter()
+		.map(|dir| home.join(dir))
+		.find(|dir| dir.join("libjvm.so").exists())
+}
+
+/// Reads a string value from the registry with `reg query`, whose output has lines like
+/// `    JavaHome    REG_SZ    C:\Program Files\Java\jdk-17`.
+fn reg_query(key: &str, value: &str) -> Option<String> {
+	let output = Command::new("reg").args(["query", key, "/v", value]).output().ok()?;
+	if !output.status.success() {
+		return None;
+	}
+	String::from_utf8_lossy(&output.stdout).lines()
+		.find_map(|line| {
+			let mut parts = line.trim().splitn(3, "    ");
+			if parts.next()? != value {
+				return None;
+			}
+			parts.next()?;
+			Some(parts.next()?.trim().to_string())
+		})
+}
+
+/// Directories to look for `hdfs.h` in, most specific first.
+fn include_dirs(pkg: Option<&pkg_config::Library>) -> Vec<PathBuf> {
+	let mut dirs: Vec<PathBuf> = hadoop_homes().into_iter().map(|home| home.join("include")).collect();
+	dirs.extend(pkg.iter().flat_map(|pkg| pkg.include_paths.iter().cloned()));
+	dirs.extend(INCLUDE_DIRS.iter().map(PathBuf::from));
+	dirs
+}
+
+/// Paths that were checked, to list in errors.
+#[derive(Default)]
+struct Search {
+	searched: Vec<PathBuf>,
+}
+impl Search {
+	/// Finds the first directory containing `file`.
+	fn find(&mut self, dirs: &[PathBuf], file: &str) -> Option<PathBuf> {
+		for dir in dirs {
+			let path = dir.join(file);
+			let found = path.exists();
+			self.searched.push(path);
+			if found {
+				return Some(dir.clone());
+			}
+		}
+		None
+	}
+}
+impl fmt::Display for Search {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let paths: Vec<String> = self.searched.iter().map(|path| path.display().to_string()).collect();
+		write!(f, "{}", paths.join(", "))
+	}
+}
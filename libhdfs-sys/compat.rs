@@ -0,0 +1,190 @@
+/* This file is part of libhdfs-sys.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * libhdfs-sys is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Stand-ins for functions missing from older versions of `libhdfs`.
+//! 
+//! When linking, the build script checks `hdfs.h` for these and re-exports the ones it lacks from here, so the
+//! bindings have the same functions whatever the Hadoop version. With `dlopen`, they're called instead when the
+//! loaded library doesn't have the real one. The stream builder and `hdfsPreadFully` are emulated with older
+//! calls; the rest fail with `ENOSYS`, or return what `libhdfs` does when there's nothing to report.
+
+use super::*;
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::os::raw::*;
+use std::ptr;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+extern "C" {
+	#[link_name = "__errno_location"]
+	fn errno_location() -> *mut c_int;
+}
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+extern "C" {
+	#[link_name = "__error"]
+	fn errno_location() -> *mut c_int;
+}
+#[cfg(windows)]
+extern "C" {
+	#[link_name = "_errno"]
+	fn errno_location() -> *mut c_int;
+}
+
+const ENOSYS: c_int = if cfg!(windows) {
+	40
+} else if cfg!(any(target_os = "macos", target_os = "ios", target_os = "freebsd")) {
+	78
+} else {
+	38
+};
+const EINVAL: c_int = 22;
+
+/// Sets `errno` and returns `-1`, like a failed `libhdfs` call.
+unsafe fn fail(errno: c_int) -> c_int {
+	*errno_location() = errno;
+	-1
+}
+
+/// What `hdfsStreamBuilderAlloc` returns, cast to the opaque `hdfsStreamBuilder`.
+struct StreamBuilder {
+	fs: hdfsFS,
+	path: CString,
+	flags: c_int,
+	buffer_size: c_int,
+	replication: c_short,
+	block_size: tSize,
+}
+
+pub unsafe fn hdfsStreamBuilderAlloc(fs: hdfsFS, path: *const c_char, flags: c_int) -> *mut hdfsStreamBuilder {
+	let builder = StreamBuilder {
+		fs,
+		path: CStr::from_ptr(path).to_owned(),
+		flags,
+		buffer_size: 0,
+		replication: 0,
+		block_size: 0,
+	};
+	Box::into_raw(Box::new(builder)) as *mut hdfsStreamBuilder
+}
+
+pub unsafe fn hdfsStreamBuilderFree(bld: *mut hdfsStreamBuilder) {
+	drop(Box::from_raw(bld as *mut StreamBuilder));
+}
+
+pub unsafe fn hdfsStreamBuilderSetBufferSize(bld: *mut hdfsStreamBuilder, bufferSize: i32) -> c_int {
+	(*(bld as *mut StreamBuilder)).buffer_size = bufferSize;
+	0
+}
+
+pub unsafe fn hdfsStreamBuilderSetReplication(bld: *mut hdfsStreamBuilder, replication: i16) -> c_int {
+	(*(bld as *mut StreamBuilder)).replication = replication;
+	0
+}
+
+/// `hdfsOpenFile` takes the block size as a `tSize`, so larger ones fail with `EINVAL`.
+pub unsafe fn hdfsStreamBuilderSetDefaultBlockSize(bld: *mut hdfsStreamBuilder, defaultBlockSize: i64) -> c_int {
+	match tSize::try_from(defaultBlockSize) {
+		Ok(size) => {
+			(*(bld as *mut StreamBuilder)).block_size = size;
+			0
+		},
+		Err(_) => fail(EINVAL),
+	}
+}
+
+/// Opens the file with `hdfsOpenFile`. Frees the builder, like the real one.
+pub unsafe fn hdfsStreamBuilderBuild(bld: *mut hdfsStreamBuilder) -> hdfsFile {
+	let bld = Box::from_raw(bld as *mut StreamBuilder);
+	hdfsOpenFile(bld.fs, bld.path.as_ptr(), bld.flags, bld.buffer_size, bld.replication, bld.block_size)
+}
+
+/// Calls `hdfsPread` until the buffer is full, failing with `EINTERNAL` at the end of the file.
+pub unsafe fn hdfsPreadFully(fs: hdfsFS, file: hdfsFile, position: tOffset, buffer: *mut c_void, length: tSize) -> c_int {
+	let mut done: tSize = 0;
+	while done < length {
+		let rt = hdfsPread(fs, file, position + done as tOffset, (buffer as *mut u8).add(done as usize) as *mut c_void, length - done);
+		if rt < 0 {
+			return -1;
+		}
+		if rt == 0 {
+			return fail(EINTERNAL as c_int);
+		}
+		done += rt;
+	}
+	0
+}
+
+pub unsafe fn hdfsTruncateFile(_fs: hdfsFS, _path: *const c_char, _newlength: tOffset) -> c_int {
+	fail(ENOSYS)
+}
+
+pub unsafe fn hdfsUnbufferFile(_file: hdfsFile) -> c_int {
+	fail(ENOSYS)
+}
+
+/// Encryption zones came with the same versions as this, so without it, nothing is encrypted.
+pub unsafe fn hdfsFileIsEncrypted(_hdfsFileInfo: *mut hdfsFileInfo) -> c_int {
+	0
+}
+
+pub unsafe fn hdfsGetDefaultBlockSizeAtPath(fs: hdfsFS, _path: *const c_char) -> tOffset {
+	hdfsGetDefaultBlockSize(fs)
+}
+
+pub unsafe fn hdfsGetLastExceptionRootCause() -> *mut c_char {
+	ptr::null_mut()
+}
+
+pub unsafe fn hdfsGetLastExceptionStackTrace() -> *mut c_char {
+	ptr::null_mut()
+}
+
+pub unsafe fn hdfsFileGetReadStatistics(_file: hdfsFile, _stats: *mut *mut hdfsReadStatistics) -> c_int {
+	fail(ENOSYS)
+}
+
+pub unsafe fn hdfsReadStatisticsGetRemoteBytesRead(stats: *const hdfsReadStatistics) -> i64 {
+	((*stats).totalBytesRead - (*stats).totalLocalBytesRead) as i64
+}
+
+pub unsafe fn hdfsFileClearReadStatistics(_file: hdfsFile) -> c_int {
+	fail(ENOSYS)
+}
+
+pub unsafe fn hdfsFileFreeReadStatistics(_stats: *mut hdfsReadStatistics) {}
+
+pub unsafe fn hdfsGetHedgedReadMetrics(_fs: hdfsFS, _metrics: *mut *mut hdfsHedgedReadMetrics) -> c_int {
+	fail(ENOSYS)
+}
+
+pub unsafe fn hdfsFreeHedgedReadMetrics(_metrics: *mut hdfsHedgedReadMetrics) {}
+
+/// Every `hdfs.h` has zero-copy reads, so this is only used with `dlopen`, for libraries without them, like
+/// libhdfs++.
+pub unsafe fn hadoopRzOptionsAlloc() -> *mut hadoopRzOptions {
+	fail(ENOSYS);
+	ptr::null_mut()
+}
+
+/// Reports that there's no JVM, since there can't be one without `libjvm`.
+pub unsafe fn JNI_GetCreatedJavaVMs(_vms: *mut *mut c_void, _len: c_int, count: *mut c_int) -> c_int {
+	*count = 0;
+	0
+}
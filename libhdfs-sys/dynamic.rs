@@ -0,0 +1,292 @@
+/* This file is part of libhdfs-sys.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * libhdfs-sys is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Loading `libhdfs` at runtime, for the `dlopen` feature.
+//! 
+//! The functions here have the same signatures as the ones `bindgen` generates when linking, but call through
+//! a table of pointers looked up when the library is first used. Call `load` first to handle a missing library;
+//! otherwise, the first call panics if it can't be loaded.
+//! 
+//! The library is found by, in order:
+//! 
+//! * The `RSHDFS_LIBRARY` environment variable, which should be the full path to `libhdfs.so`
+//! * `lib/native` under `HADOOP_HOME` or `HADOOP_PREFIX`
+//! * The platform's library search path (ex. `LD_LIBRARY_PATH`)
+//! 
+//! `libhdfs` needs `libjvm`, which is loaded from `JAVA_HOME` if it's set, and otherwise must be on the library
+//! search path.
+//! 
+//! Functions the library doesn't have, because it's from an older version of Hadoop, are filled in by the ones in
+//! `compat.rs` where there's a reasonable substitute, and otherwise panic when called.
+
+use super::*;
+use libloading::{Library, Symbol};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::os::raw::*;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Error loading `libhdfs`, returned by `load`.
+#[derive(Debug,Clone)]
+pub struct LoadError(String);
+impl fmt::Display for LoadError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "could not load libhdfs: {}", self.0)
+	}
+}
+impl Error for LoadError {}
+
+struct Loaded {
+	// Kept open for as long as the function pointers are used, which is forever
+	_jvm: Option<Library>,
+	_hdfs: Library,
+	functions: Functions,
+	hdfspp: bool,
+}
+
+static LIBRARY: OnceLock<Result<Loaded, LoadError>> = OnceLock::new();
+
+/// Loads `libhdfs` if it hasn't been already. Safe to call more than once; later calls return the first result.
+pub fn load() -> Result<(), LoadError> {
+	match LIBRARY.get_or_init(open) {
+		Ok(_) => Ok(()),
+		Err(e) => Err(e.clone()),
+	}
+}
+
+fn functions() -> &'static Functions {
+	match LIBRARY.get_or_init(open) {
+		Ok(loaded) => &loaded.functions,
+		Err(e) => panic!("{}", e),
+	}
+}
+
+/// Checks whether the loaded library is libhdfs++, the native C++ client, rather than the JNI-based `libhdfs`.
+/// They differ in places, ex. libhdfs++ takes and returns times in milliseconds rather than seconds. This is
+/// told from whether it has `hdfsGetLastError`, which only libhdfs++ implements. Returns `false` if the library
+/// can't be loaded.
+pub fn is_libhdfspp() -> bool {
+	match LIBRARY.get_or_init(open) {
+		Ok(loaded) => loaded.hdfspp,
+		Err(_) => false,
+	}
+}
+
+/// Checks whether the loaded `libhdfs` has a function itself, rather than it being filled in by a stand-in because
+/// the library is older than it. Returns `false` if the library can't be loaded.
+pub fn has_function(name: &str) -> bool {
+	match LIBRARY.get_or_init(open) {
+		Ok(loaded) => loaded.functions.has(name),
+		Err(_) => false,
+	}
+}
+
+#[cold]
+fn missing(name: &str) -> ! {
+	panic!("the loaded libhdfs does not have {}; it may be too old", name)
+}
+
+fn open() -> Result<Loaded, LoadError> {
+	let jvm = match jvm_path() {
+		Some(path) => Some(unsafe { Library::new(&path) }
+			.map_err(|e| LoadError(format!("{}: {}", path.display(), e)))?),
+		None => None,
+	};
+	
+	let mut errors = vec![];
+	for path in hdfs_paths() {
+		match unsafe { Library::new(&path) } {
+			Ok(hdfs) => {
+				let functions = unsafe { Functions::load(&hdfs) };
+				let hdfspp = unsafe { hdfs.get::<unsafe extern "C" fn()>(b"hdfsGetLastError\0") }.is_ok();
+				return Ok(Loaded { _jvm: jvm, _hdfs: hdfs, functions, hdfspp });
+			},
+			Err(e) => { errors.push(format!("{}: {}", path.display(), e)); },
+		}
+	}
+	Err(LoadError(errors.join("; ")))
+}
+
+/// Candidates for `libhdfs`, most specific first.
+fn hdfs_paths() -> Vec<PathBuf> {
+	if let Some(path) = env::var_os("RSHDFS_LIBRARY") {
+		return vec![PathBuf::from(path)];
+	}
+	let name = libloading::library_filename("hdfs");
+	let mut paths: Vec<PathBuf> = ["HADOOP_HOME", "HADOOP_PREFIX"].iter()
+		.filter_map(env::var_os)
+		.map(|home| PathBuf::from(home).join("lib").join("native").join(&name))
+		.filter(|path| path.exists())
+		.collect();
+	paths.push(PathBuf::from(name));
+	paths
+}
+
+/// Finds `libjvm` under `JAVA_HOME`, for JDK 9+ and JDK 8 layouts. On Windows, it's `jvm.dll` under `bin`.
+fn jvm_path() -> Option<PathBuf> {
+	let home = java_home()?;
+	let name = libloading::library_filename("jvm");
+	["lib/server", "jre/lib/server", "jre/lib/amd64/server", "lib/amd64/server", "bin/server", "jre/bin/server"].iter()
+		.map(|dir| home.join(dir).join(&name))
+		.find(|path| path.exists())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn java_home() -> Option<PathBuf> {
+	env::var_os("JAVA_HOME").map(PathBuf::from)
+}
+
+/// Gets `JAVA_HOME`, or the default JDK from `/usr/libexec/java_home`.
+#[cfg(target_os = "macos")]
+fn java_home() -> Option<PathBuf> {
+	if let Some(home) = env::var_os("JAVA_HOME") {
+		return Some(PathBuf::from(home));
+	}
+	let output = std::process::Command::new("/usr/libexec/java_home").output().ok()?;
+	if !output.status.success() {
+		return None;
+	}
+	Some(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Declares the function table and a wrapper for each function. Functions followed by `=> path` call that
+/// instead when the library doesn't have them.
+macro_rules! functions {
+	($( fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)? $(=> $fallback:path)?; )*) => {
+		struct Functions {
+			$( $name: Option<unsafe extern "C" fn($($ty),*) $(-> $ret)?>, )*
+		}
+		impl Functions {
+			unsafe fn load(lib: &Library) -> Self {
+				Self {
+					$( $name: lib.get(concat!(stringify!($name), "\0").as_bytes())
+						.ok()
+						.map(|sym: Symbol<unsafe extern "C" fn($($ty),*) $(-> $ret)?>| *sym), )*
+				}
+			}
+			
+			fn has(&self, name: &str) -> bool {
+				$( if name == stringify!($name) {
+					return self.$name.is_some();
+				} )*
+				false
+			}
+		}
+		$(
+			pub unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+				match functions().$name {
+					Some(f) => f($($arg),*),
+					None => fallback!($name($($arg),*) $(=> $fallback)?),
+				}
+			}
+		)*
+	};
+}
+
+macro_rules! fallback {
+	($name:ident($($arg:ident),*)) => { missing(stringify!($name)) };
+	($name:ident($($arg:ident),*) => $fallback:path) => { $fallback($($arg),*) };
+}
+
+// Everything in `hdfs.h`. Functions added in later Hadoop versions are looked up too, and fall back to the
+// stand-ins in `compat.rs` when they're missing.
+functions! {
+	fn hdfsFileIsOpenForRead(file: hdfsFile) -> c_int;
+	fn hdfsFileIsOpenForWrite(file: hdfsFile) -> c_int;
+	fn hdfsFileGetReadStatistics(file: hdfsFile, stats: *mut *mut hdfsReadStatistics) -> c_int => compat::hdfsFileGetReadStatistics;
+	fn hdfsReadStatisticsGetRemoteBytesRead(stats: *const hdfsReadStatistics) -> i64 => compat::hdfsReadStatisticsGetRemoteBytesRead;
+	fn hdfsFileClearReadStatistics(file: hdfsFile) -> c_int => compat::hdfsFileClearReadStatistics;
+	fn hdfsFileFreeReadStatistics(stats: *mut hdfsReadStatistics) => compat::hdfsFileFreeReadStatistics;
+	fn hdfsGetHedgedReadMetrics(fs: hdfsFS, metrics: *mut *mut hdfsHedgedReadMetrics) -> c_int => compat::hdfsGetHedgedReadMetrics;
+	fn hdfsFreeHedgedReadMetrics(metrics: *mut hdfsHedgedReadMetrics) => compat::hdfsFreeHedgedReadMetrics;
+	fn hdfsConnectAsUser(nn: *const c_char, port: tPort, user: *const c_char) -> hdfsFS;
+	fn hdfsConnect(nn: *const c_char, port: tPort) -> hdfsFS;
+	fn hdfsConnectAsUserNewInstance(nn: *const c_char, port: tPort, user: *const c_char) -> hdfsFS;
+	fn hdfsConnectNewInstance(nn: *const c_char, port: tPort) -> hdfsFS;
+	fn hdfsBuilderConnect(bld: *mut hdfsBuilder) -> hdfsFS;
+	fn hdfsNewBuilder() -> *mut hdfsBuilder;
+	fn hdfsBuilderSetForceNewInstance(bld: *mut hdfsBuilder);
+	fn hdfsBuilderSetNameNode(bld: *mut hdfsBuilder, nn: *const c_char);
+	fn hdfsBuilderSetNameNodePort(bld: *mut hdfsBuilder, port: tPort);
+	fn hdfsBuilderSetUserName(bld: *mut hdfsBuilder, userName: *const c_char);
+	fn hdfsBuilderSetKerbTicketCachePath(bld: *mut hdfsBuilder, kerbTicketCachePath: *const c_char);
+	fn hdfsFreeBuilder(bld: *mut hdfsBuilder);
+	fn hdfsBuilderConfSetStr(bld: *mut hdfsBuilder, key: *const c_char, val: *const c_char) -> c_int;
+	fn hdfsConfGetStr(key: *const c_char, val: *mut *mut c_char) -> c_int;
+	fn hdfsConfGetInt(key: *const c_char, val: *mut i32) -> c_int;
+	fn hdfsConfStrFree(val: *mut c_char);
+	fn hdfsDisconnect(fs: hdfsFS) -> c_int;
+	fn hdfsOpenFile(fs: hdfsFS, path: *const c_char, flags: c_int, bufferSize: c_int, replication: c_short, blocksize: tSize) -> hdfsFile;
+	fn hdfsStreamBuilderAlloc(fs: hdfsFS, path: *const c_char, flags: c_int) -> *mut hdfsStreamBuilder => compat::hdfsStreamBuilderAlloc;
+	fn hdfsStreamBuilderFree(bld: *mut hdfsStreamBuilder) => compat::hdfsStreamBuilderFree;
+	fn hdfsStreamBuilderSetBufferSize(bld: *mut hdfsStreamBuilder, bufferSize: i32) -> c_int => compat::hdfsStreamBuilderSetBufferSize;
+	fn hdfsStreamBuilderSetReplication(bld: *mut hdfsStreamBuilder, replication: i16) -> c_int => compat::hdfsStreamBuilderSetReplication;
+	fn hdfsStreamBuilderSetDefaultBlockSize(bld: *mut hdfsStreamBuilder, defaultBlockSize: i64) -> c_int => compat::hdfsStreamBuilderSetDefaultBlockSize;
+	fn hdfsStreamBuilderBuild(bld: *mut hdfsStreamBuilder) -> hdfsFile => compat::hdfsStreamBuilderBuild;
+	fn hdfsTruncateFile(fs: hdfsFS, path: *const c_char, newlength: tOffset) -> c_int => compat::hdfsTruncateFile;
+	fn hdfsUnbufferFile(file: hdfsFile) -> c_int => compat::hdfsUnbufferFile;
+	fn hdfsCloseFile(fs: hdfsFS, file: hdfsFile) -> c_int;
+	fn hdfsExists(fs: hdfsFS, path: *const c_char) -> c_int;
+	fn hdfsSeek(fs: hdfsFS, file: hdfsFile, desiredPos: tOffset) -> c_int;
+	fn hdfsTell(fs: hdfsFS, file: hdfsFile) -> tOffset;
+	fn hdfsRead(fs: hdfsFS, file: hdfsFile, buffer: *mut c_void, length: tSize) -> tSize;
+	fn hdfsPread(fs: hdfsFS, file: hdfsFile, position: tOffset, buffer: *mut c_void, length: tSize) -> tSize;
+	fn hdfsPreadFully(fs: hdfsFS, file: hdfsFile, position: tOffset, buffer: *mut c_void, length: tSize) -> c_int => compat::hdfsPreadFully;
+	fn hdfsWrite(fs: hdfsFS, file: hdfsFile, buffer: *const c_void, length: tSize) -> tSize;
+	fn hdfsFlush(fs: hdfsFS, file: hdfsFile) -> c_int;
+	fn hdfsHFlush(fs: hdfsFS, file: hdfsFile) -> c_int;
+	fn hdfsHSync(fs: hdfsFS, file: hdfsFile) -> c_int;
+	fn hdfsAvailable(fs: hdfsFS, file: hdfsFile) -> c_int;
+	fn hdfsCopy(srcFS: hdfsFS, src: *const c_char, dstFS: hdfsFS, dst: *const c_char) -> c_int;
+	fn hdfsMove(srcFS: hdfsFS, src: *const c_char, dstFS: hdfsFS, dst: *const c_char) -> c_int;
+	fn hdfsDelete(fs: hdfsFS, path: *const c_char, recursive: c_int) -> c_int;
+	fn hdfsRename(fs: hdfsFS, oldPath: *const c_char, newPath: *const c_char) -> c_int;
+	fn hdfsGetWorkingDirectory(fs: hdfsFS, buffer: *mut c_char, bufferSize: usize) -> *mut c_char;
+	fn hdfsSetWorkingDirectory(fs: hdfsFS, path: *const c_char) -> c_int;
+	fn hdfsCreateDirectory(fs: hdfsFS, path: *const c_char) -> c_int;
+	fn hdfsSetReplication(fs: hdfsFS, path: *const c_char, replication: i16) -> c_int;
+	fn hdfsListDirectory(fs: hdfsFS, path: *const c_char, numEntries: *mut c_int) -> *mut hdfsFileInfo;
+	fn hdfsGetPathInfo(fs: hdfsFS, path: *const c_char) -> *mut hdfsFileInfo;
+	fn hdfsFreeFileInfo(hdfsFileInfo: *mut hdfsFileInfo, numEntries: c_int);
+	fn hdfsFileIsEncrypted(hdfsFileInfo: *mut hdfsFileInfo) -> c_int => compat::hdfsFileIsEncrypted;
+	fn hdfsGetHosts(fs: hdfsFS, path: *const c_char, start: tOffset, length: tOffset) -> *mut *mut *mut c_char;
+	fn hdfsFreeHosts(blockHosts: *mut *mut *mut c_char);
+	fn hdfsGetDefaultBlockSize(fs: hdfsFS) -> tOffset;
+	fn hdfsGetDefaultBlockSizeAtPath(fs: hdfsFS, path: *const c_char) -> tOffset => compat::hdfsGetDefaultBlockSizeAtPath;
+	fn hdfsGetCapacity(fs: hdfsFS) -> tOffset;
+	fn hdfsGetUsed(fs: hdfsFS) -> tOffset;
+	fn hdfsChown(fs: hdfsFS, path: *const c_char, owner: *const c_char, group: *const c_char) -> c_int;
+	fn hdfsChmod(fs: hdfsFS, path: *const c_char, mode: c_short) -> c_int;
+	fn hdfsUtime(fs: hdfsFS, path: *const c_char, mtime: tTime, atime: tTime) -> c_int;
+	fn hdfsGetLastExceptionRootCause() -> *mut c_char => compat::hdfsGetLastExceptionRootCause;
+	fn hdfsGetLastExceptionStackTrace() -> *mut c_char => compat::hdfsGetLastExceptionStackTrace;
+	fn hadoopRzOptionsAlloc() -> *mut hadoopRzOptions => compat::hadoopRzOptionsAlloc;
+	fn hadoopRzOptionsSetSkipChecksum(opts: *mut hadoopRzOptions, skip: c_int) -> c_int;
+	fn hadoopRzOptionsSetByteBufferPool(opts: *mut hadoopRzOptions, className: *const c_char) -> c_int;
+	fn hadoopRzOptionsFree(opts: *mut hadoopRzOptions);
+	fn hadoopReadZero(file: hdfsFile, opts: *mut hadoopRzOptions, maxLength: i32) -> *mut hadoopRzBuffer;
+	fn hadoopRzBufferLength(buffer: *const hadoopRzBuffer) -> i32;
+	fn hadoopRzBufferGet(buffer: *const hadoopRzBuffer) -> *const c_void;
+	fn hadoopRzBufferFree(file: hdfsFile, buffer: *mut hadoopRzBuffer);
+	// From `jni.h`, in `libjvm`, which is found through `libhdfs`'s dependencies
+	fn JNI_GetCreatedJavaVMs(vms: *mut *mut c_void, len: c_int, count: *mut c_int) -> c_int => compat::JNI_GetCreatedJavaVMs;
+}
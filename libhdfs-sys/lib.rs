@@ -23,3 +23,33 @@
 #![allow(non_snake_case)]
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+include!(concat!(env!("OUT_DIR"), "/missing.rs"));
+
+// Only the functions the library lacks are used
+#[allow(dead_code)]
+mod compat;
+#[cfg(feature = "dlopen")]
+mod dynamic;
+#[cfg(feature = "dlopen")]
+pub use dynamic::*;
+
+/// Checks whether `libhdfs` has a function itself, rather than it being filled in by a stand-in because the
+/// library is older than it. When linking, this is decided at build time from `hdfs.h`.
+#[cfg(not(feature = "dlopen"))]
+pub fn has_function(name: &str) -> bool {
+	!MISSING_FUNCTIONS.contains(&name)
+}
+
+/// Checks whether the library is libhdfs++, the native C++ client, rather than the JNI-based `libhdfs`. They
+/// differ in places, ex. libhdfs++ takes and returns times in milliseconds rather than seconds. When linking, this
+/// is decided at build time from `RSHDFS_LIB_NAME`.
+#[cfg(not(feature = "dlopen"))]
+pub fn is_libhdfspp() -> bool {
+	option_env!("RSHDFS_LINKED_LIB").is_some_and(|name| name.starts_with("hdfspp"))
+}
+
+// From `jni.h`, for the JVM that `libhdfs` starts on its first connection.
+#[cfg(not(feature = "dlopen"))]
+extern "C" {
+	pub fn JNI_GetCreatedJavaVMs(vms: *mut *mut ::std::os::raw::c_void, len: i32, count: *mut i32) -> i32;
+}
@@ -0,0 +1,324 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Python bindings for `hdfs`, built with `pyo3`.
+//! 
+//! ```python
+//! import hdfs_rs
+//! fs = hdfs_rs.connect(name_node="default", user="hdfs")
+//! for entry in fs.list_dir("/"):
+//!     print(entry.name, entry.size)
+//! with fs.open("/tmp/hello.txt", "wb") as f:
+//!     f.write(b"hello")
+//! ```
+//! 
+//! Connections and files use the same `HdfsBuilder` options as the Rust API, so the same
+//! environment setup (`CLASSPATH`, `LD_LIBRARY_PATH`, Kerberos ticket caches) applies.
+
+use hdfs::{HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind, HdfsFile};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, Write};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Converts a `SystemTime` to seconds since the Unix epoch, as Python's `time.time()` does.
+fn systime_to_secs(t: SystemTime) -> f64 {
+	match t.duration_since(UNIX_EPOCH) {
+		Ok(d) => d.as_secs_f64(),
+		Err(e) => -e.duration().as_secs_f64(),
+	}
+}
+
+/// Connects to HDFS.
+/// 
+/// `name_node` defaults to `"default"`, the server specified in `hdfs-site.xml`. Pass `None`
+/// to connect to the local filesystem.
+#[pyfunction]
+#[pyo3(signature = (name_node=Some("default"), user=None, kerb_ticket_cache_path=None, conf=None, force_new_instance=false))]
+fn connect(
+	name_node: Option<&str>,
+	user: Option<&str>,
+	kerb_ticket_cache_path: Option<&str>,
+	conf: Option<HashMap<String, String>>,
+	force_new_instance: bool,
+) -> PyResult<Connection> {
+	hdfs::load_libhdfs()?;
+	let mut builder = HdfsConnection::builder();
+	builder.name_node(name_node);
+	if let Some(user) = user {
+		builder.user_name(user);
+	}
+	if let Some(path) = kerb_ticket_cache_path {
+		builder.kerb_ticket_cache_path(path);
+	}
+	for (key, value) in conf.iter().flatten() {
+		builder.conf_set(key, value)?;
+	}
+	if force_new_instance {
+		builder.force_new_instance();
+	}
+	let fs = builder.connect()?;
+	Ok(Connection { fs: Rc::new(fs) })
+}
+
+/// Connection to an HDFS filesystem.
+#[pyclass(unsendable, module = "hdfs_rs")]
+struct Connection {
+	fs: Rc<HdfsConnection>,
+}
+#[pymethods]
+impl Connection {
+	/// Checks if a path exists in the filesystem.
+	fn exists(&self, path: &str) -> PyResult<bool> {
+		Ok(self.fs.exists(path)?)
+	}
+	
+	/// Lists the contents of a directory.
+	fn list_dir(&self, path: &str) -> PyResult<Vec<DirectoryEntry>> {
+		let entries = self.fs.list_dir(path)?;
+		Ok(entries.into_iter().map(DirectoryEntry::from).collect())
+	}
+	
+	/// Deletes a file. Will not delete non-empty directories unless `recursive` is true.
+	#[pyo3(signature = (path, recursive=false))]
+	fn delete(&self, path: &str, recursive: bool) -> PyResult<()> {
+		Ok(self.fs.delete(path, recursive)?)
+	}
+	
+	/// Renames a file.
+	fn rename(&self, src: &str, dest: &str) -> PyResult<()> {
+		Ok(self.fs.rename(src, dest)?)
+	}
+	
+	/// Changes the permission bits of a file.
+	fn chmod(&self, path: &str, mode: u16) -> PyResult<()> {
+		Ok(self.fs.chmod(path, mode)?)
+	}
+	
+	/// Changes the owner and/or group of a file.
+	#[pyo3(signature = (path, owner=None, group=None))]
+	fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> PyResult<()> {
+		Ok(self.fs.chown(path, owner, group)?)
+	}
+	
+	/// Truncates a file to a certain size.
+	/// 
+	/// Returns `True` if the file was truncated immediately, or `False` if the last block is still being recovered.
+	fn truncate(&self, path: &str, size: i64) -> PyResult<bool> {
+		Ok(self.fs.truncate(path, size)?)
+	}
+	
+	/// Opens a file.
+	/// 
+	/// `mode` is one of `"rb"`, `"wb"`, or `"ab"` (the `b` is optional; files are always binary).
+	/// `replication` and `block_size` only apply to files being written.
+	#[pyo3(signature = (path, mode="rb", buffer_size=None, replication=None, block_size=None))]
+	fn open(
+		&self,
+		path: &str,
+		mode: &str,
+		buffer_size: Option<i32>,
+		replication: Option<i16>,
+		block_size: Option<i64>,
+	) -> PyResult<File> {
+		// Safe because `File` keeps the connection alive and drops the file first.
+		let fs: &'static HdfsConnection = unsafe { &*Rc::as_ptr(&self.fs) };
+		let (mut builder, writable) = match mode.trim_end_matches('b') {
+			"r" => (fs.open_read_builder(path)?, false),
+			"w" => (fs.open_create_builder(path)?, true),
+			"a" => (fs.open_append_builder(path)?, true),
+			_ => { return Err(PyValueError::new_err(format!("invalid mode: {:?}", mode))); }
+		};
+		if let Some(size) = buffer_size {
+			builder.buffer_size(size)?;
+		}
+		if let Some(repl) = replication {
+			builder.replication(repl)?;
+		}
+		if let Some(size) = block_size {
+			builder.default_block_size(size)?;
+		}
+		let file = builder.build()?;
+		Ok(File { file: Some(file), _fs: Rc::clone(&self.fs), writable })
+	}
+}
+
+/// Open HDFS file, implementing Python's binary file protocol.
+#[pyclass(unsendable, module = "hdfs_rs")]
+struct File {
+	// Must be declared before `_fs`, so that it's closed before the connection is released.
+	file: Option<HdfsFile<'static>>,
+	_fs: Rc<HdfsConnection>,
+	writable: bool,
+}
+impl File {
+	fn get(&mut self) -> PyResult<&mut HdfsFile<'static>> {
+		self.file.as_mut().ok_or_else(|| PyValueError::new_err("I/O operation on closed file"))
+	}
+}
+#[pymethods]
+impl File {
+	/// Reads up to `size` bytes, or until the end of the file if `size` is negative.
+	#[pyo3(signature = (size=-1))]
+	fn read<'py>(&mut self, py: Python<'py>, size: i64) -> PyResult<&'py PyBytes> {
+		let file = self.get()?;
+		let mut buf = vec![];
+		if size < 0 {
+			file.read_to_end(&mut buf)?;
+		} else {
+			file.take(size as u64).read_to_end(&mut buf)?;
+		}
+		Ok(PyBytes::new(py, &buf))
+	}
+	
+	/// Writes all of `data`, returning the number of bytes written.
+	fn write(&mut self, data: &[u8]) -> PyResult<usize> {
+		self.get()?.write_all(data)?;
+		Ok(data.len())
+	}
+	
+	/// Seeks to a position. Only `whence` values of 0 (start) and 1 (current) are supported.
+	#[pyo3(signature = (offset, whence=0))]
+	fn seek(&mut self, offset: i64, whence: i32) -> PyResult<u64> {
+		let pos = match whence {
+			0 if offset >= 0 => io::SeekFrom::Start(offset as u64),
+			0 => { return Err(PyValueError::new_err("negative seek position")); },
+			1 => io::SeekFrom::Current(offset),
+			_ => { return Err(PyIOError::new_err("seek only supports whence values of 0 and 1")); },
+		};
+		Ok(self.get()?.seek(pos)?)
+	}
+	
+	/// Returns the current position in the file.
+	fn tell(&mut self) -> PyResult<u64> {
+		Ok(self.get()?.seek(io::SeekFrom::Current(0))?)
+	}
+	
+	/// Flushes the client buffer to HDFS.
+	fn flush(&mut self) -> PyResult<()> {
+		Ok(self.get()?.flush()?)
+	}
+	
+	/// Flushes data and waits until it is safely on disk.
+	fn sync(&mut self) -> PyResult<()> {
+		Ok(self.get()?.sync()?)
+	}
+	
+	/// Closes the file, raising if it can't be completed. Closing an already closed file does nothing.
+	fn close(&mut self) -> PyResult<()> {
+		if let Some(file) = self.file.take() {
+			file.close()?;
+		}
+		Ok(())
+	}
+	
+	#[getter]
+	fn closed(&self) -> bool {
+		self.file.is_none()
+	}
+	
+	fn readable(&self) -> bool {
+		!self.writable
+	}
+	
+	fn writable(&self) -> bool {
+		self.writable
+	}
+	
+	fn seekable(&self) -> bool {
+		!self.writable
+	}
+	
+	fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+		slf
+	}
+	
+	fn __exit__(&mut self, _exc_type: &PyAny, _exc_value: &PyAny, _traceback: &PyAny) -> PyResult<bool> {
+		self.close()?;
+		Ok(false)
+	}
+}
+
+/// Entry returned by `Connection.list_dir`.
+#[pyclass(module = "hdfs_rs")]
+struct DirectoryEntry {
+	/// `"file"`, `"directory"`, or `"unknown"`
+	#[pyo3(get)]
+	kind: &'static str,
+	/// Name of the file, as an absolute url (ex. `hdfs://host/a/b/c`)
+	#[pyo3(get)]
+	name: String,
+	/// Time the file was last modified, in seconds since the epoch
+	#[pyo3(get)]
+	last_modified: f64,
+	#[pyo3(get)]
+	size: u64,
+	#[pyo3(get)]
+	replication: u16,
+	#[pyo3(get)]
+	block_size: u64,
+	#[pyo3(get)]
+	owner: String,
+	#[pyo3(get)]
+	group: String,
+	#[pyo3(get)]
+	permissions: u16,
+	/// Time the file was last accessed, in seconds since the epoch
+	#[pyo3(get)]
+	last_access: f64,
+}
+impl From<HdfsDirectoryEntry> for DirectoryEntry {
+	fn from(entry: HdfsDirectoryEntry) -> Self {
+		Self {
+			kind: match entry.kind {
+				HdfsDirectoryEntryKind::File => "file",
+				HdfsDirectoryEntryKind::Directory => "directory",
+				HdfsDirectoryEntryKind::Unrecognized(_) => "unknown",
+			},
+			name: entry.name,
+			last_modified: systime_to_secs(entry.last_modified),
+			size: entry.size,
+			replication: entry.replication,
+			block_size: entry.block_size,
+			owner: entry.owner,
+			group: entry.group,
+			permissions: entry.permissions,
+			last_access: systime_to_secs(entry.last_access),
+		}
+	}
+}
+#[pymethods]
+impl DirectoryEntry {
+	fn __repr__(&self) -> String {
+		format!("DirectoryEntry(kind={:?}, name={:?}, size={})", self.kind, self.name, self.size)
+	}
+}
+
+#[pymodule]
+fn hdfs_rs(_py: Python, m: &PyModule) -> PyResult<()> {
+	m.add_function(wrap_pyfunction!(connect, m)?)?;
+	m.add_class::<Connection>()?;
+	m.add_class::<File>()?;
+	m.add_class::<DirectoryEntry>()?;
+	Ok(())
+}
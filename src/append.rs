@@ -0,0 +1,175 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Appending to files that other, possibly crashed, writers may still hold the lease on.
+//! 
+//! Only one client can write to an HDFS file at a time. When a process appends to a log, restarts, and appends
+//! again, the namenode may still think the old process is writing and fail the append with
+//! `AlreadyBeingCreatedException` until the lease expires, after a minute, or is recovered.
+
+use crate::path::AsHdfsPath;
+use crate::{HdfsConnection, HdfsFile};
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// `errno` that `libhdfs` uses for Java exceptions that it has no mapping for, which includes lease conflicts.
+const EINTERNAL: i32 = 255;
+
+/// Exceptions that mean the file is still being written by someone else.
+#[cfg(feature = "ext")]
+const LEASE_EXCEPTIONS: &[&str] = &["AlreadyBeingCreatedException", "RecoveryInProgressException", "LeaseExpiredException"];
+
+/// Options for `HdfsConnection::append_or_create_with`.
+#[derive(Debug,Clone,Copy)]
+pub struct AppendOptions {
+	retries: u32,
+	backoff: Duration,
+	max_backoff: Duration,
+	recover_lease: bool,
+}
+
+impl Default for AppendOptions {
+	/// Retries 7 times, waiting 1 second at first and doubling up to 30, which outlasts the one minute lease
+	/// timeout.
+	fn default() -> Self {
+		Self {
+			retries: 7,
+			backoff: Duration::from_secs(1),
+			max_backoff: Duration::from_secs(30),
+			recover_lease: false,
+		}
+	}
+}
+
+impl AppendOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Sets how many times to retry after a lease conflict.
+	pub fn retries(mut self, retries: u32) -> Self {
+		self.retries = retries;
+		self
+	}
+	
+	/// Sets how long to wait before the first retry, and the most to wait between retries. The wait doubles
+	/// after each retry.
+	pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+		self.backoff = initial;
+		self.max_backoff = max.max(initial);
+		self
+	}
+	
+	/// Asks the namenode to recover the lease before each retry, which closes the file if its writer is gone,
+	/// instead of waiting for the lease to expire.
+	/// 
+	/// `libhdfs` can't do this, so it runs `hdfs debug recoverLease` from `HADOOP_HOME`, or from `PATH`. Failing to
+	/// run it isn't an error; the append is retried anyway.
+	pub fn recover_lease(mut self) -> Self {
+		self.recover_lease = true;
+		self
+	}
+}
+
+impl HdfsConnection {
+	/// Opens a file for appending, creating it if it doesn't exist, and retrying if another client holds the
+	/// lease. Uses the default `AppendOptions`.
+	pub fn append_or_create<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<HdfsFile> {
+		self.append_or_create_with(path, AppendOptions::default())
+	}
+	
+	/// Opens a file for appending, creating it if it doesn't exist, and retrying if another client holds the
+	/// lease.
+	/// 
+	/// Lease conflicts are recognized from the Java exception with the `ext` feature. Without it, `libhdfs` only
+	/// reports them as an unknown error, so all unknown errors are retried. Losing a race to create the file counts
+	/// as a retry too, and fails with `AlreadyExists` if `path` is a directory.
+	pub fn append_or_create_with<P: AsHdfsPath + ?Sized>(&self, path: &P, options: AppendOptions) -> io::Result<HdfsFile> {
+		let mut backoff = options.backoff;
+		let mut attempt = 0;
+		loop {
+			let err = match self.open_append(path) {
+				Ok(file) => { return Ok(file); },
+				Err(err) => err,
+			};
+			let (err, raced) = if err.kind() == io::ErrorKind::NotFound {
+				match self.open_create(path) {
+					Ok(file) => { return Ok(file); },
+					// Appending to a directory fails with NotFound too, and creating over it with AlreadyExists
+					Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && self.is_dir(path)? => {
+						return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is a directory", path.as_str_path()?)));
+					},
+					// Someone else created it first, so append to theirs
+					Err(e) if e.kind() == io::ErrorKind::AlreadyExists => (e, true),
+					Err(e) => { return Err(e); },
+				}
+			} else {
+				if !is_lease_conflict(&err) {
+					return Err(err);
+				}
+				(err, false)
+			};
+			if attempt >= options.retries {
+				return Err(err);
+			}
+			
+			// Only a lease conflict has a writer to recover from; after a lost race the winner is still writing
+			if options.recover_lease && !raced {
+				if let Ok(entry) = self.stat(path) {
+					recover_lease(&entry.name);
+				}
+			}
+			thread::sleep(backoff);
+			backoff = (backoff * 2).min(options.max_backoff);
+			attempt += 1;
+		}
+	}
+}
+
+/// Checks if an error from opening a file means someone else is writing it. Must be called right after the
+/// failed call, on the same thread.
+fn is_lease_conflict(err: &io::Error) -> bool {
+	#[cfg(feature = "ext")]
+	{
+		if let Some(cause) = crate::last_exception_root_cause() {
+			return LEASE_EXCEPTIONS.iter().any(|name| cause.contains(name));
+		}
+	}
+	err.raw_os_error() == Some(EINTERNAL)
+}
+
+/// Runs `hdfs debug recoverLease` on a file, given its full URI, ignoring failures.
+fn recover_lease(uri: &str) {
+	let hdfs = ["HADOOP_HOME", "HADOOP_PREFIX"].iter()
+		.filter_map(env::var_os)
+		.find(|home| !home.is_empty())
+		.map(|home| PathBuf::from(home).join("bin").join("hdfs"))
+		.unwrap_or_else(|| PathBuf::from("hdfs"));
+	let _ = Command::new(hdfs)
+		.args(["debug", "recoverLease", "-path", uri, "-retries", "1"])
+		.stdin(Stdio::null())
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.status();
+}
@@ -0,0 +1,122 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Audit trails of what was done to a filesystem.
+
+use crate::filesystem::Op;
+use crate::intercept::Interceptor;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Operation seen by an `Audit` hook.
+#[derive(Debug)]
+pub struct AuditRecord<'a> {
+	/// What was done
+	pub op: Op,
+	/// Path it was done to. For renames, the source and destination.
+	pub paths: &'a [&'a str],
+	/// User the `Audit` was created for
+	pub user: &'a str,
+	/// Why it failed, or `None` if it succeeded
+	pub error: Option<&'a io::Error>,
+	/// When it started
+	pub started: SystemTime,
+	/// How long it took
+	pub duration: Duration,
+}
+
+/// Interceptor that calls a hook after every mutating operation (see `Op::is_mutating`), whether or not it
+/// succeeded, so that applications can keep an audit trail:
+/// 
+/// ```ignore
+/// let fs = conn.intercept(Audit::new("etl", |record| log::info!("{}", record)));
+/// ```
+/// 
+/// Writes to a file aren't recorded separately from opening it. `libhdfs` can't tell what user a connection
+/// is acting as, so it's given to `new`, and should match the one it connected with.
+pub struct Audit {
+	user: String,
+	ops: Option<Vec<Op>>,
+	hook: Box<dyn Fn(&AuditRecord) + Send + Sync>,
+}
+
+impl Audit {
+	/// Calls `hook` after each mutating operation, done as `user`.
+	pub fn new<H: Fn(&AuditRecord) + Send + Sync + 'static>(user: &str, hook: H) -> Self {
+		Self { user: user.to_string(), ops: None, hook: Box::new(hook) }
+	}
+	
+	/// Records these operations instead, which can include reads.
+	pub fn ops(mut self, ops: &[Op]) -> Self {
+		self.ops = Some(ops.to_vec());
+		self
+	}
+	
+	/// Gets the user that operations are recorded as.
+	pub fn user(&self) -> &str {
+		&self.user
+	}
+}
+
+impl fmt::Debug for Audit {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Audit")
+			.field("user", &self.user)
+			.field("ops", &self.ops)
+			.finish()
+	}
+}
+
+impl Interceptor for Audit {
+	fn intercept<T>(&self, op: Op, paths: &[&str], call: &mut dyn FnMut() -> io::Result<T>) -> io::Result<T> {
+		let audited = match self.ops.as_ref() {
+			Some(ops) => ops.contains(&op),
+			None => op.is_mutating(),
+		};
+		if !audited {
+			return call();
+		}
+		let started = SystemTime::now();
+		let start = Instant::now();
+		let res = call();
+		(self.hook)(&AuditRecord {
+			op,
+			paths,
+			user: &self.user,
+			error: res.as_ref().err(),
+			started,
+			duration: start.elapsed(),
+		});
+		res
+	}
+}
+
+/// Formats as a single line, like `user=etl op=Rename paths=/a,/b result=ok duration=1.2ms`.
+impl<'a> fmt::Display for AuditRecord<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "user={} op={:?} paths={} ", self.user, self.op, self.paths.join(","))?;
+		match self.error {
+			Some(e) => write!(f, "result=error({})", e)?,
+			None => write!(f, "result=ok")?,
+		}
+		write!(f, " duration={:?}", self.duration)
+	}
+}
@@ -0,0 +1,133 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Client-side computation of HDFS's `MD5-of-MD5-of-CRC32C` file checksum.
+//! 
+//! `libhdfs` can't ask the datanodes for their block checksums, so this reads the whole file and
+//! recomputes them the same way. The result matches `hdfs dfs -checksum` for files written with the
+//! default `dfs.checksum.type` (`CRC32C`) and `dfs.bytes-per-checksum` (512).
+
+use hdfs::{HdfsConnection, HdfsDirectoryEntry};
+use std::fmt::Write as _;
+use std::io::{self, Read};
+
+/// Number of bytes covered by each CRC
+pub const BYTES_PER_CRC: u64 = 512;
+
+/// Size of reads from HDFS. Must be a multiple of `BYTES_PER_CRC`.
+const READ_SIZE: usize = 1024 * 1024;
+
+/// A computed file checksum.
+pub struct FileChecksum {
+	/// Algorithm name, as shown by `hdfs dfs -checksum`
+	pub algorithm: String,
+	/// Serialized checksum, as a hex string
+	pub hex: String,
+}
+
+/// Computes the checksum of a file.
+pub fn file_checksum(fs: &HdfsConnection, entry: &HdfsDirectoryEntry) -> io::Result<FileChecksum> {
+	file_checksum_with_block_size(fs, entry, entry.block_size)
+}
+
+/// Computes the checksum a file would have if it were stored with a different block size.
+/// 
+/// Useful for comparing files on filesystems with different default block sizes, ex. a local file and an HDFS file.
+pub fn file_checksum_with_block_size(fs: &HdfsConnection, entry: &HdfsDirectoryEntry, block_size: u64) -> io::Result<FileChecksum> {
+	if entry.size == 0 {
+		// HDFS reports an empty file as having a plain CRC32 checksum with no CRCs
+		return Ok(FileChecksum {
+			algorithm: "MD5-of-0MD5-of-0CRC32".into(),
+			hex: serialize(0, 0, &md5::compute(b"").0),
+		});
+	}
+	
+	let crcs_per_full_block = block_size / BYTES_PER_CRC;
+	if crcs_per_full_block == 0 || crcs_per_full_block * BYTES_PER_CRC != block_size {
+		return Err(io::Error::new(io::ErrorKind::Other, format!("unsupported block size {}", block_size)));
+	}
+	
+	let mut file = fs.open_read(&entry.name)?;
+	let mut buf = vec![0u8; READ_SIZE];
+	let mut file_md5 = md5::Context::new();
+	let mut block_crcs = Vec::with_capacity(crcs_per_full_block as usize * 4);
+	let mut block_pos = 0;
+	let mut num_blocks = 0u64;
+	loop {
+		// Don't read past the end of a block, so a CRC never spans two blocks
+		let want = (block_size - block_pos).min(READ_SIZE as u64) as usize;
+		let len = read_full(&mut file, &mut buf[..want])?;
+		if len == 0 {
+			break;
+		}
+		for chunk in buf[..len].chunks(BYTES_PER_CRC as usize) {
+			block_crcs.extend_from_slice(&crc32c::crc32c(chunk).to_be_bytes());
+		}
+		block_pos += len as u64;
+		if block_pos == block_size {
+			file_md5.consume(md5::compute(&block_crcs).0);
+			block_crcs.clear();
+			block_pos = 0;
+			num_blocks += 1;
+		}
+		if len < want {
+			break;
+		}
+	}
+	if block_pos > 0 {
+		file_md5.consume(md5::compute(&block_crcs).0);
+		num_blocks += 1;
+	}
+	
+	// The CRCs-per-block field is only filled in for files with more than one block
+	let crcs_per_block = if num_blocks > 1 { crcs_per_full_block } else { 0 };
+	Ok(FileChecksum {
+		algorithm: format!("MD5-of-{}MD5-of-{}CRC32C", crcs_per_block, BYTES_PER_CRC),
+		hex: serialize(BYTES_PER_CRC as u32, crcs_per_block, &file_md5.compute().0),
+	})
+}
+
+/// Fills `buf` as much as possible, stopping early only at the end of the file.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+	let mut filled = 0;
+	while filled < buf.len() {
+		match reader.read(&mut buf[filled..]) {
+			Ok(0) => break,
+			Ok(n) => { filled += n; },
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+			Err(e) => { return Err(e); },
+		}
+	}
+	Ok(filled)
+}
+
+/// Serializes a checksum the same way Hadoop's `MD5MD5CRC32FileChecksum` does, as a hex string.
+fn serialize(bytes_per_crc: u32, crcs_per_block: u64, md5: &[u8]) -> String {
+	let mut hex = String::with_capacity(2 * (4 + 8 + md5.len()));
+	let bytes = bytes_per_crc.to_be_bytes().iter()
+		.chain(crcs_per_block.to_be_bytes().iter())
+		.chain(md5.iter())
+		.cloned()
+		.collect::<Vec<u8>>();
+	for byte in bytes.iter() {
+		write!(hex, "{:02x}", byte).unwrap();
+	}
+	hex
+}
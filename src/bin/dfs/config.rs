@@ -0,0 +1,103 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Connection profiles, read from `~/.config/hdfs-rs/config.toml`.
+//! 
+//! Example:
+//! 
+//! ```toml
+//! # Profile to use when --profile isn't given
+//! default_profile = "prod"
+//! 
+//! [profiles.prod]
+//! name_node = "hdfs://namenode.example.com:8020"
+//! user = "etl"
+//! kerb_ticket_cache_path = "/tmp/krb5cc_etl"
+//! 
+//! [profiles.prod.conf]
+//! "dfs.client.use.datanode.hostname" = "true"
+//! ```
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Settings used to connect to a cluster.
+#[derive(Debug,Clone,Default,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+	/// Name node to connect to
+	pub name_node: Option<String>,
+	/// User to connect as
+	pub user: Option<String>,
+	/// Kerberos ticket cache to use
+	pub kerb_ticket_cache_path: Option<String>,
+	/// Hadoop configuration overrides
+	#[serde(default)]
+	pub conf: BTreeMap<String, String>,
+}
+
+/// Contents of the configuration file.
+#[derive(Debug,Clone,Default,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+	pub default_profile: Option<String>,
+	#[serde(default)]
+	pub profiles: BTreeMap<String, Profile>,
+}
+impl Config {
+	/// Path to the configuration file: `$XDG_CONFIG_HOME/hdfs-rs/config.toml`, falling back to
+	/// `~/.config/hdfs-rs/config.toml`.
+	pub fn path() -> Option<PathBuf> {
+		let base = env::var_os("XDG_CONFIG_HOME")
+			.filter(|dir| !dir.is_empty())
+			.map(PathBuf::from)
+			.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+		Some(base.join("hdfs-rs").join("config.toml"))
+	}
+	
+	/// Loads the configuration file, returning an empty configuration if it doesn't exist.
+	pub fn load() -> Result<Self, String> {
+		let path = match Self::path() {
+			Some(path) => path,
+			None => { return Ok(Self::default()); },
+		};
+		let contents = match fs::read_to_string(&path) {
+			Ok(contents) => contents,
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => { return Ok(Self::default()); },
+			Err(e) => { return Err(format!("Could not read {}: {}", path.display(), e)); },
+		};
+		toml::from_str(&contents)
+			.map_err(|e| format!("Could not parse {}: {}", path.display(), e))
+	}
+	
+	/// Gets the named profile, or the default profile if `name` is `None`.
+	pub fn profile(&self, name: Option<&str>) -> Result<Profile, String> {
+		match name.or(self.default_profile.as_deref()) {
+			Some(name) => self.profiles.get(name)
+				.cloned()
+				.ok_or_else(|| format!("No profile named {:?} in {}", name, Self::path().map(|p| p.display().to_string()).unwrap_or_default())),
+			None => Ok(Profile::default()),
+		}
+	}
+}
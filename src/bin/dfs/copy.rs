@@ -0,0 +1,232 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! HDFS to HDFS copies using a pool of worker threads, for the `cp` subcommand.
+
+use crate::checksum;
+use crate::format;
+use crate::progress;
+use hdfs::{BufferPool, HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind, Preserve};
+use indicatif::ProgressBar;
+use std::io::{self, Seek};
+use std::sync::Mutex;
+use std::thread;
+
+/// Size of the buffers used to copy file contents.
+pub const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Options for `copy`.
+#[derive(Debug,Clone,Copy)]
+pub struct Options {
+	/// Number of files to copy at once
+	pub parallel: usize,
+	/// Compare checksums of copied files, and of existing files before skipping them
+	pub verify: bool,
+	/// Replace existing files even if they look identical
+	pub overwrite: bool,
+	/// Continue partially copied files by appending to them, rather than starting over
+	pub resume: bool,
+	/// Attributes to give copied files and created directories. The block size is always kept.
+	pub preserve: Preserve,
+	/// Show a progress bar
+	pub progress: bool,
+}
+
+/// Counts of what happened to each file.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct Summary {
+	pub copied: usize,
+	pub copied_bytes: u64,
+	pub skipped: usize,
+	pub failed: usize,
+}
+
+/// A file to copy.
+struct Task {
+	src: HdfsDirectoryEntry,
+	dest: String,
+}
+
+/// Copies a file or directory tree.
+/// 
+/// `connect_src` and `connect_dest` open connections to the source and destination clusters; each worker
+/// thread opens its own. If `dest` is an existing directory, `src` is copied into it.
+pub fn copy<S, D>(connect_src: S, connect_dest: D, src: &str, dest: &str, options: Options) -> Result<Summary, String>
+	where
+		S: Fn() -> io::Result<HdfsConnection> + Sync,
+		D: Fn() -> io::Result<HdfsConnection> + Sync,
+{
+	let src_fs = connect_src()
+		.map_err(|e| format!("Could not connect to source cluster: {}", e))?;
+	let dest_fs = connect_dest()
+		.map_err(|e| format!("Could not connect to destination cluster: {}", e))?;
+	
+	let root = src_fs.stat(src)
+		.map_err(|e| format!("{}: {}", src, e))?;
+	let dest_root = match dest_fs.stat(dest) {
+		Ok(ref entry) if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) => {
+			format!("{}/{}", dest.trim_end_matches('/'), base_name(&root.name))
+		},
+		_ => dest.to_string(),
+	};
+	
+	// Create the directory structure up front, so workers only have to deal with files
+	let mut tasks = vec![];
+	let mut dirs = vec![];
+	let mut summary = Summary::default();
+	for item in src_fs.walk(&root.name) {
+		let item = match item {
+			Ok(item) => item,
+			Err(e) => {
+				eprintln!("{}: {}", src, e);
+				summary.failed += 1;
+				continue;
+			},
+		};
+		let relative = item.entry.name.strip_prefix(root.name.as_str())
+			.unwrap_or_else(|| base_name(&item.entry.name))
+			.trim_start_matches('/');
+		let dest = if relative.is_empty() {
+			dest_root.clone()
+		} else {
+			format!("{}/{}", dest_root.trim_end_matches('/'), relative)
+		};
+		match item.entry.kind {
+			HdfsDirectoryEntryKind::Directory => {
+				match dest_fs.create_dir(&dest) {
+					Ok(()) => { dirs.push(Task { src: item.entry, dest }); },
+					Err(e) => {
+						eprintln!("Could not create {}: {}", dest, e);
+						summary.failed += 1;
+					},
+				}
+			},
+			_ => { tasks.push(Task { src: item.entry, dest }); },
+		}
+	}
+	drop(src_fs);
+	
+	// Biggest files first, so one large file doesn't hold up the end of the copy
+	tasks.sort_by_key(|task| task.src.size);
+	let bar = progress::bytes_bar(options.progress, tasks.iter().map(|task| task.src.size).sum(), "Copying");
+	let tasks = Mutex::new(tasks);
+	let summary = Mutex::new(summary);
+	let pool = BufferPool::new(COPY_BUFFER_SIZE).max_idle(options.parallel.max(1));
+	thread::scope(|scope| {
+		for _ in 0..options.parallel.max(1) {
+			scope.spawn(|| {
+				let (src_fs, dest_fs) = match (connect_src(), connect_dest()) {
+					(Ok(src_fs), Ok(dest_fs)) => (src_fs, dest_fs),
+					(Err(e), _) | (_, Err(e)) => {
+						bar.suspend(|| eprintln!("Worker could not connect: {}", e));
+						return;
+					},
+				};
+				loop {
+					let task = match tasks.lock().unwrap().pop() {
+						Some(task) => task,
+						None => { break; },
+					};
+					let name = format::uri_path(&task.src.name);
+					let res = copy_file(&src_fs, &dest_fs, &task, options, &pool, &bar);
+					let mut summary = summary.lock().unwrap();
+					match res {
+						Ok(true) => {
+							summary.copied += 1;
+							summary.copied_bytes += task.src.size;
+						},
+						Ok(false) => {
+							summary.skipped += 1;
+							bar.inc(task.src.size);
+						},
+						Err(e) => {
+							bar.suspend(|| eprintln!("{}: {}", name, e));
+							summary.failed += 1;
+						},
+					}
+				}
+			});
+		}
+	});
+	bar.finish_and_clear();
+	
+	// Files left over if every worker failed to connect
+	let mut summary = summary.into_inner().unwrap();
+	summary.failed += tasks.into_inner().unwrap().len();
+	
+	// Directories last, since copying into them changes their modification times, and deepest first
+	if !options.preserve.is_empty() {
+		for dir in dirs.iter().rev() {
+			if let Err(e) = dest_fs.preserve_attributes(&dir.dest, &dir.src, options.preserve) {
+				eprintln!("Could not set attributes of {}: {}", dir.dest, e);
+				summary.failed += 1;
+			}
+		}
+	}
+	Ok(summary)
+}
+
+/// Copies a single file, returning whether it was copied or skipped.
+fn copy_file(src_fs: &HdfsConnection, dest_fs: &HdfsConnection, task: &Task, options: Options, pool: &BufferPool, bar: &ProgressBar) -> io::Result<bool> {
+	let existing = dest_fs.stat(&task.dest).ok();
+	if let Some(ref existing) = existing {
+		if !options.overwrite && existing.size == task.src.size && (!options.verify || same_checksum(src_fs, &task.src, dest_fs, existing)?) {
+			return Ok(false);
+		}
+	}
+	
+	let mut in_file = src_fs.open_read(&task.src.name)?;
+	let offset = existing.map(|existing| existing.size).filter(|len| options.resume && *len < task.src.size);
+	let mut out_file = match offset {
+		Some(offset) => {
+			in_file.seek(io::SeekFrom::Start(offset))?;
+			bar.inc(offset);
+			dest_fs.open_append(&task.dest)?
+		},
+		None => {
+			// Use the same block size, so that the checksums are comparable
+			let mut builder = dest_fs.open_create_builder(&task.dest)?;
+			builder.default_block_size(task.src.block_size as i64)?;
+			builder.build()?
+		},
+	};
+	pool.copy(&mut bar.wrap_read(in_file), &mut out_file)?;
+	out_file.close()?;
+	
+	if options.verify {
+		let copied = dest_fs.stat(&task.dest)?;
+		if !same_checksum(src_fs, &task.src, dest_fs, &copied)? {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, format!("checksum mismatch after copying to {}", task.dest)));
+		}
+	}
+	dest_fs.preserve_attributes(&task.dest, &task.src, options.preserve)?;
+	Ok(true)
+}
+
+fn same_checksum(src_fs: &HdfsConnection, src: &HdfsDirectoryEntry, dest_fs: &HdfsConnection, dest: &HdfsDirectoryEntry) -> io::Result<bool> {
+	let src_sum = checksum::file_checksum(src_fs, src)?;
+	let dest_sum = checksum::file_checksum(dest_fs, dest)?;
+	Ok(src_sum.algorithm == dest_sum.algorithm && src_sum.hex == dest_sum.hex)
+}
+
+/// Gets the last component of an HDFS path.
+fn base_name(name: &str) -> &str {
+	name.trim_end_matches('/').rsplit('/').next().unwrap_or("")
+}
@@ -0,0 +1,145 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Filters for the `find` subcommand, following the semantics of GNU `find`.
+
+use hdfs::{GlobPattern, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::time::SystemTime;
+
+/// A numeric comparison, written as `+N` (more than N), `-N` (less than N), or `N` (exactly N).
+#[derive(Debug,Clone,Copy)]
+pub enum Comparison {
+	Less(u64),
+	Exactly(u64),
+	Greater(u64),
+}
+impl Comparison {
+	fn parse(s: &str) -> Option<(Self, &str)> {
+		let (ctor, rest): (fn(u64) -> Self, &str) = if let Some(rest) = s.strip_prefix('+') {
+			(Comparison::Greater, rest)
+		} else if let Some(rest) = s.strip_prefix('-') {
+			(Comparison::Less, rest)
+		} else {
+			(Comparison::Exactly, s)
+		};
+		let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+		let value = rest[..digits].parse().ok()?;
+		Some((ctor(value), &rest[digits..]))
+	}
+	
+	pub fn matches(&self, value: u64) -> bool {
+		match *self {
+			Comparison::Less(n) => value < n,
+			Comparison::Exactly(n) => value == n,
+			Comparison::Greater(n) => value > n,
+		}
+	}
+}
+
+/// Size filter, ex. `+10M`. Sizes are rounded up to the unit before comparing.
+#[derive(Debug,Clone,Copy)]
+pub struct SizeFilter {
+	comparison: Comparison,
+	unit: u64,
+}
+impl SizeFilter {
+	/// Parses `[+-]N[ckMG]`. Without a suffix, the size is in bytes.
+	pub fn parse(s: &str) -> Result<Self, String> {
+		let invalid = || format!("invalid size: {}", s);
+		let (comparison, suffix) = Comparison::parse(s).ok_or_else(invalid)?;
+		let unit = match suffix {
+			"" | "c" => 1,
+			"k" => 1024,
+			"M" => 1024 * 1024,
+			"G" => 1024 * 1024 * 1024,
+			_ => { return Err(invalid()); },
+		};
+		Ok(Self { comparison, unit })
+	}
+	
+	pub fn matches(&self, size: u64) -> bool {
+		self.comparison.matches(size.div_ceil(self.unit))
+	}
+}
+
+/// Parses a modification age in days, `[+-]N`.
+pub fn parse_days(s: &str) -> Result<Comparison, String> {
+	match Comparison::parse(s) {
+		Some((comparison, "")) => Ok(comparison),
+		_ => Err(format!("invalid number of days: {}", s)),
+	}
+}
+
+/// Entry type filter.
+#[derive(Debug,Clone,Copy)]
+pub enum TypeFilter {
+	File,
+	Directory,
+}
+impl TypeFilter {
+	pub fn parse(s: &str) -> Result<Self, String> {
+		match s {
+			"f" => Ok(TypeFilter::File),
+			"d" => Ok(TypeFilter::Directory),
+			_ => Err(format!("invalid type: {} (expected f or d)", s)),
+		}
+	}
+}
+
+/// All of the filters given to `find`. An entry has to pass all of them to be printed.
+pub struct Filters {
+	pub name: Option<GlobPattern>,
+	pub kind: Option<TypeFilter>,
+	pub size: Option<SizeFilter>,
+	pub mtime: Option<Comparison>,
+	/// Time to measure modification ages from
+	pub now: SystemTime,
+}
+impl Filters {
+	pub fn matches(&self, entry: &HdfsDirectoryEntry) -> bool {
+		if let Some(name) = self.name.as_ref() {
+			let base = entry.name.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+			if !name.matches(base) {
+				return false;
+			}
+		}
+		match (self.kind, entry.kind) {
+			(Some(TypeFilter::File), HdfsDirectoryEntryKind::File) => {},
+			(Some(TypeFilter::Directory), HdfsDirectoryEntryKind::Directory) => {},
+			(None, _) => {},
+			_ => { return false; },
+		}
+		if let Some(size) = self.size {
+			if !size.matches(entry.size) {
+				return false;
+			}
+		}
+		if let Some(mtime) = self.mtime {
+			// Age in whole days, rounded down. Times in the future count as zero days old.
+			let age = self.now.duration_since(entry.last_modified)
+				.map(|d| d.as_secs() / 86400)
+				.unwrap_or(0);
+			if !mtime.matches(age) {
+				return false;
+			}
+		}
+		true
+	}
+}
@@ -0,0 +1,198 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Output formatting helpers, matching the output of `hdfs dfs` where possible.
+
+use hdfs::{HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Strips the scheme and authority from an entry name, ex. `hdfs://host:8020/a/b` becomes `/a/b`.
+pub fn uri_path(name: &str) -> &str {
+	let rest = match name.find(':') {
+		Some(i) if !name[..i].contains('/') => &name[i+1..],
+		_ => { return name; },
+	};
+	match rest.strip_prefix("//") {
+		Some(authority_and_path) => match authority_and_path.find('/') {
+			Some(i) => &authority_and_path[i..],
+			None => "/",
+		},
+		None => rest,
+	}
+}
+
+/// Formats permission bits like `ls -l`, ex. `drwxr-xr-x`.
+pub fn permissions_string(kind: HdfsDirectoryEntryKind, mode: u16) -> String {
+	let mut s = String::with_capacity(10);
+	s.push(match kind {
+		HdfsDirectoryEntryKind::Directory => 'd',
+		_ => '-',
+	});
+	for shift in &[6, 3, 0] {
+		let bits = (mode >> shift) & 0o7;
+		s.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+		s.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+		s.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+	}
+	if mode & 0o1000 != 0 {
+		// Sticky bit
+		let last = if mode & 0o1 != 0 { 't' } else { 'T' };
+		s.pop();
+		s.push(last);
+	}
+	s
+}
+
+/// Formats a byte count with binary prefixes, like `hdfs dfs -ls -h`, ex. `1.5 K`.
+pub fn human_size(size: u64) -> String {
+	const PREFIXES: &[&str] = &["K", "M", "G", "T", "P", "E"];
+	if size < 1024 {
+		return size.to_string();
+	}
+	let mut value = size as f64 / 1024.0;
+	let mut prefix = 0;
+	while value >= 1024.0 && prefix < PREFIXES.len() - 1 {
+		value /= 1024.0;
+		prefix += 1;
+	}
+	format!("{:.1} {}", value, PREFIXES[prefix])
+}
+
+/// Formats a size, human-readable or not.
+pub fn size_string(size: u64, human: bool) -> String {
+	if human { human_size(size) } else { size.to_string() }
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	// From Howard Hinnant's date algorithms
+	let z = days + 719468;
+	let era = if z >= 0 { z } else { z - 146096 } / 146097;
+	let doe = z - era * 146097;
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+	let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+	(year, month, day)
+}
+
+/// Seconds since the Unix epoch. Negative for times before it.
+pub fn epoch_secs(time: SystemTime) -> i64 {
+	match time.duration_since(UNIX_EPOCH) {
+		Ok(d) => d.as_secs() as i64,
+		Err(e) => -(e.duration().as_secs() as i64),
+	}
+}
+
+/// Formats a time as `YYYY-MM-DD HH:MM` in UTC, like `hdfs dfs -ls`.
+pub fn time_string(time: SystemTime) -> String {
+	let secs = epoch_secs(time);
+	let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+	let secs_of_day = secs.rem_euclid(86400);
+	format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, secs_of_day / 3600, (secs_of_day / 60) % 60)
+}
+
+/// Formats a time as `YYYY-MM-DD HH:MM:SS` in UTC, like `hdfs dfs -stat`.
+pub fn time_string_secs(time: SystemTime) -> String {
+	let secs = epoch_secs(time);
+	format!("{}:{:02}", time_string(time), secs.rem_euclid(60))
+}
+
+/// Formats an entry like a line of `hdfs dfs -ls`.
+pub fn long_listing(entry: &HdfsDirectoryEntry, human: bool) -> String {
+	let replication = match entry.kind {
+		HdfsDirectoryEntryKind::Directory => "-".to_string(),
+		_ => entry.replication.to_string(),
+	};
+	format!("{} {:>3} {} {} {:>10} {} {}",
+		permissions_string(entry.kind, entry.permissions),
+		replication,
+		entry.owner,
+		entry.group,
+		size_string(entry.size, human),
+		time_string(entry.last_modified),
+		uri_path(&entry.name),
+	)
+}
+
+/// Formats an entry according to a `hdfs dfs -stat`-style format string.
+/// 
+/// Supports `%a` (octal permissions), `%A` (symbolic permissions), `%b` (size), `%F` (type),
+/// `%g` (group), `%n` (name), `%o` (block size), `%r` (replication), `%u` (owner),
+/// `%x`/`%X` (access time), `%y`/`%Y` (modification time), and `%%`. Uppercase time specifiers are
+/// milliseconds since the epoch.
+pub fn stat_format(entry: &HdfsDirectoryEntry, format: &str) -> String {
+	let mut out = String::new();
+	let mut chars = format.chars();
+	while let Some(c) = chars.next() {
+		if c != '%' {
+			out.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('a') => out.push_str(&format!("{:o}", entry.permissions)),
+			Some('A') => out.push_str(&permissions_string(entry.kind, entry.permissions)[1..]),
+			Some('b') => out.push_str(&entry.size.to_string()),
+			Some('F') => out.push_str(match entry.kind {
+				HdfsDirectoryEntryKind::Directory => "directory",
+				HdfsDirectoryEntryKind::File if entry.size == 0 => "regular empty file",
+				HdfsDirectoryEntryKind::File => "regular file",
+				HdfsDirectoryEntryKind::Unrecognized(_) => "unknown",
+			}),
+			Some('g') => out.push_str(&entry.group),
+			Some('n') => out.push_str(uri_path(&entry.name).rsplit('/').next().unwrap_or("")),
+			Some('o') => out.push_str(&entry.block_size.to_string()),
+			Some('r') => out.push_str(&entry.replication.to_string()),
+			Some('u') => out.push_str(&entry.owner),
+			Some('x') => out.push_str(&time_string_secs(entry.last_access)),
+			Some('X') => out.push_str(&(epoch_secs(entry.last_access) * 1000).to_string()),
+			Some('y') => out.push_str(&time_string_secs(entry.last_modified)),
+			Some('Y') => out.push_str(&(epoch_secs(entry.last_modified) * 1000).to_string()),
+			Some('%') => out.push('%'),
+			Some(other) => { out.push('%'); out.push(other); },
+			None => out.push('%'),
+		}
+	}
+	out
+}
+
+/// Converts an entry to a JSON object, for `--json` output. Times are milliseconds since the epoch.
+pub fn entry_json(entry: &HdfsDirectoryEntry) -> serde_json::Value {
+	json!({
+		"path": uri_path(&entry.name),
+		"name": entry.name,
+		"type": match entry.kind {
+			HdfsDirectoryEntryKind::File => "file",
+			HdfsDirectoryEntryKind::Directory => "directory",
+			HdfsDirectoryEntryKind::Unrecognized(_) => "unknown",
+		},
+		"size": entry.size,
+		"replication": entry.replication,
+		"block_size": entry.block_size,
+		"owner": entry.owner,
+		"group": entry.group,
+		"permission": format!("{:o}", entry.permissions),
+		"modification_time": epoch_secs(entry.last_modified) * 1000,
+		"access_time": epoch_secs(entry.last_access) * 1000,
+	})
+}
@@ -0,0 +1,1418 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+
+//! `dfs`: a lightweight replacement for `hdfs dfs`, without the JVM startup time of the Java client.
+//! 
+//! Exit codes:
+//! 
+//! * 0: Success
+//! * 1: The operation failed
+//! * 2: Invalid command line arguments
+//! * 3: Could not connect to HDFS
+//! 
+//! Remote path arguments are expanded as Hadoop-style glob patterns (see `hdfs::GlobPattern`), except for
+//! destinations that may not exist yet. `--no-glob` turns this off.
+
+mod checksum;
+mod config;
+mod copy;
+mod find;
+mod format;
+mod mode;
+mod progress;
+mod shell;
+mod sync;
+mod tail;
+mod transfer;
+mod watch;
+
+use hdfs::*;
+use serde_json::json;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use structopt::StructOpt;
+use structopt::clap::{AppSettings, ArgGroup, Shell};
+
+/// Exit code when an operation fails
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid command line arguments
+const EXIT_USAGE: i32 = 2;
+/// Exit code when the connection to HDFS could not be established
+const EXIT_CONNECT: i32 = 3;
+
+/// Error returned from `real_main`, carrying the exit code to use.
+struct Failure {
+	code: i32,
+	message: String,
+}
+impl From<String> for Failure {
+	fn from(message: String) -> Self {
+		Self { code: EXIT_FAILURE, message }
+	}
+}
+
+#[derive(Debug,StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+enum Subcommand {
+	/// Lists a directory
+	Ls {
+		/// Use a long listing format, like `hdfs dfs -ls`
+		#[structopt(short="l")]
+		long: bool,
+		/// Print sizes in a human-readable format
+		#[structopt(short="h")]
+		human: bool,
+		/// Path to list
+		dir: String,
+	},
+	/// Shows information about files and directories
+	Stat {
+		/// Format string, as in `hdfs dfs -stat`. Supports %a, %A, %b, %F, %g, %n, %o, %r, %u, %x, %X, %y, and %Y.
+		#[structopt(short="c", long="format")]
+		format: Option<String>,
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Downloads a file, or a directory with -r
+	/// 
+	/// If the destination is an existing directory, the source is copied into it. Without a destination, a file is
+	/// written to stdout.
+	Get {
+		#[structopt(flatten)]
+		options: TransferArgs,
+		path: String,
+		dest: Option<PathBuf>,
+	},
+	/// Uploads a file, or a directory with -r
+	/// 
+	/// If the destination is an existing directory, the source is copied into it. Without a source, stdin is
+	/// uploaded.
+	#[structopt(setting=AppSettings::AllowMissingPositional)]
+	Put {
+		#[structopt(flatten)]
+		options: TransferArgs,
+		src: Option<PathBuf>,
+		dest: String,
+	},
+	/// Appends a local file, or stdin if `-`, to the end of a file
+	#[structopt(alias = "appendToFile")]
+	Append {
+		src: String,
+		dest: String,
+	},
+	/// Truncates a file to a given length
+	Truncate {
+		/// Wait for the namenode to finish recovering the last block, if it has to
+		#[structopt(short="w")]
+		wait: bool,
+		/// With -w, how long to wait before giving up, in seconds
+		#[structopt(long="timeout", default_value="300")]
+		timeout: f64,
+		/// New length, in bytes
+		size: u64,
+		path: String,
+	},
+	/// Copies files within or between clusters, like a lightweight `distcp`
+	/// 
+	/// Paths may be full URIs, ex. `hdfs://other-cluster:8020/path`, to copy between clusters. Existing files with
+	/// the same size and checksum are skipped.
+	Cp {
+		/// Number of files to copy at once
+		#[structopt(long="parallel", default_value="1")]
+		parallel: usize,
+		/// Don't compare checksums. Existing files with the same size are skipped.
+		#[structopt(long="skip-crc-check")]
+		skip_crc_check: bool,
+		/// Copy all files, even if they already exist at the destination
+		#[structopt(long="overwrite")]
+		overwrite: bool,
+		/// Continue partially copied files, by appending the rest of the source to them
+		#[structopt(long="resume")]
+		resume: bool,
+		/// Attributes to preserve, like `distcp -p`: any of r (replication), b (block size), u (user), g (group),
+		/// p (permissions), and t (times)
+		#[structopt(short="p", long="preserve", value_name="rbugpt")]
+		preserve: Option<Preserve>,
+		src: String,
+		dest: String,
+	},
+	/// Makes a destination directory a copy of a source directory, copying only changed files
+	/// 
+	/// Either path may be on another cluster, given as a full URI, or on the local filesystem, given as a `file:`
+	/// URI. Files are compared by size and modification time.
+	Sync {
+		/// Compare files by checksum instead of modification time
+		#[structopt(long="checksum")]
+		checksum: bool,
+		/// Delete files in the destination that aren't in the source
+		#[structopt(long="delete")]
+		delete: bool,
+		/// Only print what would be done
+		#[structopt(short="n", long="dry-run")]
+		dry_run: bool,
+		/// Attributes to preserve, like `distcp -p`: any of r (replication), b (block size), u (user), g (group),
+		/// p (permissions), and t (times)
+		#[structopt(short="p", long="preserve", value_name="rbugpt")]
+		preserve: Option<Preserve>,
+		src: String,
+		dest: String,
+	},
+	/// Renames a file
+	Mv {
+		src: String,
+		dest: String,
+	},
+	/// Removes a file
+	Rm {
+		path: String,
+		#[structopt(short="r")]
+		recursive: bool,
+		/// Move to the user's trash directory instead of deleting immediately
+		#[structopt(long="trash")]
+		trash: bool,
+	},
+	/// Creates directories
+	Mkdir {
+		/// Create missing parent directories, and don't fail if the directory already exists
+		#[structopt(short="p")]
+		parents: bool,
+		/// Permissions of the created directories, in octal. Parents created by -p get the default permissions.
+		#[structopt(short="m", parse(try_from_str = parse_octal_mode))]
+		mode: Option<u16>,
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Shows the space used by files and directories
+	Du {
+		/// Show a summary for the path itself, rather than each entry in it
+		#[structopt(short="s")]
+		summary: bool,
+		/// Print sizes in a human-readable format
+		#[structopt(short="h")]
+		human: bool,
+		path: String,
+	},
+	/// Shows the capacity and usage of the filesystem
+	Df {
+		/// Print sizes in a human-readable format
+		#[structopt(short="h")]
+		human: bool,
+	},
+	/// Changes permissions of files
+	Chmod {
+		/// Apply to directories recursively
+		#[structopt(short="R")]
+		recursive: bool,
+		/// Mode, in octal (ex. `755`) or symbolic (ex. `u+rwx,g-w`) form
+		mode: mode::Mode,
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Changes the owner and optionally the group of files
+	Chown {
+		/// Apply to directories recursively
+		#[structopt(short="R")]
+		recursive: bool,
+		/// `OWNER`, `OWNER:GROUP`, or `:GROUP`
+		owner: String,
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Changes the group of files
+	Chgrp {
+		/// Apply to directories recursively
+		#[structopt(short="R")]
+		recursive: bool,
+		group: String,
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Prints the beginning of a file. Defaults to the first kilobyte.
+	Head {
+		/// Number of bytes to print
+		#[structopt(short="c")]
+		bytes: Option<u64>,
+		/// Number of lines to print
+		#[structopt(short="n", conflicts_with="bytes")]
+		lines: Option<u64>,
+		path: String,
+	},
+	/// Prints the end of a file. Defaults to the last kilobyte.
+	Tail {
+		/// Number of bytes to print
+		#[structopt(short="c")]
+		bytes: Option<u64>,
+		/// Number of lines to print
+		#[structopt(short="n", conflicts_with="bytes")]
+		lines: Option<u64>,
+		/// Keep printing data as it is appended to the file
+		#[structopt(short="f")]
+		follow: bool,
+		/// With -f, how often to check for new data, in seconds
+		#[structopt(short="s", default_value="1")]
+		interval: f64,
+		path: String,
+	},
+	/// Prints files to stdout
+	Cat {
+		/// Files to print. Glob patterns are expanded.
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Concatenates the files in a directory into a single local file, in sorted order
+	/// 
+	/// Files with names starting with `_` or `.`, such as `_SUCCESS`, are skipped.
+	Getmerge {
+		/// Add a newline after each file
+		#[structopt(long="nl")]
+		newline: bool,
+		/// Skip empty files (only matters with --nl)
+		#[structopt(long="skip-empty-file")]
+		skip_empty: bool,
+		/// Directory to merge. Glob patterns are expanded.
+		src: String,
+		dest: PathBuf,
+	},
+	/// Checks a condition on a path, exiting with status 0 if true and 1 if false
+	#[structopt(group = ArgGroup::with_name("condition").required(true))]
+	Test {
+		/// Path exists
+		#[structopt(short="e", group="condition")]
+		exists: bool,
+		/// Path is a directory
+		#[structopt(short="d", group="condition")]
+		directory: bool,
+		/// Path is a file
+		#[structopt(short="f", group="condition")]
+		file: bool,
+		/// Path is not empty
+		#[structopt(short="s", group="condition")]
+		non_empty: bool,
+		/// File has a length of zero
+		#[structopt(short="z", group="condition")]
+		zero: bool,
+		path: String,
+	},
+	/// Counts the directories, files, and bytes under paths
+	/// 
+	/// Output columns are DIR_COUNT, FILE_COUNT, CONTENT_SIZE, and PATHNAME. Quotas are not available through
+	/// libhdfs, so `-q` is not supported.
+	Count {
+		/// Print sizes in a human-readable format
+		#[structopt(short="h")]
+		human: bool,
+		/// Print a header line
+		#[structopt(short="v")]
+		header: bool,
+		/// Glob patterns are expanded
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Prints the MD5-of-MD5-of-CRC32C checksum of files, as `hdfs dfs -checksum` does
+	/// 
+	/// The checksum is computed by reading the whole file, assuming the cluster's default checksum settings
+	/// (CRC32C over 512 byte chunks).
+	Checksum {
+		/// Glob patterns are expanded
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Shows the blocks of files and the datanodes hosting them, flagging under-replicated blocks
+	/// 
+	/// Directories are searched recursively.
+	Blocks {
+		/// Print sizes in a human-readable format
+		#[structopt(short="h")]
+		human: bool,
+		/// Glob patterns are expanded
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Recursively searches for files and directories matching all of the given filters
+	Find {
+		/// Only match entries whose name matches a glob pattern
+		#[structopt(long="name")]
+		name: Option<String>,
+		/// Only match files (`f`) or directories (`d`)
+		#[structopt(long="type", parse(try_from_str = find::TypeFilter::parse))]
+		kind: Option<find::TypeFilter>,
+		/// Only match entries with a size of more (`+N`), less (`-N`), or exactly (`N`) N bytes, or N units with a
+		/// `k`, `M`, or `G` suffix
+		#[structopt(long="size", parse(try_from_str = find::SizeFilter::parse), allow_hyphen_values = true)]
+		size: Option<find::SizeFilter>,
+		/// Only match entries last modified more (`+N`), less (`-N`), or exactly (`N`) N days ago
+		#[structopt(long="mtime", parse(try_from_str = find::parse_days), allow_hyphen_values = true)]
+		mtime: Option<find::Comparison>,
+		/// Descend at most this many levels below the starting paths
+		#[structopt(long="maxdepth")]
+		max_depth: Option<usize>,
+		/// Separate results with a null character instead of a newline, for `xargs -0`
+		#[structopt(long="print0")]
+		print0: bool,
+		/// Paths to search. Glob patterns are expanded.
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Polls a directory, printing entries as they're created, modified, or deleted
+	Watch {
+		/// How often to check for changes, in seconds
+		#[structopt(short="s", default_value="5")]
+		interval: f64,
+		/// Watch subdirectories too
+		#[structopt(short="r")]
+		recursive: bool,
+		/// Exit once an entry with this name exists, ex. `_SUCCESS`
+		#[structopt(long="until")]
+		until: Option<String>,
+		path: String,
+	},
+	/// Creates, deletes, lists, and compares snapshots of snapshottable directories
+	Snapshot {
+		#[structopt(subcommand)]
+		command: SnapshotCommand,
+	},
+	/// Lists, restores, and expunges trashed files
+	/// 
+	/// Uses the same layout as Hadoop's trash, in /user/<user>/.Trash.
+	Trash {
+		#[structopt(subcommand)]
+		command: TrashCommand,
+	},
+	/// Starts an interactive shell, which keeps the connection open between commands
+	/// 
+	/// Supports all subcommands, plus `cd`, `pwd`, `help`, and `exit`. Remote paths can be tab-completed.
+	Shell,
+	/// Prints a shell completion script to stdout
+	Completions {
+		/// Shell to generate completions for: bash, zsh, fish, powershell, or elvish
+		shell: Shell,
+	},
+}
+
+#[derive(Debug,StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+enum SnapshotCommand {
+	/// Creates a snapshot, named after the current time if no name is given, and prints its path
+	Create {
+		dir: String,
+		name: Option<String>,
+	},
+	/// Deletes a snapshot
+	Delete {
+		dir: String,
+		name: String,
+	},
+	/// Lists the snapshots of a directory
+	List {
+		dir: String,
+	},
+	/// Shows what changed between two snapshots
+	/// 
+	/// Prints `+` for created, `-` for deleted, and `M` for modified entries. Use `.` for the current state.
+	Diff {
+		dir: String,
+		from: String,
+		#[structopt(default_value=".")]
+		to: String,
+	},
+}
+
+#[derive(Debug,StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+enum TrashCommand {
+	/// Lists trashed files, with their checkpoints and original paths
+	Ls,
+	/// Moves trashed files back to where they were deleted from
+	/// 
+	/// Paths can be either the original path or the path in the trash. If an original path was trashed more than
+	/// once, the newest is restored.
+	Restore {
+		#[structopt(required = true)]
+		paths: Vec<String>,
+	},
+	/// Checkpoints the trash and deletes old checkpoints
+	Expunge {
+		/// Delete checkpoints older than this many minutes
+		#[structopt(long="older-than", default_value="1440")]
+		older_than: f64,
+		/// Delete everything in the trash, including files trashed since the last checkpoint
+		#[structopt(long="immediate", conflicts_with="older-than")]
+		immediate: bool,
+	},
+}
+
+// Options for `get` and `put`
+#[derive(Debug,StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct TransferArgs {
+	/// Copy directories recursively
+	#[structopt(short="r")]
+	recursive: bool,
+	/// Preserve modification times, access times, and permissions
+	#[structopt(short="p")]
+	preserve: bool,
+	/// Skip files that already exist at the destination with the same size and a modification time at least
+	/// as new as the source
+	#[structopt(long="update")]
+	update: bool,
+	/// Continue partially copied files, by appending the rest of the source to them
+	#[structopt(long="resume")]
+	resume: bool,
+}
+impl TransferArgs {
+	pub fn options(&self, progress: bool) -> transfer::Options {
+		transfer::Options {
+			recursive: self.recursive,
+			preserve: self.preserve,
+			update: self.update,
+			resume: self.resume,
+			progress,
+		}
+	}
+}
+
+// Options that apply to all subcommands
+#[derive(Debug,StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct GlobalArgs {
+	/// Nameserver URL to connect to. Overrides the profile's name node.
+	#[structopt(short="N")]
+	pub name_server: Option<String>,
+	/// Username to connect as. Overrides the profile's user.
+	#[structopt(short="U")]
+	pub username: Option<String>,
+	/// Connection profile to use, from ~/.config/hdfs-rs/config.toml
+	#[structopt(long="profile", env="DFS_PROFILE")]
+	pub profile_name: Option<String>,
+	/// Settings from the selected profile
+	#[structopt(skip)]
+	pub profile: config::Profile,
+	/// Print one JSON object per line instead of text, for ls, stat, du, count, df, blocks, and snapshot list
+	#[structopt(long="json", global = true)]
+	pub json: bool,
+	/// Don't show progress bars for transfers
+	#[structopt(long="no-progress")]
+	pub no_progress: bool,
+	/// Take path arguments literally, rather than expanding glob patterns in them
+	#[structopt(long="no-glob", global = true)]
+	pub no_glob: bool,
+}
+impl GlobalArgs {
+	/// Name node to connect to by default, from the command line or the profile.
+	pub fn default_name_node(&self) -> Option<&str> {
+		self.name_server.as_deref().or(self.profile.name_node.as_deref())
+	}
+	
+	/// User whose home and trash directories to use: from the command line, the profile, or the environment.
+	pub fn user_name(&self) -> Result<String, Failure> {
+		self.username.clone()
+			.or_else(|| self.profile.user.clone())
+			.or_else(|| env::var("HADOOP_USER_NAME").ok())
+			.or_else(|| env::var("USER").ok())
+			.filter(|name| !name.is_empty())
+			.ok_or_else(|| Failure { code: EXIT_USAGE, message: "Could not determine the user name; pass -U".into() })
+	}
+	
+	pub fn connect(&self) -> io::Result<HdfsConnection> {
+		self.connect_to(self.default_name_node())
+	}
+	
+	/// Connects to a specific name node, rather than the default one.
+	/// 
+	/// Every connection gets its own filesystem instance, since `cp` and `sync` connect more than once and
+	/// dropping one connection to a cached instance would close it for the others.
+	pub fn connect_to(&self, name_node: Option<&str>) -> io::Result<HdfsConnection> {
+		let mut builder = HdfsConnection::builder();
+		builder.name_node(name_node);
+		if let Some(name) = self.username.as_ref().or(self.profile.user.as_ref()) {
+			builder.user_name(name);
+		}
+		if let Some(path) = self.profile.kerb_ticket_cache_path.as_ref() {
+			builder.kerb_ticket_cache_path(path);
+		}
+		for (key, value) in self.profile.conf.iter() {
+			builder.conf_set(key, value)?;
+		}
+		builder.connect_isolated()
+	}
+}
+
+#[derive(Debug,StructOpt)]
+#[structopt(name = "dfs", rename_all = "kebab-case")]
+#[structopt(after_help = "EXIT CODES:\n    0    Success\n    1    The operation failed\n    2    Invalid command line arguments\n    3    Could not connect to HDFS")]
+struct Args {
+	#[structopt(flatten)]
+	pub global: GlobalArgs,
+	#[structopt(subcommand)]
+	pub subcommand: Subcommand,
+}
+
+fn main() {
+	if let Err(err) = real_main() {
+		if !err.message.is_empty() {
+			eprintln!("{}", err.message);
+		}
+		process::exit(err.code);
+	}
+}
+
+fn real_main() -> Result<(), Failure> {
+	let app = Args::clap();
+	let matches = match app.get_matches_safe() {
+		Ok(matches) => matches,
+		// --help and --version are reported as errors but are successful
+		Err(e) if !e.use_stderr() => e.exit(),
+		Err(e) => { return Err(Failure { code: EXIT_USAGE, message: e.message }); },
+	};
+	let Args { global: mut args, subcommand } = Args::from_clap(&matches);
+	
+	if let Subcommand::Completions { shell } = subcommand {
+		Args::clap().gen_completions_to("dfs", shell, &mut io::stdout());
+		return Ok(());
+	}
+	
+	args.profile = config::Config::load()
+		.and_then(|config| config.profile(args.profile_name.as_deref()))
+		.map_err(|message| Failure { code: EXIT_USAGE, message })?;
+	load_libhdfs()
+		.map_err(|e| Failure { code: EXIT_CONNECT, message: e.to_string() })?;
+	// Without HADOOP_HOME, leave it to libhdfs to complain about the classpath
+	let _ = init_classpath();
+	let fs = args.connect()
+		.map_err(|e| Failure { code: EXIT_CONNECT, message: format!("Could not connect to hdfs: {}", e) })?;
+	
+	if let Subcommand::Shell = subcommand {
+		return shell::run(&args, &fs);
+	}
+	run(&args, &fs, subcommand)
+}
+
+/// Runs a subcommand.
+fn run(args: &GlobalArgs, fs: &HdfsConnection, subcommand: Subcommand) -> Result<(), Failure> {
+	match subcommand {
+		Subcommand::Ls { long, human, dir } => {
+			for dir in expand_paths(args, fs, &dir)?.iter() {
+				let entries = fs.list_dir(dir)
+					.map_err(|e| format!("Could not list directory: {}", e))?;
+				
+				if args.json {
+					for entry in entries.iter() {
+						println!("{}", format::entry_json(entry));
+					}
+				} else if long {
+					// Listing a file returns just the file, which is printed without a count
+					let kind = fs.stat(dir)
+						.map_err(|e| format!("Could not stat {}: {}", dir, e))?
+						.kind;
+					if !matches!(kind, HdfsDirectoryEntryKind::File) {
+						println!("Found {} items", entries.len());
+					}
+					for entry in entries.iter() {
+						println!("{}", format::long_listing(entry, human));
+					}
+				} else {
+					for entry in entries.into_iter() {
+						println!("{:<80} {:>10} {:>10} {:>10}",
+							entry.name,
+							format::size_string(entry.size, human),
+							entry.owner,
+							entry.group,
+						);
+					}
+				}
+			}
+		},
+		Subcommand::Stat { format, paths } => {
+			for pattern in paths.iter() {
+				for entry in expand_glob(args, fs, pattern)?.into_iter() {
+					if args.json {
+						println!("{}", format::entry_json(&entry));
+						continue;
+					}
+					match format.as_ref() {
+						Some(fmt) => println!("{}", format::stat_format(&entry, fmt)),
+						None => {
+							println!("  File: {}", format::uri_path(&entry.name));
+							println!("{}", format::stat_format(&entry, concat!(
+								"  Type: %F\n",
+								"  Size: %b\tBlock size: %o\tReplication: %r\n",
+								" Perms: (%a/%A)\tOwner: %u\tGroup: %g\n",
+								"Modify: %y\n",
+								"Access: %x",
+							)));
+						},
+					}
+				}
+			}
+		},
+		Subcommand::Get { options, path, dest } => {
+			let paths = expand_paths(args, fs, &path)?;
+			match dest {
+				Some(dest) => {
+					if paths.len() > 1 && !dest.is_dir() {
+						return Err(format!("{}: Not a directory, and the source matches more than one path", dest.display()).into());
+					}
+					let mut transfer = transfer::Transfer::new(&fs, options.options(!args.no_progress));
+					for path in paths.iter() {
+						transfer.get(path, &dest);
+					}
+					transfer.finish()?;
+				},
+				None if options.recursive => {
+					return Err(Failure { code: EXIT_USAGE, message: "A destination is required with -r".into() });
+				},
+				None => {
+					let stdout = io::stdout();
+					let mut out_file = stdout.lock();
+					for path in paths.iter() {
+						let mut in_file = fs.open_read(path)
+							.map_err(|e| format!("Could not open input file: {}", e))?;
+						io::copy(&mut in_file, &mut out_file)
+							.map_err(|e| format!("Could not copy data: {}", e))?;
+					}
+					out_file.flush()
+						.map_err(|e| format!("Could not copy data: {}", e))?;
+				},
+			}
+		},
+		Subcommand::Put { options, src, dest } => {
+			match src {
+				Some(src) => {
+					let mut transfer = transfer::Transfer::new(&fs, options.options(!args.no_progress));
+					transfer.put(&src, &dest);
+					transfer.finish()?;
+				},
+				None => {
+					let mut out_file = fs.open_create(&dest)
+						.map_err(|e| format!("Could not open output file: {}", e))?;
+					let stdin = io::stdin();
+					io::copy(&mut stdin.lock(), &mut out_file)
+						.map_err(|e| format!("Could not copy data: {}", e))?;
+					out_file.close()
+						.map_err(|e| format!("Could not copy data: {}", e))?;
+				},
+			}
+		},
+		Subcommand::Append { src, dest } => {
+			let stdin = io::stdin();
+			let mut in_file: Box<dyn Read> = if src == "-" {
+				Box::new(stdin.lock())
+			} else {
+				Box::new(File::open(&src)
+					.map_err(|e| format!("Could not open input file: {}", e))?
+				)
+			};
+			
+			let mut out_file = fs.open_append(&dest)
+				.map_err(|e| format!("Could not open output file: {}", e))?;
+			io::copy(&mut in_file, &mut out_file)
+				.map_err(|e| format!("Could not copy data: {}", e))?;
+			out_file.close()
+				.map_err(|e| format!("Could not copy data: {}", e))?;
+		},
+		Subcommand::Truncate { wait, timeout, size, path } => {
+			if !timeout.is_finite() || timeout <= 0.0 {
+				return Err(Failure { code: EXIT_USAGE, message: "Timeout must be positive".into() });
+			}
+			for path in expand_paths(args, fs, &path)?.iter() {
+				let done = fs.truncate(path, size as i64)
+					.map_err(|e| format!("Could not truncate {}: {}", format::uri_path(path), e))?;
+				if !done {
+					if !wait {
+						println!("Truncating {} to length {}. Wait for block recovery to complete before further updating this file.", format::uri_path(path), size);
+					} else {
+						// libhdfs can't ask whether the file is closed, but the reported length of a file excludes its
+						// last block until recovery finishes.
+						println!("Waiting for {} to be truncated to length {}...", format::uri_path(path), size);
+						let deadline = Instant::now() + Duration::from_secs_f64(timeout);
+						loop {
+							let entry = fs.stat(path)
+								.map_err(|e| format!("Could not stat {}: {}", format::uri_path(path), e))?;
+							if entry.size == size {
+								break;
+							}
+							// Recovery only ever shows less, so someone else has written to the file since
+							if entry.size > size {
+								return Err(format!("Could not wait for {} to be truncated: its length is now {}, past {}",
+									format::uri_path(path), entry.size, size).into());
+							}
+							if Instant::now() >= deadline {
+								return Err(format!("Timed out waiting for {} to be truncated to length {}; its length is {}",
+									format::uri_path(path), size, entry.size).into());
+							}
+							thread::sleep(Duration::from_secs(1));
+						}
+						println!("Truncated {} to length {}", format::uri_path(path), size);
+					}
+				}
+			}
+		},
+		Subcommand::Cp { parallel, skip_crc_check, overwrite, resume, preserve, ref src, ref dest } => {
+			let options = copy::Options {
+				parallel,
+				verify: !skip_crc_check,
+				overwrite,
+				resume,
+				preserve: preserve.unwrap_or_default(),
+				progress: !args.no_progress,
+			};
+			let src_cluster = cluster_of(src).or(args.default_name_node());
+			let dest_cluster = cluster_of(dest).or(args.default_name_node());
+			let src_fs = args.connect_to(src_cluster)
+				.map_err(|e| Failure { code: EXIT_CONNECT, message: format!("Could not connect to source cluster: {}", e) })?;
+			let srcs = expand_paths(args, &src_fs, src)?;
+			drop(src_fs);
+			let mut summary = copy::Summary::default();
+			for src in srcs.iter() {
+				let copied = copy::copy(
+					|| args.connect_to(src_cluster),
+					|| args.connect_to(dest_cluster),
+					src,
+					dest,
+					options,
+				)?;
+				summary.copied += copied.copied;
+				summary.copied_bytes += copied.copied_bytes;
+				summary.skipped += copied.skipped;
+				summary.failed += copied.failed;
+			}
+			println!("Copied {} files ({} bytes), skipped {}, failed {}",
+				summary.copied,
+				summary.copied_bytes,
+				summary.skipped,
+				summary.failed,
+			);
+			if summary.failed > 0 {
+				return Err(format!("Failed to copy {} files", summary.failed).into());
+			}
+		},
+		Subcommand::Sync { checksum, delete, dry_run, preserve, ref src, ref dest } => {
+			let options = sync::Options { checksum, delete, dry_run, preserve: preserve.unwrap_or_default() };
+			let connect = |path| args.connect_to(cluster_of(path).or(args.default_name_node()))
+				.map_err(|e| Failure { code: EXIT_CONNECT, message: format!("Could not connect to hdfs: {}", e) });
+			let src_fs = connect(src)?;
+			let dest_fs = connect(dest)?;
+			let src = expand_one(args, &src_fs, src)?;
+			let summary = sync::sync(&src_fs, &dest_fs, &src, dest, options)?;
+			println!("{}Copied {} files ({} bytes), created {} directories, deleted {}, {} unchanged, {} failed",
+				if dry_run { "(dry run) " } else { "" },
+				summary.copied,
+				summary.copied_bytes,
+				summary.created_dirs,
+				summary.deleted,
+				summary.unchanged,
+				summary.failed,
+			);
+			if summary.failed > 0 {
+				return Err(format!("Failed with {} errors", summary.failed).into());
+			}
+		},
+		Subcommand::Mv { src, dest } => {
+			let srcs = expand_paths(args, fs, &src)?;
+			// Renaming onto an existing directory moves the source into it
+			if srcs.len() > 1 && !fs.is_dir(&dest).unwrap_or(false) {
+				return Err(format!("{}: Not a directory, and the source matches more than one path", dest).into());
+			}
+			for src in srcs.iter() {
+				fs.rename(src, &dest)
+					.map_err(|e| format!("Could not rename {}: {}", format::uri_path(src), e))?;
+			}
+		},
+		Subcommand::Rm { path, recursive, trash } => {
+			let paths = expand_paths(args, fs, &path)?;
+			let user = if trash { Some(args.user_name()?) } else { None };
+			for path in paths.iter() {
+				if let Some(user) = user.as_ref() {
+					let dest = Trash::new(fs, user).move_to_trash(path)
+						.map_err(|e| format!("Could not move {} to trash: {}", format::uri_path(path), e))?;
+					println!("Moved {} to trash at {}", format::uri_path(path), dest);
+				} else {
+					fs.delete(path, recursive)
+						.map_err(|e| format!("Could not delete {}: {}", format::uri_path(path), e))?;
+				}
+			}
+		},
+		Subcommand::Mkdir { parents, mode, paths } => {
+			for path in paths.iter() {
+				if !parents {
+					if fs.exists(path).map_err(|e| format!("Could not create {}: {}", path, e))? {
+						return Err(format!("Could not create {}: File exists", path).into());
+					}
+					if let Some(parent) = parent_path(path) {
+						if !fs.exists(parent).map_err(|e| format!("Could not create {}: {}", path, e))? {
+							return Err(format!("Could not create {}: Parent directory {} does not exist", path, parent).into());
+						}
+					}
+				}
+				// With a mode, the directory is created under a temporary name and renamed once it has it
+				let res = match mode {
+					Some(mode) => fs.create_dir_with_mode(path, mode),
+					None => fs.create_dir(path),
+				};
+				res.map_err(|e| format!("Could not create {}: {}", path, e))?;
+			}
+		},
+		Subcommand::Du { summary, human, path } => {
+			let mut entries = vec![];
+			for path in expand_paths(args, fs, &path)?.iter() {
+				if summary {
+					entries.push(fs.stat(path));
+				} else {
+					entries.extend(fs.list_dir(path)
+						.map_err(|e| format!("Could not list directory: {}", e))?
+						.into_iter()
+						.map(Ok));
+				}
+			}
+			for entry in entries.into_iter() {
+				let entry = entry.map_err(|e| format!("Could not stat {}: {}", path, e))?;
+				let name = format::uri_path(&entry.name);
+				let summary = fs.content_summary(&entry.name)
+					.map_err(|e| format!("Could not compute usage of {}: {}", name, e))?;
+				if args.json {
+					println!("{}", json!({
+						"path": name,
+						"length": summary.length,
+						"space_consumed": summary.space_consumed,
+					}));
+					continue;
+				}
+				println!("{:<10} {:<10} {}",
+					format::size_string(summary.length, human),
+					format::size_string(summary.space_consumed, human),
+					name,
+				);
+			}
+		},
+		Subcommand::Df { human } => {
+			let capacity = fs.capacity()
+				.map_err(|e| format!("Could not get capacity: {}", e))?;
+			let used = fs.used()
+				.map_err(|e| format!("Could not get used space: {}", e))?;
+			let root = fs.stat("/")
+				.map_err(|e| format!("Could not stat /: {}", e))?;
+			let name = root.name.trim_end_matches('/');
+			let percent = if capacity == 0 { 0.0 } else { used as f64 * 100.0 / capacity as f64 };
+			if args.json {
+				println!("{}", json!({
+					"filesystem": if name.is_empty() { "/" } else { name },
+					"capacity": capacity,
+					"used": used,
+					"remaining": capacity.saturating_sub(used),
+				}));
+				return Ok(());
+			}
+			println!("{:<30} {:>12} {:>12} {:>12} {:>5}", "Filesystem", "Size", "Used", "Available", "Use%");
+			println!("{:<30} {:>12} {:>12} {:>12} {:>4.0}%",
+				if name.is_empty() { "/" } else { name },
+				format::size_string(capacity, human),
+				format::size_string(used, human),
+				format::size_string(capacity.saturating_sub(used), human),
+				percent,
+			);
+		},
+		Subcommand::Chmod { recursive, mode, paths } => {
+			for_each_entry(args, fs, &paths, recursive, |entry| {
+				let is_dir = matches!(entry.kind, HdfsDirectoryEntryKind::Directory);
+				let new_mode = mode.apply(entry.permissions, is_dir);
+				if mode.is_relative() && new_mode == entry.permissions {
+					return Ok(());
+				}
+				fs.chmod(&entry.name, new_mode)
+			})?;
+		},
+		Subcommand::Chown { recursive, owner, paths } => {
+			let (owner, group) = match owner.find(':') {
+				Some(i) => (&owner[..i], Some(&owner[i+1..])),
+				None => (owner.as_str(), None),
+			};
+			let owner = Some(owner).filter(|s| !s.is_empty());
+			let group = group.filter(|s| !s.is_empty());
+			if owner.is_none() && group.is_none() {
+				return Err(Failure { code: EXIT_USAGE, message: "No owner or group specified".into() });
+			}
+			for_each_entry(args, fs, &paths, recursive, |entry| fs.chown(&entry.name, owner, group))?;
+		},
+		Subcommand::Chgrp { recursive, group, paths } => {
+			for_each_entry(args, fs, &paths, recursive, |entry| fs.chown(&entry.name, None, Some(&group)))?;
+		},
+		Subcommand::Head { bytes, lines, path } => {
+			let path = expand_one(args, fs, &path)?;
+			tail::head(&fs, &path, tail::Amount::from_args(bytes, lines))?;
+		},
+		Subcommand::Tail { bytes, lines, follow, interval, path } => {
+			if !interval.is_finite() || interval <= 0.0 {
+				return Err(Failure { code: EXIT_USAGE, message: "Interval must be positive".into() });
+			}
+			let follow = if follow { Some(Duration::from_secs_f64(interval)) } else { None };
+			let path = expand_one(args, fs, &path)?;
+			tail::tail(&fs, &path, tail::Amount::from_args(bytes, lines), follow)?;
+		},
+		Subcommand::Cat { paths } => {
+			let stdout = io::stdout();
+			let mut out = stdout.lock();
+			for pattern in paths.iter() {
+				for entry in expand_glob(args, fs, pattern)?.into_iter() {
+					let name = format::uri_path(&entry.name);
+					if let HdfsDirectoryEntryKind::Directory = entry.kind {
+						return Err(format!("Could not read {}: Is a directory", name).into());
+					}
+					let mut file = fs.open_read(&entry.name)
+						.map_err(|e| format!("Could not open {}: {}", name, e))?;
+					io::copy(&mut file, &mut out)
+						.map_err(|e| format!("Could not copy {}: {}", name, e))?;
+				}
+			}
+			out.flush()
+				.map_err(|e| format!("Could not write output: {}", e))?;
+		},
+		Subcommand::Getmerge { newline, skip_empty, src, dest } => {
+			let mut parts = vec![];
+			for entry in expand_glob(args, fs, &src)?.into_iter() {
+				match entry.kind {
+					HdfsDirectoryEntryKind::Directory => {
+						parts.extend(fs.list_dir(&entry.name)
+							.map_err(|e| format!("Could not list {}: {}", format::uri_path(&entry.name), e))?
+							.into_iter()
+							.filter(|child| matches!(child.kind, HdfsDirectoryEntryKind::File))
+							.filter(|child| !child.name.rsplit('/').next().unwrap_or("").starts_with(&['_', '.'][..]))
+						);
+					},
+					_ => { parts.push(entry); },
+				}
+			}
+			parts.sort_by(|a, b| a.name.cmp(&b.name));
+			
+			let mut out = io::BufWriter::new(File::create(&dest)
+				.map_err(|e| format!("Could not create output file: {}", e))?);
+			for part in parts.iter() {
+				if skip_empty && part.size == 0 {
+					continue;
+				}
+				let mut file = fs.open_read(&part.name)
+					.map_err(|e| format!("Could not open {}: {}", format::uri_path(&part.name), e))?;
+				io::copy(&mut file, &mut out)
+					.map_err(|e| format!("Could not copy {}: {}", format::uri_path(&part.name), e))?;
+				if newline {
+					out.write_all(b"\n")
+						.map_err(|e| format!("Could not write output file: {}", e))?;
+				}
+			}
+			out.flush()
+				.map_err(|e| format!("Could not write output file: {}", e))?;
+		},
+		Subcommand::Test { exists, directory, file, non_empty, zero, path } => {
+			let literal = if args.no_glob { Some(path.clone()) } else { GlobPattern::as_literal(&path) };
+			let entry = match literal {
+				Some(literal) => match fs.stat(&literal) {
+					Ok(entry) => Some(entry),
+					Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+					Err(e) => { return Err(format!("{}: {}", path, e).into()); },
+				},
+				// Nothing matching a pattern is the same as a missing path
+				None => {
+					let mut entries = fs.glob(&path)
+						.map_err(|e| format!("{}: {}", path, e))?;
+					if entries.len() > 1 {
+						return Err(Failure { code: EXIT_USAGE, message: format!("{}: matches more than one path", path) });
+					}
+					entries.pop()
+				},
+			};
+			let result = match entry {
+				None => false,
+				Some(_) if exists => true,
+				Some(entry) if directory => matches!(entry.kind, HdfsDirectoryEntryKind::Directory),
+				Some(entry) if file => matches!(entry.kind, HdfsDirectoryEntryKind::File),
+				Some(entry) if non_empty => match entry.kind {
+					HdfsDirectoryEntryKind::Directory => !fs.list_dir(&entry.name)
+						.map_err(|e| format!("{}: {}", path, e))?
+						.is_empty(),
+					_ => entry.size > 0,
+				},
+				Some(entry) => {
+					debug_assert!(zero);
+					entry.size == 0
+				},
+			};
+			if !result {
+				return Err(Failure { code: EXIT_FAILURE, message: String::new() });
+			}
+		},
+		Subcommand::Count { human, header, paths } => {
+			if header && !args.json {
+				println!("{:>12} {:>12} {:>18} PATHNAME", "DIR_COUNT", "FILE_COUNT", "CONTENT_SIZE");
+			}
+			for pattern in paths.iter() {
+				for entry in expand_glob(args, fs, pattern)?.into_iter() {
+					let name = format::uri_path(&entry.name);
+					let summary = fs.content_summary(&entry.name)
+						.map_err(|e| format!("Could not count {}: {}", name, e))?;
+					if args.json {
+						println!("{}", json!({
+							"path": name,
+							"directory_count": summary.directory_count,
+							"file_count": summary.file_count,
+							"content_size": summary.length,
+						}));
+						continue;
+					}
+					println!("{:>12} {:>12} {:>18} {}",
+						summary.directory_count,
+						summary.file_count,
+						format::size_string(summary.length, human),
+						name,
+					);
+				}
+			}
+		},
+		Subcommand::Checksum { paths } => {
+			for pattern in paths.iter() {
+				for entry in expand_glob(args, fs, pattern)?.into_iter() {
+					let name = format::uri_path(&entry.name);
+					if let HdfsDirectoryEntryKind::Directory = entry.kind {
+						return Err(format!("{}: Is a directory", name).into());
+					}
+					let checksum = checksum::file_checksum(&fs, &entry)
+						.map_err(|e| format!("Could not checksum {}: {}", name, e))?;
+					println!("{}\t{}\t{}", name, checksum.algorithm, checksum.hex);
+				}
+			}
+		},
+		Subcommand::Blocks { human, paths } => {
+			let mut num_errors = 0;
+			let (mut num_files, mut num_blocks, mut num_under_replicated) = (0, 0, 0);
+			for pattern in paths.iter() {
+				for root in expand_glob(args, fs, pattern)?.into_iter() {
+					for item in fs.walk(&root.name) {
+						let entry = match item {
+							Ok(item) => item.entry,
+							Err(e) => {
+								eprintln!("{}: {}", format::uri_path(&root.name), e);
+								num_errors += 1;
+								continue;
+							},
+						};
+						if let HdfsDirectoryEntryKind::Directory = entry.kind {
+							continue;
+						}
+						let name = format::uri_path(&entry.name);
+						let blocks = match fs.get_block_locations(&entry.name, 0, entry.size) {
+							Ok(blocks) => blocks,
+							Err(e) => {
+								eprintln!("Could not get blocks of {}: {}", name, e);
+								num_errors += 1;
+								continue;
+							},
+						};
+						num_files += 1;
+						num_blocks += blocks.len();
+						if !args.json {
+							println!("{} {}, replication {}, {} blocks",
+								name, format::size_string(entry.size, human), entry.replication, blocks.len());
+						}
+						for (i, block) in blocks.iter().enumerate() {
+							let under_replicated = block.hosts.len() < entry.replication as usize;
+							if under_replicated {
+								num_under_replicated += 1;
+							}
+							if args.json {
+								println!("{}", json!({
+									"path": name,
+									"block": i,
+									"offset": block.offset,
+									"length": block.length,
+									"replication": entry.replication,
+									"hosts": block.hosts,
+								}));
+							} else {
+								println!("  {}: offset {}, length {}, hosts {}{}",
+									i,
+									format::size_string(block.offset, human),
+									format::size_string(block.length, human),
+									block.hosts.join(","),
+									if under_replicated { "  UNDER-REPLICATED" } else { "" },
+								);
+							}
+						}
+					}
+				}
+			}
+			if !args.json {
+				println!("Total: {} files, {} blocks, {} under-replicated blocks", num_files, num_blocks, num_under_replicated);
+			}
+			if num_errors > 0 {
+				return Err(format!("Failed with {} errors", num_errors).into());
+			}
+		},
+		Subcommand::Find { name, kind, size, mtime, max_depth, print0, paths } => {
+			let filters = find::Filters {
+				name: name.map(|name| GlobPattern::new(&name))
+					.transpose()
+					.map_err(|e| Failure { code: EXIT_USAGE, message: e.to_string() })?,
+				kind,
+				size,
+				mtime,
+				now: SystemTime::now(),
+			};
+			let terminator = if print0 { '\0' } else { '\n' };
+			
+			let stdout = io::stdout();
+			let mut out = stdout.lock();
+			let mut num_errors = 0;
+			for pattern in paths.iter() {
+				for root in expand_glob(args, fs, pattern)?.into_iter() {
+					let mut walk = fs.walk(&root.name);
+					if let Some(depth) = max_depth {
+						walk = walk.max_depth(depth);
+					}
+					for item in walk {
+						match item {
+							Ok(item) if filters.matches(&item.entry) => {
+								write!(out, "{}{}", format::uri_path(&item.entry.name), terminator)
+									.map_err(|e| format!("Could not write output: {}", e))?;
+							},
+							Ok(_) => {},
+							Err(e) => {
+								eprintln!("{}: {}", format::uri_path(&root.name), e);
+								num_errors += 1;
+							},
+						}
+					}
+				}
+			}
+			out.flush()
+				.map_err(|e| format!("Could not write output: {}", e))?;
+			if num_errors > 0 {
+				return Err(format!("Failed with {} errors", num_errors).into());
+			}
+		},
+		Subcommand::Watch { interval, recursive, until, path } => {
+			if !interval.is_finite() || interval <= 0.0 {
+				return Err(Failure { code: EXIT_USAGE, message: "Interval must be positive".into() });
+			}
+			let options = watch::Options {
+				interval: Duration::from_secs_f64(interval),
+				recursive,
+				until,
+			};
+			let path = expand_one(args, fs, &path)?;
+			watch::watch(fs, &path, &options)?;
+		},
+		Subcommand::Snapshot { command } => match command {
+			SnapshotCommand::Create { dir, name } => {
+				let dir = expand_one(args, fs, &dir)?;
+				let path = fs.create_snapshot(&dir, name.as_deref())
+					.map_err(|e| format!("Could not create snapshot of {}: {}", dir, e))?;
+				println!("{}", format::uri_path(&path));
+			},
+			SnapshotCommand::Delete { dir, name } => {
+				let dir = expand_one(args, fs, &dir)?;
+				fs.delete_snapshot(&dir, &name)
+					.map_err(|e| format!("Could not delete snapshot {} of {}: {}", name, dir, e))?;
+			},
+			SnapshotCommand::List { dir } => {
+				let dir = expand_one(args, fs, &dir)?;
+				let snapshots = fs.list_snapshots(&dir)
+					.map_err(|e| format!("Could not list snapshots of {}: {}", dir, e))?;
+				for snapshot in snapshots.iter() {
+					if args.json {
+						println!("{}", format::entry_json(snapshot));
+					} else {
+						println!("{}  {}", format::time_string(snapshot.last_modified), format::uri_path(&snapshot.name));
+					}
+				}
+			},
+			SnapshotCommand::Diff { dir, from, to } => {
+				let dir = expand_one(args, fs, &dir)?;
+				let diff = fs.snapshot_diff(&dir, &from, &to)
+					.map_err(|e| format!("Could not compare snapshots of {}: {}", dir, e))?;
+				for item in diff.iter() {
+					let kind = match item.kind {
+						SnapshotDiffKind::Created => "+",
+						SnapshotDiffKind::Deleted => "-",
+						SnapshotDiffKind::Modified => "M",
+					};
+					println!("{}\t./{}", kind, item.path);
+				}
+			},
+		},
+		Subcommand::Trash { command } => {
+			let user = args.user_name()?;
+			let trash = Trash::new(fs, &user);
+			match command {
+				TrashCommand::Ls => {
+					let items = trash.list()
+						.map_err(|e| format!("Could not list {}: {}", trash.root(), e))?;
+					for item in items.iter() {
+						println!("{:<14} {}", item.checkpoint, item.original_path);
+					}
+				},
+				TrashCommand::Restore { paths } => {
+					let items = trash.list()
+						.map_err(|e| format!("Could not list {}: {}", trash.root(), e))?;
+					let mut num_errors = 0;
+					for path in paths.iter() {
+						let path = format::uri_path(path).trim_end_matches('/');
+						// Checkpoint names sort by time, with `Current` after all of them
+						let item = items.iter()
+							.filter(|item| item.original_path == path || format::uri_path(&item.entry.name) == path)
+							.max_by(|a, b| a.checkpoint.cmp(&b.checkpoint));
+						let res = match item {
+							Some(item) => trash.restore(item).map_err(|e| e.to_string()),
+							None => Err("Not in the trash".to_string()),
+						};
+						if let Err(e) = res {
+							eprintln!("Could not restore {}: {}", path, e);
+							num_errors += 1;
+						}
+					}
+					if num_errors > 0 {
+						return Err(format!("Failed with {} errors", num_errors).into());
+					}
+				},
+				TrashCommand::Expunge { older_than, immediate } => {
+					let older_than = if immediate {
+						None
+					} else if !older_than.is_finite() || older_than < 0.0 {
+						return Err(Failure { code: EXIT_USAGE, message: "Age must not be negative".into() });
+					} else {
+						Some(Duration::from_secs_f64(older_than * 60.0))
+					};
+					let deleted = trash.expunge(older_than)
+						.map_err(|e| format!("Could not expunge {}: {}", trash.root(), e))?;
+					println!("Deleted {} checkpoints", deleted);
+				},
+			}
+		},
+		Subcommand::Completions { .. } | Subcommand::Shell => {
+			return Err(Failure { code: EXIT_USAGE, message: "Not available in the shell".into() });
+		},
+	}
+	
+	Ok(())
+}
+
+/// Expands a glob pattern, failing if it doesn't match anything.
+/// 
+/// With `--no-glob`, stats the pattern as a literal path instead.
+fn expand_glob(args: &GlobalArgs, fs: &HdfsConnection, pattern: &str) -> Result<Vec<HdfsDirectoryEntry>, Failure> {
+	if args.no_glob {
+		let entry = fs.stat(pattern)
+			.map_err(|e| format!("{}: {}", pattern, e))?;
+		return Ok(vec![entry]);
+	}
+	let entries = fs.glob(pattern)
+		.map_err(|e| format!("{}: {}", pattern, e))?;
+	if entries.is_empty() {
+		return Err(format!("{}: No such file or directory", pattern).into());
+	}
+	Ok(entries)
+}
+
+/// Expands a glob pattern into the paths it matches, failing if it doesn't match anything.
+/// 
+/// Paths without metacharacters are returned without checking that they exist, leaving that to the subcommand.
+fn expand_paths(args: &GlobalArgs, fs: &HdfsConnection, pattern: &str) -> Result<Vec<String>, Failure> {
+	if args.no_glob {
+		return Ok(vec![pattern.to_string()]);
+	}
+	if let Some(path) = GlobPattern::as_literal(pattern) {
+		return Ok(vec![path]);
+	}
+	Ok(expand_glob(args, fs, pattern)?.into_iter().map(|entry| entry.name).collect())
+}
+
+/// Expands a glob pattern that must match exactly one path.
+fn expand_one(args: &GlobalArgs, fs: &HdfsConnection, pattern: &str) -> Result<String, Failure> {
+	let mut paths = expand_paths(args, fs, pattern)?;
+	if paths.len() > 1 {
+		return Err(Failure { code: EXIT_USAGE, message: format!("{}: matches more than one path", pattern) });
+	}
+	Ok(paths.remove(0))
+}
+
+/// Calls `op` on each path, or on every entry under each path if `recursive` is set.
+/// 
+/// Errors are printed as they happen, rather than stopping the whole operation.
+fn for_each_entry<F>(args: &GlobalArgs, fs: &HdfsConnection, patterns: &[String], recursive: bool, mut op: F) -> Result<(), Failure>
+	where F: FnMut(&HdfsDirectoryEntry) -> io::Result<()>
+{
+	let mut paths = vec![];
+	for pattern in patterns.iter() {
+		paths.extend(expand_paths(args, fs, pattern)?);
+	}
+	let mut num_errors = 0;
+	for path in paths.iter() {
+		let entries: Box<dyn Iterator<Item=io::Result<HdfsDirectoryEntry>>> = if recursive {
+			Box::new(fs.walk(path).map(|res| res.map(|item| item.entry)))
+		} else {
+			Box::new(std::iter::once(fs.stat(path)))
+		};
+		for entry in entries {
+			let res = match entry {
+				Ok(entry) => op(&entry).map_err(|e| format!("{}: {}", format::uri_path(&entry.name), e)),
+				Err(e) => Err(format!("{}: {}", path, e)),
+			};
+			if let Err(msg) = res {
+				eprintln!("{}", msg);
+				num_errors += 1;
+			}
+		}
+	}
+	if num_errors > 0 {
+		return Err(format!("Failed with {} errors", num_errors).into());
+	}
+	Ok(())
+}
+
+/// Gets the `scheme://authority` part of a path, if it has one.
+fn cluster_of(path: &str) -> Option<&str> {
+	let i = path.find("://")?;
+	if path[..i].contains('/') {
+		return None;
+	}
+	let end = path[i+3..].find('/').map(|j| i + 3 + j).unwrap_or(path.len());
+	Some(&path[..end])
+}
+
+/// Parses a permission mode written in octal, ex. `755`.
+fn parse_octal_mode(s: &str) -> Result<u16, String> {
+	u16::from_str_radix(s, 8)
+		.ok()
+		.filter(|mode| *mode <= 0o7777)
+		.ok_or_else(|| format!("invalid octal mode: {}", s))
+}
+
+/// Gets the parent directory of a path, or `None` for the root or a bare relative name.
+fn parent_path(path: &str) -> Option<&str> {
+	let path = path.trim_end_matches('/');
+	let index = path.rfind('/')?;
+	if index == 0 {
+		return Some("/");
+	}
+	Some(&path[..index])
+}
@@ -0,0 +1,182 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Parsing of `chmod`-style permission modes, in octal (`755`) or symbolic (`u+rwx,g-w`) form.
+
+use std::str::FromStr;
+
+/// One clause of a symbolic mode, ex. `go-w`.
+#[derive(Debug,Clone,Copy)]
+pub struct Clause {
+	/// Mask of the permission bits of the users the clause applies to
+	who: u16,
+	op: char,
+	/// Permission bits, in all three user positions
+	perms: u16,
+	/// `X`: execute only if a directory or already executable by someone
+	conditional_exec: bool,
+}
+
+/// Permission mode to apply to a file.
+#[derive(Debug,Clone)]
+pub enum Mode {
+	/// Sets the permissions to an absolute value
+	Octal(u16),
+	/// Modifies the existing permissions
+	Symbolic(Vec<Clause>),
+}
+impl Mode {
+	/// Whether applying this mode needs the existing permissions of the file.
+	pub fn is_relative(&self) -> bool {
+		match self {
+			Mode::Octal(_) => false,
+			Mode::Symbolic(_) => true,
+		}
+	}
+	
+	/// Computes the new permissions for a file with permissions `current`.
+	pub fn apply(&self, current: u16, is_dir: bool) -> u16 {
+		let clauses = match self {
+			Mode::Octal(mode) => { return *mode; },
+			Mode::Symbolic(clauses) => clauses,
+		};
+		let mut mode = current;
+		for clause in clauses.iter() {
+			let mut perms = clause.perms;
+			if clause.conditional_exec && (is_dir || mode & 0o111 != 0) {
+				perms |= 0o111;
+			}
+			// The sticky bit isn't tied to a user class
+			let sticky = perms & 0o1000;
+			let perms = (perms & clause.who) | sticky;
+			match clause.op {
+				'+' => { mode |= perms; },
+				'-' => { mode &= !perms; },
+				_ => { mode = (mode & !(clause.who | sticky)) | perms; },
+			}
+		}
+		mode
+	}
+}
+impl FromStr for Mode {
+	type Err = String;
+	
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if !s.is_empty() && s.chars().all(|c| c.is_digit(8)) {
+			return u16::from_str_radix(s, 8)
+				.ok()
+				.filter(|mode| *mode <= 0o7777)
+				.map(Mode::Octal)
+				.ok_or_else(|| format!("invalid octal mode: {}", s));
+		}
+		
+		let invalid = || format!("invalid mode: {}", s);
+		let mut clauses = vec![];
+		for part in s.split(',') {
+			let mut chars = part.chars().peekable();
+			let mut who = 0;
+			while let Some(c) = chars.peek() {
+				who |= match c {
+					'u' => 0o700,
+					'g' => 0o070,
+					'o' => 0o007,
+					'a' => 0o777,
+					_ => { break; },
+				};
+				chars.next();
+			}
+			if who == 0 {
+				who = 0o777;
+			}
+			
+			let op = match chars.next() {
+				Some(c @ '+') | Some(c @ '-') | Some(c @ '=') => c,
+				_ => { return Err(invalid()); },
+			};
+			
+			let mut perms = 0;
+			let mut conditional_exec = false;
+			for c in chars {
+				match c {
+					'r' => { perms |= 0o444; },
+					'w' => { perms |= 0o222; },
+					'x' => { perms |= 0o111; },
+					'X' => { conditional_exec = true; },
+					't' => { perms |= 0o1000; },
+					_ => { return Err(invalid()); },
+				}
+			}
+			clauses.push(Clause { who, op, perms, conditional_exec });
+		}
+		Ok(Mode::Symbolic(clauses))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn apply(mode: &str, current: u16, is_dir: bool) -> u16 {
+		mode.parse::<Mode>().unwrap().apply(current, is_dir)
+	}
+	
+	#[test]
+	fn octal() {
+		assert_eq!(apply("755", 0o600, false), 0o755);
+		assert_eq!(apply("1777", 0o755, true), 0o1777);
+		assert!(!"755".parse::<Mode>().unwrap().is_relative());
+		assert!("10000".parse::<Mode>().is_err());
+	}
+	
+	#[test]
+	fn symbolic() {
+		assert_eq!(apply("u+x,g-w,o=r", 0o664, false), 0o744);
+		assert_eq!(apply("a-w", 0o666, false), 0o444);
+		assert_eq!(apply("+x", 0o644, false), 0o755);
+		assert_eq!(apply("go=", 0o755, false), 0o700);
+		assert_eq!(apply("ug=rw", 0o751, false), 0o661);
+		assert!("u+x".parse::<Mode>().unwrap().is_relative());
+	}
+	
+	#[test]
+	fn conditional_exec() {
+		// `X` adds execute to directories, and to files someone can already execute
+		assert_eq!(apply("a+X", 0o644, true), 0o755);
+		assert_eq!(apply("a+X", 0o644, false), 0o644);
+		assert_eq!(apply("a+X", 0o744, false), 0o755);
+		assert_eq!(apply("g+X", 0o700, false), 0o710);
+	}
+	
+	#[test]
+	fn sticky() {
+		assert_eq!(apply("+t", 0o777, true), 0o1777);
+		assert_eq!(apply("o+t", 0o777, true), 0o1777);
+		assert_eq!(apply("-t", 0o1777, true), 0o777);
+	}
+	
+	#[test]
+	fn invalid() {
+		assert!("".parse::<Mode>().is_err());
+		assert!("u".parse::<Mode>().is_err());
+		assert!("u+z".parse::<Mode>().is_err());
+		assert!("q+x".parse::<Mode>().is_err());
+		assert!("u+x,".parse::<Mode>().is_err());
+	}
+}
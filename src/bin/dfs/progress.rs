@@ -0,0 +1,40 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Progress bars for transfers.
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Creates a progress bar for transferring `len` bytes, shown on stderr.
+/// 
+/// The bar is hidden if `show` is false or stderr isn't a terminal.
+pub fn bytes_bar(show: bool, len: u64, message: &str) -> ProgressBar {
+	if !show {
+		return ProgressBar::hidden();
+	}
+	let bar = ProgressBar::new(len);
+	bar.set_style(ProgressStyle::default_bar()
+		.template("{msg} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec}, ETA {eta}")
+		.expect("progress bar template is valid")
+		.progress_chars("=> ")
+	);
+	bar.set_message(message.to_string());
+	bar
+}
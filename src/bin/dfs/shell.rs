@@ -0,0 +1,194 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Interactive shell, for the `shell` subcommand.
+
+use crate::{format, Failure, GlobalArgs, Subcommand, EXIT_FAILURE};
+use hdfs::{HdfsConnection, HdfsDirectoryEntryKind};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::env;
+use std::iter;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use structopt::clap::ErrorKind;
+
+/// Commands handled by the shell itself
+const BUILTINS: &[&str] = &["cd", "pwd", "help", "exit", "quit"];
+
+/// Subcommands, for completion
+const COMMANDS: &[&str] = &[
+	"append", "blocks", "cat", "checksum", "chgrp", "chmod", "chown", "count", "cp", "df", "du", "find", "get",
+	"getmerge", "head", "ls", "mkdir", "mv", "put", "rm", "snapshot", "stat", "sync", "tail", "test", "trash",
+	"truncate", "watch",
+];
+
+/// Runs the shell until the user exits.
+pub fn run(args: &GlobalArgs, fs: &HdfsConnection) -> Result<(), Failure> {
+	let mut editor = Editor::new()
+		.map_err(|e| format!("Could not start shell: {}", e))?;
+	editor.set_helper(Some(ShellHelper { fs }));
+	let history = history_path();
+	if let Some(path) = history.as_ref() {
+		// There's no history the first time
+		let _ = editor.load_history(path);
+	}
+	
+	let home = fs.working_directory()
+		.map_err(|e| format!("Could not get working directory: {}", e))?;
+	loop {
+		let cwd = fs.working_directory().unwrap_or_else(|_| home.clone());
+		let line = match editor.readline(&format!("dfs:{}> ", format::uri_path(&cwd))) {
+			Ok(line) => line,
+			Err(ReadlineError::Interrupted) => { continue; },
+			Err(ReadlineError::Eof) => { break; },
+			Err(e) => { return Err(format!("Could not read input: {}", e).into()); },
+		};
+		if line.trim().is_empty() {
+			continue;
+		}
+		let _ = editor.add_history_entry(line.as_str());
+		
+		let words = match shell_words::split(&line) {
+			Ok(words) => words,
+			Err(e) => {
+				eprintln!("{}", e);
+				continue;
+			},
+		};
+		let res = match words[0].as_str() {
+			"exit" | "quit" => { break; },
+			"pwd" => {
+				println!("{}", cwd);
+				Ok(())
+			},
+			"cd" => change_dir(fs, words.get(1).map(|s| s.as_str()).unwrap_or(&home)),
+			"help" => {
+				let _ = Subcommand::clap().print_help();
+				println!("\n\nShell commands: {}", BUILTINS.join(", "));
+				Ok(())
+			},
+			_ => match Subcommand::from_iter_safe(iter::once("dfs".to_string()).chain(words)) {
+				Ok(subcommand) => crate::run(args, fs, subcommand),
+				Err(e) if matches!(e.kind, ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed) => {
+					println!("{}", e.message);
+					Ok(())
+				},
+				Err(e) => Err(e.message.into()),
+			},
+		};
+		if let Err(err) = res {
+			if !err.message.is_empty() {
+				eprintln!("{}", err.message);
+			}
+		}
+	}
+	
+	if let Some(path) = history.as_ref() {
+		if let Some(dir) = path.parent() {
+			let _ = std::fs::create_dir_all(dir);
+		}
+		if let Err(e) = editor.save_history(path) {
+			eprintln!("Could not save history to {}: {}", path.display(), e);
+		}
+	}
+	Ok(())
+}
+
+fn change_dir(fs: &HdfsConnection, path: &str) -> Result<(), Failure> {
+	let entry = fs.stat(path)
+		.map_err(|e| format!("cd: {}: {}", path, e))?;
+	if !matches!(entry.kind, HdfsDirectoryEntryKind::Directory) {
+		return Err(Failure { code: EXIT_FAILURE, message: format!("cd: {}: Not a directory", path) });
+	}
+	fs.set_working_directory(&entry.name)
+		.map_err(|e| format!("cd: {}: {}", path, e))?;
+	Ok(())
+}
+
+/// History file: `$XDG_DATA_HOME/hdfs-rs/history`, falling back to `~/.local/share/hdfs-rs/history`.
+fn history_path() -> Option<PathBuf> {
+	let base = env::var_os("XDG_DATA_HOME")
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+		.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+	Some(base.join("hdfs-rs").join("history"))
+}
+
+/// Tab completion of commands and remote paths.
+struct ShellHelper<'a> {
+	fs: &'a HdfsConnection,
+}
+impl<'a> ShellHelper<'a> {
+	fn complete_path(&self, word: &str) -> Vec<Pair> {
+		let (dir, prefix) = match word.rfind('/') {
+			Some(i) => (&word[..i+1], &word[i+1..]),
+			None => ("", word),
+		};
+		let entries = match self.fs.list_dir(if dir.is_empty() { "." } else { dir }) {
+			Ok(entries) => entries,
+			Err(_) => { return vec![]; },
+		};
+		let mut candidates: Vec<Pair> = entries.iter()
+			.filter_map(|entry| {
+				let name = entry.name.trim_end_matches('/').rsplit('/').next()?;
+				if !name.starts_with(prefix) {
+					return None;
+				}
+				let suffix = if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) { "/" } else { "" };
+				Some(Pair {
+					display: format!("{}{}", name, suffix),
+					replacement: format!("{}{}{}", dir, name, suffix),
+				})
+			})
+			.collect();
+		candidates.sort_by(|a, b| a.display.cmp(&b.display));
+		candidates
+	}
+}
+impl<'a> Completer for ShellHelper<'a> {
+	type Candidate = Pair;
+	
+	fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+		let start = line[..pos].rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+		let word = &line[start..pos];
+		if line[..start].trim().is_empty() {
+			let candidates = BUILTINS.iter().chain(COMMANDS.iter())
+				.filter(|command| command.starts_with(word))
+				.map(|command| Pair { display: command.to_string(), replacement: format!("{} ", command) })
+				.collect();
+			return Ok((start, candidates));
+		}
+		if word.starts_with('-') {
+			return Ok((start, vec![]));
+		}
+		Ok((start, self.complete_path(word)))
+	}
+}
+impl<'a> Hinter for ShellHelper<'a> {
+	type Hint = String;
+}
+impl<'a> Highlighter for ShellHelper<'a> {}
+impl<'a> Validator for ShellHelper<'a> {}
+impl<'a> Helper for ShellHelper<'a> {}
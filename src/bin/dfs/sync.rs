@@ -0,0 +1,261 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! One-way synchronization of directory trees, for the `sync` subcommand.
+
+use crate::checksum;
+use crate::format;
+use crate::copy::COPY_BUFFER_SIZE;
+use hdfs::{BufferPool, HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind, Preserve};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+
+/// Options for `sync`.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct Options {
+	/// Compare checksums instead of modification times
+	pub checksum: bool,
+	/// Delete destination entries that don't exist in the source
+	pub delete: bool,
+	/// Print what would be done, without changing anything
+	pub dry_run: bool,
+	/// Attributes to give copied files and created directories. Modification times and block sizes are always
+	/// kept.
+	pub preserve: Preserve,
+}
+
+/// Counts of what was done.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct Summary {
+	pub copied: usize,
+	pub copied_bytes: u64,
+	pub created_dirs: usize,
+	pub deleted: usize,
+	pub unchanged: usize,
+	pub failed: usize,
+}
+
+/// Makes `dest` on `dest_fs` a copy of `src` on `src_fs`, copying only what changed.
+/// 
+/// Files are considered changed if their sizes or modification times differ, or their checksums with
+/// `Options::checksum`. Copied files get the modification time of their source, so that the next sync skips them.
+pub fn sync(src_fs: &HdfsConnection, dest_fs: &HdfsConnection, src: &str, dest: &str, options: Options) -> Result<Summary, String> {
+	let root = src_fs.stat(src)
+		.map_err(|e| format!("{}: {}", src, e))?;
+	let mut existing = index(dest_fs, dest)?;
+	if let Some(dest_root) = existing.get("") {
+		if matches!(root.kind, HdfsDirectoryEntryKind::Directory) != matches!(dest_root.kind, HdfsDirectoryEntryKind::Directory) {
+			return Err(format!("{} and {} are not both files or both directories", src, dest));
+		}
+	}
+	let mut summary = Summary::default();
+	let pool = BufferPool::new(COPY_BUFFER_SIZE).max_idle(1);
+	let mut created_dirs = vec![];
+	
+	for item in src_fs.walk(&root.name) {
+		let entry = match item {
+			Ok(item) => item.entry,
+			Err(e) => {
+				eprintln!("{}: {}", src, e);
+				summary.failed += 1;
+				continue;
+			},
+		};
+		let relative = relative_path(&root.name, &entry.name).to_string();
+		let dest_path = join(dest, &relative);
+		let dest_entry = existing.remove(&relative);
+		let is_dir = matches!(entry.kind, HdfsDirectoryEntryKind::Directory);
+		
+		// An entry of the wrong type has to be removed first
+		let dest_entry = match dest_entry {
+			Some(dest_entry) if is_dir != matches!(dest_entry.kind, HdfsDirectoryEntryKind::Directory) => {
+				let prefix = format!("{}/", relative);
+				existing.retain(|name, _| !name.starts_with(&prefix));
+				if !run(&mut summary, "delete", &dest_path, options, || dest_fs.delete(&dest_path, true)) {
+					continue;
+				}
+				summary.deleted += 1;
+				None
+			},
+			other => other,
+		};
+		
+		if is_dir {
+			if dest_entry.is_none() && run(&mut summary, "mkdir", &dest_path, options, || dest_fs.create_dir(&dest_path)) {
+				summary.created_dirs += 1;
+				created_dirs.push((entry, dest_path));
+			}
+			continue;
+		}
+		
+		let changed = match dest_entry {
+			None => true,
+			Some(ref dest_entry) => match is_changed(src_fs, &entry, dest_fs, dest_entry, options) {
+				Ok(changed) => changed,
+				Err(e) => {
+					eprintln!("Could not compare {}: {}", dest_path, e);
+					summary.failed += 1;
+					continue;
+				},
+			},
+		};
+		if !changed {
+			summary.unchanged += 1;
+		} else if run(&mut summary, "copy", &dest_path, options, || copy_file(src_fs, &entry, dest_fs, &dest_path, &pool, options.preserve)) {
+			summary.copied += 1;
+			summary.copied_bytes += entry.size;
+		}
+	}
+	
+	// Directories once they're filled in, deepest first, so their modification times stick
+	if !options.preserve.is_empty() && !options.dry_run {
+		for (entry, dest_path) in created_dirs.iter().rev() {
+			if let Err(e) = dest_fs.preserve_attributes(dest_path, entry, options.preserve) {
+				eprintln!("Could not set attributes of {}: {}", dest_path, e);
+				summary.failed += 1;
+			}
+		}
+	}
+	
+	if options.delete {
+		// Entries are sorted by name, so a directory comes before its contents. Siblings like `logs.txt` can sort
+		// between a directory and its contents though, so every deleted directory is remembered.
+		let mut deleted = BTreeSet::new();
+		for (relative, _) in existing.into_iter() {
+			if is_under_any(&deleted, &relative) {
+				continue;
+			}
+			let dest_path = join(dest, &relative);
+			if run(&mut summary, "delete", &dest_path, options, || dest_fs.delete(&dest_path, true)) {
+				summary.deleted += 1;
+				deleted.insert(relative);
+			}
+		}
+	}
+	Ok(summary)
+}
+
+/// Prints an action and runs it, unless this is a dry run. Returns whether it succeeded.
+fn run<F>(summary: &mut Summary, action: &str, path: &str, options: Options, op: F) -> bool
+	where F: FnOnce() -> io::Result<()>
+{
+	println!("{} {}", action, format::uri_path(path));
+	if options.dry_run {
+		return true;
+	}
+	match op() {
+		Ok(()) => true,
+		Err(e) => {
+			eprintln!("Could not {} {}: {}", action, path, e);
+			summary.failed += 1;
+			false
+		},
+	}
+}
+
+/// Lists everything under `path`, keyed by path relative to it. Returns an empty index if `path` doesn't exist.
+fn index(fs: &HdfsConnection, path: &str) -> Result<BTreeMap<String, HdfsDirectoryEntry>, String> {
+	let root = match fs.stat(path) {
+		Ok(root) => root,
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => { return Ok(BTreeMap::new()); },
+		Err(e) => { return Err(format!("{}: {}", path, e)); },
+	};
+	let mut index = BTreeMap::new();
+	for item in fs.walk(&root.name) {
+		let entry = item.map_err(|e| format!("{}: {}", path, e))?.entry;
+		index.insert(relative_path(&root.name, &entry.name).to_string(), entry);
+	}
+	Ok(index)
+}
+
+fn is_changed(src_fs: &HdfsConnection, src: &HdfsDirectoryEntry, dest_fs: &HdfsConnection, dest: &HdfsDirectoryEntry, options: Options) -> io::Result<bool> {
+	if src.size != dest.size {
+		return Ok(true);
+	}
+	if !options.checksum {
+		return Ok(format::epoch_secs(src.last_modified) != format::epoch_secs(dest.last_modified));
+	}
+	// Use the source's block size for both, in case the filesystems' defaults differ
+	let src_sum = checksum::file_checksum_with_block_size(src_fs, src, src.block_size)?;
+	let dest_sum = checksum::file_checksum_with_block_size(dest_fs, dest, src.block_size)?;
+	Ok(src_sum.hex != dest_sum.hex)
+}
+
+fn copy_file(src_fs: &HdfsConnection, src: &HdfsDirectoryEntry, dest_fs: &HdfsConnection, dest: &str, pool: &BufferPool, preserve: Preserve) -> io::Result<()> {
+	let mut in_file = src_fs.open_read(&src.name)?;
+	let mut builder = dest_fs.open_create_builder(dest)?;
+	builder.default_block_size(src.block_size as i64)?;
+	let mut out_file = builder.build()?;
+	pool.copy(&mut in_file, &mut out_file)?;
+	out_file.close()?;
+	// The modification time is always kept, so that the next sync skips the file
+	dest_fs.preserve_attributes(dest, src, preserve.times())
+}
+
+/// Gets the path of `name` relative to `root`, or an empty string for the root itself.
+fn relative_path<'a>(root: &str, name: &'a str) -> &'a str {
+	name.strip_prefix(root)
+		.unwrap_or(name)
+		.trim_start_matches('/')
+}
+
+/// Checks if any parent directory of the relative path `relative` is in `dirs`.
+fn is_under_any(dirs: &BTreeSet<String>, relative: &str) -> bool {
+	let mut path = relative;
+	while let Some(i) = path.rfind('/') {
+		path = &path[..i];
+		if dirs.contains(path) {
+			return true;
+		}
+	}
+	false
+}
+
+/// Joins a relative path onto a base path.
+fn join(base: &str, relative: &str) -> String {
+	if relative.is_empty() {
+		base.to_string()
+	} else {
+		format!("{}/{}", base.trim_end_matches('/'), relative)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn is_under_any_checks_every_deleted_dir() {
+		let mut deleted = BTreeSet::new();
+		deleted.insert("logs".to_string());
+		deleted.insert("logs.txt".to_string());
+		deleted.insert("a/b".to_string());
+		
+		assert!(is_under_any(&deleted, "logs/x"));
+		assert!(is_under_any(&deleted, "logs/x/y"));
+		assert!(is_under_any(&deleted, "a/b/c"));
+		assert!(!is_under_any(&deleted, "logs"));
+		assert!(!is_under_any(&deleted, "logs.txt2"));
+		assert!(!is_under_any(&deleted, "logsx/y"));
+		assert!(!is_under_any(&deleted, "a/bc"));
+		assert!(!is_under_any(&deleted, "a"));
+	}
+}
@@ -0,0 +1,160 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! `head` and `tail` subcommands.
+
+use hdfs::HdfsConnection;
+use std::io::{self, BufRead, BufReader, Read, Seek, Write};
+use std::thread;
+use std::time::Duration;
+
+/// How much of a file to show.
+#[derive(Debug,Clone,Copy)]
+pub enum Amount {
+	Bytes(u64),
+	Lines(u64),
+}
+impl Amount {
+	/// Picks the amount from the `-c` and `-n` options, defaulting to 1 KiB like `hdfs dfs`.
+	pub fn from_args(bytes: Option<u64>, lines: Option<u64>) -> Self {
+		match (bytes, lines) {
+			(Some(bytes), _) => Amount::Bytes(bytes),
+			(None, Some(lines)) => Amount::Lines(lines),
+			(None, None) => Amount::Bytes(1024),
+		}
+	}
+}
+
+/// Size of chunks to read backwards from the end of the file when looking for lines
+const TAIL_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Prints the beginning of a file.
+pub fn head(fs: &HdfsConnection, path: &str, amount: Amount) -> Result<(), String> {
+	let file = fs.open_read(path)
+		.map_err(|e| format!("Could not open {}: {}", path, e))?;
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	
+	match amount {
+		Amount::Bytes(n) => {
+			io::copy(&mut file.take(n), &mut out)
+				.map_err(|e| format!("Could not read {}: {}", path, e))?;
+		},
+		Amount::Lines(n) => {
+			let mut reader = BufReader::new(file);
+			let mut line = vec![];
+			for _ in 0..n {
+				line.clear();
+				let len = reader.read_until(b'\n', &mut line)
+					.map_err(|e| format!("Could not read {}: {}", path, e))?;
+				if len == 0 {
+					break;
+				}
+				out.write_all(&line)
+					.map_err(|e| format!("Could not write output: {}", e))?;
+			}
+		},
+	}
+	out.flush()
+		.map_err(|e| format!("Could not write output: {}", e))?;
+	Ok(())
+}
+
+/// Prints the end of a file, optionally following data appended to it.
+pub fn tail(fs: &HdfsConnection, path: &str, amount: Amount, follow: Option<Duration>) -> Result<(), String> {
+	let size = fs.stat(path)
+		.map_err(|e| format!("Could not stat {}: {}", path, e))?
+		.size;
+	let start = match amount {
+		Amount::Bytes(n) => size.saturating_sub(n),
+		Amount::Lines(n) => find_last_lines(fs, path, size, n)
+			.map_err(|e| format!("Could not read {}: {}", path, e))?,
+	};
+	let mut pos = copy_range(fs, path, start, size)?;
+	
+	let interval = match follow {
+		Some(interval) => interval,
+		None => { return Ok(()); },
+	};
+	loop {
+		thread::sleep(interval);
+		let size = fs.stat(path)
+			.map_err(|e| format!("Could not stat {}: {}", path, e))?
+			.size;
+		if size < pos {
+			eprintln!("{}: file truncated", path);
+			pos = 0;
+		}
+		if size > pos {
+			// Readers only see the length of the file as it was when opened, so re-open each time
+			pos = copy_range(fs, path, pos, size)?;
+		}
+	}
+}
+
+/// Copies bytes `start..end` of a file to stdout, returning the position after the last byte copied.
+fn copy_range(fs: &HdfsConnection, path: &str, start: u64, end: u64) -> Result<u64, String> {
+	let mut file = fs.open_read(path)
+		.map_err(|e| format!("Could not open {}: {}", path, e))?;
+	file.seek(io::SeekFrom::Start(start))
+		.map_err(|e| format!("Could not seek {}: {}", path, e))?;
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	let copied = io::copy(&mut file.take(end - start), &mut out)
+		.map_err(|e| format!("Could not read {}: {}", path, e))?;
+	out.flush()
+		.map_err(|e| format!("Could not write output: {}", e))?;
+	Ok(start + copied)
+}
+
+/// Finds the offset where the last `n` lines of a file of length `size` begin, reading backwards
+/// from the end.
+fn find_last_lines(fs: &HdfsConnection, path: &str, size: u64, n: u64) -> io::Result<u64> {
+	if n == 0 {
+		return Ok(size);
+	}
+	let file = fs.open_read(path)?;
+	let mut buf = vec![0u8; TAIL_CHUNK_SIZE as usize];
+	let mut end = size;
+	// A trailing newline ends the last line rather than starting a new one
+	let mut newlines_to_skip = n;
+	let mut first_chunk = true;
+	while end > 0 {
+		let start = end.saturating_sub(TAIL_CHUNK_SIZE);
+		let chunk = &mut buf[..(end - start) as usize];
+		file.read_exact_at(start, chunk)?;
+		let mut search = &chunk[..];
+		if first_chunk && search.last() == Some(&b'\n') {
+			search = &search[..search.len() - 1];
+		}
+		first_chunk = false;
+		
+		for (i, byte) in search.iter().enumerate().rev() {
+			if *byte == b'\n' {
+				if newlines_to_skip == 1 {
+					return Ok(start + i as u64 + 1);
+				}
+				newlines_to_skip -= 1;
+			}
+		}
+		end = start;
+	}
+	Ok(0)
+}
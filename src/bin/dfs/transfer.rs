@@ -0,0 +1,310 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Copying files and directory trees between the local filesystem and HDFS, for `put` and `get`.
+
+use crate::format;
+use crate::progress;
+use hdfs::{HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::fs::{self, File, FileTimes, OpenOptions};
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Options shared by `put` and `get`.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct Options {
+	/// Copy directories recursively
+	pub recursive: bool,
+	/// Copy modification times, access times, and permissions
+	pub preserve: bool,
+	/// Skip files that already exist at the destination with the same size and a modification time at
+	/// least as new as the source
+	pub update: bool,
+	/// Continue partially copied files by appending to them, rather than starting over
+	pub resume: bool,
+	/// Show progress bars
+	pub progress: bool,
+}
+
+/// Copies between HDFS and the local filesystem, carrying on past errors in individual files.
+pub struct Transfer<'a> {
+	fs: &'a HdfsConnection,
+	options: Options,
+	num_errors: usize,
+}
+impl<'a> Transfer<'a> {
+	pub fn new(fs: &'a HdfsConnection, options: Options) -> Self {
+		Self { fs, options, num_errors: 0 }
+	}
+	
+	/// Returns an error if any file failed to copy. The errors themselves have already been printed.
+	pub fn finish(self) -> Result<(), String> {
+		if self.num_errors > 0 {
+			return Err(format!("Failed with {} errors", self.num_errors));
+		}
+		Ok(())
+	}
+	
+	fn report(&mut self, res: Result<(), String>) {
+		if let Err(msg) = res {
+			eprintln!("{}", msg);
+			self.num_errors += 1;
+		}
+	}
+	
+	/// Uploads a local file or directory. If `dest` is an existing directory, `src` is copied into it.
+	pub fn put(&mut self, src: &Path, dest: &str) {
+		let res = (|| {
+			let meta = fs::metadata(src)
+				.map_err(|e| format!("{}: {}", src.display(), e))?;
+			if meta.is_dir() && !self.options.recursive {
+				return Err(format!("{}: Is a directory (use -r to copy recursively)", src.display()));
+			}
+			let dest = match self.fs.stat(dest) {
+				Ok(ref entry) if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) => {
+					let name = src.file_name()
+						.ok_or_else(|| format!("{}: Cannot copy into a directory without a file name", src.display()))?;
+					join(dest, &name.to_string_lossy())
+				},
+				_ => dest.to_string(),
+			};
+			self.put_entry(src, &meta, &dest);
+			Ok(())
+		})();
+		self.report(res);
+	}
+	
+	fn put_entry(&mut self, src: &Path, meta: &fs::Metadata, dest: &str) {
+		let res = if meta.is_dir() {
+			self.put_dir(src, dest)
+		} else {
+			self.put_file(src, meta, dest)
+		};
+		let res = res.and_then(|()| {
+			if !self.options.preserve {
+				return Ok(());
+			}
+			self.fs.set_times(dest, meta.modified().ok(), meta.accessed().ok())
+				.and_then(|()| match local_mode(meta) {
+					Some(mode) => self.fs.chmod(dest, mode),
+					None => Ok(()),
+				})
+				.map_err(|e| format!("Could not preserve attributes of {}: {}", dest, e))
+		});
+		self.report(res);
+	}
+	
+	fn put_dir(&mut self, src: &Path, dest: &str) -> Result<(), String> {
+		self.fs.create_dir(dest)
+			.map_err(|e| format!("Could not create {}: {}", dest, e))?;
+		for (path, meta) in read_local_dir(src)?.into_iter() {
+			let name = path.file_name().unwrap().to_string_lossy().into_owned();
+			self.put_entry(&path, &meta, &join(dest, &name));
+		}
+		Ok(())
+	}
+	
+	fn put_file(&mut self, src: &Path, meta: &fs::Metadata, dest: &str) -> Result<(), String> {
+		let existing = if self.options.update || self.options.resume {
+			self.fs.stat(dest).ok()
+		} else {
+			None
+		};
+		if let Some(ref entry) = existing {
+			if self.options.update && is_up_to_date(entry.size, entry.last_modified, meta.len(), meta.modified().ok()) {
+				return Ok(());
+			}
+		}
+		let offset = resume_offset(self.options, existing.map(|entry| entry.size), meta.len());
+		if offset == Some(meta.len()) {
+			return Ok(());
+		}
+		
+		let mut in_file = File::open(src)
+			.map_err(|e| format!("Could not open {}: {}", src.display(), e))?;
+		let mut out_file = match offset {
+			Some(offset) => {
+				in_file.seek(SeekFrom::Start(offset))
+					.map_err(|e| format!("Could not seek {}: {}", src.display(), e))?;
+				self.fs.open_append(dest)
+			},
+			None => self.fs.open_create(dest),
+		}.map_err(|e| format!("Could not create {}: {}", dest, e))?;
+		let bar = progress::bytes_bar(self.options.progress, meta.len(), dest);
+		bar.set_position(offset.unwrap_or(0));
+		hdfs::copy(&mut bar.wrap_read(in_file), &mut out_file)
+			.map_err(|e| format!("Could not copy {} to {}: {}", src.display(), dest, e))?;
+		out_file.close()
+			.map_err(|e| format!("Could not copy {} to {}: {}", src.display(), dest, e))?;
+		bar.finish();
+		Ok(())
+	}
+	
+	/// Downloads a file or directory. If `dest` is an existing directory, `src` is copied into it.
+	pub fn get(&mut self, src: &str, dest: &Path) {
+		let res = (|| {
+			let entry = self.fs.stat(src)
+				.map_err(|e| format!("{}: {}", src, e))?;
+			if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) && !self.options.recursive {
+				return Err(format!("{}: Is a directory (use -r to copy recursively)", src));
+			}
+			let dest = if dest.is_dir() {
+				dest.join(base_name(&entry.name))
+			} else {
+				dest.to_path_buf()
+			};
+			self.get_entry(&entry, &dest);
+			Ok(())
+		})();
+		self.report(res);
+	}
+	
+	fn get_entry(&mut self, entry: &HdfsDirectoryEntry, dest: &Path) {
+		let res = match entry.kind {
+			HdfsDirectoryEntryKind::Directory => self.get_dir(entry, dest),
+			_ => self.get_file(entry, dest),
+		};
+		let res = res.and_then(|()| {
+			if !self.options.preserve {
+				return Ok(());
+			}
+			set_local_mode(dest, entry.permissions)
+				.and_then(|()| {
+					// Directories can't be opened for writing, but setting times doesn't need write access
+					let file = File::open(dest)?;
+					file.set_times(FileTimes::new()
+						.set_modified(entry.last_modified)
+						.set_accessed(entry.last_access)
+					)
+				})
+				.map_err(|e| format!("Could not preserve attributes of {}: {}", dest.display(), e))
+		});
+		self.report(res);
+	}
+	
+	fn get_dir(&mut self, entry: &HdfsDirectoryEntry, dest: &Path) -> Result<(), String> {
+		fs::create_dir_all(dest)
+			.map_err(|e| format!("Could not create {}: {}", dest.display(), e))?;
+		let children = self.fs.list_dir(&entry.name)
+			.map_err(|e| format!("Could not list {}: {}", format::uri_path(&entry.name), e))?;
+		for child in children.iter() {
+			self.get_entry(child, &dest.join(base_name(&child.name)));
+		}
+		Ok(())
+	}
+	
+	fn get_file(&mut self, entry: &HdfsDirectoryEntry, dest: &Path) -> Result<(), String> {
+		let name = format::uri_path(&entry.name);
+		let existing = if self.options.update || self.options.resume {
+			fs::metadata(dest).ok()
+		} else {
+			None
+		};
+		if let Some(ref meta) = existing {
+			if self.options.update && is_up_to_date(meta.len(), meta.modified().unwrap_or(UNIX_EPOCH), entry.size, Some(entry.last_modified)) {
+				return Ok(());
+			}
+		}
+		let offset = resume_offset(self.options, existing.map(|meta| meta.len()), entry.size);
+		if offset == Some(entry.size) {
+			return Ok(());
+		}
+		
+		let mut in_file = self.fs.open_read(&entry.name)
+			.map_err(|e| format!("Could not open {}: {}", name, e))?;
+		let mut out_file = match offset {
+			Some(offset) => {
+				in_file.seek(SeekFrom::Start(offset))
+					.map_err(|e| format!("Could not seek {}: {}", name, e))?;
+				OpenOptions::new().append(true).open(dest)
+			},
+			None => File::create(dest),
+		}.map_err(|e| format!("Could not create {}: {}", dest.display(), e))?;
+		let bar = progress::bytes_bar(self.options.progress, entry.size, name);
+		bar.set_position(offset.unwrap_or(0));
+		hdfs::copy_out(&mut bar.wrap_read(in_file), &mut out_file)
+			.map_err(|e| format!("Could not copy {} to {}: {}", name, dest.display(), e))?;
+		bar.finish();
+		Ok(())
+	}
+}
+
+/// Gets the offset to resume copying a file from, given the length of the partial copy, if resuming is enabled
+/// and possible.
+fn resume_offset(options: Options, dest_len: Option<u64>, src_len: u64) -> Option<u64> {
+	if !options.resume {
+		return None;
+	}
+	// A longer destination isn't a partial copy of this file
+	dest_len.filter(|len| *len <= src_len)
+}
+
+/// Checks if a destination file can be skipped by `--update`. Times are compared to the second, since that's
+/// all HDFS keeps.
+fn is_up_to_date(dest_size: u64, dest_modified: SystemTime, src_size: u64, src_modified: Option<SystemTime>) -> bool {
+	let src_modified = match src_modified {
+		Some(time) => time,
+		None => { return false; },
+	};
+	dest_size == src_size && format::epoch_secs(dest_modified) >= format::epoch_secs(src_modified)
+}
+
+/// Lists a local directory, sorted by name.
+fn read_local_dir(path: &Path) -> Result<Vec<(PathBuf, fs::Metadata)>, String> {
+	let mut entries = fs::read_dir(path)
+		.and_then(|iter| iter
+			.map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.metadata()?))))
+			.collect::<io::Result<Vec<_>>>()
+		)
+		.map_err(|e| format!("Could not list {}: {}", path.display(), e))?;
+	entries.sort_by(|a, b| a.0.cmp(&b.0));
+	Ok(entries)
+}
+
+/// Gets the last component of an HDFS path.
+fn base_name(name: &str) -> &str {
+	name.trim_end_matches('/').rsplit('/').next().unwrap_or("")
+}
+
+/// Joins a name onto an HDFS directory path.
+fn join(dir: &str, name: &str) -> String {
+	format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+#[cfg(unix)]
+fn local_mode(meta: &fs::Metadata) -> Option<u16> {
+	use std::os::unix::fs::PermissionsExt;
+	Some((meta.permissions().mode() & 0o7777) as u16)
+}
+#[cfg(not(unix))]
+fn local_mode(_meta: &fs::Metadata) -> Option<u16> {
+	None
+}
+
+#[cfg(unix)]
+fn set_local_mode(path: &Path, mode: u16) -> io::Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+	fs::set_permissions(path, fs::Permissions::from_mode(mode as u32))
+}
+#[cfg(not(unix))]
+fn set_local_mode(_path: &Path, _mode: u16) -> io::Result<()> {
+	Ok(())
+}
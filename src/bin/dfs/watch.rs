@@ -0,0 +1,82 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Polling a directory for changes, for the `watch` subcommand.
+
+use crate::format;
+use hdfs::{DirWatcher, HdfsConnection, WatchEvent};
+use std::thread;
+use std::time::Duration;
+
+/// Options for `watch`.
+#[derive(Debug,Clone)]
+pub struct Options {
+	/// Time between polls
+	pub interval: Duration,
+	/// Watch subdirectories too
+	pub recursive: bool,
+	/// Stop once an entry with this name exists
+	pub until: Option<String>,
+}
+
+/// Polls `path`, printing changes, until `until` appears or forever.
+pub fn watch(fs: &HdfsConnection, path: &str, options: &Options) -> Result<(), String> {
+	let mut watcher = DirWatcher::new(path);
+	if options.recursive {
+		watcher = watcher.recursive();
+	}
+	fs.stat(path)
+		.and_then(|_| watcher.poll(fs))
+		.map_err(|e| format!("Could not list {}: {}", path, e))?;
+	if found(&watcher, options) {
+		return Ok(());
+	}
+	loop {
+		thread::sleep(options.interval);
+		let events = match watcher.poll(fs) {
+			Ok(events) => events,
+			Err(e) => {
+				// Keep going through transient errors, ex. namenode failovers
+				eprintln!("Could not list {}: {}", path, e);
+				continue;
+			},
+		};
+		for event in events.iter() {
+			let action = match event {
+				WatchEvent::Created(_) => "created",
+				WatchEvent::Modified(_) => "modified",
+				WatchEvent::Removed(_) => "deleted",
+			};
+			println!("{} {}", action, format::uri_path(&event.entry().name));
+		}
+		if found(&watcher, options) {
+			return Ok(());
+		}
+	}
+}
+
+/// Checks if the `until` entry exists.
+fn found(watcher: &DirWatcher, options: &Options) -> bool {
+	let until = match options.until.as_ref() {
+		Some(until) => until,
+		None => { return false; },
+	};
+	watcher.entries().any(|entry| entry.name.trim_end_matches('/').rsplit('/').next() == Some(until.as_str()))
+}
@@ -0,0 +1,233 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Caching the metadata of another filesystem, for read-mostly workloads that ask for the same paths repeatedly.
+
+use crate::filesystem::{FileReader, FileSystem, FileWriter};
+use crate::path::{parent, uri_path};
+use crate::HdfsDirectoryEntry;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+struct Cached<T> {
+	value: T,
+	at: Instant,
+}
+
+#[derive(Default)]
+struct Cache {
+	// `None` for paths that don't exist
+	stats: HashMap<String, Cached<Option<HdfsDirectoryEntry>>>,
+	listings: HashMap<String, Cached<Vec<HdfsDirectoryEntry>>>,
+}
+
+/// `FileSystem` wrapper that caches the results of `stat`, `exists`, and `list_dir` for a fixed time.
+/// 
+/// Changes made through the wrapper invalidate what they affect: the changed paths, everything under them, and
+/// their parents' listings. Files opened for writing are invalidated again when they're dropped. Changes made
+/// by anything else show up once the cached results expire, or after calling `invalidate`.
+/// 
+/// Paths are cached as given, apart from the scheme and authority of URIs and trailing slashes, so `a/../b` and
+/// `b` are cached separately.
+pub struct CachedFs<F> {
+	inner: F,
+	ttl: Duration,
+	cache: Mutex<Cache>,
+}
+
+impl<F: FileSystem> CachedFs<F> {
+	/// Wraps a filesystem, keeping results for `ttl`.
+	pub fn new(inner: F, ttl: Duration) -> Self {
+		Self { inner, ttl, cache: Mutex::new(Cache::default()) }
+	}
+	
+	/// Forgets what's cached about `path`, everything under it, and its parent's listing.
+	pub fn invalidate(&self, path: &str) {
+		let path = key(path);
+		let mut cache = self.cache.lock().unwrap();
+		cache.stats.retain(|cached, _| !is_within(cached, &path));
+		cache.listings.retain(|cached, _| !is_within(cached, &path));
+		if let Some(parent) = parent(&path) {
+			cache.listings.remove(parent);
+		}
+	}
+	
+	/// Forgets everything.
+	pub fn clear(&self) {
+		let mut cache = self.cache.lock().unwrap();
+		cache.stats.clear();
+		cache.listings.clear();
+	}
+	
+	/// Gets the wrapped filesystem.
+	pub fn inner(&self) -> &F {
+		&self.inner
+	}
+	
+	/// Unwraps the wrapped filesystem.
+	pub fn into_inner(self) -> F {
+		self.inner
+	}
+	
+	/// Invalidates a path that may have been created, along with its parents, which are created too if they're
+	/// missing.
+	fn invalidate_created(&self, path: &str) {
+		let mut dir = Some(path);
+		while let Some(path) = dir {
+			self.invalidate(path);
+			dir = parent(path);
+		}
+	}
+	
+	fn cached_stat(&self, path: &str) -> io::Result<Option<HdfsDirectoryEntry>> {
+		let key = key(path);
+		if let Some(cached) = self.cache.lock().unwrap().stats.get(&key) {
+			if cached.at.elapsed() < self.ttl {
+				return Ok(cached.value.clone());
+			}
+		}
+		let value = match self.inner.stat(path) {
+			Ok(entry) => Some(entry),
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+			Err(e) => { return Err(e); },
+		};
+		self.cache.lock().unwrap().stats.insert(key, Cached { value: value.clone(), at: Instant::now() });
+		Ok(value)
+	}
+}
+
+impl<F: FileSystem> FileSystem for CachedFs<F> {
+	fn stat(&self, path: &str) -> io::Result<HdfsDirectoryEntry> {
+		self.cached_stat(path)?
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} does not exist", path)))
+	}
+	fn exists(&self, path: &str) -> io::Result<bool> {
+		Ok(self.cached_stat(path)?.is_some())
+	}
+	fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		let key = key(path);
+		if let Some(cached) = self.cache.lock().unwrap().listings.get(&key) {
+			if cached.at.elapsed() < self.ttl {
+				return Ok(cached.value.clone());
+			}
+		}
+		let entries = self.inner.list_dir(path)?;
+		self.cache.lock().unwrap().listings.insert(key, Cached { value: entries.clone(), at: Instant::now() });
+		Ok(entries)
+	}
+	fn create_dir(&self, path: &str) -> io::Result<()> {
+		let res = self.inner.create_dir(path);
+		self.invalidate_created(path);
+		res
+	}
+	fn delete(&self, path: &str, recursive: bool) -> io::Result<()> {
+		let res = self.inner.delete(path, recursive);
+		self.invalidate(path);
+		res
+	}
+	fn rename(&self, src: &str, dest: &str) -> io::Result<()> {
+		let res = self.inner.rename(src, dest);
+		self.invalidate(src);
+		self.invalidate(dest);
+		res
+	}
+	fn chmod(&self, path: &str, mode: u16) -> io::Result<()> {
+		let res = self.inner.chmod(path, mode);
+		self.invalidate(path);
+		res
+	}
+	fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> io::Result<()> {
+		let res = self.inner.chown(path, owner, group);
+		self.invalidate(path);
+		res
+	}
+	fn set_times(&self, path: &str, modified: Option<SystemTime>, accessed: Option<SystemTime>) -> io::Result<()> {
+		let res = self.inner.set_times(path, modified, accessed);
+		self.invalidate(path);
+		res
+	}
+	fn truncate(&self, path: &str, size: u64) -> io::Result<bool> {
+		let res = self.inner.truncate(path, size);
+		self.invalidate(path);
+		res
+	}
+	fn open_read(&self, path: &str) -> io::Result<Box<dyn FileReader + '_>> {
+		self.inner.open_read(path)
+	}
+	fn open_create(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		let res = self.inner.open_create(path);
+		self.invalidate_created(path);
+		Ok(Box::new(CachedWriter { fs: self, path: path.to_string(), inner: Some(res?) }))
+	}
+	fn open_append(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		let res = self.inner.open_append(path);
+		self.invalidate_created(path);
+		Ok(Box::new(CachedWriter { fs: self, path: path.to_string(), inner: Some(res?) }))
+	}
+}
+
+/// File opened for writing through a `CachedFs`, which invalidates it when it's closed.
+struct CachedWriter<'a, F: FileSystem> {
+	fs: &'a CachedFs<F>,
+	path: String,
+	// Only `None` when dropped
+	inner: Option<Box<dyn FileWriter + 'a>>,
+}
+impl<'a, F: FileSystem> Write for CachedWriter<'a, F> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.inner.as_mut().unwrap().write(buf)
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.as_mut().unwrap().flush()
+	}
+}
+impl<'a, F: FileSystem> FileWriter for CachedWriter<'a, F> {
+	fn sync(&mut self) -> io::Result<()> {
+		self.inner.as_mut().unwrap().sync()
+	}
+	fn close(self: Box<Self>) -> io::Result<()> {
+		// Dropping `self` afterwards invalidates the path
+		let mut this = self;
+		this.inner.take().unwrap().close()
+	}
+}
+impl<'a, F: FileSystem> Drop for CachedWriter<'a, F> {
+	fn drop(&mut self) {
+		// Close first, so the size is final
+		drop(self.inner.take());
+		self.fs.invalidate(&self.path);
+	}
+}
+
+/// Gets the key that a path is cached under.
+fn key(path: &str) -> String {
+	let path = uri_path(path);
+	match path.trim_end_matches('/') {
+		"" if path.starts_with('/') => "/".to_string(),
+		trimmed => trimmed.to_string(),
+	}
+}
+
+/// Checks if `path` is `dir` or under it.
+fn is_within(path: &str, dir: &str) -> bool {
+	path == dir || dir == "/" || (path.starts_with(dir) && path[dir.len()..].starts_with('/'))
+}
@@ -0,0 +1,159 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Writers that check what was stored against what was written.
+
+use crate::filesystem::{FileSystem, FileWriter};
+use std::io::{self, Read, Write};
+
+/// Checksum that `ChecksummedWriter` computes.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ChecksumAlgorithm {
+	/// CRC-32C, as 4 big-endian bytes
+	Crc32c,
+	/// MD5, as 16 bytes
+	Md5,
+}
+
+enum Digest {
+	Crc32c(u32),
+	Md5(md5::Context),
+}
+
+impl Digest {
+	fn new(algorithm: ChecksumAlgorithm) -> Self {
+		match algorithm {
+			ChecksumAlgorithm::Crc32c => Digest::Crc32c(0),
+			ChecksumAlgorithm::Md5 => Digest::Md5(md5::Context::new()),
+		}
+	}
+	
+	fn update(&mut self, data: &[u8]) {
+		match self {
+			Digest::Crc32c(crc) => { *crc = crc32c::crc32c_append(*crc, data); },
+			Digest::Md5(context) => { context.consume(data); },
+		}
+	}
+	
+	fn finish(self) -> Vec<u8> {
+		match self {
+			Digest::Crc32c(crc) => crc.to_be_bytes().to_vec(),
+			Digest::Md5(context) => context.compute().0.to_vec(),
+		}
+	}
+}
+
+/// Creates a file and checksums the data written to it. `finish` closes the file, then reads it back and
+/// fails if its contents don't match.
+/// 
+/// `libhdfs` can't get a file's checksum from the datanodes, so verifying means reading the whole file again.
+/// Dropping the writer without calling `finish` closes the file without verifying it.
+pub struct ChecksummedWriter<'a, F: FileSystem + ?Sized> {
+	fs: &'a F,
+	path: String,
+	file: Box<dyn FileWriter + 'a>,
+	algorithm: ChecksumAlgorithm,
+	digest: Digest,
+	len: u64,
+}
+
+impl<'a, F: FileSystem + ?Sized> ChecksummedWriter<'a, F> {
+	/// Creates or replaces a file.
+	pub fn create(fs: &'a F, path: &str, algorithm: ChecksumAlgorithm) -> io::Result<Self> {
+		Ok(Self {
+			fs,
+			path: path.to_string(),
+			file: fs.open_create(path)?,
+			algorithm,
+			digest: Digest::new(algorithm),
+			len: 0,
+		})
+	}
+	
+	/// Gets the number of bytes written so far.
+	pub fn len(&self) -> u64 {
+		self.len
+	}
+	
+	/// Checks if nothing has been written yet.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	
+	/// Closes the file, then reads it back and compares its length and checksum with what was written. Returns
+	/// the checksum, or an `InvalidData` error if the file doesn't match.
+	pub fn finish(self) -> io::Result<Vec<u8>> {
+		let Self { fs, path, file, algorithm, digest, len } = self;
+		file.close()?;
+		let expected = digest.finish();
+		
+		let (stored, stored_len) = checksum_reader(algorithm, &mut fs.open_read(&path)?)?;
+		if stored_len != len {
+			return Err(io::Error::new(io::ErrorKind::InvalidData,
+				format!("{} has {} bytes, but {} were written", path, stored_len, len)));
+		}
+		if stored != expected {
+			return Err(io::Error::new(io::ErrorKind::InvalidData,
+				format!("{} does not match the {:?} checksum of what was written", path, algorithm)));
+		}
+		Ok(expected)
+	}
+}
+
+impl<'a, F: FileSystem + ?Sized> Write for ChecksummedWriter<'a, F> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let n = self.file.write(buf)?;
+		self.digest.update(&buf[..n]);
+		self.len += n as u64;
+		Ok(n)
+	}
+	
+	fn flush(&mut self) -> io::Result<()> {
+		self.file.flush()
+	}
+}
+
+impl<'a, F: FileSystem + ?Sized> FileWriter for ChecksummedWriter<'a, F> {
+	fn sync(&mut self) -> io::Result<()> {
+		self.file.sync()
+	}
+	/// Closes the file without verifying it.
+	fn close(self: Box<Self>) -> io::Result<()> {
+		self.file.close()
+	}
+}
+
+/// Checksums everything `reader` reads, returning the checksum and the number of bytes read.
+pub(crate) fn checksum_reader<R: Read + ?Sized>(algorithm: ChecksumAlgorithm, reader: &mut R) -> io::Result<(Vec<u8>, u64)> {
+	let mut digest = Digest::new(algorithm);
+	let mut buf = vec![0; 1024 * 1024];
+	let mut len = 0u64;
+	loop {
+		let n = match reader.read(&mut buf) {
+			Ok(0) => { break; },
+			Ok(n) => n,
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => { continue; },
+			Err(e) => { return Err(e); },
+		};
+		digest.update(&buf[..n]);
+		len += n as u64;
+	}
+	Ok((digest.finish(), len))
+}
@@ -0,0 +1,92 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Building the Java classpath that `libhdfs` needs from a Hadoop installation.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Directories under `share/hadoop` that `libhdfs` needs, in the order `hadoop classpath` lists them. Ones ending
+/// in `/*` are expanded to the jars in them.
+const SHARE_DIRS: &[&str] = &["common/lib/*", "common/*", "hdfs", "hdfs/lib/*", "hdfs/*"];
+
+/// Builds the classpath for the Hadoop installation at `HADOOP_HOME` (or `HADOOP_PREFIX`), like
+/// `hadoop classpath --glob` does, but without the MapReduce and YARN jars.
+/// 
+/// The configuration directory comes first, from `HADOOP_CONF_DIR` or `etc/hadoop` in the installation.
+pub fn hadoop_classpath() -> io::Result<String> {
+	let home = ["HADOOP_HOME", "HADOOP_PREFIX"].iter()
+		.filter_map(env::var_os)
+		.find(|home| !home.is_empty())
+		.map(PathBuf::from)
+		.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HADOOP_HOME is not set"))?;
+	let conf_dir = env::var_os("HADOOP_CONF_DIR")
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+		.unwrap_or_else(|| home.join("etc").join("hadoop"));
+	
+	let mut entries = vec![conf_dir];
+	let share = home.join("share").join("hadoop");
+	for dir in SHARE_DIRS {
+		match dir.strip_suffix("/*") {
+			Some(dir) => { entries.extend(jars(&share.join(dir))?); },
+			None => { entries.push(share.join(dir)); },
+		}
+	}
+	let classpath = env::join_paths(entries)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+	classpath.into_string()
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "classpath is not valid UTF-8"))
+}
+
+/// Sets `CLASSPATH` to `hadoop_classpath()`, unless it's already set.
+/// 
+/// Must be called before the first connection, since that starts the JVM, which reads `CLASSPATH` once.
+/// Modifies the environment, so call it early on, before starting other threads.
+pub fn init_classpath() -> io::Result<()> {
+	if env::var_os("CLASSPATH").map(|classpath| !classpath.is_empty()).unwrap_or(false) {
+		return Ok(());
+	}
+	env::set_var("CLASSPATH", hadoop_classpath()?);
+	return Ok(());
+}
+
+/// Lists the jars in a directory, sorted by name. Missing directories are skipped, as `hadoop classpath` does.
+fn jars(dir: &Path) -> io::Result<Vec<PathBuf>> {
+	let entries = match fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => { return Ok(vec![]); },
+		Err(e) => { return Err(e); },
+	};
+	let mut jars = vec![];
+	for entry in entries {
+		let path = entry?.path();
+		let is_jar = path.extension()
+			.map(|ext| ext.eq_ignore_ascii_case("jar"))
+			.unwrap_or(false);
+		if is_jar {
+			jars.push(path);
+		}
+	}
+	jars.sort();
+	Ok(jars)
+}
@@ -0,0 +1,237 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Property-based conformance suite for `FileSystem` implementations.
+//! 
+//! `Conformance` generates random sequences of operations with proptest, runs each sequence against a backend
+//! and against a `MemoryFs` as the reference model, and fails on the first operation where they disagree, or
+//! where the directory trees differ afterwards. Failing sequences are shrunk to a minimal one before being
+//! reported. Since `MemoryFs` follows HDFS's semantics, running the suite against other backends, like the
+//! local filesystem through libhdfs, shows where they diverge from HDFS.
+//! 
+//! Only whether an operation succeeded is compared, not the kind of error, since libhdfs reports many
+//! failures as a generic I/O error.
+
+use crate::filesystem::FileSystem;
+use crate::memory::MemoryFs;
+use crate::path::{base_name, uri_path};
+use crate::HdfsDirectoryEntryKind;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestCaseError, TestError, TestRunner};
+use std::io::{self, Read, Write};
+
+/// Names that generated paths are made of. Few enough that operations often hit the same paths.
+const NAMES: &[&str] = &["a", "b", "c"];
+
+/// Permissions that `chmod` operations set.
+const MODES: &[u16] = &[0o600, 0o644, 0o700, 0o755];
+
+/// Runs random operation sequences against a `FileSystem`, created by `Conformance::new`.
+pub struct Conformance {
+	root: String,
+	cases: u32,
+	max_ops: usize,
+}
+
+/// Operation in a generated sequence. Paths are relative to the root directory of the run.
+#[derive(Debug,Clone)]
+enum FsOp {
+	CreateDir(String),
+	Write(String, Vec<u8>),
+	Append(String, Vec<u8>),
+	Rename(String, String),
+	Delete(String, bool),
+	Read(String),
+	ListDir(String),
+	Stat(String),
+	Chmod(String, u16),
+}
+
+/// What an operation returned, with the parts that should be the same on every backend.
+#[derive(Debug,Clone,PartialEq,Eq)]
+enum Outcome {
+	Failed,
+	Done,
+	Data(Vec<u8>),
+	/// Sorted names, whether they're directories, and sizes
+	Entries(Vec<(String, bool, u64)>),
+	Entry(bool, u64, u16),
+}
+
+/// File or directory in a tree snapshot: path relative to the root, whether it's a directory, permissions, and
+/// contents for files.
+type Snapshot = Vec<(String, bool, u16, Option<Vec<u8>>)>;
+
+impl Conformance {
+	/// Runs sequences inside `root`, which is deleted and recreated before each one.
+	pub fn new(root: &str) -> Self {
+		Self {
+			root: root.trim_end_matches('/').to_string(),
+			cases: 32,
+			max_ops: 16,
+		}
+	}
+	
+	/// Sets how many sequences to run. Defaults to 32.
+	pub fn cases(mut self, cases: u32) -> Self {
+		self.cases = cases;
+		self
+	}
+	
+	/// Sets the maximum length of a sequence. Defaults to 16.
+	pub fn max_ops(mut self, max_ops: usize) -> Self {
+		self.max_ops = max_ops;
+		self
+	}
+	
+	/// Runs the suite against `fs`. On failure, the error describes the shortest failing sequence found and
+	/// where it diverged.
+	pub fn run<F: FileSystem + ?Sized>(&self, fs: &F) -> io::Result<()> {
+		let mut runner = TestRunner::new(Config {
+			cases: self.cases,
+			failure_persistence: None,
+			..Config::default()
+		});
+		let result = runner.run(&vec(op(), 1..=self.max_ops.max(1)), |ops| {
+			self.run_case(fs, &ops).map_err(TestCaseError::fail)
+		});
+		match result {
+			Ok(()) => Ok(()),
+			Err(TestError::Fail(reason, ops)) => Err(io::Error::new(io::ErrorKind::Other,
+				format!("{}\nminimal failing sequence: {:#?}", reason, ops))),
+			Err(TestError::Abort(reason)) => Err(io::Error::new(io::ErrorKind::Other, reason.to_string())),
+		}
+	}
+	
+	fn run_case<F: FileSystem + ?Sized>(&self, fs: &F, ops: &[FsOp]) -> Result<(), String> {
+		let model = MemoryFs::new();
+		let backend_root = reset(fs, &self.root)
+			.map_err(|e| format!("could not create {}: {}", self.root, e))?;
+		let model_root = reset(&model, &self.root)
+			.map_err(|e| format!("could not create {} in the model: {}", self.root, e))?;
+		
+		for (i, op) in ops.iter().enumerate() {
+			let actual = apply(fs, &self.root, op);
+			let expected = apply(&model, &self.root, op);
+			if actual != expected {
+				return Err(format!("step {} ({:?}): backend returned {:?}, model returned {:?}", i, op, actual, expected));
+			}
+			
+			let actual = snapshot(fs, &backend_root).map_err(|e| format!("step {} ({:?}): could not list the backend: {}", i, op, e))?;
+			let expected = snapshot(&model, &model_root).map_err(|e| format!("step {} ({:?}): could not list the model: {}", i, op, e))?;
+			if actual != expected {
+				return Err(format!("step {} ({:?}): backend contains {:?}, model contains {:?}", i, op, actual, expected));
+			}
+		}
+		Ok(())
+	}
+}
+
+fn path() -> impl Strategy<Value = String> {
+	vec(proptest::sample::select(NAMES), 1..=3).prop_map(|names| names.join("/"))
+}
+
+fn data() -> impl Strategy<Value = Vec<u8>> {
+	vec(any::<u8>(), 0..64)
+}
+
+fn op() -> impl Strategy<Value = FsOp> {
+	prop_oneof![
+		path().prop_map(FsOp::CreateDir),
+		(path(), data()).prop_map(|(path, data)| FsOp::Write(path, data)),
+		(path(), data()).prop_map(|(path, data)| FsOp::Append(path, data)),
+		(path(), path()).prop_map(|(src, dest)| FsOp::Rename(src, dest)),
+		(path(), any::<bool>()).prop_map(|(path, recursive)| FsOp::Delete(path, recursive)),
+		path().prop_map(FsOp::Read),
+		path().prop_map(FsOp::ListDir),
+		path().prop_map(FsOp::Stat),
+		(path(), proptest::sample::select(MODES)).prop_map(|(path, mode)| FsOp::Chmod(path, mode)),
+	]
+}
+
+/// Deletes and recreates the root directory, returning its absolute path.
+fn reset<F: FileSystem + ?Sized>(fs: &F, root: &str) -> io::Result<String> {
+	match fs.delete(root, true) {
+		Ok(()) => {},
+		Err(_) if !fs.exists(root)? => {},
+		Err(e) => { return Err(e); },
+	}
+	fs.create_dir(root)?;
+	Ok(uri_path(&fs.stat(root)?.name).trim_end_matches('/').to_string())
+}
+
+fn apply<F: FileSystem + ?Sized>(fs: &F, root: &str, op: &FsOp) -> Outcome {
+	let full = |path: &str| format!("{}/{}", root, path);
+	let result = match op {
+		FsOp::CreateDir(path) => fs.create_dir(&full(path)).map(|()| Outcome::Done),
+		FsOp::Write(path, data) => fs.open_create(&full(path))
+			.and_then(|mut file| { file.write_all(data)?; file.close() })
+			.map(|()| Outcome::Done),
+		FsOp::Append(path, data) => fs.open_append(&full(path))
+			.and_then(|mut file| { file.write_all(data)?; file.close() })
+			.map(|()| Outcome::Done),
+		FsOp::Rename(src, dest) => fs.rename(&full(src), &full(dest)).map(|()| Outcome::Done),
+		FsOp::Delete(path, recursive) => fs.delete(&full(path), *recursive).map(|()| Outcome::Done),
+		FsOp::Read(path) => fs.open_read(&full(path))
+			.and_then(|mut file| {
+				let mut data = vec![];
+				file.read_to_end(&mut data)?;
+				Ok(Outcome::Data(data))
+			}),
+		FsOp::ListDir(path) => fs.list_dir(&full(path)).map(|entries| {
+			let mut entries: Vec<_> = entries.iter()
+				.map(|entry| (base_name(&entry.name).to_string(), is_dir(&entry.kind), entry.size))
+				.collect();
+			entries.sort();
+			Outcome::Entries(entries)
+		}),
+		FsOp::Stat(path) => fs.stat(&full(path)).map(|entry| Outcome::Entry(is_dir(&entry.kind), entry.size, entry.permissions)),
+		FsOp::Chmod(path, mode) => fs.chmod(&full(path), *mode).map(|()| Outcome::Done),
+	};
+	result.unwrap_or(Outcome::Failed)
+}
+
+/// Lists everything under `root`, sorted by path.
+fn snapshot<F: FileSystem + ?Sized>(fs: &F, root: &str) -> io::Result<Snapshot> {
+	let mut items = vec![];
+	let mut dirs = vec![root.to_string()];
+	while let Some(dir) = dirs.pop() {
+		for entry in fs.list_dir(&dir)? {
+			let path = uri_path(&entry.name).trim_end_matches('/').to_string();
+			let relative = path.strip_prefix(root).unwrap_or(&path).to_string();
+			if is_dir(&entry.kind) {
+				items.push((relative, true, entry.permissions, None));
+				dirs.push(path);
+			} else {
+				let mut data = vec![];
+				fs.open_read(&path)?.read_to_end(&mut data)?;
+				items.push((relative, false, entry.permissions, Some(data)));
+			}
+		}
+	}
+	items.sort();
+	Ok(items)
+}
+
+fn is_dir(kind: &HdfsDirectoryEntryKind) -> bool {
+	matches!(kind, HdfsDirectoryEntryKind::Directory)
+}
+
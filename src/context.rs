@@ -0,0 +1,109 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Tagging requests, so that cluster logs attribute them to the job that made them rather than to an anonymous
+//! client.
+//! 
+//! There are two tags. The caller context is Hadoop's per-thread tag for requests, which the namenode writes to its
+//! audit log with each request when `hadoop.caller.context.enabled` is set on it. `libhdfs` has no call for it,
+//! so it's set through JNI. The client name is per connection, and shows up in lease messages in the namenode's
+//! log and in datanodes' client trace logs of reads and writes.
+
+use crate::jni::{Env, Frame, JValue};
+use crate::{HdfsBuilder, HdfsConnection};
+use std::io;
+use std::marker::PhantomData;
+use std::ptr;
+
+impl HdfsBuilder {
+	/// Tags the connection's client name, so that it's `DFSClient_<tag>_<random number>_<thread ID>` rather than
+	/// `DFSClient_NONMAPREDUCE_...`. This is how MapReduce tasks identify themselves, through the
+	/// `mapreduce.task.attempt.id` property.
+	/// 
+	/// The name is chosen when a filesystem instance is created, so this also calls `force_new_instance`.
+	pub fn client_name_tag(&mut self, tag: &str) -> io::Result<()> {
+		if tag.is_empty() || tag.contains(char::is_whitespace) {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid client name tag {:?}", tag)));
+		}
+		self.conf_set("mapreduce.task.attempt.id", tag)?;
+		self.force_new_instance();
+		Ok(())
+	}
+}
+
+/// Clears the current thread's caller context when dropped. Returned by `HdfsConnection::caller_context`.
+#[must_use = "the caller context is cleared when this is dropped"]
+pub struct CallerContextGuard<'a> {
+	_fs: PhantomData<&'a HdfsConnection>,
+	// The context belongs to the thread that set it
+	_not_send: PhantomData<*const ()>,
+}
+
+impl<'a> Drop for CallerContextGuard<'a> {
+	fn drop(&mut self) {
+		let _ = set_current(None);
+	}
+}
+
+impl HdfsConnection {
+	/// Sets the caller context of the current thread to `context`, ex. a job ID, until the returned guard is
+	/// dropped; see `set_caller_context`.
+	pub fn caller_context(&self, context: &str) -> io::Result<CallerContextGuard> {
+		self.set_caller_context(Some(context))?;
+		Ok(CallerContextGuard { _fs: PhantomData, _not_send: PhantomData })
+	}
+	
+	/// Sets or clears the caller context of the current thread, which the namenode records in its audit log for
+	/// every request the thread makes afterwards, through any connection.
+	/// 
+	/// The namenode only logs it when `hadoop.caller.context.enabled` is set, and truncates it to
+	/// `hadoop.caller.context.max.size` bytes, 128 by default. The thread stays attached to the JVM, since
+	/// detaching would lose the context.
+	pub fn set_caller_context(&self, context: Option<&str>) -> io::Result<()> {
+		set_current(context)
+	}
+}
+
+/// Sets the current thread's caller context, with `CallerContext.setCurrent`.
+fn set_current(context: Option<&str>) -> io::Result<()> {
+	unsafe {
+		let env = Env::current()?
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "the JVM hasn't been started"))?;
+		set_current_in(&env, context)
+	}
+}
+
+/// Does the JNI calls for `set_current`.
+unsafe fn set_current_in(env: &Env, context: Option<&str>) -> io::Result<()> {
+	let mut frame = Frame::new(env);
+	let context_class = frame.class(b"org/apache/hadoop/ipc/CallerContext\0")
+		.map_err(|e| io::Error::new(e.kind(), format!("{}; it needs Hadoop 2.8 or later", e)))?;
+	let mut java_context = ptr::null_mut();
+	if let Some(context) = context {
+		let builder_class = frame.class(b"org/apache/hadoop/ipc/CallerContext$Builder\0")?;
+		let string = frame.new_string(context)?;
+		let builder = frame.new_object(builder_class, b"(Ljava/lang/String;)V\0", &[JValue { l: string }])?;
+		let build = frame.method(builder_class, b"build\0", b"()Lorg/apache/hadoop/ipc/CallerContext;\0")?;
+		java_context = frame.call_object(builder, build, &[], "could not build a CallerContext")?;
+	}
+	
+	let set_current = frame.static_method(context_class, b"setCurrent\0", b"(Lorg/apache/hadoop/ipc/CallerContext;)V\0")?;
+	frame.call_static_void(context_class, set_current, &[JValue { l: java_context }], "CallerContext.setCurrent failed")
+}
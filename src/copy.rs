@@ -0,0 +1,279 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Copies that read and write at the same time.
+//! 
+//! `io::copy` alternates between reading and writing, so each side sits idle while the other works. These read
+//! on one thread and write on another, passing buffers from `BufferPool` through a bounded channel, which
+//! roughly doubles throughput when both sides are slow, like HDFS streams.
+//! 
+//! `HdfsFile`s can't be moved to another thread, so each function keeps one side on the calling thread: `copy`
+//! for writing to HDFS, `copy_out` for reading from HDFS, and `copy_file` for HDFS to HDFS.
+//! 
+//! `HdfsConnection::copy` copies within one filesystem, leaving the streaming to `libhdfs` where it can.
+
+use crate::path::{self, base_name, AsHdfsPath};
+use crate::pool::{BufferPool, PooledBuffer};
+use crate::{check_rt, HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind, Preserve};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::OnceLock;
+use std::thread;
+
+/// Size of the buffers passed between the threads.
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// How many filled buffers can wait for the writer before the reader blocks.
+const DEPTH: usize = 4;
+
+/// Buffers shared by every copy in the process.
+fn pool() -> &'static BufferPool {
+	static POOL: OnceLock<BufferPool> = OnceLock::new();
+	POOL.get_or_init(|| BufferPool::new(BUFFER_SIZE).max_idle(DEPTH * 4))
+}
+
+/// Filled buffer, and how many bytes of it were read.
+type Chunk = (PooledBuffer<'static>, usize);
+
+/// Copies everything from `reader` to `writer`, reading on a separate thread. Returns the number of bytes
+/// copied.
+/// 
+/// The writer stays on the calling thread, so it can be an `HdfsFile`, ex. to upload a local file. It's flushed
+/// once everything has been written.
+pub fn copy<R, W>(reader: &mut R, writer: &mut W) -> io::Result<u64>
+	where
+		R: Read + Send + ?Sized,
+		W: Write + ?Sized,
+{
+	let (tx, rx) = mpsc::sync_channel(DEPTH);
+	thread::scope(|scope| {
+		let read = scope.spawn(move || read_loop(reader, tx));
+		let written = write_loop(writer, rx);
+		join(read).and(written)
+	})
+}
+
+/// Copies everything from `reader` to `writer`, writing on a separate thread. Returns the number of bytes copied.
+/// 
+/// The reader stays on the calling thread, so it can be an `HdfsFile`, ex. to download a file. The writer is
+/// flushed once everything has been written.
+pub fn copy_out<R, W>(reader: &mut R, writer: &mut W) -> io::Result<u64>
+	where
+		R: Read + ?Sized,
+		W: Write + Send + ?Sized,
+{
+	let (tx, rx) = mpsc::sync_channel(DEPTH);
+	thread::scope(|scope| {
+		let write = scope.spawn(move || write_loop(writer, rx));
+		let read = read_loop(reader, tx);
+		let written = match write.join() {
+			Ok(res) => res,
+			Err(_) => Err(io::Error::new(io::ErrorKind::Other, "writer thread panicked")),
+		};
+		read.and(written)
+	})
+}
+
+/// Copies a file between HDFS paths, possibly on different clusters, reading on a separate thread. The
+/// destination is created or replaced, with the same block size as the source. Returns the number of bytes
+/// copied.
+/// 
+/// Connections can't be shared between threads, so the source connection is borrowed mutably while it's used
+/// by the reading thread. To copy within one cluster, make the second connection with
+/// `HdfsBuilder::connect_isolated`; two plain `connect`s would share a cached filesystem instance, and dropping
+/// either connection would close it for both.
+pub fn copy_file<S, D>(src_fs: &mut HdfsConnection, src: &S, dest_fs: &HdfsConnection, dest: &D) -> io::Result<u64>
+	where S: AsHdfsPath + ?Sized, D: AsHdfsPath + ?Sized
+{
+	copy_file_preserving(src_fs, src, dest_fs, dest, Preserve::new().block_size())
+}
+
+/// Like `copy_file`, but gives the destination the attributes of the source that `preserve` selects, once it's
+/// been written. The block size is only kept if it's selected.
+pub fn copy_file_preserving<S, D>(src_fs: &mut HdfsConnection, src: &S, dest_fs: &HdfsConnection, dest: &D, preserve: Preserve) -> io::Result<u64>
+	where S: AsHdfsPath + ?Sized, D: AsHdfsPath + ?Sized
+{
+	// Check the source before creating the destination, which would otherwise be left empty
+	let entry = src_fs.stat(src)?;
+	let mut builder = dest_fs.open_create_builder(dest)?;
+	preserve.apply_to_builder(&mut builder, &entry)?;
+	let mut out_file = builder.build()?;
+	
+	let (tx, rx) = mpsc::sync_channel(DEPTH);
+	let name = entry.name.as_str();
+	let copied = thread::scope(|scope| {
+		let read = scope.spawn(move || {
+			let mut in_file = src_fs.open_read(name)?;
+			read_loop(&mut in_file, tx)
+		});
+		let written = write_loop(&mut out_file, rx);
+		join(read).and(written)
+	})?;
+	out_file.close()?;
+	dest_fs.preserve_attributes(dest, &entry, preserve)?;
+	Ok(copied)
+}
+
+impl HdfsConnection {
+	/// Copies a file or directory tree within this filesystem. If `dest` is an existing directory, `src` is
+	/// copied into it. Existing files are replaced.
+	/// 
+	/// HDFS has no server-side copy, so the data always passes through the client. With `libhdfs`, this uses
+	/// `hdfsCopy`, which streams it within the JVM instead of copying every buffer out to Rust and back. libhdfs++
+	/// doesn't support it, so there, or if it fails with `Unsupported`, files are streamed here instead, with the
+	/// same block size as their source.
+	pub fn copy<S: AsHdfsPath + ?Sized, D: AsHdfsPath + ?Sized>(&self, src: &S, dest: &D) -> io::Result<()> {
+		let src = &*src.as_str_path()?;
+		let dest = &*dest.as_str_path()?;
+		if !libhdfs_sys::is_libhdfspp() {
+			let c_src = src.as_c_path()?;
+			let c_dest = dest.as_c_path()?;
+			let rt = unsafe { libhdfs_sys::hdfsCopy(self.p.as_ptr(), c_src.as_ptr(), self.p.as_ptr(), c_dest.as_ptr()) };
+			match check_rt(rt) {
+				Err(ref e) if e.kind() == io::ErrorKind::Unsupported => {},
+				res => { return res; },
+			}
+		}
+		self.copy_streaming(src, dest)
+	}
+	
+	/// Copies like `hdfsCopy`, but through this process.
+	fn copy_streaming(&self, src: &str, dest: &str) -> io::Result<()> {
+		let root = self.stat(src)?;
+		let dest = match self.stat(dest) {
+			Ok(ref entry) if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) => path::join(dest, base_name(&root.name)),
+			Ok(_) => dest.to_string(),
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => dest.to_string(),
+			Err(e) => { return Err(e); },
+		};
+		self.copy_tree(&root, self, &dest, Verify::Nothing)?;
+		Ok(())
+	}
+	
+	/// Copies `root` and everything under it to exactly `dest` on `dest_fs`, one file at a time on this thread.
+	/// Files are created with the same block size as their source. Returns the number of bytes copied.
+	pub(crate) fn copy_tree(&self, root: &HdfsDirectoryEntry, dest_fs: &HdfsConnection, dest: &str, verify: Verify) -> io::Result<u64> {
+		// List everything first, so copying a directory into itself doesn't copy the copy
+		let entries = self.walk(&root.name)
+			.map(|item| item.map(|item| item.entry))
+			.collect::<io::Result<Vec<_>>>()?;
+		let mut copied = 0;
+		for entry in entries {
+			let relative = entry.name.strip_prefix(root.name.as_str())
+				.unwrap_or(&entry.name)
+				.trim_start_matches('/');
+			let target = if relative.is_empty() { dest.to_string() } else { path::join(dest, relative) };
+			if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) {
+				dest_fs.create_dir(&target)?;
+				continue;
+			}
+			
+			let mut in_file = self.open_read(&entry.name)?;
+			let mut builder = dest_fs.open_create_builder(&target)?;
+			builder.default_block_size(entry.block_size as i64)?;
+			let mut out_file = HashingWriter { inner: builder.build()?, hasher: DefaultHasher::new() };
+			let len = pool().copy(&mut in_file, &mut out_file)?;
+			let HashingWriter { inner, hasher } = out_file;
+			inner.close()?;
+			copied += len;
+			
+			if verify == Verify::Nothing {
+				continue;
+			}
+			let copy = dest_fs.stat(&target)?;
+			if copy.size != entry.size || len != entry.size {
+				return Err(io::Error::new(io::ErrorKind::InvalidData,
+					format!("{} has {} bytes after copying, but {} has {}", target, copy.size, entry.name, entry.size)));
+			}
+			if verify == Verify::Contents {
+				let mut read_back = HashingWriter { inner: io::sink(), hasher: DefaultHasher::new() };
+				pool().copy(&mut dest_fs.open_read(&copy.name)?, &mut read_back)?;
+				if read_back.hasher.finish() != hasher.finish() {
+					return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} differs from {} after copying", target, entry.name)));
+				}
+			}
+		}
+		Ok(copied)
+	}
+}
+
+/// What `HdfsConnection::copy_tree` checks after copying each file.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub(crate) enum Verify {
+	Nothing,
+	/// The copy is the same size as the source
+	Size,
+	/// The copy is the same size, and reads back the same as what was written
+	Contents,
+}
+
+/// Hashes everything written through it.
+struct HashingWriter<W> {
+	inner: W,
+	hasher: DefaultHasher,
+}
+impl<W: Write> Write for HashingWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let len = self.inner.write(buf)?;
+		self.hasher.write(&buf[..len]);
+		Ok(len)
+	}
+	
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+/// Reads into buffers and sends them until the end of the reader, or until the writer hangs up.
+fn read_loop<R: Read + ?Sized>(reader: &mut R, tx: SyncSender<Chunk>) -> io::Result<()> {
+	loop {
+		let mut buf = pool().get();
+		let len = match reader.read(&mut buf) {
+			Ok(0) => { return Ok(()); },
+			Ok(len) => len,
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => { continue; },
+			Err(e) => { return Err(e); },
+		};
+		if tx.send((buf, len)).is_err() {
+			// The writer failed, and will return the error
+			return Ok(());
+		}
+	}
+}
+
+/// Writes buffers until the reader hangs up, then flushes.
+fn write_loop<W: Write + ?Sized>(writer: &mut W, rx: Receiver<Chunk>) -> io::Result<u64> {
+	let mut written = 0;
+	for (buf, len) in rx {
+		writer.write_all(&buf[..len])?;
+		written += len as u64;
+	}
+	writer.flush()?;
+	Ok(written)
+}
+
+fn join(handle: thread::ScopedJoinHandle<'_, io::Result<()>>) -> io::Result<()> {
+	match handle.join() {
+		Ok(res) => res,
+		Err(_) => Err(io::Error::new(io::ErrorKind::Other, "reader thread panicked")),
+	}
+}
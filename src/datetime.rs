@@ -0,0 +1,48 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! `chrono` conversions for entry times, with the `chrono` feature.
+//! 
+//! These have the same precision as the `SystemTime`s they're converted from: whole seconds with `libhdfs`, and
+//! milliseconds with libhdfs++.
+
+use crate::path::AsHdfsPath;
+use crate::{HdfsConnection, HdfsDirectoryEntry};
+use chrono::{DateTime, Utc};
+use std::io;
+
+impl HdfsDirectoryEntry {
+	/// Gets `last_modified` as a `DateTime`.
+	pub fn last_modified_utc(&self) -> DateTime<Utc> {
+		self.last_modified.into()
+	}
+	
+	/// Gets `last_access` as a `DateTime`.
+	pub fn last_access_utc(&self) -> DateTime<Utc> {
+		self.last_access.into()
+	}
+}
+
+impl HdfsConnection {
+	/// Changes the modification and access times of a file, like `set_times`.
+	pub fn set_times_utc<P: AsHdfsPath + ?Sized>(&self, path: &P, modified: Option<DateTime<Utc>>, accessed: Option<DateTime<Utc>>) -> io::Result<()> {
+		self.set_times(path, modified.map(Into::into), accessed.map(Into::into))
+	}
+}
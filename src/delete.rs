@@ -0,0 +1,210 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Recursive deletes with a dry run, a size guard, and progress reporting, and deleting many paths at once.
+
+use crate::filesystem::Op;
+use crate::intercept::{Interceptor, Retry};
+use crate::path::AsHdfsPath;
+use crate::{HdfsBuilder, HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::io;
+
+/// Callback for `DeleteOptions::progress`.
+type Progress<'a> = Box<dyn FnMut(&HdfsDirectoryEntry) + 'a>;
+
+/// Options for `HdfsConnection::delete_recursive_with`.
+#[derive(Default)]
+pub struct DeleteOptions<'a> {
+	dry_run: bool,
+	max_entries: Option<u64>,
+	progress: Option<Progress<'a>>,
+}
+
+/// What `HdfsConnection::delete_recursive_with` deleted, or would have deleted in a dry run.
+#[derive(Debug,Clone,Default)]
+pub struct DeleteSummary {
+	pub files: u64,
+	pub directories: u64,
+	/// Total size of the files
+	pub bytes: u64,
+	/// Everything that would be deleted, contents before their directories. Only filled in by a dry run.
+	pub entries: Vec<HdfsDirectoryEntry>,
+}
+
+impl<'a> DeleteOptions<'a> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Lists what would be deleted, in `DeleteSummary::entries`, without deleting anything.
+	pub fn dry_run(mut self) -> Self {
+		self.dry_run = true;
+		self
+	}
+	
+	/// Refuses to delete anything if the tree has more than `max` files and directories, counting the path
+	/// itself.
+	pub fn max_entries(mut self, max: u64) -> Self {
+		self.max_entries = Some(max);
+		self
+	}
+	
+	/// Calls `progress` after each file or directory is deleted, or for each one that would be in a dry run.
+	/// 
+	/// This deletes entries one at a time, contents before their directories, instead of with a single
+	/// recursive delete, so it takes a call to the namenode per entry.
+	pub fn progress<F: FnMut(&HdfsDirectoryEntry) + 'a>(mut self, progress: F) -> Self {
+		self.progress = Some(Box::new(progress));
+		self
+	}
+}
+
+/// Options for `HdfsConnection::delete_many`.
+/// 
+/// By default, paths are deleted one at a time on the calling thread, non-recursively, without retrying, and a
+/// path that doesn't exist is an error.
+#[derive(Default)]
+pub struct BulkDeleteOptions<'a> {
+	recursive: bool,
+	ignore_missing: bool,
+	retry: Option<Retry>,
+	parallel: usize,
+	builder: Option<&'a HdfsBuilder>,
+}
+
+impl<'a> BulkDeleteOptions<'a> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Deletes directories along with their contents.
+	pub fn recursive(mut self) -> Self {
+		self.recursive = true;
+		self
+	}
+	
+	/// Counts paths that don't exist as deleted.
+	pub fn ignore_missing(mut self) -> Self {
+		self.ignore_missing = true;
+		self
+	}
+	
+	/// Retries failed deletes with the backoff and error kinds of `retry`. Its operations are ignored, since
+	/// deletes aren't retried by default.
+	/// 
+	/// A delete can fail after the namenode has carried it out, ex. if the connection drops before the reply, so
+	/// a path that's gone by the time a retry is made counts as deleted.
+	pub fn retry(mut self, retry: Retry) -> Self {
+		self.retry = Some(retry.ops(&[Op::Delete]));
+		self
+	}
+	
+	/// Deletes up to `parallel` paths at once: on this connection on the calling thread, and on others made by
+	/// `connect_isolated` on a clone of `builder`, one per extra thread. They have to be isolated, since dropping
+	/// a worker's connection would otherwise close the filesystem instance it shares with this one. If connecting
+	/// fails, the work is spread over the connections that could be made.
+	pub fn parallel(mut self, parallel: usize, builder: &'a HdfsBuilder) -> Self {
+		self.parallel = parallel;
+		self.builder = Some(builder);
+		self
+	}
+}
+
+impl HdfsConnection {
+	/// Deletes every path in `paths`, returning the results in the same order.
+	/// 
+	/// Each delete is a round trip to the namenode, so this is mostly useful with `BulkDeleteOptions::parallel`
+	/// and `retry`, for deleting many paths whose parents differ. To delete everything in a tree, delete the root
+	/// recursively instead.
+	pub fn delete_many<P: AsHdfsPath + Sync>(&self, paths: &[P], options: BulkDeleteOptions<'_>) -> Vec<io::Result<()>> {
+		let (recursive, ignore_missing, retry) = (options.recursive, options.ignore_missing, options.retry.as_ref());
+		self.for_each_parallel(options.builder, options.parallel, paths.len(), |fs, i| {
+			paths[i].as_str_path().and_then(|path| delete_one(fs, &path, recursive, ignore_missing, retry))
+		})
+	}
+	
+	/// Deletes `path` and everything under it, with the safety checks and reporting in `options`.
+	/// 
+	/// The tree is listed before anything is deleted, so nothing is deleted if listing fails or the
+	/// `max_entries` guard trips.
+	pub fn delete_recursive_with<P: AsHdfsPath + ?Sized>(&self, path: &P, mut options: DeleteOptions<'_>) -> io::Result<DeleteSummary> {
+		let path = &*path.as_str_path()?;
+		let mut entries = vec![];
+		for item in self.walk(path) {
+			entries.push(item?.entry);
+			if let Some(max) = options.max_entries {
+				if entries.len() as u64 > max {
+					return Err(io::Error::new(io::ErrorKind::Other,
+						format!("refusing to delete {}, since it has more than {} entries", path, max)));
+				}
+			}
+		}
+		// The walk is depth-first with directories first, so reversing it puts contents before directories
+		entries.reverse();
+		
+		let mut summary = DeleteSummary::default();
+		let one_at_a_time = options.progress.is_some();
+		if !options.dry_run && !one_at_a_time {
+			if let Some(root) = entries.last() {
+				self.delete(&root.name, true)?;
+			}
+		}
+		for entry in entries.iter() {
+			if !options.dry_run && one_at_a_time {
+				self.delete(&entry.name, false)?;
+			}
+			match entry.kind {
+				HdfsDirectoryEntryKind::Directory => { summary.directories += 1; },
+				_ => {
+					summary.files += 1;
+					summary.bytes += entry.size;
+				},
+			}
+			if let Some(ref mut progress) = options.progress {
+				progress(entry);
+			}
+		}
+		if options.dry_run {
+			summary.entries = entries;
+		}
+		Ok(summary)
+	}
+}
+
+/// Deletes one path for `delete_many`, with retries.
+fn delete_one(fs: &HdfsConnection, path: &str, recursive: bool, ignore_missing: bool, retry: Option<&Retry>) -> io::Result<()> {
+	let mut attempts = 0;
+	let mut call = || {
+		attempts += 1;
+		match fs.delete(path, recursive) {
+			Ok(()) => Ok(()),
+			// libhdfs doesn't say why a delete failed, so check whether the path exists
+			Err(e) => match fs.exists(path) {
+				Ok(false) if attempts > 1 || ignore_missing => Ok(()),
+				Ok(false) => Err(io::Error::new(io::ErrorKind::NotFound, format!("{} does not exist", path))),
+				_ => Err(e),
+			},
+		}
+	};
+	match retry {
+		Some(retry) => retry.intercept(Op::Delete, &[path], &mut call),
+		None => call(),
+	}
+}
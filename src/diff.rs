@@ -0,0 +1,172 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Comparing two directory trees, possibly on different filesystems, for planning copies and verifying backups.
+
+use crate::path::AsHdfsPath;
+use crate::{HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::collections::BTreeMap;
+use std::io;
+use std::time::UNIX_EPOCH;
+
+/// Kind of difference reported by `diff_trees`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum TreeDiffKind {
+	/// Exists in tree B only
+	Added,
+	/// Exists in tree A only
+	Removed,
+	/// A file that differs between the trees, according to the `DiffOptions`
+	Changed,
+}
+
+/// Entry returned by `diff_trees`.
+#[derive(Debug,Clone)]
+pub struct TreeDiffEntry {
+	pub kind: TreeDiffKind,
+	/// Path relative to the roots of the trees, or an empty string for the roots themselves
+	pub path: String,
+	/// The entry in tree A, unless it was added
+	pub a: Option<HdfsDirectoryEntry>,
+	/// The entry in tree B, unless it was removed
+	pub b: Option<HdfsDirectoryEntry>,
+}
+
+/// Function that checksums a file, for `DiffOptions::checksum`.
+type ChecksumFn<'a> = dyn Fn(&HdfsConnection, &HdfsDirectoryEntry, u64) -> io::Result<Vec<u8>> + 'a;
+
+/// How `diff_trees` decides whether files have changed.
+/// 
+/// Files of different sizes have always changed. By default, files of the same size have changed if their
+/// modification times differ by a second or more, since `set_times` only has whole seconds.
+pub struct DiffOptions<'a> {
+	compare_times: bool,
+	checksum: Option<Box<ChecksumFn<'a>>>,
+}
+
+impl<'a> DiffOptions<'a> {
+	pub fn new() -> Self {
+		Self {
+			compare_times: true,
+			checksum: None,
+		}
+	}
+	
+	/// Sets whether files of the same size with different modification times have changed. Turning this off
+	/// without setting a checksum compares only sizes.
+	pub fn compare_times(mut self, compare_times: bool) -> Self {
+		self.compare_times = compare_times;
+		self
+	}
+	
+	/// Compares files of the same size by checksum, instead of by modification time.
+	/// 
+	/// The function gets the filesystem and entry of the file, and the block size of the file in tree A, which
+	/// it should use for both files if its checksum depends on block boundaries, like HDFS's
+	/// `MD5-of-MD5-of-CRC32C`. `libhdfs` can't ask the datanodes for checksums, so this usually means reading
+	/// both files.
+	pub fn checksum<F>(mut self, checksum: F) -> Self
+		where F: Fn(&HdfsConnection, &HdfsDirectoryEntry, u64) -> io::Result<Vec<u8>> + 'a
+	{
+		self.checksum = Some(Box::new(checksum));
+		self
+	}
+	
+	fn is_changed(&self, fs_a: &HdfsConnection, a: &HdfsDirectoryEntry, fs_b: &HdfsConnection, b: &HdfsDirectoryEntry) -> io::Result<bool> {
+		if a.size != b.size {
+			return Ok(true);
+		}
+		if let Some(ref checksum) = self.checksum {
+			return Ok(checksum(fs_a, a, a.block_size)? != checksum(fs_b, b, a.block_size)?);
+		}
+		Ok(self.compare_times && epoch_secs(a) != epoch_secs(b))
+	}
+}
+
+impl<'a> Default for DiffOptions<'a> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Compares the tree at `path_a` on `fs_a` with the tree at `path_b` on `fs_b`, returning the differences sorted
+/// by path.
+/// 
+/// Everything under an added or removed directory is reported too, and a directory sorts before its contents, so
+/// the contents of a removed directory can be skipped when deleting it recursively. Directories are only compared
+/// by existence; an entry that's a file in one tree and a directory in the other is reported as removed, then
+/// added. A path that
+/// doesn't exist is treated as an empty tree, so diffing against a copy that hasn't been made yet reports
+/// everything as added.
+pub fn diff_trees<PA, PB>(fs_a: &HdfsConnection, path_a: &PA, fs_b: &HdfsConnection, path_b: &PB, options: &DiffOptions) -> io::Result<Vec<TreeDiffEntry>>
+	where PA: AsHdfsPath + ?Sized, PB: AsHdfsPath + ?Sized
+{
+	let mut tree_a = index(fs_a, &path_a.as_str_path()?)?;
+	let tree_b = index(fs_b, &path_b.as_str_path()?)?;
+	
+	let mut diff = vec![];
+	for (path, b) in tree_b.into_iter() {
+		let a = match tree_a.remove(&path) {
+			None => None,
+			Some(a) if is_dir(&a) != is_dir(&b) => {
+				diff.push(TreeDiffEntry { kind: TreeDiffKind::Removed, path: path.clone(), a: Some(a), b: None });
+				None
+			},
+			Some(a) => Some(a),
+		};
+		let kind = match a {
+			None => TreeDiffKind::Added,
+			Some(ref a) if !is_dir(a) && options.is_changed(fs_a, a, fs_b, &b)? => TreeDiffKind::Changed,
+			Some(_) => { continue; },
+		};
+		diff.push(TreeDiffEntry { kind, path, a, b: Some(b) });
+	}
+	diff.extend(tree_a.into_iter().map(|(path, a)| TreeDiffEntry { kind: TreeDiffKind::Removed, path, a: Some(a), b: None }));
+	// Stable, so a removal stays before an addition at the same path
+	diff.sort_by(|a, b| a.path.cmp(&b.path));
+	Ok(diff)
+}
+
+/// Walks a tree, keyed by path relative to its root. Returns an empty index if the root doesn't exist.
+fn index(fs: &HdfsConnection, root: &str) -> io::Result<BTreeMap<String, HdfsDirectoryEntry>> {
+	let root = match fs.stat(root) {
+		Ok(root) => root,
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => { return Ok(BTreeMap::new()); },
+		Err(e) => { return Err(e); },
+	};
+	let mut index = BTreeMap::new();
+	for item in fs.walk(&root.name) {
+		let entry = item?.entry;
+		let path = entry.name.strip_prefix(root.name.as_str())
+			.unwrap_or(&entry.name)
+			.trim_start_matches('/')
+			.to_string();
+		index.insert(path, entry);
+	}
+	Ok(index)
+}
+
+fn is_dir(entry: &HdfsDirectoryEntry) -> bool {
+	matches!(entry.kind, HdfsDirectoryEntryKind::Directory)
+}
+
+fn epoch_secs(entry: &HdfsDirectoryEntry) -> u64 {
+	entry.last_modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
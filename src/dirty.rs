@@ -0,0 +1,94 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! What happens to files dropped with unsynced writes.
+//! 
+//! Dropping an `HdfsFile` closes it, which flushes what's buffered and completes the file, but ignores errors.
+//! When a caller panics or returns early partway through writing, the output is left looking finished, just
+//! shorter. A `DirtyDrop` policy decides what to do instead, for files that were written to since they were last
+//! synced with `HdfsFile::sync`, and weren't closed with `HdfsFile::close`.
+
+use crate::{HdfsConnection, HdfsFile, HdfsStreamBuilder};
+use std::io::{self, Write};
+use std::process;
+use std::thread;
+
+/// What to do when a file is dropped with writes that haven't been synced, set with
+/// `HdfsConnection::set_dirty_drop` or `HdfsStreamBuilder::dirty_drop`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum DirtyDrop {
+	/// Flush before closing, ignoring errors, which is what closing does anyway. Failures are logged with the
+	/// `log` feature.
+	#[default]
+	Flush,
+	/// Sync to disk on the datanodes with `hsync` before closing, so that what was written survives a cluster
+	/// failure. Failures are logged with the `log` feature.
+	Sync,
+	/// Print an error to stderr, and to the `log` crate with the `log` feature, then abort the process without
+	/// closing the file. It stays under construction until its lease expires and the namenode recovers it, and the
+	/// process exits with a failure instead of carrying on as if the output were complete.
+	Abort,
+}
+
+impl HdfsConnection {
+	/// Sets what happens to files opened after this that are dropped with unsynced writes, unless overridden with
+	/// `HdfsStreamBuilder::dirty_drop`.
+	pub fn set_dirty_drop(&self, policy: DirtyDrop) {
+		self.dirty_drop.set(policy);
+	}
+}
+
+impl<'a> HdfsStreamBuilder<'a> {
+	/// Sets what happens if the file is dropped with unsynced writes, instead of the connection's policy.
+	pub fn dirty_drop(&mut self, policy: DirtyDrop) {
+		self.dirty_drop = Some(policy);
+	}
+}
+
+impl<'a> HdfsFile<'a> {
+	/// Applies the file's `DirtyDrop` policy. Called when dropping a file that was written to since it was last
+	/// synced.
+	pub(crate) fn drop_dirty(&mut self) {
+		let res = match self.dirty_drop {
+			DirtyDrop::Flush => self.flush(),
+			DirtyDrop::Sync => self.sync(),
+			DirtyDrop::Abort => {
+				let message = format!("{} was dropped with unsynced writes{}, aborting",
+					self.fs.files.path(self.id).unwrap_or_else(|| "<raw handle>".to_string()),
+					if thread::panicking() { " while panicking" } else { "" });
+				#[cfg(feature = "log")]
+				log::error!(target: "hdfs", "{}", message);
+				eprintln!("hdfs: {}", message);
+				process::abort();
+			},
+		};
+		if let Err(e) = res {
+			report_failure(&e);
+		}
+	}
+}
+
+#[cfg(feature = "log")]
+fn report_failure(e: &io::Error) {
+	log::warn!(target: "hdfs", "could not flush a file being dropped with unsynced writes: {}", e);
+}
+
+#[cfg(not(feature = "log"))]
+fn report_failure(_e: &io::Error) {}
@@ -0,0 +1,182 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! HDFS in a Docker container for integration tests, with the `integration` feature.
+
+use crate::minicluster::{free_port, log_tail, run};
+use crate::{HdfsBuilder, HdfsConnection};
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Image used if `RSHDFS_TEST_DOCKER_IMAGE` isn't set.
+pub const DEFAULT_DOCKER_IMAGE: &str = "apache/hadoop:3";
+
+/// How long to wait for the namenode to leave safe mode and the datanode to register, including pulling the image.
+const START_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Run in the container: formats the namenode, starts it and a datanode, and stops once stdin is closed, which
+/// happens when `DockerCluster` is dropped or the test process exits. Then the container exits and `--rm`
+/// deletes it.
+const SCRIPT: &str = "hdfs namenode -format -force -nonInteractive && { hdfs namenode & hdfs datanode & cat >/dev/null; }";
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A single-node HDFS cluster running in a Docker container, stopped when dropped.
+/// 
+/// ```ignore
+/// let cluster = hdfs::DockerCluster::start()?;
+/// let fs = cluster.connect()?;
+/// fs.create_dir("/test")?;
+/// ```
+/// 
+/// The container uses the host's network, so that `libhdfs` can reach the datanode at the address the namenode
+/// gives it, which only works with Docker on Linux. The image is `RSHDFS_TEST_DOCKER_IMAGE`, or
+/// `DEFAULT_DOCKER_IMAGE`; it has to be laid out like the `apache/hadoop` images, which write the Hadoop
+/// configuration from `CORE-SITE.XML_*` and `HDFS-SITE.XML_*` environment variables. The test process still
+/// needs the Hadoop jars on its `CLASSPATH` for `libhdfs`; see `init_classpath`.
+pub struct DockerCluster {
+	child: Child,
+	name: String,
+	port: u16,
+	http_port: u16,
+	log: PathBuf,
+}
+
+impl DockerCluster {
+	/// Starts a container with HDFS listening on free ports, waiting until it can be written to.
+	pub fn start() -> io::Result<Self> {
+		let image = env::var("RSHDFS_TEST_DOCKER_IMAGE").unwrap_or_else(|_| DEFAULT_DOCKER_IMAGE.to_string());
+		let name = format!("hdfs-rs-{}-{}", std::process::id(), NEXT_ID.fetch_add(1, Ordering::SeqCst));
+		let port = free_port()?;
+		let http_port = free_port()?;
+		let log = env::temp_dir().join(format!("{}.log", name));
+		let log_file = File::create(&log)?;
+		
+		let mut conf = vec![
+			format!("CORE-SITE.XML_fs.defaultFS=hdfs://localhost:{}", port),
+			format!("HDFS-SITE.XML_dfs.namenode.rpc-address=localhost:{}", port),
+			format!("HDFS-SITE.XML_dfs.namenode.http-address=localhost:{}", http_port),
+			"HDFS-SITE.XML_dfs.replication=1".to_string(),
+			"HDFS-SITE.XML_dfs.permissions.enabled=false".to_string(),
+			"HDFS-SITE.XML_dfs.namenode.safemode.extension=0".to_string(),
+		];
+		for key in ["address", "http.address", "ipc.address"].iter() {
+			conf.push(format!("HDFS-SITE.XML_dfs.datanode.{}=localhost:{}", key, free_port()?));
+		}
+		
+		let mut command = Command::new("docker");
+		command.args(["run", "--rm", "-i", "--network", "host", "--name", &name]);
+		for var in conf.iter() {
+			command.arg("-e").arg(var);
+		}
+		command.arg(&image).args(["bash", "-c", SCRIPT])
+			.stdin(Stdio::piped())
+			.stdout(log_file.try_clone()?)
+			.stderr(log_file);
+		let child = command.spawn()?;
+		let mut cluster = Self { child, name, port, http_port, log };
+		
+		let started = Instant::now();
+		while !cluster.ready() {
+			if let Some(status) = cluster.child.try_wait()? {
+				return Err(cluster.failure(&format!("container exited with {}", status)));
+			}
+			if started.elapsed() > START_TIMEOUT {
+				return Err(cluster.failure("timed out waiting for HDFS to start in the container"));
+			}
+			thread::sleep(Duration::from_secs(1));
+		}
+		Ok(cluster)
+	}
+	
+	/// Checks whether the namenode has left safe mode and a datanode has registered with it.
+	fn ready(&self) -> bool {
+		let leaving_safe_mode = Command::new("docker")
+			.args(["exec", &self.name, "hdfs", "dfsadmin", "-safemode", "get"])
+			.output();
+		match leaving_safe_mode {
+			Ok(output) if output.status.success() && String::from_utf8_lossy(&output.stdout).contains("OFF") => {},
+			_ => { return false; },
+		}
+		let report = Command::new("docker")
+			.args(["exec", &self.name, "hdfs", "dfsadmin", "-report", "-live"])
+			.output();
+		match report {
+			Ok(output) => output.status.success() && String::from_utf8_lossy(&output.stdout).contains("Live datanodes (1)"),
+			Err(_) => false,
+		}
+	}
+	
+	/// Gets the namenode's RPC port.
+	pub fn port(&self) -> u16 {
+		self.port
+	}
+	
+	/// Gets the namenode's web UI and WebHDFS port.
+	pub fn http_port(&self) -> u16 {
+		self.http_port
+	}
+	
+	/// Gets the URI of the namenode, ex. `hdfs://localhost:40123`.
+	pub fn name_node(&self) -> String {
+		format!("hdfs://localhost:{}", self.port)
+	}
+	
+	/// Gets the name of the container, for `docker exec` and `docker logs`.
+	pub fn container_name(&self) -> &str {
+		&self.name
+	}
+	
+	/// Gets a builder for connecting to the cluster.
+	/// 
+	/// It forces a new filesystem instance for each connection; see `HdfsBuilder::force_new_instance`.
+	pub fn builder(&self) -> HdfsBuilder {
+		let mut builder = HdfsBuilder::new();
+		builder.name_node(Some(&self.name_node()));
+		builder.force_new_instance();
+		builder
+	}
+	
+	/// Connects to the cluster.
+	pub fn connect(&self) -> io::Result<HdfsConnection> {
+		self.builder().connect()
+	}
+	
+	/// Makes an error including the end of the container's output, for when it didn't start.
+	fn failure(&self, message: &str) -> io::Error {
+		io::Error::new(io::ErrorKind::Other, format!("{}:\n{}", message, log_tail(&self.log)))
+	}
+}
+
+impl Drop for DockerCluster {
+	fn drop(&mut self) {
+		// Closing stdin ends the script, but if it's stuck, make sure the container goes away
+		drop(self.child.stdin.take());
+		let _ = run(Command::new("docker").args(["rm", "-f", &self.name]).stdout(Stdio::null()).stderr(Stdio::null()));
+		let _ = self.child.wait();
+		let _ = fs::remove_file(&self.log);
+	}
+}
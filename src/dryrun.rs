@@ -0,0 +1,218 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Running code against a filesystem without changing it.
+
+use crate::filesystem::{FileReader, FileSystem, FileWriter, Op};
+use crate::{HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Mutating operation that a `DryRunFs` skipped.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct PlannedOp {
+	pub op: Op,
+	/// Path it would have been done to. For renames, the source and destination.
+	pub paths: Vec<String>,
+	/// For files opened for writing, how many bytes would have been written
+	pub bytes: Option<u64>,
+}
+
+/// Formats like `Rename /a /b`, or `OpenCreate /a (12 bytes)`.
+impl fmt::Display for PlannedOp {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:?} {}", self.op, self.paths.join(" "))?;
+		if let Some(bytes) = self.bytes {
+			write!(f, " ({} bytes)", bytes)?;
+		}
+		Ok(())
+	}
+}
+
+/// `FileSystem` wrapper that records mutating operations instead of doing them, so that tools can show what a
+/// job would do. Reads pass through to the wrapped filesystem.
+/// 
+/// Skipped operations return what they would on success, but only after checking what they can with reads, so
+/// that deleting a path that doesn't exist still fails with `NotFound`. Reads don't see the skipped changes: a
+/// directory that was "created" still doesn't exist.
+/// 
+/// Files opened for writing discard what's written to them, and are recorded when they're dropped, with how many
+/// bytes were written.
+pub struct DryRunFs<F> {
+	inner: F,
+	planned: Mutex<Vec<PlannedOp>>,
+}
+
+impl<F: FileSystem> DryRunFs<F> {
+	/// Wraps a filesystem, with nothing recorded yet.
+	pub fn new(inner: F) -> Self {
+		Self { inner, planned: Mutex::new(vec![]) }
+	}
+	
+	/// Gets the operations that were skipped, in order.
+	pub fn planned(&self) -> Vec<PlannedOp> {
+		self.planned.lock().unwrap().clone()
+	}
+	
+	/// Forgets the operations that were skipped.
+	pub fn clear(&self) {
+		self.planned.lock().unwrap().clear();
+	}
+	
+	/// Gets the wrapped filesystem.
+	pub fn inner(&self) -> &F {
+		&self.inner
+	}
+	
+	/// Unwraps the wrapped filesystem.
+	pub fn into_inner(self) -> F {
+		self.inner
+	}
+	
+	fn record(&self, op: Op, paths: &[&str], bytes: Option<u64>) {
+		self.planned.lock().unwrap().push(PlannedOp {
+			op,
+			paths: paths.iter().map(|path| path.to_string()).collect(),
+			bytes,
+		});
+	}
+	
+	/// Stats a path that an operation needs to exist.
+	fn existing(&self, path: &str) -> io::Result<HdfsDirectoryEntry> {
+		self.inner.stat(path)
+	}
+	
+	/// Checks that `path` isn't a directory, for opening it for writing.
+	fn check_writable(&self, path: &str) -> io::Result<()> {
+		match self.inner.stat(path) {
+			Ok(entry) if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) => {
+				Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is a directory", path)))
+			},
+			Ok(_) => Ok(()),
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+			Err(e) => Err(e),
+		}
+	}
+}
+
+impl<F: FileSystem> FileSystem for DryRunFs<F> {
+	fn stat(&self, path: &str) -> io::Result<HdfsDirectoryEntry> {
+		self.inner.stat(path)
+	}
+	fn exists(&self, path: &str) -> io::Result<bool> {
+		self.inner.exists(path)
+	}
+	fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		self.inner.list_dir(path)
+	}
+	fn create_dir(&self, path: &str) -> io::Result<()> {
+		match self.inner.stat(path) {
+			Ok(entry) if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) => { return Ok(()); },
+			Ok(_) => { return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is a file", path))); },
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+			Err(e) => { return Err(e); },
+		}
+		self.record(Op::CreateDir, &[path], None);
+		Ok(())
+	}
+	fn delete(&self, path: &str, recursive: bool) -> io::Result<()> {
+		let entry = self.existing(path)?;
+		if !recursive && matches!(entry.kind, HdfsDirectoryEntryKind::Directory) && !self.inner.list_dir(path)?.is_empty() {
+			return Err(io::Error::new(io::ErrorKind::Other, format!("{} is non empty", path)));
+		}
+		self.record(Op::Delete, &[path], None);
+		Ok(())
+	}
+	fn rename(&self, src: &str, dest: &str) -> io::Result<()> {
+		self.existing(src)?;
+		if self.inner.exists(dest)? {
+			return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", dest)));
+		}
+		self.record(Op::Rename, &[src, dest], None);
+		Ok(())
+	}
+	fn chmod(&self, path: &str, _mode: u16) -> io::Result<()> {
+		self.existing(path)?;
+		self.record(Op::Chmod, &[path], None);
+		Ok(())
+	}
+	fn chown(&self, path: &str, _owner: Option<&str>, _group: Option<&str>) -> io::Result<()> {
+		self.existing(path)?;
+		self.record(Op::Chown, &[path], None);
+		Ok(())
+	}
+	fn set_times(&self, path: &str, _modified: Option<SystemTime>, _accessed: Option<SystemTime>) -> io::Result<()> {
+		self.existing(path)?;
+		self.record(Op::SetTimes, &[path], None);
+		Ok(())
+	}
+	fn truncate(&self, path: &str, size: u64) -> io::Result<bool> {
+		let entry = self.existing(path)?;
+		if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) {
+			return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is a directory", path)));
+		}
+		if size > entry.size {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot truncate {} to a larger size", path)));
+		}
+		self.record(Op::Truncate, &[path], None);
+		Ok(true)
+	}
+	fn open_read(&self, path: &str) -> io::Result<Box<dyn FileReader + '_>> {
+		self.inner.open_read(path)
+	}
+	fn open_create(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		self.check_writable(path)?;
+		Ok(Box::new(DiscardWriter { fs: self, op: Op::OpenCreate, path: path.to_string(), written: 0 }))
+	}
+	fn open_append(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		self.existing(path)?;
+		self.check_writable(path)?;
+		Ok(Box::new(DiscardWriter { fs: self, op: Op::OpenAppend, path: path.to_string(), written: 0 }))
+	}
+}
+
+/// File opened for writing through a `DryRunFs`, which counts what's written to it.
+struct DiscardWriter<'a, F: FileSystem> {
+	fs: &'a DryRunFs<F>,
+	op: Op,
+	path: String,
+	written: u64,
+}
+impl<'a, F: FileSystem> io::Write for DiscardWriter<'a, F> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.written += buf.len() as u64;
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+impl<'a, F: FileSystem> FileWriter for DiscardWriter<'a, F> {
+	fn sync(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+impl<'a, F: FileSystem> Drop for DiscardWriter<'a, F> {
+	fn drop(&mut self) {
+		self.fs.record(self.op, &[&self.path], Some(self.written));
+	}
+}
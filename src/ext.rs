@@ -0,0 +1,154 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Parts of the `libhdfs` API that older versions lack, enabled by the `ext` feature: read statistics, hedged
+//! read metrics, file state checks, and details of the last Java exception.
+//! 
+//! The `ext` feature also generates bindings for `hdfs_ext.h` in `libhdfs_sys`. Those functions are only
+//! implemented by libhdfs++, so there are no safe wrappers for them here.
+
+use crate::{check_rt, cstr_to_str, HdfsConnection, HdfsFile};
+use std::io;
+use std::ptr;
+
+/// Read statistics of a file, returned by `HdfsFile::read_statistics`.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct ReadStatistics {
+	/// Total bytes read
+	pub total_bytes_read: u64,
+	/// Bytes read from a datanode on the same host
+	pub total_local_bytes_read: u64,
+	/// Bytes read with short circuit reads, bypassing the datanode
+	pub total_short_circuit_bytes_read: u64,
+	/// Bytes read with zero-copy reads
+	pub total_zero_copy_bytes_read: u64,
+}
+impl ReadStatistics {
+	/// Bytes read from datanodes on other hosts.
+	pub fn remote_bytes_read(&self) -> u64 {
+		self.total_bytes_read.saturating_sub(self.total_local_bytes_read)
+	}
+}
+
+/// Hedged read metrics of a connection, returned by `HdfsConnection::hedged_read_metrics`.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct HedgedReadMetrics {
+	/// Number of hedged reads started
+	pub ops: u64,
+	/// Number of hedged reads that finished before the original read
+	pub ops_win: u64,
+	/// Number of hedged reads run in the calling thread, because the pool was full
+	pub ops_in_cur_thread: u64,
+}
+
+impl HdfsConnection {
+	/// Gets the hedged read metrics of the client, which are shared by all connections to the same filesystem.
+	pub fn hedged_read_metrics(&self) -> io::Result<HedgedReadMetrics> {
+		let mut p = ptr::null_mut();
+		let rt = unsafe { libhdfs_sys::hdfsGetHedgedReadMetrics(self.p.as_ptr(), &mut p) };
+		check_rt(rt)?;
+		let metrics = unsafe {
+			let raw = &*p;
+			HedgedReadMetrics {
+				ops: raw.hedgedReadOps,
+				ops_win: raw.hedgedReadOpsWin,
+				ops_in_cur_thread: raw.hedgedReadOpsInCurThread,
+			}
+		};
+		unsafe { libhdfs_sys::hdfsFreeHedgedReadMetrics(p); }
+		return Ok(metrics);
+	}
+}
+
+impl<'a> HdfsFile<'a> {
+	/// Checks if the file was opened for reading.
+	pub fn is_open_for_read(&self) -> bool {
+		unsafe { libhdfs_sys::hdfsFileIsOpenForRead(self.p.as_ptr()) != 0 }
+	}
+	
+	/// Checks if the file was opened for writing.
+	pub fn is_open_for_write(&self) -> bool {
+		unsafe { libhdfs_sys::hdfsFileIsOpenForWrite(self.p.as_ptr()) != 0 }
+	}
+	
+	/// Gets statistics about how the file's data has been read. Only available for files opened for reading.
+	pub fn read_statistics(&self) -> io::Result<ReadStatistics> {
+		let mut p = ptr::null_mut();
+		let rt = unsafe { libhdfs_sys::hdfsFileGetReadStatistics(self.p.as_ptr(), &mut p) };
+		check_rt(rt)?;
+		let stats = unsafe {
+			let raw = &*p;
+			ReadStatistics {
+				total_bytes_read: raw.totalBytesRead,
+				total_local_bytes_read: raw.totalLocalBytesRead,
+				total_short_circuit_bytes_read: raw.totalShortCircuitBytesRead,
+				total_zero_copy_bytes_read: raw.totalZeroCopyBytesRead,
+			}
+		};
+		unsafe { libhdfs_sys::hdfsFileFreeReadStatistics(p); }
+		return Ok(stats);
+	}
+	
+	/// Checks that everything read from the file so far used short-circuit local reads, as set up by
+	/// `HdfsBuilder::short_circuit_reads`. If not, the error says what the statistics suggest went wrong.
+	pub fn verify_short_circuit(&self) -> io::Result<()> {
+		let stats = self.read_statistics()?;
+		let reason = if stats.total_bytes_read == 0 {
+			"nothing has been read from the file yet".to_string()
+		} else if stats.total_short_circuit_bytes_read == stats.total_bytes_read {
+			return Ok(());
+		} else if stats.total_local_bytes_read == 0 {
+			"the data was read from datanodes on other hosts, and short-circuit reads only apply to blocks stored on this host".to_string()
+		} else if stats.total_short_circuit_bytes_read == 0 {
+			"the data was read through the local datanode; check that `dfs.client.read.shortcircuit` is enabled, that \
+				`dfs.domain.socket.path` matches the datanode's and is accessible to this user, and that the `libhadoop` \
+				native library can be loaded".to_string()
+		} else {
+			format!("only {} of {} bytes were read with short-circuit reads; the rest came from {}",
+				stats.total_short_circuit_bytes_read, stats.total_bytes_read,
+				if stats.remote_bytes_read() > 0 { "datanodes on other hosts" } else { "the local datanode" })
+		};
+		return Err(io::Error::new(io::ErrorKind::Other, format!("short-circuit reads were not used: {}", reason)));
+	}
+	
+	/// Resets the file's read statistics to zero.
+	pub fn clear_read_statistics(&mut self) -> io::Result<()> {
+		let rt = unsafe { libhdfs_sys::hdfsFileClearReadStatistics(self.p.as_ptr()) };
+		return check_rt(rt);
+	}
+}
+
+/// Gets the root cause of the last exception thrown by a `libhdfs` call on this thread, if any.
+/// 
+/// `io::Error`s only have the `errno` that `libhdfs` maps the exception to; this has the Java message.
+pub fn last_exception_root_cause() -> Option<String> {
+	unsafe {
+		let p = libhdfs_sys::hdfsGetLastExceptionRootCause();
+		if p.is_null() { None } else { Some(cstr_to_str(p)) }
+	}
+}
+
+/// Gets the stack trace of the last exception thrown by a `libhdfs` call on this thread, if any.
+pub fn last_exception_stack_trace() -> Option<String> {
+	unsafe {
+		let p = libhdfs_sys::hdfsGetLastExceptionStackTrace();
+		if p.is_null() { None } else { Some(cstr_to_str(p)) }
+	}
+}
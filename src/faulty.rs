@@ -0,0 +1,300 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Injecting faults into filesystem operations, for testing how code handles them.
+
+use crate::filesystem::{FileReader, FileSystem, FileWriter, Op};
+use crate::path::uri_path;
+use crate::HdfsDirectoryEntry;
+use std::io;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug,Clone)]
+enum Effect {
+	Error(io::ErrorKind),
+	Latency(Duration),
+	ShortReads(usize),
+	ShortWrites(usize),
+}
+
+/// Fault to inject with `FaultyFs::inject`.
+/// 
+/// By default, a fault applies to every operation, on every path, every time. Narrow it down with the other
+/// methods:
+/// 
+/// ```ignore
+/// // The third write to anything under /data fails
+/// fs.inject(Fault::error(io::ErrorKind::BrokenPipe).ops(&[Op::Write]).path("/data").nth(3));
+/// ```
+#[derive(Debug,Clone)]
+pub struct Fault {
+	effect: Effect,
+	ops: Option<Vec<Op>>,
+	path: Option<String>,
+	skip: usize,
+	times: Option<usize>,
+	// Matching operations seen so far
+	seen: usize,
+}
+
+impl Fault {
+	fn new(effect: Effect, ops: Option<Vec<Op>>) -> Self {
+		Self { effect, ops, path: None, skip: 0, times: None, seen: 0 }
+	}
+	
+	/// Fails operations with an error of `kind`.
+	pub fn error(kind: io::ErrorKind) -> Self {
+		Self::new(Effect::Error(kind), None)
+	}
+	
+	/// Sleeps for `delay` before operations.
+	pub fn latency(delay: Duration) -> Self {
+		Self::new(Effect::Latency(delay), None)
+	}
+	
+	/// Makes `read` calls return at most `max` bytes. Applies to `Op::Read` only.
+	pub fn short_reads(max: usize) -> Self {
+		Self::new(Effect::ShortReads(max), Some(vec![Op::Read]))
+	}
+	
+	/// Makes `write` calls write at most `max` bytes. Applies to `Op::Write` only.
+	pub fn short_writes(max: usize) -> Self {
+		Self::new(Effect::ShortWrites(max), Some(vec![Op::Write]))
+	}
+	
+	/// Only applies to these operations.
+	pub fn ops(mut self, ops: &[Op]) -> Self {
+		self.ops = Some(ops.to_vec());
+		self
+	}
+	
+	/// Only applies to `path` and paths under it. For renames, either path can match.
+	pub fn path(mut self, path: &str) -> Self {
+		self.path = Some(path.trim_end_matches('/').to_string());
+		self
+	}
+	
+	/// Lets the first `n` matching operations through before applying.
+	pub fn after(mut self, n: usize) -> Self {
+		self.skip = n;
+		self
+	}
+	
+	/// Only applies `n` times, then lets operations through again.
+	pub fn times(mut self, n: usize) -> Self {
+		self.times = Some(n);
+		self
+	}
+	
+	/// Only applies to the `n`th matching operation, counting from 1.
+	pub fn nth(self, n: usize) -> Self {
+		self.after(n.saturating_sub(1)).times(1)
+	}
+	
+	fn matches(&self, op: Op, paths: &[&str]) -> bool {
+		if let Some(ops) = self.ops.as_ref() {
+			if !ops.contains(&op) {
+				return false;
+			}
+		}
+		match self.path.as_ref() {
+			// Compared without the scheme and authority, so that `/a` matches `hdfs://host/a`
+			Some(prefix) => paths.iter().any(|path| {
+				let path = uri_path(path);
+				path == prefix || (path.starts_with(prefix.as_str()) && path[prefix.len()..].starts_with('/'))
+			}),
+			None => true,
+		}
+	}
+	
+	/// Counts a matching operation, returning whether the fault applies to it.
+	fn fires(&mut self) -> bool {
+		self.seen += 1;
+		self.seen > self.skip && self.times.map(|times| self.seen <= self.skip + times).unwrap_or(true)
+	}
+}
+
+/// `FileSystem` wrapper that injects faults into the operations on another one: errors, latency, and short reads
+/// and writes, on specific paths or operations, or on the Nth one. Files opened through it are wrapped too, so
+/// faults can apply to their reads and writes.
+/// 
+/// Faults are checked in the order they were injected. Latency from every matching fault is added up, and the
+/// first matching error is returned.
+pub struct FaultyFs<F> {
+	inner: F,
+	faults: Mutex<Vec<Fault>>,
+}
+
+impl<F: FileSystem> FaultyFs<F> {
+	/// Wraps a filesystem, with no faults yet.
+	pub fn new(inner: F) -> Self {
+		Self { inner, faults: Mutex::new(vec![]) }
+	}
+	
+	/// Adds a fault.
+	pub fn inject(&self, fault: Fault) {
+		self.faults.lock().unwrap().push(fault);
+	}
+	
+	/// Removes all faults.
+	pub fn clear(&self) {
+		self.faults.lock().unwrap().clear();
+	}
+	
+	/// Gets the wrapped filesystem.
+	pub fn inner(&self) -> &F {
+		&self.inner
+	}
+	
+	/// Unwraps the wrapped filesystem.
+	pub fn into_inner(self) -> F {
+		self.inner
+	}
+	
+	/// Applies the faults for an operation. Returns the most bytes it may transfer, for reads and writes.
+	fn check(&self, op: Op, paths: &[&str]) -> io::Result<Option<usize>> {
+		let mut delay = Duration::from_secs(0);
+		let mut error = None;
+		let mut limit: Option<usize> = None;
+		for fault in self.faults.lock().unwrap().iter_mut() {
+			if !fault.matches(op, paths) || !fault.fires() {
+				continue;
+			}
+			match fault.effect {
+				Effect::Error(kind) => { error = error.or(Some(kind)); },
+				Effect::Latency(d) => { delay += d; },
+				Effect::ShortReads(max) | Effect::ShortWrites(max) => {
+					limit = Some(limit.map(|limit| limit.min(max)).unwrap_or(max));
+				},
+			}
+		}
+		if delay > Duration::from_secs(0) {
+			thread::sleep(delay);
+		}
+		if let Some(kind) = error {
+			return Err(io::Error::new(kind, format!("injected fault in {:?} of {}", op, paths.join(" and "))));
+		}
+		Ok(limit)
+	}
+}
+
+impl<F: FileSystem> FileSystem for FaultyFs<F> {
+	fn stat(&self, path: &str) -> io::Result<HdfsDirectoryEntry> {
+		self.check(Op::Stat, &[path])?;
+		self.inner.stat(path)
+	}
+	fn exists(&self, path: &str) -> io::Result<bool> {
+		self.check(Op::Exists, &[path])?;
+		self.inner.exists(path)
+	}
+	fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		self.check(Op::ListDir, &[path])?;
+		self.inner.list_dir(path)
+	}
+	fn create_dir(&self, path: &str) -> io::Result<()> {
+		self.check(Op::CreateDir, &[path])?;
+		self.inner.create_dir(path)
+	}
+	fn delete(&self, path: &str, recursive: bool) -> io::Result<()> {
+		self.check(Op::Delete, &[path])?;
+		self.inner.delete(path, recursive)
+	}
+	fn rename(&self, src: &str, dest: &str) -> io::Result<()> {
+		self.check(Op::Rename, &[src, dest])?;
+		self.inner.rename(src, dest)
+	}
+	fn chmod(&self, path: &str, mode: u16) -> io::Result<()> {
+		self.check(Op::Chmod, &[path])?;
+		self.inner.chmod(path, mode)
+	}
+	fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> io::Result<()> {
+		self.check(Op::Chown, &[path])?;
+		self.inner.chown(path, owner, group)
+	}
+	fn set_times(&self, path: &str, modified: Option<SystemTime>, accessed: Option<SystemTime>) -> io::Result<()> {
+		self.check(Op::SetTimes, &[path])?;
+		self.inner.set_times(path, modified, accessed)
+	}
+	fn truncate(&self, path: &str, size: u64) -> io::Result<bool> {
+		self.check(Op::Truncate, &[path])?;
+		self.inner.truncate(path, size)
+	}
+	fn open_read(&self, path: &str) -> io::Result<Box<dyn FileReader + '_>> {
+		self.check(Op::OpenRead, &[path])?;
+		let inner = self.inner.open_read(path)?;
+		Ok(Box::new(FaultyFile { fs: self, path: path.to_string(), inner }))
+	}
+	fn open_create(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		self.check(Op::OpenCreate, &[path])?;
+		let inner = self.inner.open_create(path)?;
+		Ok(Box::new(FaultyFile { fs: self, path: path.to_string(), inner }))
+	}
+	fn open_append(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		self.check(Op::OpenAppend, &[path])?;
+		let inner = self.inner.open_append(path)?;
+		Ok(Box::new(FaultyFile { fs: self, path: path.to_string(), inner }))
+	}
+}
+
+/// File opened through a `FaultyFs`, wrapping a `Box<dyn FileReader>` or `Box<dyn FileWriter>`.
+struct FaultyFile<'a, F, T> {
+	fs: &'a FaultyFs<F>,
+	path: String,
+	inner: T,
+}
+impl<'a, F: FileSystem, T: io::Read> io::Read for FaultyFile<'a, F, T> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let len = match self.fs.check(Op::Read, &[&self.path])? {
+			Some(max) => buf.len().min(max),
+			None => buf.len(),
+		};
+		self.inner.read(&mut buf[..len])
+	}
+}
+impl<'a, F: FileSystem, T: io::Seek> io::Seek for FaultyFile<'a, F, T> {
+	fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+		self.fs.check(Op::Seek, &[&self.path])?;
+		self.inner.seek(pos)
+	}
+}
+impl<'a, F: FileSystem, T: io::Write> io::Write for FaultyFile<'a, F, T> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let len = match self.fs.check(Op::Write, &[&self.path])? {
+			Some(max) => buf.len().min(max),
+			None => buf.len(),
+		};
+		self.inner.write(&buf[..len])
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		self.fs.check(Op::Flush, &[&self.path])?;
+		self.inner.flush()
+	}
+}
+impl<'a, F: FileSystem, T: FileWriter> FileWriter for FaultyFile<'a, F, T> {
+	fn sync(&mut self) -> io::Result<()> {
+		self.fs.check(Op::Sync, &[&self.path])?;
+		self.inner.sync()
+	}
+	fn close(self: Box<Self>) -> io::Result<()> {
+		Box::new(self.inner).close()
+	}
+}
@@ -0,0 +1,230 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! A trait for the filesystem operations of `HdfsConnection`, so that code can be written against it and run with
+//! wrappers that add behavior, like `FaultyFs`, or with `Interceptor`s layered on top.
+
+use crate::intercept::{Intercepted, Interceptor};
+use crate::{HdfsConnection, HdfsDirectoryEntry, HdfsFile};
+use std::convert::TryFrom;
+use std::io;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Filesystem operation, for wrappers that act on some operations but not others, like `FaultyFs` and
+/// `Intercepted`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub enum Op {
+	Stat,
+	Exists,
+	ListDir,
+	CreateDir,
+	Delete,
+	Rename,
+	Chmod,
+	Chown,
+	SetTimes,
+	Truncate,
+	OpenRead,
+	OpenCreate,
+	OpenAppend,
+	/// A `read` call on a file opened through the wrapper
+	Read,
+	/// A `seek` call on a file opened through the wrapper
+	Seek,
+	/// A `write` call on a file opened through the wrapper
+	Write,
+	/// A `flush` call on a file opened through the wrapper
+	Flush,
+	/// A `sync` call on a file opened through the wrapper
+	Sync,
+}
+
+impl Op {
+	/// Whether this is a call on an open file, rather than a request to the namenode.
+	pub fn is_file_io(self) -> bool {
+		matches!(self, Op::Read | Op::Seek | Op::Write | Op::Flush | Op::Sync)
+	}
+	
+	/// Whether this changes the filesystem: creating, deleting, renaming, changing attributes or contents, or
+	/// opening a file for writing. Reads and writes on open files don't count.
+	pub fn is_mutating(self) -> bool {
+		matches!(self,
+			Op::CreateDir | Op::Delete | Op::Rename | Op::Chmod | Op::Chown | Op::SetTimes | Op::Truncate |
+			Op::OpenCreate | Op::OpenAppend)
+	}
+}
+
+/// File opened for reading by a `FileSystem`.
+pub trait FileReader: io::Read + io::Seek {}
+impl<T: io::Read + io::Seek + ?Sized> FileReader for T {}
+
+/// File opened for writing by a `FileSystem`.
+pub trait FileWriter: io::Write {
+	/// Flushes the file and waits until the data is on disk, like `HdfsFile::sync`.
+	fn sync(&mut self) -> io::Result<()>;
+	
+	/// Closes the file, returning errors that dropping it would ignore, like `HdfsFile::close`. Files closed this
+	/// way don't count as dropped with unsynced writes for `DirtyDrop`.
+	/// 
+	/// By default, this flushes and then drops the file.
+	fn close(self: Box<Self>) -> io::Result<()> {
+		let mut this = self;
+		this.flush()
+	}
+}
+impl<'a> FileWriter for HdfsFile<'a> {
+	fn sync(&mut self) -> io::Result<()> {
+		HdfsFile::sync(self)
+	}
+	fn close(self: Box<Self>) -> io::Result<()> {
+		HdfsFile::close(*self)
+	}
+}
+impl<W: FileWriter + ?Sized> FileWriter for Box<W> {
+	fn sync(&mut self) -> io::Result<()> {
+		(**self).sync()
+	}
+	fn close(self: Box<Self>) -> io::Result<()> {
+		(*self).close()
+	}
+}
+
+/// Filesystem operations, with the same meaning as the `HdfsConnection` methods of the same name.
+/// 
+/// This is object safe, so wrappers can be stacked at runtime as `Box<dyn FileSystem>`.
+pub trait FileSystem {
+	fn stat(&self, path: &str) -> io::Result<HdfsDirectoryEntry>;
+	fn exists(&self, path: &str) -> io::Result<bool>;
+	fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>>;
+	fn create_dir(&self, path: &str) -> io::Result<()>;
+	fn delete(&self, path: &str, recursive: bool) -> io::Result<()>;
+	fn rename(&self, src: &str, dest: &str) -> io::Result<()>;
+	fn chmod(&self, path: &str, mode: u16) -> io::Result<()>;
+	fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> io::Result<()>;
+	fn set_times(&self, path: &str, modified: Option<SystemTime>, accessed: Option<SystemTime>) -> io::Result<()>;
+	fn truncate(&self, path: &str, size: u64) -> io::Result<bool>;
+	fn open_read(&self, path: &str) -> io::Result<Box<dyn FileReader + '_>>;
+	fn open_create(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>>;
+	fn open_append(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>>;
+	
+	/// Wraps this filesystem so that `interceptor` runs around each of its operations. Calls can be chained to
+	/// stack several, with the last one outermost:
+	/// 
+	/// ```ignore
+	/// let fs = conn.intercept(Retry::new(3)).intercept(metrics.clone());
+	/// ```
+	fn intercept<I: Interceptor>(self, interceptor: I) -> Intercepted<Self, I> where Self: Sized {
+		Intercepted::new(self, interceptor)
+	}
+}
+
+impl FileSystem for HdfsConnection {
+	fn stat(&self, path: &str) -> io::Result<HdfsDirectoryEntry> {
+		HdfsConnection::stat(self, path)
+	}
+	fn exists(&self, path: &str) -> io::Result<bool> {
+		HdfsConnection::exists(self, path)
+	}
+	fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		HdfsConnection::list_dir(self, path)
+	}
+	fn create_dir(&self, path: &str) -> io::Result<()> {
+		HdfsConnection::create_dir(self, path)
+	}
+	fn delete(&self, path: &str, recursive: bool) -> io::Result<()> {
+		HdfsConnection::delete(self, path, recursive)
+	}
+	fn rename(&self, src: &str, dest: &str) -> io::Result<()> {
+		HdfsConnection::rename(self, src, dest)
+	}
+	fn chmod(&self, path: &str, mode: u16) -> io::Result<()> {
+		HdfsConnection::chmod(self, path, mode)
+	}
+	fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> io::Result<()> {
+		HdfsConnection::chown(self, path, owner, group)
+	}
+	fn set_times(&self, path: &str, modified: Option<SystemTime>, accessed: Option<SystemTime>) -> io::Result<()> {
+		HdfsConnection::set_times(self, path, modified, accessed)
+	}
+	fn truncate(&self, path: &str, size: u64) -> io::Result<bool> {
+		let size = libhdfs_sys::tOffset::try_from(size)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "size too large"))?;
+		HdfsConnection::truncate(self, path, size)
+	}
+	fn open_read(&self, path: &str) -> io::Result<Box<dyn FileReader + '_>> {
+		Ok(Box::new(HdfsConnection::open_read(self, path)?))
+	}
+	fn open_create(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		Ok(Box::new(HdfsConnection::open_create(self, path)?))
+	}
+	fn open_append(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		Ok(Box::new(HdfsConnection::open_append(self, path)?))
+	}
+}
+
+/// Implements `FileSystem` for a pointer type, by calling through to what it points to.
+macro_rules! forward_filesystem {
+	($($ptr:ty),*) => {$(
+		impl<T: FileSystem + ?Sized> FileSystem for $ptr {
+			fn stat(&self, path: &str) -> io::Result<HdfsDirectoryEntry> {
+				(**self).stat(path)
+			}
+			fn exists(&self, path: &str) -> io::Result<bool> {
+				(**self).exists(path)
+			}
+			fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
+				(**self).list_dir(path)
+			}
+			fn create_dir(&self, path: &str) -> io::Result<()> {
+				(**self).create_dir(path)
+			}
+			fn delete(&self, path: &str, recursive: bool) -> io::Result<()> {
+				(**self).delete(path, recursive)
+			}
+			fn rename(&self, src: &str, dest: &str) -> io::Result<()> {
+				(**self).rename(src, dest)
+			}
+			fn chmod(&self, path: &str, mode: u16) -> io::Result<()> {
+				(**self).chmod(path, mode)
+			}
+			fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> io::Result<()> {
+				(**self).chown(path, owner, group)
+			}
+			fn set_times(&self, path: &str, modified: Option<SystemTime>, accessed: Option<SystemTime>) -> io::Result<()> {
+				(**self).set_times(path, modified, accessed)
+			}
+			fn truncate(&self, path: &str, size: u64) -> io::Result<bool> {
+				(**self).truncate(path, size)
+			}
+			fn open_read(&self, path: &str) -> io::Result<Box<dyn FileReader + '_>> {
+				(**self).open_read(path)
+			}
+			fn open_create(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+				(**self).open_create(path)
+			}
+			fn open_append(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+				(**self).open_append(path)
+			}
+		}
+	)*};
+}
+forward_filesystem!(&T, Box<T>, Rc<T>, Arc<T>);
@@ -0,0 +1,420 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Hadoop-style glob patterns.
+
+use crate::path::join;
+use crate::{HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::io;
+
+fn invalid_pattern(pattern: &str, reason: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidInput, format!("invalid glob pattern {:?}: {}", pattern, reason))
+}
+
+/// Checks if a pattern contains any glob metacharacters.
+pub(crate) fn has_glob_chars(pattern: &str) -> bool {
+	let mut chars = pattern.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => { chars.next(); },
+			'*' | '?' | '[' | '{' => { return true; },
+			_ => {},
+		}
+	}
+	false
+}
+
+/// Removes backslash escapes from a pattern without metacharacters.
+fn unescape(pattern: &str) -> String {
+	let mut out = String::with_capacity(pattern.len());
+	let mut chars = pattern.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => { out.extend(chars.next()); },
+			c => { out.push(c); },
+		}
+	}
+	out
+}
+
+/// Expands `{a,b}` alternations, which may contain slashes and nest, into separate patterns.
+fn expand_braces(pattern: &str) -> io::Result<Vec<String>> {
+	let chars: Vec<char> = pattern.chars().collect();
+	let mut open = None;
+	let mut depth = 0;
+	// Indices of top-level commas inside the first brace group
+	let mut commas = vec![];
+	let mut i = 0;
+	while i < chars.len() {
+		match chars[i] {
+			'\\' => { i += 1; },
+			// Braces and commas in a character class are just characters. An unclosed class is left for
+			// `compile` to reject.
+			'[' => {
+				if let Some(end) = class_end(&chars, i) {
+					i = end;
+				}
+			},
+			'{' => {
+				if depth == 0 {
+					open = Some(i);
+				}
+				depth += 1;
+			},
+			',' if depth == 1 => { commas.push(i); },
+			'}' if depth > 0 => {
+				depth -= 1;
+				if depth == 0 {
+					let open = open.unwrap();
+					let prefix: String = chars[..open].iter().collect();
+					let suffix: String = chars[i+1..].iter().collect();
+					let mut bounds = vec![open];
+					bounds.extend(commas.iter().cloned());
+					bounds.push(i);
+					
+					let mut out = vec![];
+					for window in bounds.windows(2) {
+						let alternative: String = chars[window[0]+1..window[1]].iter().collect();
+						out.extend(expand_braces(&format!("{}{}{}", prefix, alternative, suffix))?);
+					}
+					return Ok(out);
+				}
+			},
+			_ => {},
+		}
+		i += 1;
+	}
+	if depth != 0 {
+		return Err(invalid_pattern(pattern, "unclosed '{'"));
+	}
+	Ok(vec![pattern.to_string()])
+}
+
+/// Finds the `]` closing the character class opened at `open`, following the same rules as `compile`.
+fn class_end(chars: &[char], open: usize) -> Option<usize> {
+	let mut i = open + 1;
+	if let Some('^') | Some('!') = chars.get(i) {
+		i += 1;
+	}
+	// A `]` right after the opening is a literal
+	let first = i;
+	while i < chars.len() {
+		match chars[i] {
+			'\\' => { i += 1; },
+			']' if i > first => { return Some(i); },
+			_ => {},
+		}
+		i += 1;
+	}
+	None
+}
+
+#[derive(Debug,Clone)]
+enum Token {
+	Literal(char),
+	AnyChar,
+	AnyString,
+	Class { negated: bool, ranges: Vec<(char, char)> },
+}
+impl Token {
+	fn matches(&self, c: char) -> bool {
+		match self {
+			Token::Literal(l) => *l == c,
+			Token::AnyChar => c != '/',
+			Token::AnyString => c != '/',
+			Token::Class { negated, ranges } => {
+				ranges.iter().any(|(lo, hi)| *lo <= c && c <= *hi) != *negated
+			},
+		}
+	}
+}
+
+/// A compiled glob pattern for matching a single path component, or a whole path.
+/// 
+/// Supports `*` (any string), `?` (any character), `[abc]`, `[a-z]` and `[^a]` character classes,
+/// `{a,b}` alternations, and `\` escapes. Wildcards do not match `/`.
+#[derive(Debug,Clone)]
+pub struct GlobPattern {
+	// One entry per brace expansion
+	alternatives: Vec<Vec<Token>>,
+}
+impl GlobPattern {
+	/// Gets the path a pattern refers to if it has no metacharacters, with any escapes removed.
+	pub fn as_literal(pattern: &str) -> Option<String> {
+		if has_glob_chars(pattern) {
+			return None;
+		}
+		Some(unescape(pattern))
+	}
+	
+	/// Compiles a pattern, returning an `InvalidInput` error if it is malformed.
+	pub fn new(pattern: &str) -> io::Result<Self> {
+		let alternatives = expand_braces(pattern)?
+			.iter()
+			.map(|alternative| Self::compile(pattern, alternative))
+			.collect::<io::Result<Vec<_>>>()?;
+		Ok(Self { alternatives })
+	}
+	
+	fn compile(pattern: &str, alternative: &str) -> io::Result<Vec<Token>> {
+		let mut tokens = vec![];
+		let mut chars = alternative.chars().peekable();
+		while let Some(c) = chars.next() {
+			tokens.push(match c {
+				'\\' => Token::Literal(chars.next().ok_or_else(|| invalid_pattern(pattern, "trailing '\\'"))?),
+				'?' => Token::AnyChar,
+				'*' => {
+					// Collapse repeated stars
+					while chars.peek() == Some(&'*') {
+						chars.next();
+					}
+					Token::AnyString
+				},
+				'[' => {
+					let negated = match chars.peek() {
+						Some('^') | Some('!') => { chars.next(); true },
+						_ => false,
+					};
+					let mut ranges = vec![];
+					loop {
+						let lo = match chars.next() {
+							Some(']') if !ranges.is_empty() => { break; },
+							Some('\\') => chars.next().ok_or_else(|| invalid_pattern(pattern, "unclosed '['"))?,
+							Some(c) => c,
+							None => { return Err(invalid_pattern(pattern, "unclosed '['")); },
+						};
+						let mut hi = lo;
+						if chars.peek() == Some(&'-') {
+							chars.next();
+							hi = match chars.next() {
+								Some(']') => {
+									// Trailing `-` is a literal
+									ranges.push((lo, lo));
+									ranges.push(('-', '-'));
+									break;
+								},
+								Some('\\') => chars.next().ok_or_else(|| invalid_pattern(pattern, "unclosed '['"))?,
+								Some(c) => c,
+								None => { return Err(invalid_pattern(pattern, "unclosed '['")); },
+							};
+							if hi < lo {
+								return Err(invalid_pattern(pattern, "invalid character range"));
+							}
+						}
+						ranges.push((lo, hi));
+					}
+					Token::Class { negated, ranges }
+				},
+				c => Token::Literal(c),
+			});
+		}
+		Ok(tokens)
+	}
+	
+	/// Checks if `name` matches the pattern.
+	pub fn matches(&self, name: &str) -> bool {
+		let name: Vec<char> = name.chars().collect();
+		self.alternatives.iter().any(|tokens| match_tokens(tokens, &name))
+	}
+}
+
+/// Wildcard matching with single-star backtracking.
+fn match_tokens(tokens: &[Token], name: &[char]) -> bool {
+	let (mut t, mut n) = (0, 0);
+	// Position of the last `*` and the name position it started matching at
+	let mut backtrack: Option<(usize, usize)> = None;
+	while n < name.len() {
+		match tokens.get(t) {
+			Some(Token::AnyString) => {
+				backtrack = Some((t, n));
+				t += 1;
+				continue;
+			},
+			Some(token) if token.matches(name[n]) => {
+				t += 1;
+				n += 1;
+				continue;
+			},
+			_ => {},
+		}
+		match backtrack {
+			// Let the star absorb one more character, if it can
+			Some((star_t, star_n)) if name[star_n] != '/' => {
+				backtrack = Some((star_t, star_n + 1));
+				t = star_t + 1;
+				n = star_n + 1;
+			},
+			_ => { return false; },
+		}
+	}
+	tokens[t..].iter().all(|token| matches!(token, Token::AnyString))
+}
+
+/// Splits a leading `scheme://authority` off of a path, if present.
+fn split_authority(path: &str) -> (&str, &str) {
+	if let Some(i) = path.find("://") {
+		if !path[..i].contains('/') {
+			let rest = &path[i+3..];
+			let end = rest.find('/').map(|j| i + 3 + j).unwrap_or(path.len());
+			return (&path[..end], &path[end..]);
+		}
+	}
+	("", path)
+}
+
+impl HdfsConnection {
+	/// Finds all files and directories matching a glob pattern, sorted by name.
+	/// 
+	/// Patterns follow the same syntax as `hdfs dfs` arguments; see `GlobPattern`. Alternations may span
+	/// directories, ex. `/data/{a/b,c}/*`. A pattern without any metacharacters returns the path itself if it exists.
+	/// Returns an empty list if nothing matches.
+	pub fn glob(&self, pattern: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		let mut results = vec![];
+		for expanded in expand_braces(pattern)? {
+			self.glob_expanded(&expanded, &mut results)?;
+		}
+		results.sort_by(|a, b| a.name.cmp(&b.name));
+		results.dedup_by(|a, b| a.name == b.name);
+		Ok(results)
+	}
+	
+	fn glob_expanded(&self, pattern: &str, results: &mut Vec<HdfsDirectoryEntry>) -> io::Result<()> {
+		let (authority, path) = split_authority(pattern);
+		let absolute = path.starts_with('/');
+		// Paths matched so far, along with their entries if they were found by listing
+		let mut candidates: Vec<(String, Option<HdfsDirectoryEntry>)> = vec![(
+			format!("{}{}", authority, if absolute { "/" } else { "" }),
+			None,
+		)];
+		
+		for component in path.split('/').filter(|c| !c.is_empty()) {
+			let mut next = vec![];
+			if !has_glob_chars(component) {
+				let component = unescape(component);
+				for (parent, _) in candidates {
+					next.push((join(&parent, &component), None));
+				}
+			} else {
+				let matcher = GlobPattern::new(component)?;
+				for (parent, entry) in candidates {
+					if let Some(HdfsDirectoryEntryKind::File) = entry.as_ref().map(|e| e.kind) {
+						continue;
+					}
+					let listing = match self.list_dir(if parent.is_empty() { "." } else { &parent }) {
+						Ok(listing) => listing,
+						Err(ref e) if e.kind() == io::ErrorKind::NotFound => { continue; },
+						Err(e) => { return Err(e); },
+					};
+					for child in listing.into_iter() {
+						let name = child.name.trim_end_matches('/').rsplit('/').next().unwrap_or("").to_string();
+						if matcher.matches(&name) {
+							next.push((child.name.clone(), Some(child)));
+						}
+					}
+				}
+			}
+			candidates = next;
+		}
+		
+		for (path, entry) in candidates {
+			match entry {
+				Some(entry) => results.push(entry),
+				None => match self.stat(&path) {
+					Ok(entry) => results.push(entry),
+					Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+					Err(e) => { return Err(e); },
+				},
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn matches(pattern: &str, name: &str) -> bool {
+		GlobPattern::new(pattern).unwrap().matches(name)
+	}
+	
+	#[test]
+	fn wildcards() {
+		assert!(matches("*.txt", "a.txt"));
+		assert!(matches("*.txt", ".txt"));
+		assert!(!matches("*.txt", "a.txt.gz"));
+		assert!(matches("a*b*c", "aXbYbZc"));
+		assert!(matches("?x", "ax"));
+		assert!(!matches("?x", "x"));
+		// Wildcards stop at slashes
+		assert!(!matches("*", "a/b"));
+		assert!(!matches("a?b", "a/b"));
+	}
+	
+	#[test]
+	fn classes() {
+		assert!(matches("[abc]", "b"));
+		assert!(!matches("[abc]", "d"));
+		assert!(matches("[a-c]x", "cx"));
+		assert!(!matches("[a-c]x", "dx"));
+		assert!(matches("[^a-c]", "d"));
+		assert!(matches("[!a-c]", "d"));
+		assert!(!matches("[^a-c]", "b"));
+		// A leading `]` and a trailing `-` are literals
+		assert!(matches("[]a]", "]"));
+		assert!(matches("[a-]", "-"));
+		assert!(matches("[\\]]", "]"));
+		assert!(GlobPattern::new("[abc").is_err());
+		assert!(GlobPattern::new("[c-a]").is_err());
+	}
+	
+	#[test]
+	fn escapes() {
+		assert!(matches("\\*", "*"));
+		assert!(!matches("\\*", "a"));
+		assert!(matches("a\\{b,c}", "a{b,c}"));
+		assert!(GlobPattern::new("a\\").is_err());
+		assert_eq!(GlobPattern::as_literal("a\\[b\\]"), Some("a[b]".to_string()));
+		assert_eq!(GlobPattern::as_literal("a*"), None);
+	}
+	
+	#[test]
+	fn braces() {
+		assert_eq!(expand_braces("a{b,c}d").unwrap(), vec!["abd", "acd"]);
+		assert_eq!(expand_braces("{a,{b,c}}x").unwrap(), vec!["ax", "bx", "cx"]);
+		assert_eq!(expand_braces("{a,b}{1,2}").unwrap(), vec!["a1", "a2", "b1", "b2"]);
+		assert_eq!(expand_braces("/d/{a/b,c}/*").unwrap(), vec!["/d/a/b/*", "/d/c/*"]);
+		assert_eq!(expand_braces("{}").unwrap(), vec![""]);
+		assert!(expand_braces("{a,b").is_err());
+		assert!(expand_braces("{a,{b}").is_err());
+		// A `}` without a `{` is a literal
+		assert!(matches("a}b", "a}b"));
+	}
+	
+	#[test]
+	fn braces_in_classes() {
+		assert_eq!(expand_braces("[{,}]x").unwrap(), vec!["[{,}]x"]);
+		assert_eq!(expand_braces("{a,[,}]}").unwrap(), vec!["a", "[,}]"]);
+		assert!(matches("[{]", "{"));
+		assert!(matches("[{,}]x", ",x"));
+		assert!(matches("{a,[,}]}", "}"));
+		assert!(!matches("{a,[,}]}", "[,}]"));
+	}
+}
@@ -0,0 +1,158 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Describing which cluster a connection points at, for logs and error reports.
+
+use crate::path::{base_name, parent, split_uri};
+use crate::{cstr_to_str, last_error, str_to_cstr, BuilderSetting, HdfsConnection};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::os::raw::c_char;
+use std::ptr;
+
+/// Client configuration properties included in `ConnectionInfo::conf`. Other properties aren't, even if they're set
+/// on the builder, since they may hold credentials.
+pub const KEY_CLIENT_CONFS: &[&str] = &[
+	"fs.defaultFS",
+	"hadoop.security.authentication",
+	"dfs.replication",
+	"dfs.blocksize",
+	"dfs.client.socket-timeout",
+	"dfs.client.read.shortcircuit",
+	"dfs.client.use.datanode.hostname",
+	"ipc.client.connect.timeout",
+	"ipc.client.connect.max.retries",
+];
+
+/// Which cluster a connection points at, and how it's configured, returned by `HdfsConnection::info`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ConnectionInfo {
+	/// Scheme and authority of the filesystem, ex. `hdfs://namenode:8020`, or `file:` for the local filesystem
+	pub uri: String,
+	/// Name node passed to `HdfsBuilder::name_node`, if the connection came from a builder
+	pub name_node: Option<String>,
+	/// User the connection acts as, or `None` if it isn't known; see `HdfsConnection::info`
+	pub user: Option<String>,
+	/// Whether the connection has a filesystem instance of its own, rather than one that may be cached and shared,
+	/// from `HdfsBuilder::force_new_instance`
+	pub isolated: bool,
+	/// Values of `KEY_CLIENT_CONFS`. Properties set on the builder override the configuration files; unset
+	/// properties are left out.
+	pub conf: BTreeMap<String, String>,
+}
+
+/// What's known about how a connection was made.
+#[derive(Debug,Default)]
+pub(crate) struct Origin {
+	settings: Vec<BuilderSetting>,
+	// Working directory right after connecting, which is the user's home directory
+	initial_dir: Option<String>,
+	info: RefCell<Option<ConnectionInfo>>,
+}
+impl Origin {
+	pub(crate) fn new(settings: Vec<BuilderSetting>, initial_dir: Option<String>) -> Self {
+		Self { settings, initial_dir, info: RefCell::default() }
+	}
+}
+
+impl HdfsConnection {
+	/// Gets the filesystem URI, user, and client configuration of the connection. It's read on the first call,
+	/// then cached.
+	/// 
+	/// `libhdfs` can't say which user a connection acts as, so unless one was passed to
+	/// `HdfsBuilder::user_name`, it's taken from the working directory right after connecting, which is the
+	/// user's home directory, `/user/<name>`. It's `None` for the local filesystem and for connections from
+	/// `HdfsConnection::from_raw`, and can be wrong if the filesystem instance is shared with a connection that
+	/// changed its working directory first; use `HdfsBuilder::force_new_instance` to avoid that.
+	pub fn info(&self) -> io::Result<ConnectionInfo> {
+		if let Some(info) = self.origin.info.borrow().as_ref() {
+			return Ok(info.clone());
+		}
+		
+		let working_dir = self.working_directory()?;
+		let uri = split_uri(&working_dir).0.to_string();
+		
+		let mut name_node = None;
+		let mut user = None;
+		let mut isolated = false;
+		let mut conf = BTreeMap::new();
+		for key in KEY_CLIENT_CONFS {
+			if let Some(value) = conf_get(key)? {
+				conf.insert(key.to_string(), value);
+			}
+		}
+		for setting in &self.origin.settings {
+			match setting {
+				BuilderSetting::Conf(key, value) if KEY_CLIENT_CONFS.contains(&key.as_str()) => {
+					conf.insert(key.clone(), value.clone());
+				},
+				BuilderSetting::Conf(..) => {},
+				BuilderSetting::NameNode(host) => { name_node = host.clone(); },
+				BuilderSetting::UserName(name) => { user = Some(name.clone()); },
+				BuilderSetting::ForceNewInstance => { isolated = true; },
+				BuilderSetting::KerbTicketCachePath(_) => {},
+			}
+		}
+		if user.is_none() && uri.contains("//") {
+			user = self.origin.initial_dir.as_deref()
+				.map(|dir| split_uri(dir).1)
+				.filter(|dir| parent(dir) == Some("/user"))
+				.map(|dir| base_name(dir).to_string());
+		}
+		
+		let info = ConnectionInfo { uri, name_node, user, isolated, conf };
+		*self.origin.info.borrow_mut() = Some(info.clone());
+		Ok(info)
+	}
+}
+
+impl fmt::Debug for HdfsConnection {
+	/// Shows the connection's `info`, so that logs identify which cluster it points at.
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut s = f.debug_struct("HdfsConnection");
+		match self.info() {
+			Ok(info) => s
+				.field("uri", &info.uri)
+				.field("user", &info.user)
+				.field("isolated", &info.isolated)
+				.field("conf", &info.conf),
+			Err(e) => s.field("info", &e),
+		};
+		s.finish()
+	}
+}
+
+/// Reads a property from the Hadoop configuration files, `None` if it's unset.
+fn conf_get(key: &str) -> io::Result<Option<String>> {
+	let key = str_to_cstr(key);
+	let mut value: *mut c_char = ptr::null_mut();
+	let rt = unsafe { libhdfs_sys::hdfsConfGetStr(key.as_ptr(), &mut value) };
+	if rt != 0 {
+		return Err(last_error());
+	}
+	if value.is_null() {
+		return Ok(None);
+	}
+	let s = unsafe { cstr_to_str(value) };
+	unsafe { libhdfs_sys::hdfsConfStrFree(value); }
+	Ok(Some(s))
+}
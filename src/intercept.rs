@@ -0,0 +1,435 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Composable layers around a `FileSystem`.
+//! 
+//! Cross-cutting behavior, like retrying, metrics, and rate limiting, is written as an `Interceptor`, which runs
+//! around every operation, and is stacked on a filesystem with `FileSystem::intercept`:
+//! 
+//! ```ignore
+//! let metrics = Arc::new(Metrics::new());
+//! let fs = conn
+//!     .intercept(Retry::new(3))
+//!     .intercept(RateLimit::new(100.0))
+//!     .intercept(metrics.clone());
+//! ```
+//! 
+//! Each layer wraps the ones before it, so here every attempt made by `Retry` is rate limited, and the metrics
+//! count one call however many attempts it took.
+
+use crate::filesystem::{FileReader, FileSystem, FileWriter, Op};
+use crate::HdfsDirectoryEntry;
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Code that runs around each operation of an `Intercepted` filesystem, including reads and writes on the files
+/// it opens.
+pub trait Interceptor {
+	/// Runs `op` on `paths`, by calling `call` any number of times, or by returning without calling it. For
+	/// renames, `paths` is the source and destination.
+	fn intercept<T>(&self, op: Op, paths: &[&str], call: &mut dyn FnMut() -> io::Result<T>) -> io::Result<T>;
+}
+impl<I: Interceptor> Interceptor for &I {
+	fn intercept<T>(&self, op: Op, paths: &[&str], call: &mut dyn FnMut() -> io::Result<T>) -> io::Result<T> {
+		(**self).intercept(op, paths, call)
+	}
+}
+impl<I: Interceptor> Interceptor for Arc<I> {
+	fn intercept<T>(&self, op: Op, paths: &[&str], call: &mut dyn FnMut() -> io::Result<T>) -> io::Result<T> {
+		(**self).intercept(op, paths, call)
+	}
+}
+
+/// `FileSystem` with an `Interceptor` around its operations, created by `FileSystem::intercept`.
+pub struct Intercepted<F, I> {
+	inner: F,
+	interceptor: I,
+}
+
+impl<F: FileSystem, I: Interceptor> Intercepted<F, I> {
+	/// Wraps a filesystem. Same as `inner.intercept(interceptor)`.
+	pub fn new(inner: F, interceptor: I) -> Self {
+		Self { inner, interceptor }
+	}
+	
+	/// Gets the wrapped filesystem.
+	pub fn inner(&self) -> &F {
+		&self.inner
+	}
+	
+	/// Gets the interceptor.
+	pub fn interceptor(&self) -> &I {
+		&self.interceptor
+	}
+	
+	/// Unwraps the wrapped filesystem and the interceptor.
+	pub fn into_inner(self) -> (F, I) {
+		(self.inner, self.interceptor)
+	}
+}
+
+impl<F: FileSystem, I: Interceptor> FileSystem for Intercepted<F, I> {
+	fn stat(&self, path: &str) -> io::Result<HdfsDirectoryEntry> {
+		self.interceptor.intercept(Op::Stat, &[path], &mut || self.inner.stat(path))
+	}
+	fn exists(&self, path: &str) -> io::Result<bool> {
+		self.interceptor.intercept(Op::Exists, &[path], &mut || self.inner.exists(path))
+	}
+	fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		self.interceptor.intercept(Op::ListDir, &[path], &mut || self.inner.list_dir(path))
+	}
+	fn create_dir(&self, path: &str) -> io::Result<()> {
+		self.interceptor.intercept(Op::CreateDir, &[path], &mut || self.inner.create_dir(path))
+	}
+	fn delete(&self, path: &str, recursive: bool) -> io::Result<()> {
+		self.interceptor.intercept(Op::Delete, &[path], &mut || self.inner.delete(path, recursive))
+	}
+	fn rename(&self, src: &str, dest: &str) -> io::Result<()> {
+		self.interceptor.intercept(Op::Rename, &[src, dest], &mut || self.inner.rename(src, dest))
+	}
+	fn chmod(&self, path: &str, mode: u16) -> io::Result<()> {
+		self.interceptor.intercept(Op::Chmod, &[path], &mut || self.inner.chmod(path, mode))
+	}
+	fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> io::Result<()> {
+		self.interceptor.intercept(Op::Chown, &[path], &mut || self.inner.chown(path, owner, group))
+	}
+	fn set_times(&self, path: &str, modified: Option<SystemTime>, accessed: Option<SystemTime>) -> io::Result<()> {
+		self.interceptor.intercept(Op::SetTimes, &[path], &mut || self.inner.set_times(path, modified, accessed))
+	}
+	fn truncate(&self, path: &str, size: u64) -> io::Result<bool> {
+		self.interceptor.intercept(Op::Truncate, &[path], &mut || self.inner.truncate(path, size))
+	}
+	fn open_read(&self, path: &str) -> io::Result<Box<dyn FileReader + '_>> {
+		let inner = self.interceptor.intercept(Op::OpenRead, &[path], &mut || self.inner.open_read(path))?;
+		Ok(Box::new(InterceptedFile { fs: self, path: path.to_string(), inner }))
+	}
+	fn open_create(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		let inner = self.interceptor.intercept(Op::OpenCreate, &[path], &mut || self.inner.open_create(path))?;
+		Ok(Box::new(InterceptedFile { fs: self, path: path.to_string(), inner }))
+	}
+	fn open_append(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		let inner = self.interceptor.intercept(Op::OpenAppend, &[path], &mut || self.inner.open_append(path))?;
+		Ok(Box::new(InterceptedFile { fs: self, path: path.to_string(), inner }))
+	}
+}
+
+/// File opened through an `Intercepted`, wrapping a `Box<dyn FileReader>` or `Box<dyn FileWriter>`.
+struct InterceptedFile<'a, F, I, T> {
+	fs: &'a Intercepted<F, I>,
+	path: String,
+	inner: T,
+}
+impl<'a, F: FileSystem, I: Interceptor, T: io::Read> io::Read for InterceptedFile<'a, F, I, T> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let inner = &mut self.inner;
+		self.fs.interceptor.intercept(Op::Read, &[&self.path], &mut || inner.read(buf))
+	}
+}
+impl<'a, F: FileSystem, I: Interceptor, T: io::Seek> io::Seek for InterceptedFile<'a, F, I, T> {
+	fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+		let inner = &mut self.inner;
+		self.fs.interceptor.intercept(Op::Seek, &[&self.path], &mut || inner.seek(pos))
+	}
+}
+impl<'a, F: FileSystem, I: Interceptor, T: io::Write> io::Write for InterceptedFile<'a, F, I, T> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let inner = &mut self.inner;
+		self.fs.interceptor.intercept(Op::Write, &[&self.path], &mut || inner.write(buf))
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		let inner = &mut self.inner;
+		self.fs.interceptor.intercept(Op::Flush, &[&self.path], &mut || inner.flush())
+	}
+}
+impl<'a, F: FileSystem, I: Interceptor, T: FileWriter> FileWriter for InterceptedFile<'a, F, I, T> {
+	fn sync(&mut self) -> io::Result<()> {
+		let inner = &mut self.inner;
+		self.fs.interceptor.intercept(Op::Sync, &[&self.path], &mut || inner.sync())
+	}
+	fn close(self: Box<Self>) -> io::Result<()> {
+		Box::new(self.inner).close()
+	}
+}
+
+/// Operations that can be repeated without changing the result, which `Retry` retries by default.
+const IDEMPOTENT_OPS: &[Op] = &[
+	Op::Stat, Op::Exists, Op::ListDir, Op::CreateDir, Op::Chmod, Op::Chown, Op::SetTimes, Op::OpenRead,
+];
+
+/// Error kinds that retrying won't fix, which `Retry` doesn't retry by default.
+const PERMANENT_ERRORS: &[io::ErrorKind] = &[
+	io::ErrorKind::NotFound,
+	io::ErrorKind::AlreadyExists,
+	io::ErrorKind::PermissionDenied,
+	io::ErrorKind::InvalidInput,
+	io::ErrorKind::InvalidData,
+];
+
+/// Interceptor that retries failed operations, with exponential backoff.
+/// 
+/// By default, it retries operations that are safe to repeat, like `stat` and `list_dir`, but not renames,
+/// deletes, creating files, or reads and writes on open files, and it doesn't retry errors like `NotFound` that
+/// won't go away.
+#[derive(Debug,Clone)]
+pub struct Retry {
+	retries: usize,
+	backoff: Duration,
+	max_backoff: Duration,
+	ops: Vec<Op>,
+	kinds: Option<Vec<io::ErrorKind>>,
+}
+
+impl Retry {
+	/// Retries up to `retries` times after the first attempt, waiting 100ms before the first retry and doubling
+	/// that each time, up to 10s.
+	pub fn new(retries: usize) -> Self {
+		Self {
+			retries,
+			backoff: Duration::from_millis(100),
+			max_backoff: Duration::from_secs(10),
+			ops: IDEMPOTENT_OPS.to_vec(),
+			kinds: None,
+		}
+	}
+	
+	/// Sets how long to wait before the first retry, and the most to wait between retries.
+	pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+		self.backoff = initial;
+		self.max_backoff = max;
+		self
+	}
+	
+	/// Only retries these operations.
+	pub fn ops(mut self, ops: &[Op]) -> Self {
+		self.ops = ops.to_vec();
+		self
+	}
+	
+	/// Only retries errors of these kinds.
+	pub fn kinds(mut self, kinds: &[io::ErrorKind]) -> Self {
+		self.kinds = Some(kinds.to_vec());
+		self
+	}
+	
+	fn retryable(&self, e: &io::Error) -> bool {
+		match self.kinds.as_ref() {
+			Some(kinds) => kinds.contains(&e.kind()),
+			None => !PERMANENT_ERRORS.contains(&e.kind()),
+		}
+	}
+}
+
+impl Interceptor for Retry {
+	fn intercept<T>(&self, op: Op, _paths: &[&str], call: &mut dyn FnMut() -> io::Result<T>) -> io::Result<T> {
+		if !self.ops.contains(&op) {
+			return call();
+		}
+		let mut delay = self.backoff;
+		let mut attempt = 0;
+		loop {
+			match call() {
+				Err(ref e) if attempt < self.retries && self.retryable(e) => {
+					attempt += 1;
+					thread::sleep(delay);
+					delay = (delay * 2).min(self.max_backoff);
+				},
+				res => { return res; },
+			}
+		}
+	}
+}
+
+/// Counts for one operation, from `Metrics`.
+#[derive(Debug,Clone,Copy,Default,PartialEq,Eq)]
+pub struct OpStats {
+	/// How many times it was called
+	pub calls: u64,
+	/// How many of the calls failed
+	pub errors: u64,
+	/// Time spent in all of the calls
+	pub total_time: Duration,
+	/// Time spent in the slowest call
+	pub max_time: Duration,
+}
+
+/// Interceptor that counts calls, errors, and time spent for each operation.
+/// 
+/// Share it with an `Arc` to read the counts while the filesystem is in use.
+#[derive(Debug,Default)]
+pub struct Metrics {
+	stats: Mutex<BTreeMap<Op, OpStats>>,
+}
+
+impl Metrics {
+	/// Starts with no counts.
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Gets the counts for an operation.
+	pub fn get(&self, op: Op) -> OpStats {
+		self.stats.lock().unwrap().get(&op).copied().unwrap_or_default()
+	}
+	
+	/// Gets the counts for every operation that's been called.
+	pub fn snapshot(&self) -> BTreeMap<Op, OpStats> {
+		self.stats.lock().unwrap().clone()
+	}
+	
+	/// Sets the counts back to zero.
+	pub fn reset(&self) {
+		self.stats.lock().unwrap().clear();
+	}
+}
+
+impl Interceptor for Metrics {
+	fn intercept<T>(&self, op: Op, _paths: &[&str], call: &mut dyn FnMut() -> io::Result<T>) -> io::Result<T> {
+		let start = Instant::now();
+		let res = call();
+		let elapsed = start.elapsed();
+		
+		let mut stats = self.stats.lock().unwrap();
+		let stats = stats.entry(op).or_default();
+		stats.calls += 1;
+		if res.is_err() {
+			stats.errors += 1;
+		}
+		stats.total_time += elapsed;
+		stats.max_time = stats.max_time.max(elapsed);
+		res
+	}
+}
+
+/// Interceptor that limits how many operations run per second, by making callers wait.
+/// 
+/// By default, it limits requests to the namenode, but not reads and writes on open files.
+#[derive(Debug)]
+pub struct RateLimit {
+	per_second: f64,
+	burst: f64,
+	ops: Option<Vec<Op>>,
+	// Operations that can run without waiting, which is negative when callers are waiting, and when it was last
+	// topped up
+	bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimit {
+	/// Allows `per_second` operations per second, in bursts of up to a second's worth. Panics if `per_second`
+	/// isn't positive.
+	pub fn new(per_second: f64) -> Self {
+		assert!(per_second > 0.0, "rate limit must be positive");
+		let burst = per_second.max(1.0);
+		Self { per_second, burst, ops: None, bucket: Mutex::new((burst, Instant::now())) }
+	}
+	
+	/// Allows up to `burst` operations at once after a quiet period.
+	pub fn burst(mut self, burst: usize) -> Self {
+		self.burst = burst.max(1) as f64;
+		self.bucket = Mutex::new((self.burst, Instant::now()));
+		self
+	}
+	
+	/// Only limits these operations.
+	pub fn ops(mut self, ops: &[Op]) -> Self {
+		self.ops = Some(ops.to_vec());
+		self
+	}
+	
+	/// Waits until an operation may run.
+	fn acquire(&self) {
+		let wait = {
+			let mut bucket = self.bucket.lock().unwrap();
+			let (ref mut tokens, ref mut last) = *bucket;
+			let now = Instant::now();
+			*tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.per_second).min(self.burst);
+			*last = now;
+			*tokens -= 1.0;
+			if *tokens >= 0.0 {
+				return;
+			}
+			Duration::from_secs_f64(-*tokens / self.per_second)
+		};
+		thread::sleep(wait);
+	}
+}
+
+impl Interceptor for RateLimit {
+	fn intercept<T>(&self, op: Op, _paths: &[&str], call: &mut dyn FnMut() -> io::Result<T>) -> io::Result<T> {
+		let limited = match self.ops.as_ref() {
+			Some(ops) => ops.contains(&op),
+			None => !op.is_file_io(),
+		};
+		if limited {
+			self.acquire();
+		}
+		call()
+	}
+}
+
+/// Interceptor that logs each operation, its paths, how long it took, and its error if it failed, to the `log`
+/// crate under the `hdfs::ops` target.
+/// 
+/// Reads and writes on open files are logged at the `Trace` level, since there are a lot of them.
+#[cfg(feature = "log")]
+#[derive(Debug,Clone)]
+pub struct Trace {
+	level: log::Level,
+}
+
+#[cfg(feature = "log")]
+impl Trace {
+	/// Logs at the `Debug` level.
+	pub fn new() -> Self {
+		Self { level: log::Level::Debug }
+	}
+	
+	/// Logs at `level` instead.
+	pub fn level(mut self, level: log::Level) -> Self {
+		self.level = level;
+		self
+	}
+}
+
+#[cfg(feature = "log")]
+impl Default for Trace {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(feature = "log")]
+impl Interceptor for Trace {
+	fn intercept<T>(&self, op: Op, paths: &[&str], call: &mut dyn FnMut() -> io::Result<T>) -> io::Result<T> {
+		let level = if op.is_file_io() { log::Level::Trace } else { self.level };
+		if !log::log_enabled!(target: "hdfs::ops", level) {
+			return call();
+		}
+		let start = Instant::now();
+		let res = call();
+		match res {
+			Ok(_) => log::log!(target: "hdfs::ops", level, "{:?} {} ({:?})", op, paths.join(" "), start.elapsed()),
+			Err(ref e) => log::log!(target: "hdfs::ops", level, "{:?} {} failed: {} ({:?})", op, paths.join(" "), e, start.elapsed()),
+		}
+		res
+	}
+}
@@ -0,0 +1,389 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Calling into Hadoop's Java classes where `libhdfs` has no function for something.
+//! 
+//! This goes through the JNI function tables of the JVM that `libhdfs` starts, with just the few functions needed
+//! declared here. Their positions in the tables are fixed by the JNI specification.
+
+use crate::HdfsConnection;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+pub(crate) type JInt = i32;
+
+const JNI_OK: JInt = 0;
+const JNI_EDETACHED: JInt = -2;
+const JNI_VERSION_1_2: JInt = 0x0001_0002;
+
+// Indices into `JNIInvokeInterface`
+const ATTACH_CURRENT_THREAD: usize = 4;
+const DETACH_CURRENT_THREAD: usize = 5;
+const GET_ENV: usize = 6;
+
+// Indices into `JNINativeInterface`
+const FIND_CLASS: usize = 6;
+const EXCEPTION_OCCURRED: usize = 15;
+const EXCEPTION_CLEAR: usize = 17;
+const DELETE_LOCAL_REF: usize = 23;
+const NEW_OBJECT_A: usize = 30;
+const IS_INSTANCE_OF: usize = 32;
+const GET_METHOD_ID: usize = 33;
+const CALL_OBJECT_METHOD_A: usize = 36;
+const CALL_VOID_METHOD_A: usize = 63;
+const GET_STATIC_METHOD_ID: usize = 113;
+const CALL_STATIC_VOID_METHOD_A: usize = 143;
+const NEW_STRING_UTF: usize = 167;
+const GET_STRING_UTF_CHARS: usize = 169;
+const RELEASE_STRING_UTF_CHARS: usize = 170;
+const GET_ARRAY_LENGTH: usize = 171;
+const NEW_BYTE_ARRAY: usize = 176;
+const GET_BYTE_ARRAY_REGION: usize = 200;
+const SET_BYTE_ARRAY_REGION: usize = 208;
+const EXCEPTION_CHECK: usize = 228;
+
+/// Java exceptions that map to an error kind other than `Other`. Subclasses are matched too.
+const EXCEPTION_KINDS: &[(&[u8], io::ErrorKind)] = &[
+	(b"java/io/FileNotFoundException\0", io::ErrorKind::NotFound),
+	(b"org/apache/hadoop/fs/FileAlreadyExistsException\0", io::ErrorKind::AlreadyExists),
+	(b"org/apache/hadoop/security/AccessControlException\0", io::ErrorKind::PermissionDenied),
+	(b"java/lang/UnsupportedOperationException\0", io::ErrorKind::Unsupported),
+];
+
+/// Argument to a JNI method call.
+#[repr(C)]
+#[derive(Clone,Copy)]
+pub(crate) union JValue {
+	pub(crate) l: *mut c_void,
+	pub(crate) j: i64,
+}
+
+/// JNI environment of the current thread, from `Env::current`.
+pub(crate) struct Env {
+	vm: *mut c_void,
+	pub(crate) env: *mut c_void,
+	// Whether the thread was attached to get it
+	attached: bool,
+}
+
+impl Env {
+	/// Gets the current thread's JNI environment, attaching the thread to the JVM if it isn't yet. Returns `None`
+	/// if there's no JVM, because no connection has been made.
+	pub(crate) unsafe fn current() -> io::Result<Option<Self>> {
+		#[cfg(feature = "dlopen")]
+		{
+			// If `libhdfs` can't be loaded, the JVM can't have been started by it
+			if libhdfs_sys::load().is_err() {
+				return Ok(None);
+			}
+		}
+		let mut vm: *mut c_void = ptr::null_mut();
+		let mut count: JInt = 0;
+		if libhdfs_sys::JNI_GetCreatedJavaVMs(&mut vm, 1, &mut count) != JNI_OK {
+			return Err(io::Error::new(io::ErrorKind::Other, "could not get the JVM"));
+		}
+		if count == 0 || vm.is_null() {
+			return Ok(None);
+		}
+		
+		let mut env: *mut c_void = ptr::null_mut();
+		match table_fn::<unsafe extern "C" fn(*mut c_void, *mut *mut c_void, JInt) -> JInt>(vm, GET_ENV)(vm, &mut env, JNI_VERSION_1_2) {
+			JNI_OK => Ok(Some(Self { vm, env, attached: false })),
+			JNI_EDETACHED => {
+				let attach = table_fn::<unsafe extern "C" fn(*mut c_void, *mut *mut c_void, *mut c_void) -> JInt>(vm, ATTACH_CURRENT_THREAD);
+				if attach(vm, &mut env, ptr::null_mut()) != JNI_OK {
+					return Err(io::Error::new(io::ErrorKind::Other, "could not attach to the JVM"));
+				}
+				Ok(Some(Self { vm, env, attached: true }))
+			},
+			rt => Err(io::Error::new(io::ErrorKind::Other, format!("could not get the JNI environment: {}", rt))),
+		}
+	}
+	
+	/// Detaches the thread from the JVM if `current` attached it. Anything set on the Java thread, like
+	/// thread-locals, is lost.
+	pub(crate) unsafe fn detach(self) {
+		if self.attached {
+			table_fn::<unsafe extern "C" fn(*mut c_void) -> JInt>(self.vm, DETACH_CURRENT_THREAD)(self.vm);
+		}
+	}
+	
+	/// Gets a function from the environment's table. `F` must be the function's type.
+	pub(crate) unsafe fn function<F: Copy>(&self, index: usize) -> F {
+		table_fn(self.env, index)
+	}
+	
+	/// Checks for a pending Java exception, clearing it so that later JNI calls work, and makes an error of it.
+	pub(crate) unsafe fn check_exception(&self, message: &str) -> io::Result<()> {
+		if self.function::<unsafe extern "C" fn(*mut c_void) -> u8>(EXCEPTION_CHECK)(self.env) != 0 {
+			return Err(self.exception(message));
+		}
+		Ok(())
+	}
+	
+	/// Clears the pending Java exception, so that later JNI calls work, and makes an error of `message` followed
+	/// by the exception. The error kind is picked from the exception's class, ex. `NotFound` for
+	/// `FileNotFoundException`.
+	pub(crate) unsafe fn exception(&self, message: &str) -> io::Error {
+		let throwable = self.function::<unsafe extern "C" fn(*mut c_void) -> *mut c_void>(EXCEPTION_OCCURRED)(self.env);
+		self.clear_exception();
+		if throwable.is_null() {
+			return io::Error::new(io::ErrorKind::Other, message);
+		}
+		let kind = self.exception_kind(throwable);
+		let description = self.describe(throwable);
+		self.delete_local_ref(throwable);
+		match description {
+			Some(description) => io::Error::new(kind, format!("{}: {}", message, description)),
+			None => io::Error::new(kind, message),
+		}
+	}
+	
+	unsafe fn clear_exception(&self) {
+		self.function::<unsafe extern "C" fn(*mut c_void)>(EXCEPTION_CLEAR)(self.env);
+	}
+	
+	/// Picks an error kind for a Java exception from `EXCEPTION_KINDS`.
+	unsafe fn exception_kind(&self, throwable: *mut c_void) -> io::ErrorKind {
+		let find_class = self.function::<unsafe extern "C" fn(*mut c_void, *const c_char) -> *mut c_void>(FIND_CLASS);
+		let is_instance_of = self.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> u8>(IS_INSTANCE_OF);
+		for (name, kind) in EXCEPTION_KINDS {
+			let class = find_class(self.env, CStr::from_bytes_with_nul(name).unwrap().as_ptr());
+			if class.is_null() {
+				// Not on the classpath, so it can't have been thrown
+				self.clear_exception();
+				continue;
+			}
+			let is_instance = is_instance_of(self.env, throwable, class) != 0;
+			self.delete_local_ref(class);
+			if is_instance {
+				return *kind;
+			}
+		}
+		io::ErrorKind::Other
+	}
+	
+	/// Gets `toString()` of a Java object, or `None` if that fails.
+	pub(crate) unsafe fn describe(&self, object: *mut c_void) -> Option<String> {
+		let find_class = self.function::<unsafe extern "C" fn(*mut c_void, *const c_char) -> *mut c_void>(FIND_CLASS);
+		let get_method = self.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *const c_char, *const c_char) -> *mut c_void>(GET_METHOD_ID);
+		let call = self.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *const JValue) -> *mut c_void>(CALL_OBJECT_METHOD_A);
+		
+		let class = find_class(self.env, b"java/lang/Object\0".as_ptr() as *const c_char);
+		if class.is_null() {
+			self.clear_exception();
+			return None;
+		}
+		let to_string = get_method(self.env, class, b"toString\0".as_ptr() as *const c_char, b"()Ljava/lang/String;\0".as_ptr() as *const c_char);
+		self.delete_local_ref(class);
+		if to_string.is_null() {
+			self.clear_exception();
+			return None;
+		}
+		let string = call(self.env, object, to_string, ptr::null());
+		if string.is_null() {
+			self.clear_exception();
+			return None;
+		}
+		let description = self.string(string);
+		self.delete_local_ref(string);
+		description
+	}
+	
+	/// Copies a Java string, or returns `None` if its characters can't be gotten.
+	unsafe fn string(&self, string: *mut c_void) -> Option<String> {
+		let chars = self.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *mut u8) -> *const c_char>(GET_STRING_UTF_CHARS)(self.env, string, ptr::null_mut());
+		if chars.is_null() {
+			self.clear_exception();
+			return None;
+		}
+		// Java's modified UTF-8 only differs for null characters and characters outside the BMP
+		let copy = CStr::from_ptr(chars).to_string_lossy().into_owned();
+		self.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *const c_char)>(RELEASE_STRING_UTF_CHARS)(self.env, string, chars);
+		Some(copy)
+	}
+	
+	/// Deletes a local reference, so it doesn't leak if the thread never returns to Java.
+	pub(crate) unsafe fn delete_local_ref(&self, object: *mut c_void) {
+		if !object.is_null() {
+			self.function::<unsafe extern "C" fn(*mut c_void, *mut c_void)>(DELETE_LOCAL_REF)(self.env, object);
+		}
+	}
+}
+
+/// Gets a function from the table that a `JavaVM` or `JNIEnv` points to. `F` must be the function's type.
+unsafe fn table_fn<F: Copy>(object: *mut c_void, index: usize) -> F {
+	let table = *(object as *const *const *const c_void);
+	let f = *table.add(index);
+	mem::transmute_copy(&f)
+}
+
+/// Makes JNI calls, keeping the local references they return to delete them when it's dropped. Local references
+/// would otherwise leak if the thread never returns to Java, which threads from Rust don't.
+pub(crate) struct Frame<'a> {
+	pub(crate) env: &'a Env,
+	refs: Vec<*mut c_void>,
+}
+
+impl<'a> Frame<'a> {
+	pub(crate) fn new(env: &'a Env) -> Self {
+		Self { env, refs: vec![] }
+	}
+	
+	fn keep(&mut self, object: *mut c_void) -> *mut c_void {
+		if !object.is_null() {
+			self.refs.push(object);
+		}
+		object
+	}
+	
+	/// Finds a class, by its name with slashes and a null terminator, ex. `b"java/lang/String\0"`.
+	pub(crate) unsafe fn class(&mut self, name: &[u8]) -> io::Result<*mut c_void> {
+		let name = CStr::from_bytes_with_nul(name).unwrap();
+		let class = self.env.function::<unsafe extern "C" fn(*mut c_void, *const c_char) -> *mut c_void>(FIND_CLASS)(self.env.env, name.as_ptr());
+		if class.is_null() {
+			return Err(self.env.exception(&format!("could not find {}", name.to_string_lossy().replace('/', "."))));
+		}
+		Ok(self.keep(class))
+	}
+	
+	/// Finds an instance method, by its name and JNI type signature, both with null terminators.
+	pub(crate) unsafe fn method(&self, class: *mut c_void, name: &[u8], signature: &[u8]) -> io::Result<*mut c_void> {
+		let get_method = self.env.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *const c_char, *const c_char) -> *mut c_void>(GET_METHOD_ID);
+		let name = CStr::from_bytes_with_nul(name).unwrap();
+		let method = get_method(self.env.env, class, name.as_ptr(), CStr::from_bytes_with_nul(signature).unwrap().as_ptr());
+		if method.is_null() {
+			return Err(self.env.exception(&format!("could not find method {}", name.to_string_lossy())));
+		}
+		Ok(method)
+	}
+	
+	/// Finds a static method, by its name and JNI type signature, both with null terminators.
+	pub(crate) unsafe fn static_method(&self, class: *mut c_void, name: &[u8], signature: &[u8]) -> io::Result<*mut c_void> {
+		let get_method = self.env.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *const c_char, *const c_char) -> *mut c_void>(GET_STATIC_METHOD_ID);
+		let name = CStr::from_bytes_with_nul(name).unwrap();
+		let method = get_method(self.env.env, class, name.as_ptr(), CStr::from_bytes_with_nul(signature).unwrap().as_ptr());
+		if method.is_null() {
+			return Err(self.env.exception(&format!("could not find static method {}", name.to_string_lossy())));
+		}
+		Ok(method)
+	}
+	
+	/// Makes a Java string. Fails with `InvalidInput` if `s` contains a null character.
+	pub(crate) unsafe fn new_string(&mut self, s: &str) -> io::Result<*mut c_void> {
+		let s = CString::new(s)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} contains a null character", s)))?;
+		let string = self.env.function::<unsafe extern "C" fn(*mut c_void, *const c_char) -> *mut c_void>(NEW_STRING_UTF)(self.env.env, s.as_ptr());
+		if string.is_null() {
+			return Err(self.env.exception("could not make a Java string"));
+		}
+		Ok(self.keep(string))
+	}
+	
+	/// Makes a Java `byte[]` with a copy of `data`.
+	pub(crate) unsafe fn new_byte_array(&mut self, data: &[u8]) -> io::Result<*mut c_void> {
+		if data.len() > JInt::max_value() as usize {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "too much data for a Java array"));
+		}
+		let array = self.env.function::<unsafe extern "C" fn(*mut c_void, JInt) -> *mut c_void>(NEW_BYTE_ARRAY)(self.env.env, data.len() as JInt);
+		if array.is_null() {
+			return Err(self.env.exception("could not make a Java byte array"));
+		}
+		self.keep(array);
+		self.env.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, JInt, JInt, *const u8)>(SET_BYTE_ARRAY_REGION)(self.env.env, array, 0, data.len() as JInt, data.as_ptr());
+		self.env.check_exception("could not fill a Java byte array")?;
+		Ok(array)
+	}
+	
+	/// Copies a Java `byte[]`.
+	pub(crate) unsafe fn byte_array(&self, array: *mut c_void) -> io::Result<Vec<u8>> {
+		let len = self.env.function::<unsafe extern "C" fn(*mut c_void, *mut c_void) -> JInt>(GET_ARRAY_LENGTH)(self.env.env, array);
+		let mut data = vec![0; len.max(0) as usize];
+		self.env.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, JInt, JInt, *mut u8)>(GET_BYTE_ARRAY_REGION)(self.env.env, array, 0, len, data.as_mut_ptr());
+		self.env.check_exception("could not read a Java byte array")?;
+		Ok(data)
+	}
+	
+	/// Makes an object with the constructor of `class` with the type signature `signature`.
+	pub(crate) unsafe fn new_object(&mut self, class: *mut c_void, signature: &[u8], args: &[JValue]) -> io::Result<*mut c_void> {
+		let constructor = self.method(class, b"<init>\0", signature)?;
+		let object = self.env.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *const JValue) -> *mut c_void>(NEW_OBJECT_A)(self.env.env, class, constructor, args.as_ptr());
+		if object.is_null() {
+			return Err(self.env.exception("could not make a Java object"));
+		}
+		Ok(self.keep(object))
+	}
+	
+	/// Calls a method that returns an object, which may be null. Errors are `message` followed by the exception.
+	pub(crate) unsafe fn call_object(&mut self, object: *mut c_void, method: *mut c_void, args: &[JValue], message: &str) -> io::Result<*mut c_void> {
+		let rt = self.env.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *const JValue) -> *mut c_void>(CALL_OBJECT_METHOD_A)(self.env.env, object, method, args.as_ptr());
+		self.env.check_exception(message)?;
+		Ok(self.keep(rt))
+	}
+	
+	/// Calls a method that returns nothing. Errors are `message` followed by the exception.
+	pub(crate) unsafe fn call_void(&self, object: *mut c_void, method: *mut c_void, args: &[JValue], message: &str) -> io::Result<()> {
+		self.env.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *const JValue)>(CALL_VOID_METHOD_A)(self.env.env, object, method, args.as_ptr());
+		self.env.check_exception(message)
+	}
+	
+	/// Calls a static method that returns nothing. Errors are `message` followed by the exception.
+	pub(crate) unsafe fn call_static_void(&self, class: *mut c_void, method: *mut c_void, args: &[JValue], message: &str) -> io::Result<()> {
+		self.env.function::<unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *const JValue)>(CALL_STATIC_VOID_METHOD_A)(self.env.env, class, method, args.as_ptr());
+		self.env.check_exception(message)
+	}
+	
+	/// Makes an `org.apache.hadoop.fs.Path`.
+	pub(crate) unsafe fn hadoop_path(&mut self, path: &str) -> io::Result<*mut c_void> {
+		let class = self.class(b"org/apache/hadoop/fs/Path\0")?;
+		let string = self.new_string(path)?;
+		self.new_object(class, b"(Ljava/lang/String;)V\0", &[JValue { l: string }])
+	}
+}
+
+impl<'a> Drop for Frame<'a> {
+	fn drop(&mut self) {
+		for object in self.refs.drain(..) {
+			unsafe { self.env.delete_local_ref(object); }
+		}
+	}
+}
+
+impl HdfsConnection {
+	/// Calls `f` with the connection's Java `org.apache.hadoop.fs.FileSystem` object, for calling methods that
+	/// `libhdfs` doesn't wrap.
+	/// 
+	/// The JNI `libhdfs` hands out a global reference to the `FileSystem` as the `hdfsFS` handle. libhdfs++
+	/// doesn't use Java at all, so this fails with `NotConnected` there, since there's no JVM.
+	pub(crate) fn with_java_fs<T, F>(&self, f: F) -> io::Result<T>
+		where F: FnOnce(&mut Frame, *mut c_void) -> io::Result<T>
+	{
+		unsafe {
+			let env = Env::current()?
+				.ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "the JVM hasn't been started"))?;
+			let res = f(&mut Frame::new(&env), self.p.as_ptr() as *mut c_void);
+			env.detach();
+			res
+		}
+	}
+}
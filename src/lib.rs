@@ -38,10 +38,33 @@
 //! 
 //! * `RSHDFS_HEADER_DIR`: Directory with `hdfs.h` in it
 //! * `RSHDFS_LIB_DIR`: Directory with `libhdfs.so` or `libhdfs.a` in it
-//! * `RSHDFS_STATIC`: If set to a non-empty string, link `libhdfs.a` instead of `libhdfs.so` (the default).
-//!   You will probably need `RUSTFLAGS="-C relocation-model=dynamic-no-pic"` to make this work.
+//! * `RSHDFS_LIB_NAME`: Name of the library to link, if not `hdfs`
+//! * `RSHDFS_STATIC`: If set to a non-empty string, link `libhdfs.a` instead of `libhdfs.so` (the default), like
+//!   the `static` feature. See "Static Binaries" below.
 //! * `JAVA_HOME`: For linking to `libjni` when using a static library. If not set, the build script will
 //!   try to guess based on where the `java` executable in your path is symlinked to.
+//! * `HADOOP_HOME` or `HADOOP_PREFIX`: A Hadoop distribution to fall back to when `RSHDFS_HEADER_DIR` or
+//!   `RSHDFS_LIB_DIR` aren't set. `hdfs.h` is taken from its `include` directory and the library from `lib/native`.
+//! 
+//! Without those, the build script also tries a `libhdfs` pkg-config file and where Cloudera parcels, HDP,
+//! and Bigtop packages install Hadoop, and lists the paths it searched if it can't find `hdfs.h` or the library.
+//! 
+//! When cross-compiling, each of those can be suffixed with the target, ex. `RSHDFS_LIB_DIR_aarch64-unknown-linux-gnu`
+//! or `JAVA_HOME_aarch64_unknown_linux_gnu`, to set it for the target only. The host's `java` and system library
+//! directories aren't used, so `JAVA_HOME` has to point to a JDK for the target, and bindgen is passed `--target`.
+//! 
+//! Alternatively, enable the `hadoop-3_3` or `hadoop-2_10` feature to use a copy of `hdfs.h` bundled with
+//! `libhdfs-sys`, so only the library is needed to build. `RSHDFS_HEADER_DIR` still takes priority. The Hadoop 2.10
+//! header lacks `hdfsPreadFully` and the exception queries.
+//! 
+//! Functions added in later Hadoop versions, like the stream builder and `hdfsTruncateFile`, are filled in by
+//! `libhdfs-sys` when `hdfs.h` doesn't declare them, or with `dlopen`, when the loaded library doesn't have them.
+//! Opening files falls back to `hdfsOpenFile`, and the rest fail with an `Unsupported` error, so the same code
+//! works against older Hadoop installations. `libhdfs_sys::has_function` tells whether a function is the real one.
+//! 
+//! Generating the bindings needs libclang. To build without it, disable the default `bindgen` feature and enable
+//! `pregenerated`, which uses bindings checked in to `libhdfs-sys`, generated from the Hadoop 3.3 header. They
+//! have Linux's values for the `O_*` flags and `time_t`, so they can't be used on other platforms.
 //! 
 //! When running an executable using this library, you need to ensure two things for `libhdfs`:
 //! 
@@ -50,6 +73,34 @@
 //!   reads and other optimizations.
 //! * `CLASSPATH` is set up to load all of the hadoop libraries, without wildcards. You can do this with
 //!   `export CLASSPATH="$(hadoop classpath --glob)"`
+//!   or by calling `init_classpath` before connecting, which builds it from `HADOOP_HOME` if it isn't set.
+//! 
+//! On Windows, link against `hdfs.lib` from an MSVC build of Hadoop and `jvm.lib` from the JDK, which is found
+//! with `JAVA_HOME`, or the registry entries written by its installer if that isn't set. At runtime, the directories containing
+//! `hdfs.dll` and `jvm.dll` (`%JAVA_HOME%\bin\server`) need to be on the `PATH`.
+//! 
+//! On macOS, `libjvm.dylib` is found with `/usr/libexec/java_home` if `JAVA_HOME` isn't set, and the `dfs` binary,
+//! tests, and examples get an rpath for it and `libhdfs.dylib`. Other executables need `DYLD_LIBRARY_PATH` set
+//! to both directories, or an rpath added by their build script; see `build.rs` for how.
+//! 
+//! With the `dlopen` feature, `libhdfs` isn't linked at all, but loaded when it's first used, so the same
+//! executable can run on hosts without Hadoop installed. Call `load_libhdfs` early to handle it being missing;
+//! otherwise the first call into it panics. See the `libhdfs_sys` docs for where it looks for the library.
+//! 
+//! Static Binaries
+//! ---------------
+//! 
+//! For deploying a single binary, enable the `static` feature, with `RSHDFS_LIB_DIR` pointing at `libhdfs.a` if
+//! it isn't found. `libhdfs` is then linked in, and only `libjvm.so` is loaded from outside the binary. The `dfs`
+//! binary, tests, and examples get an rpath to where `libjvm.so` was at build time, so they don't need
+//! `LD_LIBRARY_PATH`; set `RSHDFS_JVM_RPATH` while building to use another directory, ex.
+//! `$ORIGIN/jre/lib/server` to ship a JRE next to the binary. `libhdfs.a` is usually built without `-fPIC`, so they
+//! are also linked with `-no-pie`. Other binaries need the same link arguments from their build script, using
+//! the `DEP_HDFS_STATIC` and `DEP_HDFS_JVM_DIR` variables that `libhdfs-sys` sets; see `build.rs`.
+//! 
+//! The JVM can't be fully static, and JDK builds link against glibc, so musl targets can't load it. Build for a
+//! `-gnu` target instead. Alternatively, `pregenerated` and `dlopen` together give a binary with no link-time
+//! dependency on `libhdfs` or `libjvm`, which finds both at runtime from `HADOOP_HOME` and `JAVA_HOME`.
 //! 
 //! Signals
 //! -------
@@ -61,16 +112,171 @@
 //! and SIGHUP, or register your signal handler after at least one `HdfsConnection` has been created. See
 //! [Oracle's documentation on signals](https://www.oracle.com/technetwork/java/javase/signals-139944.html)
 //! for more info.
+//! 
+//! Optional Features
+//! -----------------
+//! 
+//! * `positioned-io`: Implements `positioned_io::ReadAt` and `WriteAt` for `HdfsFile`.
+//! * `bytes`: Adds `HdfsFile` methods for reading into `bytes::BytesMut` and `bytes::Bytes` buffers.
+//! * `log`: Lets `capture_stderr` forward what the JVM writes to stderr to the `log` crate, adds the `Trace`
+//!   interceptor, which logs filesystem operations, and sends `HdfsConnection::warn_on_open_files` warnings
+//!   there instead of to stderr.
+//! * `chrono`: Adds `HdfsDirectoryEntry::last_modified_utc` and `last_access_utc`, and
+//!   `HdfsConnection::set_times_utc`, which use `chrono::DateTime<Utc>`.
+//! * `tar`: Adds `HdfsConnection::write_tar` and `unpack_tar`, for streaming directories to and from tar
+//!   archives.
+//! * `checksum`: Adds `ChecksummedWriter`, which verifies files against a CRC-32C or MD5 of what was written, and
+//!   `Manifest`, which records and verifies the checksums of a directory tree, and
+//!   `HdfsConnection::checksum_tree`, which checksums a tree in parallel.
+//! * `dlopen`: Loads `libhdfs` at runtime rather than linking to it.
+//! * `static`: Links `libhdfs.a` rather than `libhdfs.so`.
+//! * `integration`: Adds `MiniCluster` and `DockerCluster`, which run a single-node HDFS cluster for tests, from
+//!   a Hadoop installation or in a Docker container, and enables the integration tests:
+//!   `cargo test --features integration`.
+//! * `conformance`: Adds `Conformance`, a proptest-driven suite that checks a `FileSystem` against `MemoryFs`,
+//!   an in-memory model of HDFS's semantics.
+//! * `hadoop-3_3`, `hadoop-2_10`: Generates bindings from bundled headers for that Hadoop version.
+//! * `bindgen` (default): Generates the `libhdfs` bindings at build time.
+//! * `pregenerated`: Uses pre-generated bindings instead of `bindgen`.
+//! * `ext`: Adds read statistics, hedged read metrics, and Java exception details, which need Hadoop 3, and
+//!   bindings for libhdfs++'s `hdfs_ext.h` to `libhdfs_sys`. Set `RSHDFS_LIB_NAME=hdfspp` while building to link
+//!   libhdfs++ instead of `libhdfs`.
 
 pub extern crate libhdfs_sys;
 
+#[cfg(feature = "ext")]
+mod ext;
+mod append;
+mod audit;
+mod cached;
+#[cfg(feature = "checksum")]
+mod checksummed;
+#[cfg(feature = "chrono")]
+mod datetime;
+mod classpath;
+#[cfg(feature = "conformance")]
+mod conformance;
+mod context;
+mod copy;
+mod delete;
+mod diff;
+mod dirty;
+#[cfg(feature = "integration")]
+mod docker;
+mod dryrun;
+mod faulty;
+mod filesystem;
+mod glob;
+mod info;
+mod intercept;
+mod jni;
+mod lines;
+mod list;
+#[cfg(feature = "checksum")]
+mod manifest;
+mod memory;
+#[cfg(feature = "integration")]
+mod minicluster;
+mod mode;
+mod moving;
+mod path;
+mod pool;
+mod preserve;
+mod probe;
+mod quota;
+mod recursive;
+mod registry;
+mod router;
+#[cfg(feature = "checksum")]
+mod scan;
+mod shutdown;
+mod smallfiles;
+mod snapshot;
+mod split;
+mod stat;
+#[cfg(unix)]
+mod stderr;
+mod stream;
+mod syncing;
+#[cfg(feature = "tar")]
+mod tarball;
+mod temp;
+mod transfer;
+mod trash;
+mod walk;
+mod watcher;
+mod xattr;
+mod zerocopy;
+
+#[cfg(feature = "ext")]
+pub use ext::{last_exception_root_cause, last_exception_stack_trace, HedgedReadMetrics, ReadStatistics};
+pub use append::AppendOptions;
+pub use audit::{Audit, AuditRecord};
+pub use cached::CachedFs;
+#[cfg(feature = "checksum")]
+pub use checksummed::{ChecksumAlgorithm, ChecksummedWriter};
+pub use classpath::{hadoop_classpath, init_classpath};
+#[cfg(feature = "conformance")]
+pub use conformance::Conformance;
+pub use context::CallerContextGuard;
+pub use copy::{copy, copy_file, copy_file_preserving, copy_out};
+pub use delete::{BulkDeleteOptions, DeleteOptions, DeleteSummary};
+pub use diff::{diff_trees, DiffOptions, TreeDiffEntry, TreeDiffKind};
+pub use dirty::DirtyDrop;
+#[cfg(feature = "integration")]
+pub use docker::{DockerCluster, DEFAULT_DOCKER_IMAGE};
+pub use dryrun::{DryRunFs, PlannedOp};
+pub use faulty::{Fault, FaultyFs};
+pub use filesystem::{FileReader, FileSystem, FileWriter, Op};
+pub use glob::GlobPattern;
+pub use info::{ConnectionInfo, KEY_CLIENT_CONFS};
+#[cfg(feature = "log")]
+pub use intercept::Trace;
+pub use intercept::{Intercepted, Interceptor, Metrics, OpStats, RateLimit, Retry};
+pub use lines::{Line, LineReader};
+pub use list::{ListOptions, SortBy};
+#[cfg(feature = "checksum")]
+pub use manifest::{Manifest, ManifestEntry, ManifestMismatch, ManifestMismatchKind};
+pub use memory::MemoryFs;
+#[cfg(feature = "integration")]
+pub use minicluster::MiniCluster;
+pub use moving::{MoveOptions, MoveStrategy};
+pub use path::{AsHdfsPath, HdfsPath};
+pub use pool::{BufferPool, PooledBuffer};
+pub use preserve::Preserve;
+pub use probe::Probe;
+pub use quota::{QuotaExceeded, QuotaPolicy, QuotaWriter};
+pub use recursive::{RecursiveOptions, RecursiveSummary};
+pub use registry::OpenFile;
+pub use router::HdfsRouter;
+#[cfg(feature = "checksum")]
+pub use scan::TreeChecksum;
+pub use shutdown::shutdown;
+pub use smallfiles::{SmallFileArchive, SmallFileArchiver, DEFAULT_CONTAINER_SIZE};
+pub use snapshot::{SnapshotDiffEntry, SnapshotDiffKind, CURRENT_STATE};
+pub use split::Split;
+#[cfg(unix)]
+pub use stderr::{capture_stderr, StderrCapture, StderrTarget};
+pub use stream::StreamOptions;
+pub use syncing::{SyncPolicy, SyncingWriter};
+pub use temp::TempFile;
+pub use transfer::{Collision, TransferOptions, TransferSummary};
+pub use trash::{Trash, TrashEntry, CURRENT_CHECKPOINT};
+pub use walk::{Walk, WalkEntry};
+pub use watcher::{DirWatcher, WatchEvent, WatchHandle};
+pub use xattr::{ContentChecksum, XATTR_CONTENT_CHECKSUM, XATTR_CONTENT_TYPE, XATTR_RETAIN_UNTIL};
+
+use info::Origin;
+use registry::Registry;
+use zerocopy::ZeroCopy;
+use std::cell::Cell;
 use std::convert::TryFrom;
 use std::ffi::{CStr, CString};
 use std::io;
 use std::mem;
 use std::os::raw::*;
 use std::ptr::{self, NonNull};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Allocate a new `CString` from a `str` slice. Panics if it contains null bytes.
 fn str_to_cstr(s: &str) -> CString {
@@ -95,29 +301,122 @@ fn check_rt(rt: c_int) -> io::Result<()> {
 	if rt == 0 {
 		return Ok(());
 	} else {
-		return Err(io::Error::last_os_error());
+		return Err(last_error());
 	}
 }
 
+/// Gets the `errno` set by the last failed `libhdfs` call.
+#[cfg(not(windows))]
+fn last_errno() -> c_int {
+	io::Error::last_os_error().raw_os_error().unwrap_or(0)
+}
+
+/// Converts the `errno` set by the last failed `libhdfs` call to an `io::Error`.
+/// 
+/// `libhdfs` reports errors through `errno` everywhere, which is the OS error code except on Windows.
+#[cfg(not(windows))]
+fn last_error() -> io::Error {
+	io::Error::last_os_error()
+}
+
+#[cfg(windows)]
+extern "C" {
+	fn _errno() -> *mut c_int;
+	fn strerror(errnum: c_int) -> *const c_char;
+}
+
+/// Gets the `errno` set by the last failed `libhdfs` call, from the C runtime.
+#[cfg(windows)]
+fn last_errno() -> c_int {
+	unsafe { *_errno() }
+}
+
+/// Converts the `errno` set by the last failed `libhdfs` call to an `io::Error`.
+/// 
+/// On Windows, `errno` is separate from `GetLastError`, which `io::Error::last_os_error` uses, and has different
+/// values, so it's mapped to an error kind here instead. Values are from the MSVC runtime's `errno.h`.
+#[cfg(windows)]
+fn last_error() -> io::Error {
+	let errno = last_errno();
+	let kind = match errno {
+		1 | 13 => io::ErrorKind::PermissionDenied, // EPERM, EACCES
+		2 => io::ErrorKind::NotFound, // ENOENT
+		4 => io::ErrorKind::Interrupted, // EINTR
+		11 => io::ErrorKind::WouldBlock, // EAGAIN
+		17 => io::ErrorKind::AlreadyExists, // EEXIST
+		22 => io::ErrorKind::InvalidInput, // EINVAL
+		40 => io::ErrorKind::Unsupported, // ENOSYS
+		32 => io::ErrorKind::BrokenPipe, // EPIPE
+		107 => io::ErrorKind::ConnectionRefused, // ECONNREFUSED
+		138 => io::ErrorKind::TimedOut, // ETIMEDOUT
+		_ => io::ErrorKind::Other,
+	};
+	let message = unsafe { cstr_to_str(strerror(errno)) };
+	io::Error::new(kind, format!("{} (errno {})", message, errno))
+}
+
 /// Gets a pointer from an `Option<CStr>`; either the pointer to the string or `NULL`.
 fn opt_cstr_as_ptr<T: AsRef<CStr>>(s: &Option<T>) -> *const c_char {
 	s.as_ref().map(|v| v.as_ref().as_ptr()).unwrap_or(ptr::null())
 }
 
-/// Converts `time_t` to a `SystemTime` object.
+/// Converts a time from `hdfsFileInfo` to a `SystemTime`. `libhdfs` gives seconds, while libhdfs++ passes along
+/// the namenode's milliseconds.
 fn time_t_to_systime(v: &libhdfs_sys::tTime) -> SystemTime {
-	SystemTime::UNIX_EPOCH + Duration::from_secs(*v as u64)
+	// Times before the epoch are clamped to it, like `opt_systime_to_time_t` does
+	let v = (*v).max(0) as u64;
+	if libhdfs_sys::is_libhdfspp() {
+		SystemTime::UNIX_EPOCH + Duration::from_millis(v)
+	} else {
+		SystemTime::UNIX_EPOCH + Duration::from_secs(v)
+	}
+}
+
+/// Converts an optional `SystemTime` to a time for `hdfsUtime`, in seconds or in milliseconds for libhdfs++, with
+/// `-1` for `None`. Times before the epoch are clamped to it.
+fn opt_systime_to_time_t(v: Option<SystemTime>) -> libhdfs_sys::tTime {
+	match v {
+		Some(time) => time.duration_since(SystemTime::UNIX_EPOCH)
+			.map(|d| if libhdfs_sys::is_libhdfspp() { d.as_millis() as libhdfs_sys::tTime } else { d.as_secs() as libhdfs_sys::tTime })
+			.unwrap_or(0),
+		None => -1,
+	}
 }
 
 
 
+/// Loads `libhdfs`, failing with a `NotFound` error if it can't be found.
+/// 
+/// Only needed with the `dlopen` feature; otherwise `libhdfs` is linked in and this does nothing.
+pub fn load_libhdfs() -> io::Result<()> {
+	#[cfg(feature = "dlopen")]
+	{
+		libhdfs_sys::load()
+			.map_err(|e| io::Error::new(io::ErrorKind::NotFound, e))?;
+	}
+	return Ok(());
+}
+
 /// Builds an HDFS connection
+/// 
+/// `connect` consumes the builder; clone it first to make more connections with the same settings.
+/// 
+/// Filesystem cache
+/// ================
+/// Hadoop caches filesystem instances by scheme, namenode, and user, and `connect` reuses a cached instance when
+/// there is one. A reused instance keeps the configuration it was created with, so properties set with `conf_set`
+/// (and methods that use it, like `socket_timeout`) are silently ignored, and the instance is shared with every
+/// other connection to the same namenode as the same user: they share a working directory, and disconnecting
+/// any of them closes the instance for all of them. Use `connect_isolated` when that matters, ex. when several
+/// tenants in one process connect with different settings.
 pub struct HdfsBuilder {
 	// Only `None` when `connect` consumes it
 	p: Option<NonNull<libhdfs_sys::hdfsBuilder>>,
 	// Builder doesn't copy strings, it copies pointers, so need
 	// to keep the strings alive.
-	allocated_strings: Vec<CString>
+	allocated_strings: Vec<CString>,
+	// Everything set so far, to replay into a new builder when cloning
+	settings: Vec<BuilderSetting>,
 }
 impl HdfsBuilder {
 	fn ptr(&self) -> *mut libhdfs_sys::hdfsBuilder {
@@ -134,7 +433,7 @@ impl HdfsBuilder {
 			NonNull::new(libhdfs_sys::hdfsNewBuilder())
 				.expect("Could not create hdfs builder")
 		};
-		Self {p: Some(p), allocated_strings: vec![]}
+		Self {p: Some(p), allocated_strings: vec![], settings: vec![]}
 	}
 	
 	/// Sets a Hadoop configuration property.
@@ -143,12 +442,97 @@ impl HdfsBuilder {
 		let value_p = str_to_cstr_pooled(&mut self.allocated_strings, value);
 		
 		let rt = unsafe { libhdfs_sys::hdfsBuilderConfSetStr(self.ptr(), key_p, value_p) };
-		return check_rt(rt);
+		check_rt(rt)?;
+		self.settings.push(BuilderSetting::Conf(key.to_string(), value.to_string()));
+		return Ok(());
+	}
+	
+	/// Enables short-circuit local reads, where blocks stored on the same host are read straight from the
+	/// datanode's disks. `socket_path` must be the datanode's `dfs.domain.socket.path`.
+	/// 
+	/// Short-circuit reads silently fall back to reading through the datanode if they can't be set up, ex.
+	/// if `libhadoop` isn't loaded; with the `ext` feature, `HdfsFile::verify_short_circuit` checks whether
+	/// they were used.
+	pub fn short_circuit_reads(&mut self, socket_path: &str) -> io::Result<()> {
+		self.conf_set("dfs.client.read.shortcircuit", "true")?;
+		self.conf_set("dfs.domain.socket.path", socket_path)
+	}
+	
+	/// Enables hedged reads: if a read from a datanode takes longer than `threshold`, the same data is also
+	/// requested from another datanode, and whichever answers first is used. `threadpool_size` limits how many
+	/// hedged reads can run at once, across all files of the connection.
+	/// 
+	/// With the `ext` feature, `HdfsConnection::hedged_read_metrics` shows how often they happen and win.
+	pub fn enable_hedged_reads(&mut self, threadpool_size: usize, threshold: Duration) -> io::Result<()> {
+		if threadpool_size == 0 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "hedged reads need at least one thread"));
+		}
+		self.conf_set("dfs.client.hedged.read.threadpool.size", &threadpool_size.to_string())?;
+		self.conf_set("dfs.client.hedged.read.threshold.millis", &threshold.as_millis().max(1).to_string())
+	}
+	
+	/// Sets how long reads from datanodes can stall before timing out, `dfs.client.socket-timeout`. The default
+	/// is 60 seconds; zero waits forever.
+	pub fn socket_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+		self.conf_set_millis("dfs.client.socket-timeout", timeout)
+	}
+	
+	/// Sets how long writes to datanodes can stall before timing out, `dfs.datanode.socket.write.timeout`. The
+	/// default is 8 minutes; zero waits forever.
+	pub fn datanode_write_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+		self.conf_set_millis("dfs.datanode.socket.write.timeout", timeout)
+	}
+	
+	/// Sets how long connecting to the namenode can take before timing out, `ipc.client.connect.timeout`. The
+	/// default is 20 seconds.
+	pub fn connect_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+		if timeout.is_zero() {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "connect timeout must be more than zero"));
+		}
+		self.conf_set_millis("ipc.client.connect.timeout", timeout)
+	}
+	
+	/// Sets how many times connecting to the namenode is retried after a failure, and how long to wait between
+	/// attempts: `ipc.client.connect.max.retries` and `ipc.client.connect.retry.interval`. The defaults are 10
+	/// retries, 1 second apart.
+	pub fn connect_retries(&mut self, retries: u32, interval: Duration) -> io::Result<()> {
+		if retries > i32::MAX as u32 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("too many retries: {}", retries)));
+		}
+		self.conf_set("ipc.client.connect.max.retries", &retries.to_string())?;
+		self.conf_set_millis("ipc.client.connect.retry.interval", interval)
 	}
 	
-	/// Forces creation of a new instance, rather than re-using a cached one.
+	/// Sets the size of the packets that data is sent to datanodes in, `dfs.client-write-packet-size`. The default
+	/// is 64 KiB. Must be between 512 bytes, the size of a checksummed chunk, and 16 MiB, the most that datanodes
+	/// accept.
+	pub fn write_packet_size(&mut self, size: usize) -> io::Result<()> {
+		if !(512..=16 * 1024 * 1024).contains(&size) {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput,
+				format!("write packet size must be between 512 bytes and 16 MiB, not {}", size)));
+		}
+		self.conf_set("dfs.client-write-packet-size", &size.to_string())
+	}
+	
+	/// Sets a property that Hadoop reads as an `int` number of milliseconds.
+	fn conf_set_millis(&mut self, key: &str, duration: Duration) -> io::Result<()> {
+		let millis = duration.as_millis();
+		if millis > i32::MAX as u128 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is too long: {:?}", key, duration)));
+		}
+		self.conf_set(key, &millis.to_string())
+	}
+	
+	/// Forces creation of a new filesystem instance, rather than re-using a cached one, so that the connection
+	/// uses this builder's configuration and doesn't share state with other connections. The new instance isn't
+	/// added to the cache, so later connections can't reuse it either. See the filesystem cache notes on
+	/// `HdfsBuilder`.
+	/// 
+	/// Dropping a connection closes its instance, so connections that each have their own can be dropped without
+	/// closing the others, ex. ones used by tests running on other threads.
 	pub fn force_new_instance(&mut self) {
 		unsafe { libhdfs_sys::hdfsBuilderSetForceNewInstance(self.ptr()); }
+		self.settings.push(BuilderSetting::ForceNewInstance);
 	}
 	
 	/// Specifies the name node to connect to.
@@ -159,27 +543,74 @@ impl HdfsBuilder {
 	pub fn name_node(&mut self, host: Option<&str>) {
 		let host_p = host.map(|host| str_to_cstr_pooled(&mut self.allocated_strings, host)).unwrap_or(ptr::null());
 		unsafe { libhdfs_sys::hdfsBuilderSetNameNode(self.ptr(), host_p); }
+		self.settings.push(BuilderSetting::NameNode(host.map(str::to_string)));
 	}
 	
 	/// Specifies the username to connect as
 	pub fn user_name(&mut self, name: &str) {
 		let name_p = str_to_cstr_pooled(&mut self.allocated_strings, name);
 		unsafe { libhdfs_sys::hdfsBuilderSetUserName(self.ptr(), name_p); }
+		self.settings.push(BuilderSetting::UserName(name.to_string()));
+	}
+	
+	/// Specifies the path to the Kerberos ticket cache to use when connecting to a secured cluster.
+	pub fn kerb_ticket_cache_path(&mut self, path: &str) {
+		let path_p = str_to_cstr_pooled(&mut self.allocated_strings, path);
+		unsafe { libhdfs_sys::hdfsBuilderSetKerbTicketCachePath(self.ptr(), path_p); }
+		self.settings.push(BuilderSetting::KerbTicketCachePath(path.to_string()));
+	}
+	
+	/// Connects to HDFS with a filesystem instance of its own, consuming the builder. Same as calling
+	/// `force_new_instance` and then `connect`.
+	/// 
+	/// Nothing is shared with other connections, at the cost of a new namenode connection and client threads for
+	/// each one, so it's best for connections that live a while.
+	pub fn connect_isolated(mut self) -> io::Result<HdfsConnection> {
+		self.force_new_instance();
+		self.connect()
 	}
 	
 	/// Connects to HDFS, consuming the builder.
+	/// 
+	/// This may reuse a cached filesystem instance; see the filesystem cache notes on `HdfsBuilder`.
 	pub fn connect(mut self) -> io::Result<HdfsConnection> {
 		let p_maybe = unsafe {
 			NonNull::new(libhdfs_sys::hdfsBuilderConnect(self.ptr()))
 		};
 		self.p = None;
+		let settings = mem::take(&mut self.settings);
 		mem::drop(self);
 
 		if let Some(p) = p_maybe {
-			return Ok(HdfsConnection {p});
+			let mut fs = HdfsConnection { p, files: Registry::default(), dirty_drop: Cell::default(), origin: Origin::default() };
+			fs.origin = Origin::new(settings, fs.working_directory().ok());
+			return Ok(fs);
 		} else {
-			return Err(io::Error::last_os_error());
+			return Err(last_error());
+		}
+	}
+}
+impl Clone for HdfsBuilder {
+	/// Creates a builder with the same settings, so that it can be connected separately or changed, ex. to connect
+	/// as another user. `libhdfs` builders can't be copied, so the settings are applied again to a new one.
+	/// 
+	/// Panics
+	/// ======
+	/// Panics if the builder object could not be allocated.
+	fn clone(&self) -> Self {
+		let mut builder = Self::new();
+		for setting in &self.settings {
+			match setting {
+				BuilderSetting::Conf(key, value) => {
+					builder.conf_set(key, value).expect("Could not copy hdfs builder configuration");
+				},
+				BuilderSetting::ForceNewInstance => { builder.force_new_instance(); },
+				BuilderSetting::NameNode(host) => { builder.name_node(host.as_deref()); },
+				BuilderSetting::UserName(name) => { builder.user_name(name); },
+				BuilderSetting::KerbTicketCachePath(path) => { builder.kerb_ticket_cache_path(path); },
+			}
 		}
+		builder
 	}
 }
 impl Drop for HdfsBuilder {
@@ -194,9 +625,22 @@ impl Drop for HdfsBuilder {
 unsafe impl Send for HdfsBuilder {}
 
 
+/// Something set on an `HdfsBuilder`, in the order it was set.
+#[derive(Debug,Clone)]
+enum BuilderSetting {
+	Conf(String, String),
+	ForceNewInstance,
+	NameNode(Option<String>),
+	UserName(String),
+	KerbTicketCachePath(String),
+}
+
 /// Connection to an HDFS filesystem.
 pub struct HdfsConnection {
 	p: NonNull<libhdfs_sys::hdfs_internal>,
+	files: Registry,
+	dirty_drop: Cell<DirtyDrop>,
+	origin: Origin,
 }
 impl HdfsConnection {
 	/// Creates a builder for creating a connection.
@@ -206,25 +650,84 @@ impl HdfsConnection {
 		HdfsBuilder::new()
 	}
 	
+	/// Gets the `libhdfs` handle, for calling `libhdfs_sys` functions that aren't wrapped yet.
+	/// 
+	/// The connection still owns the handle, so it must not be disconnected, or used after the connection is
+	/// dropped.
+	pub fn as_raw(&self) -> libhdfs_sys::hdfsFS {
+		self.p.as_ptr()
+	}
+	
+	/// Gives up ownership of the `libhdfs` handle, without disconnecting it. It can be turned back into a
+	/// connection with `from_raw`, or must be disconnected with `hdfsDisconnect`.
+	pub fn into_raw(self) -> libhdfs_sys::hdfsFS {
+		let p = self.p.as_ptr();
+		mem::forget(self);
+		p
+	}
+	
+	/// Takes ownership of a `libhdfs` handle, ex. from `into_raw` or `hdfsBuilderConnect`. Panics if it's null.
+	/// 
+	/// # Safety
+	/// 
+	/// `p` must be a connected handle that isn't owned by anything else, since the connection will disconnect it
+	/// when dropped.
+	pub unsafe fn from_raw(p: libhdfs_sys::hdfsFS) -> Self {
+		Self { p: NonNull::new(p).expect("null hdfsFS"), files: Registry::default(), dirty_drop: Cell::default(), origin: Origin::default() }
+	}
+	
 	/// Checks if a path exists in the filesystem.
-	pub fn exists(&self, path: &str) -> io::Result<bool> {
-		let path = str_to_cstr(path);
+	pub fn exists<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<bool> {
+		let path = path.as_c_path()?;
 		
 		// This API is stupid
 		let rt = unsafe { libhdfs_sys::hdfsExists(self.p.as_ptr(), path.as_ptr()) };
 		if rt == 0 {
 			return Ok(true);
 		}
-		let err = io::Error::last_os_error();
+		let err = last_error();
 		if err.kind() == io::ErrorKind::NotFound {
 			return Ok(false);
 		}
 		return Err(err);
 	}
 	
+	/// Checks if a path is a file. Returns `false` if it doesn't exist.
+	pub fn is_file<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<bool> {
+		match self.stat(path) {
+			Ok(entry) => Ok(matches!(entry.kind, HdfsDirectoryEntryKind::File)),
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+			Err(e) => Err(e),
+		}
+	}
+	
+	/// Checks if a path is a directory. Returns `false` if it doesn't exist.
+	pub fn is_dir<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<bool> {
+		match self.stat(path) {
+			Ok(entry) => Ok(matches!(entry.kind, HdfsDirectoryEntryKind::Directory)),
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+			Err(e) => Err(e),
+		}
+	}
+	
+	/// Gets information about a file or directory.
+	pub fn stat<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<HdfsDirectoryEntry> {
+		let path = path.as_c_path()?;
+		let p_maybe = unsafe {
+			NonNull::new(libhdfs_sys::hdfsGetPathInfo(self.p.as_ptr(), path.as_ptr()))
+		};
+		let p = match p_maybe {
+			Some(p) => p,
+			None => { return Err(last_error()); },
+		};
+		let entry = unsafe { HdfsDirectoryEntry::from_raw(p.as_ref()) };
+		unsafe { libhdfs_sys::hdfsFreeFileInfo(p.as_ptr(), 1); }
+		return Ok(entry);
+	}
+	
 	/// Changes the permission bits of a file
-	pub fn chmod(&self, path: &str, mode: u16) -> io::Result<()> {
-		let path = str_to_cstr(path);
+	pub fn chmod<P: AsHdfsPath + ?Sized>(&self, path: &P, mode: u16) -> io::Result<()> {
+		let path = path.as_c_path()?;
 		let rt = unsafe { libhdfs_sys::hdfsChmod(self.p.as_ptr(), path.as_ptr(), mode as c_short) };
 		return check_rt(rt);
 	}
@@ -232,42 +735,78 @@ impl HdfsConnection {
 	/// Changes the owner and group of a file.
 	/// 
 	/// Specifying `None` for either the owner or group means that it won't be updated.
-	pub fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> io::Result<()> {
-		let path = str_to_cstr(path);
+	pub fn chown<P: AsHdfsPath + ?Sized>(&self, path: &P, owner: Option<&str>, group: Option<&str>) -> io::Result<()> {
+		let path = path.as_c_path()?;
 		let owner = owner.map(|s| str_to_cstr(s));
 		let group = group.map(|s| str_to_cstr(s));
 		let rt = unsafe { libhdfs_sys::hdfsChown(self.p.as_ptr(), path.as_ptr(), opt_cstr_as_ptr(&owner), opt_cstr_as_ptr(&group)) };
 		return check_rt(rt);
 	}
 	
+	/// Changes the modification and access times of a file.
+	/// 
+	/// Specifying `None` for either time means that it won't be updated. HDFS stores times with millisecond
+	/// precision, but `libhdfs` only sets whole seconds; libhdfs++ sets milliseconds.
+	pub fn set_times<P: AsHdfsPath + ?Sized>(&self, path: &P, modified: Option<SystemTime>, accessed: Option<SystemTime>) -> io::Result<()> {
+		let path = path.as_c_path()?;
+		let rt = unsafe { libhdfs_sys::hdfsUtime(self.p.as_ptr(), path.as_ptr(), opt_systime_to_time_t(modified), opt_systime_to_time_t(accessed)) };
+		return check_rt(rt);
+	}
+	
+	/// Changes the replication factor of an existing file. The datanodes add or remove replicas in the background.
+	pub fn set_replication<P: AsHdfsPath + ?Sized>(&self, path: &P, replication: i16) -> io::Result<()> {
+		let path = path.as_c_path()?;
+		let rt = unsafe { libhdfs_sys::hdfsSetReplication(self.p.as_ptr(), path.as_ptr(), replication) };
+		return check_rt(rt);
+	}
+	
+	/// Creates a directory, along with any missing parent directories.
+	/// 
+	/// Succeeds if the directory already exists.
+	pub fn create_dir<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<()> {
+		let path = path.as_c_path()?;
+		let rt = unsafe { libhdfs_sys::hdfsCreateDirectory(self.p.as_ptr(), path.as_ptr()) };
+		return check_rt(rt);
+	}
+	
 	/// Deletes a file.
 	/// 
 	/// Will not delete non-empty directories unless `recursive` is true
-	pub fn delete(&self, path: &str, recursive: bool) -> io::Result<()> {
-		let path = str_to_cstr(path);
+	pub fn delete<P: AsHdfsPath + ?Sized>(&self, path: &P, recursive: bool) -> io::Result<()> {
+		let path = path.as_c_path()?;
 		let rt = unsafe { libhdfs_sys::hdfsDelete(self.p.as_ptr(), path.as_ptr(), if recursive { 1 } else { 0 }) };
 		return check_rt(rt);
 	}
 	
 	/// Truncates a file to a certain size
-	pub fn truncate(&self, path: &str, size: libhdfs_sys::tOffset) -> io::Result<()> {
-		let path = str_to_cstr(path);
+	/// 
+	/// Returns `true` if the file was truncated immediately, or `false` if the namenode has started recovering
+	/// the last block in the background. In the latter case, the file should not be written to until recovery
+	/// finishes.
+	pub fn truncate<P: AsHdfsPath + ?Sized>(&self, path: &P, size: libhdfs_sys::tOffset) -> io::Result<bool> {
+		let path = path.as_c_path()?;
 		let rt = unsafe { libhdfs_sys::hdfsTruncateFile(self.p.as_ptr(), path.as_ptr(), size) };
-		return check_rt(rt);
+		if rt < 0 {
+			return Err(last_error());
+		}
+		return Ok(rt == 1);
 	}
 	
 	/// Renames a file
-	pub fn rename(&self, src: &str, dest: &str) -> io::Result<()> {
-		let src = str_to_cstr(src);
-		let dest = str_to_cstr(dest);
+	pub fn rename<S: AsHdfsPath + ?Sized, D: AsHdfsPath + ?Sized>(&self, src: &S, dest: &D) -> io::Result<()> {
+		let src = src.as_c_path()?;
+		let dest = dest.as_c_path()?;
 		let rt = unsafe { libhdfs_sys::hdfsRename(self.p.as_ptr(), src.as_ptr(), dest.as_ptr()) };
 		return check_rt(rt);
 	}
 	
-	/// Moves a file to a different HDFS filesystem
-	pub fn move_to(&self, src: &str, dest_fs: &HdfsConnection, dest: &str) -> io::Result<()> {
-		let src = str_to_cstr(src);
-		let dest = str_to_cstr(dest);
+	/// Moves a file to a different HDFS filesystem.
+	/// 
+	/// `libhdfs` copies the file and deletes the source, without checking the copy. See `move_to_with` for a move
+	/// that renames when it can, and verifies when it can't.
+	pub fn move_to<S: AsHdfsPath + ?Sized, D: AsHdfsPath + ?Sized>(&self, src: &S, dest_fs: &HdfsConnection, dest: &D) -> io::Result<()> {
+		let src = src.as_c_path()?;
+		let dest = dest.as_c_path()?;
 		let rt = unsafe { libhdfs_sys::hdfsMove(
 			self.p.as_ptr(),
 			src.as_ptr(),
@@ -277,12 +816,48 @@ impl HdfsConnection {
 		return check_rt(rt);
 	}
 	
-	/// Lists the contents of a directory
-	pub fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
-		let path = str_to_cstr(&path);
+	/// Gets the working directory, which relative paths are resolved against.
+	pub fn working_directory(&self) -> io::Result<String> {
+		// `libhdfs` sets `errno` to `ERANGE` if the buffer is too small. `libc` isn't a dependency, but the value is
+		// the same on Linux, macOS, and Windows.
+		const ERANGE: i32 = 34;
+		let mut buf: Vec<u8> = vec![0; 1024];
+		loop {
+			let p = unsafe { libhdfs_sys::hdfsGetWorkingDirectory(self.p.as_ptr(), buf.as_mut_ptr() as *mut c_char, buf.len() as _) };
+			if !p.is_null() {
+				return Ok(unsafe { cstr_to_str(p) });
+			}
+			// Retry with a bigger buffer if the path didn't fit
+			match last_errno() {
+				ERANGE if buf.len() < 1024 * 1024 => {
+					let len = buf.len() * 2;
+					buf.resize(len, 0);
+				},
+				_ => { return Err(last_error()); },
+			}
+		}
+	}
+	
+	/// Sets the working directory, which relative paths are resolved against.
+	/// 
+	/// Note that `libhdfs` may share a single filesystem instance, and so a single working directory, between
+	/// connections to the same name node, unless `HdfsBuilder::force_new_instance` is used.
+	pub fn set_working_directory<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<()> {
+		let path = path.as_c_path()?;
+		let rt = unsafe { libhdfs_sys::hdfsSetWorkingDirectory(self.p.as_ptr(), path.as_ptr()) };
+		return check_rt(rt);
+	}
+	
+	/// Lists the contents of a directory.
+	/// 
+	/// Listing a file returns a single entry for it, the same as `stat`, like Hadoop's `FileSystem.listStatus`.
+	/// Clients that fail to list files instead are handled by falling back to `stat`, so this is the same on
+	/// every version of `libhdfs`. Listing a path that doesn't exist fails with `NotFound`.
+	pub fn list_dir<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		let c_path = path.as_c_path()?;
 		let mut num_entries = 123i32; // Initialize to non-zero for empty dir detection
 		let p_maybe = unsafe {
-			NonNull::new(libhdfs_sys::hdfsListDirectory(self.p.as_ptr(), path.as_ptr(), &mut num_entries as *mut _))
+			NonNull::new(libhdfs_sys::hdfsListDirectory(self.p.as_ptr(), c_path.as_ptr(), &mut num_entries as *mut _))
 		};
 		
 		let p = match p_maybe {
@@ -292,7 +867,14 @@ impl HdfsConnection {
 				return Ok(vec![]);
 			},
 			None => {
-				return Err(io::Error::last_os_error());
+				let err = last_error();
+				if matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory) {
+					match self.stat(path) {
+						Ok(entry) if matches!(entry.kind, HdfsDirectoryEntryKind::File) => { return Ok(vec![entry]); },
+						_ => {},
+					}
+				}
+				return Err(err);
 			},
 		};
 		
@@ -305,50 +887,144 @@ impl HdfsConnection {
 		Ok(v)
 	}
 	
-	fn stream_builder(&self, path: &str, flags: u32) -> io::Result<HdfsStreamBuilder> {
-		let path = str_to_cstr(path);
+	/// Recursively walks a directory tree, starting at `path`.
+	/// 
+	/// See `Walk` for details.
+	pub fn walk<P: AsHdfsPath + ?Sized>(&self, path: &P) -> Walk {
+		Walk::new(self, path.as_str_path().map(|path| path.into_owned()))
+	}
+	
+	/// Computes the total size and number of files and directories under a path.
+	/// 
+	/// `libhdfs` does not expose the namenode's content summary call, so this walks the tree client-side,
+	/// which may take a while for large trees.
+	pub fn content_summary<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<ContentSummary> {
+		let mut summary = ContentSummary::default();
+		for item in self.walk(path) {
+			let item = item?;
+			match item.entry.kind {
+				HdfsDirectoryEntryKind::Directory => {
+					summary.directory_count += 1;
+				},
+				_ => {
+					summary.file_count += 1;
+					summary.length += item.entry.size;
+					summary.space_consumed += item.entry.size * item.entry.replication as u64;
+				},
+			}
+		}
+		return Ok(summary);
+	}
+	
+	/// Gets the raw capacity of the filesystem, in bytes.
+	pub fn capacity(&self) -> io::Result<u64> {
+		let rt = unsafe { libhdfs_sys::hdfsGetCapacity(self.p.as_ptr()) };
+		if rt < 0 {
+			return Err(last_error());
+		}
+		return Ok(rt as u64);
+	}
+	
+	/// Gets the total raw size of all files in the filesystem, in bytes.
+	pub fn used(&self) -> io::Result<u64> {
+		let rt = unsafe { libhdfs_sys::hdfsGetUsed(self.p.as_ptr()) };
+		if rt < 0 {
+			return Err(last_error());
+		}
+		return Ok(rt as u64);
+	}
+	
+	/// Gets the blocks of a file that overlap the range `start..start+len`, and the datanodes hosting them.
+	/// 
+	/// `libhdfs` only returns the hostnames, so offsets and lengths are computed from the file's block size.
+	/// They will be off for files with variable-length blocks, ex. ones appended to with `NEW_BLOCK`.
+	pub fn get_block_locations<P: AsHdfsPath + ?Sized>(&self, path: &P, start: u64, len: u64) -> io::Result<Vec<BlockLocation>> {
+		let c_path = path.as_c_path()?;
+		let entry = self.stat(&*c_path)?;
+		let p_maybe = unsafe {
+			NonNull::new(libhdfs_sys::hdfsGetHosts(self.p.as_ptr(), c_path.as_ptr(), start as libhdfs_sys::tOffset, len as libhdfs_sys::tOffset))
+		};
+		let p = match p_maybe {
+			Some(p) => p,
+			None => { return Err(last_error()); },
+		};
+		
+		let block_size = entry.block_size.max(1);
+		let mut offset = start / block_size * block_size;
+		let mut blocks = vec![];
+		unsafe {
+			let mut block = p.as_ptr();
+			while !(*block).is_null() {
+				let mut hosts = vec![];
+				let mut host = *block;
+				while !(*host).is_null() {
+					hosts.push(cstr_to_str(*host));
+					host = host.add(1);
+				}
+				blocks.push(BlockLocation {
+					offset,
+					length: block_size.min(entry.size.saturating_sub(offset)),
+					hosts,
+				});
+				offset += block_size;
+				block = block.add(1);
+			}
+			libhdfs_sys::hdfsFreeHosts(p.as_ptr());
+		}
+		return Ok(blocks);
+	}
+	
+	fn stream_builder(&self, path: &CStr, flags: u32) -> io::Result<HdfsStreamBuilder> {
 		let p_maybe = unsafe {
 			NonNull::new(libhdfs_sys::hdfsStreamBuilderAlloc(self.p.as_ptr(), path.as_ptr(), flags as i32))
 		};
 		if let Some(p) = p_maybe {
-			return Ok(HdfsStreamBuilder { fs: self, p });
+			return Ok(HdfsStreamBuilder {
+				fs: self,
+				p,
+				path: path.to_string_lossy().into_owned(),
+				writing: flags & libhdfs_sys::O_WRONLY != 0,
+				dirty_drop: None,
+				skip_checksums: false,
+			});
 		} else {
-			return Err(io::Error::last_os_error());
+			return Err(last_error());
 		}
 	}
 	
 	/// Creates a stream builder for opening a file for reading
-	pub fn open_read_builder(&self, path: &str) -> io::Result<HdfsStreamBuilder> {
-		self.stream_builder(path, libhdfs_sys::O_RDONLY)
+	pub fn open_read_builder<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<HdfsStreamBuilder> {
+		self.stream_builder(&path.as_c_path()?, libhdfs_sys::O_RDONLY)
 	}
 	
 	/// Creates a stream builder for opening a file for writing, creating if it does not exist
-	pub fn open_create_builder(&self, path: &str) -> io::Result<HdfsStreamBuilder> {
-		self.stream_builder(path, libhdfs_sys::O_WRONLY)
+	pub fn open_create_builder<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<HdfsStreamBuilder> {
+		self.stream_builder(&path.as_c_path()?, libhdfs_sys::O_WRONLY)
 	}
 	
 	/// Creates a stream builder for opening a file for appending, creating if it does not exist
-	pub fn open_append_builder(&self, path: &str) -> io::Result<HdfsStreamBuilder> {
-		self.stream_builder(path, libhdfs_sys::O_WRONLY | libhdfs_sys::O_APPEND)
+	pub fn open_append_builder<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<HdfsStreamBuilder> {
+		self.stream_builder(&path.as_c_path()?, libhdfs_sys::O_WRONLY | libhdfs_sys::O_APPEND)
 	}
 	
 	/// Opens a file for reading, using the default stream builder arguments
-	pub fn open_read(&self, path: &str) -> io::Result<HdfsFile> {
+	pub fn open_read<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<HdfsFile> {
 		self.open_read_builder(path)?.build()
 	}
 	
 	/// Opens a file for writing, creating if it does not exist, using the default stream builder arguments
-	pub fn open_create(&self, path: &str) -> io::Result<HdfsFile> {
+	pub fn open_create<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<HdfsFile> {
 		self.open_create_builder(path)?.build()
 	}
 	
 	/// Opens a file for appending, creating if it does not exist, using the default stream builder arguments
-	pub fn open_append(&self, path: &str) -> io::Result<HdfsFile> {
+	pub fn open_append<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<HdfsFile> {
 		self.open_append_builder(path)?.build()
 	}
 }
 impl Drop for HdfsConnection {
 	fn drop(&mut self) {
+		self.files.warn_if_open();
 		unsafe {
 			libhdfs_sys::hdfsDisconnect(self.p.as_ptr());
 		}
@@ -360,6 +1036,11 @@ unsafe impl Send for HdfsConnection {}
 pub struct HdfsStreamBuilder<'a> {
 	fs: &'a HdfsConnection,
 	p: NonNull<libhdfs_sys::hdfsStreamBuilder>,
+	// For the connection's list of open files
+	path: String,
+	writing: bool,
+	dirty_drop: Option<DirtyDrop>,
+	skip_checksums: bool,
 }
 impl<'a> HdfsStreamBuilder<'a> {
 	/// Sets the client-side buffer size.
@@ -383,16 +1064,34 @@ impl<'a> HdfsStreamBuilder<'a> {
 	}
 	
 	/// Builds the stream, opening the file.
-	pub fn build(self) -> io::Result<HdfsFile<'a>> {
+	pub fn build(mut self) -> io::Result<HdfsFile<'a>> {
 		let fs = self.fs;
 		let p_maybe = unsafe {
 			NonNull::new(libhdfs_sys::hdfsStreamBuilderBuild(self.p.as_ptr()))
 		};
+		// The builder is freed by building, so only the path needs dropping
+		let path = mem::take(&mut self.path);
+		let writing = self.writing;
+		let dirty_drop = self.dirty_drop.unwrap_or_else(|| fs.dirty_drop.get());
+		let skip_checksums = self.skip_checksums;
 		mem::forget(self);
 		if let Some(p) = p_maybe {
-			return Ok(HdfsFile { fs, p });
+			let mut file = HdfsFile {
+				fs,
+				p,
+				stats: Cell::new(IoStats::default()),
+				opened: Instant::now(),
+				id: fs.files.register(Some(path), writing),
+				dirty: false,
+				dirty_drop,
+				zero_copy: None,
+			};
+			if skip_checksums {
+				file.zero_copy = Some(ZeroCopy::new()?);
+			}
+			return Ok(file);
 		} else {
-			return Err(io::Error::last_os_error());
+			return Err(last_error());
 		}
 	}
 }
@@ -406,6 +1105,11 @@ impl<'a> Drop for HdfsStreamBuilder<'a> {
 
 
 
+/// Most bytes that `HdfsFile` passes to `libhdfs` in one write call. `libhdfs` copies each call's data into a
+/// new Java array, so writing a huge buffer at once could exhaust the JVM's heap, and a call can't take more
+/// than `tSize::MAX` bytes anyway.
+pub const WRITE_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
 /// Open HDFS file.
 /// 
 /// Supports the `Read`, `Write`, and `Seek` interfaces.
@@ -414,6 +1118,15 @@ impl<'a> Drop for HdfsStreamBuilder<'a> {
 pub struct HdfsFile<'a> {
 	fs: &'a HdfsConnection,
 	p: NonNull<libhdfs_sys::hdfsFile_internal>,
+	stats: Cell<IoStats>,
+	opened: Instant,
+	// Key in the connection's list of open files
+	id: u64,
+	// Whether there are writes that haven't been synced
+	dirty: bool,
+	dirty_drop: DirtyDrop,
+	// Set if reads skip checksums
+	zero_copy: Option<ZeroCopy>,
 }
 impl<'a> HdfsFile<'a> {
 	/// Requests that the file be flushed to disk, blocking until it does so.
@@ -421,43 +1134,317 @@ impl<'a> HdfsFile<'a> {
 	/// `flush` sends the client buffer to HDFS only. This function waits until the data
 	/// is safely on disk.
 	pub fn sync(&mut self) -> io::Result<()> {
+		let start = Instant::now();
 		let rt = unsafe { libhdfs_sys::hdfsHSync(self.fs.p.as_ptr(), self.p.as_ptr()) };
+		let res = check_rt(rt);
+		self.record_sync(start);
+		if res.is_ok() {
+			self.dirty = false;
+		}
+		return res;
+	}
+	
+	/// Closes the file, returning any error, which dropping it ignores.
+	/// 
+	/// For a file being written, closing flushes what's left and completes the file, so an error means it may be
+	/// missing data. Closing never applies the `DirtyDrop` policy.
+	pub fn close(mut self) -> io::Result<()> {
+		self.fs.files.unregister(self.id);
+		self.zero_copy = None;
+		let rt = unsafe { libhdfs_sys::hdfsCloseFile(self.fs.p.as_ptr(), self.p.as_ptr()) };
+		mem::forget(self);
 		return check_rt(rt);
 	}
-}
-impl<'a> io::Read for HdfsFile<'a> {
-	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-		let num_to_read = buf.len().min(libhdfs_sys::tSize::max_value() as usize);
-		let rt = unsafe { libhdfs_sys::hdfsRead(
+	
+	/// Gets the bytes transferred, calls made, and time spent in them, since the file was opened.
+	/// 
+	/// Comparing the time spent in calls to `IoStats::elapsed` tells whether a slow transfer is waiting on HDFS
+	/// or on the code using the file.
+	pub fn io_stats(&self) -> IoStats {
+		let mut stats = self.stats.get();
+		stats.elapsed = self.opened.elapsed();
+		return stats;
+	}
+	
+	/// Gets the `libhdfs` handle, for calling `libhdfs_sys` functions that aren't wrapped yet, along with
+	/// `HdfsConnection::as_raw` for the connection it was opened with.
+	/// 
+	/// The file still owns the handle, so it must not be closed, or used after the file is dropped. Reads and
+	/// writes done through it aren't counted in `io_stats`.
+	pub fn as_raw(&self) -> libhdfs_sys::hdfsFile {
+		self.p.as_ptr()
+	}
+	
+	/// Gives up ownership of the `libhdfs` handle, without closing it. It can be turned back into a file with
+	/// `from_raw`, or must be closed with `hdfsCloseFile` before the connection is dropped.
+	pub fn into_raw(mut self) -> libhdfs_sys::hdfsFile {
+		let p = self.p.as_ptr();
+		self.fs.files.unregister(self.id);
+		self.zero_copy = None;
+		mem::forget(self);
+		p
+	}
+	
+	/// Takes ownership of a `libhdfs` file handle opened on `fs`, ex. from `into_raw` or `hdfsOpenFile`. Panics
+	/// if it's null. `io_stats` starts counting from here.
+	/// 
+	/// # Safety
+	/// 
+	/// `p` must be an open file on `fs`'s handle that isn't owned by anything else, since the file will close it
+	/// when dropped.
+	pub unsafe fn from_raw(fs: &'a HdfsConnection, p: libhdfs_sys::hdfsFile) -> Self {
+		HdfsFile {
+			fs,
+			p: NonNull::new(p).expect("null hdfsFile"),
+			stats: Cell::new(IoStats::default()),
+			opened: Instant::now(),
+			id: fs.files.register(None, false),
+			dirty: false,
+			dirty_drop: fs.dirty_drop.get(),
+			zero_copy: None,
+		}
+	}
+	
+	fn record_read(&self, start: Instant, len: usize) {
+		let mut stats = self.stats.get();
+		stats.read_calls += 1;
+		stats.bytes_read += len as u64;
+		stats.read_time += start.elapsed();
+		self.stats.set(stats);
+	}
+	
+	fn record_write(&self, start: Instant, len: usize) {
+		let mut stats = self.stats.get();
+		stats.write_calls += 1;
+		stats.bytes_written += len as u64;
+		stats.write_time += start.elapsed();
+		self.stats.set(stats);
+	}
+	
+	fn record_sync(&self, start: Instant) {
+		let mut stats = self.stats.get();
+		stats.sync_calls += 1;
+		stats.sync_time += start.elapsed();
+		self.stats.set(stats);
+	}
+	
+	/// Reads up to `len` bytes into `ptr`, returning the number of bytes read.
+	/// 
+	/// `ptr` must be valid for writes of `len` bytes. It does not need to be initialized.
+	unsafe fn read_raw(&mut self, ptr: *mut u8, len: usize) -> io::Result<usize> {
+		let start = Instant::now();
+		if let Some(zero_copy) = &self.zero_copy {
+			let res = zero_copy.read(self.p.as_ptr(), ptr, len);
+			self.record_read(start, *res.as_ref().unwrap_or(&0));
+			return res;
+		}
+		let num_to_read = len.min(libhdfs_sys::tSize::max_value() as usize);
+		let rt = libhdfs_sys::hdfsRead(
 			self.fs.p.as_ptr(),
 			self.p.as_ptr(),
-			buf.as_mut_ptr() as *mut c_void,
+			ptr as *mut c_void,
 			num_to_read as libhdfs_sys::tSize
-		)};
+		);
 		if rt < 0 {
-			return Err(io::Error::last_os_error());
+			let err = last_error();
+			self.record_read(start, 0);
+			return Err(err);
 		}
+		self.record_read(start, rt as usize);
 		return Ok(rt as usize);
 	}
-}
-impl<'a> io::Write for HdfsFile<'a> {
-	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-		let num_to_read = buf.len().min(libhdfs_sys::tSize::max_value() as usize);
+	
+	/// Writes up to `buf.len()` bytes with a single `hdfsWrite` call, returning the number of bytes written.
+	fn write_chunk(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let num_to_write = buf.len().min(libhdfs_sys::tSize::max_value() as usize);
+		let start = Instant::now();
 		let rt = unsafe { libhdfs_sys::hdfsWrite(
 			self.fs.p.as_ptr(),
 			self.p.as_ptr(),
 			buf.as_ptr() as *const c_void,
+			num_to_write as libhdfs_sys::tSize
+		)};
+		if rt < 0 {
+			let err = last_error();
+			self.record_write(start, 0);
+			return Err(err);
+		}
+		self.record_write(start, rt as usize);
+		self.dirty |= rt > 0;
+		return Ok(rt as usize);
+	}
+	
+	/// Reads data into the spare capacity of `buf` (`buf.capacity() - buf.len()`) with a single read call,
+	/// appending it to the vector and returning the number of bytes read.
+	/// 
+	/// Unlike `Read::read`, the buffer does not need to be zero-initialized first, which is significant for
+	/// large reads. Reserve capacity beforehand, ex. with `Vec::with_capacity` or `Vec::reserve`.
+	pub fn read_uninit(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+		let len = buf.len();
+		let spare = buf.spare_capacity_mut();
+		unsafe {
+			let n = self.read_raw(spare.as_mut_ptr() as *mut u8, spare.len())?;
+			buf.set_len(len + n);
+			return Ok(n);
+		}
+	}
+	
+	/// Reads data until the spare capacity of `buf` is filled or the end of the file is reached,
+	/// returning the number of bytes appended.
+	/// 
+	/// Like `read_uninit`, this does not zero-initialize the buffer first.
+	pub fn read_to_capacity(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+		let start_len = buf.len();
+		while buf.len() < buf.capacity() {
+			match self.read_uninit(buf) {
+				Ok(0) => { break; },
+				Ok(_) => {},
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+				Err(e) => { return Err(e); },
+			}
+		}
+		return Ok(buf.len() - start_len);
+	}
+	
+	/// Reads data until the spare capacity of `buf` is filled or the end of the file is reached,
+	/// returning the number of bytes appended.
+	/// 
+	/// Like `read_uninit`, this does not zero-initialize the buffer first.
+	#[cfg(feature = "bytes")]
+	pub fn read_to_capacity_bytes(&mut self, buf: &mut bytes::BytesMut) -> io::Result<usize> {
+		let start_len = buf.len();
+		use bytes::BufMut;
+		while buf.len() < buf.capacity() {
+			// The buffer isn't full, so `chunk_mut` is exactly the spare capacity rather than new space
+			let spare = buf.chunk_mut();
+			let rt = unsafe { self.read_raw(spare.as_mut_ptr(), spare.len()) };
+			match rt {
+				Ok(0) => { break; },
+				Ok(n) => unsafe { buf.advance_mut(n); },
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+				Err(e) => { return Err(e); },
+			}
+		}
+		return Ok(buf.len() - start_len);
+	}
+	
+	/// Reads up to `len` bytes into a newly allocated `Bytes`, stopping early only at the end of the file.
+	#[cfg(feature = "bytes")]
+	pub fn read_bytes(&mut self, len: usize) -> io::Result<bytes::Bytes> {
+		let mut buf = bytes::BytesMut::with_capacity(len);
+		self.read_to_capacity_bytes(&mut buf)?;
+		return Ok(buf.freeze());
+	}
+	
+	/// Reads data starting at `pos`, without changing the current position of the stream.
+	/// 
+	/// Uses `hdfsPread`. Returns the number of bytes read, which is zero at the end of the file.
+	pub fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+		let pos = libhdfs_sys::tOffset::try_from(pos)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "read offset overflow"))?;
+		let num_to_read = buf.len().min(libhdfs_sys::tSize::max_value() as usize);
+		let start = Instant::now();
+		let rt = unsafe { libhdfs_sys::hdfsPread(
+			self.fs.p.as_ptr(),
+			self.p.as_ptr(),
+			pos,
+			buf.as_mut_ptr() as *mut c_void,
 			num_to_read as libhdfs_sys::tSize
 		)};
 		if rt < 0 {
-			return Err(io::Error::last_os_error());
+			let err = last_error();
+			self.record_read(start, 0);
+			return Err(err);
 		}
+		self.record_read(start, rt as usize);
 		return Ok(rt as usize);
 	}
 	
+	/// Reads exactly enough data to fill `buf`, starting at `pos`, without changing the current position
+	/// of the stream.
+	/// 
+	/// Returns an `UnexpectedEof` error if the file ends before `buf` is filled.
+	pub fn read_exact_at(&self, mut pos: u64, mut buf: &mut [u8]) -> io::Result<()> {
+		while !buf.is_empty() {
+			match self.read_at(pos, buf) {
+				Ok(0) => {
+					return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer"));
+				},
+				Ok(n) => {
+					buf = &mut buf[n..];
+					pos += n as u64;
+				},
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+				Err(e) => { return Err(e); },
+			}
+		}
+		return Ok(());
+	}
+	
+	/// Writes data at `pos`.
+	/// 
+	/// HDFS files can only be written sequentially, so `pos` must be the current position of the stream
+	/// (i.e. the end of the file), otherwise this returns an `InvalidInput` error.
+	pub fn write_at(&mut self, pos: u64, buf: &[u8]) -> io::Result<usize> {
+		let current_pos = unsafe { libhdfs_sys::hdfsTell(self.fs.p.as_ptr(), self.p.as_ptr()) };
+		if current_pos < 0 {
+			return Err(last_error());
+		}
+		if current_pos as u64 != pos {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "HDFS files can only be written at the end"));
+		}
+		return io::Write::write(self, buf);
+	}
+}
+#[cfg(feature = "positioned-io")]
+impl<'a> positioned_io::ReadAt for HdfsFile<'a> {
+	fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+		HdfsFile::read_at(self, pos, buf)
+	}
+}
+#[cfg(feature = "positioned-io")]
+impl<'a> positioned_io::WriteAt for HdfsFile<'a> {
+	/// Note: only writes at the current end of the file are supported, see `HdfsFile::write_at`.
+	fn write_at(&mut self, pos: u64, buf: &[u8]) -> io::Result<usize> {
+		HdfsFile::write_at(self, pos, buf)
+	}
+	
 	fn flush(&mut self) -> io::Result<()> {
+		io::Write::flush(self)
+	}
+}
+impl<'a> io::Read for HdfsFile<'a> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		unsafe { self.read_raw(buf.as_mut_ptr(), buf.len()) }
+	}
+}
+impl<'a> io::Write for HdfsFile<'a> {
+	/// Writes all of `buf` unless there's an error, in calls of at most `WRITE_CHUNK_SIZE` bytes, so buffers
+	/// bigger than `libhdfs` can take in one call aren't cut short. If a call fails after some of `buf` was
+	/// written, returns how much was and drops the error; the next write retries the rest, and fails if the error
+	/// recurs.
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let mut written = 0;
+		for chunk in buf.chunks(WRITE_CHUNK_SIZE) {
+			let n = match self.write_chunk(chunk) {
+				Ok(n) => n,
+				Err(_) if written > 0 => { break; },
+				Err(e) => { return Err(e); },
+			};
+			written += n;
+			if n < chunk.len() {
+				break;
+			}
+		}
+		return Ok(written);
+	}
+	
+	fn flush(&mut self) -> io::Result<()> {
+		let start = Instant::now();
 		let rt = unsafe { libhdfs_sys::hdfsFlush(self.fs.p.as_ptr(), self.p.as_ptr()) };
-		return check_rt(rt);
+		let res = check_rt(rt);
+		self.record_sync(start);
+		return res;
 	}
 }
 impl<'a> io::Seek for HdfsFile<'a> {
@@ -473,7 +1460,7 @@ impl<'a> io::Seek for HdfsFile<'a> {
 			io::SeekFrom::Current(delta) => {
 				let current_pos = unsafe { libhdfs_sys::hdfsTell(self.fs.p.as_ptr(), self.p.as_ptr()) };
 				if current_pos < 0 {
-					return Err(io::Error::last_os_error());
+					return Err(last_error());
 				}
 				if delta == 0 {
 					return Ok(current_pos as u64);
@@ -494,20 +1481,24 @@ impl<'a> io::Seek for HdfsFile<'a> {
 }
 impl<'a> Drop for HdfsFile<'a> {
 	fn drop(&mut self) {
+		if self.dirty {
+			self.drop_dirty();
+		}
+		self.fs.files.unregister(self.id);
 		unsafe {
 			libhdfs_sys::hdfsCloseFile(self.fs.p.as_ptr(), self.p.as_ptr());
 		}
 	}
 }
 
-/// Entry returned by `HdfsConnection::list_dir`.
+/// Entry returned by `HdfsConnection::list_dir` and `HdfsConnection::stat`.
 #[derive(Debug,Clone)]
 pub struct HdfsDirectoryEntry {
 	/// What type of entry? File or Directory?
 	pub kind: HdfsDirectoryEntryKind,
 	/// Name of the file, as an absolute url (ex. `hdfs://host/a/b/c`)
 	pub name: String,
-	/// The time the file was last modified
+	/// The time the file was last modified. Whole seconds with `libhdfs`, and milliseconds with libhdfs++.
 	pub last_modified: SystemTime,
 	/// The size of the file
 	pub size: u64,
@@ -521,7 +1512,7 @@ pub struct HdfsDirectoryEntry {
 	pub group: String,
 	/// Permission bits on the file
 	pub permissions: u16,
-	/// The time the file was last accessed.
+	/// The time the file was last accessed, with the same precision as `last_modified`.
 	pub last_access: SystemTime,
 }
 impl HdfsDirectoryEntry {
@@ -541,6 +1532,74 @@ impl HdfsDirectoryEntry {
 	}
 }
 
+/// Summary of a directory tree, returned by `HdfsConnection::content_summary`.
+#[derive(Debug,Clone,Default)]
+pub struct ContentSummary {
+	/// Total size of all files
+	pub length: u64,
+	/// Number of files
+	pub file_count: u64,
+	/// Number of directories, including the root
+	pub directory_count: u64,
+	/// Total size of all files, multiplied by their replication factor
+	pub space_consumed: u64,
+}
+
+/// I/O done on an `HdfsFile`, returned by `HdfsFile::io_stats`.
+#[derive(Debug,Clone,Copy,Default,PartialEq,Eq)]
+pub struct IoStats {
+	/// Bytes returned by reads, including `read_at`
+	pub bytes_read: u64,
+	/// Number of read calls to `libhdfs`, including ones that failed
+	pub read_calls: u64,
+	/// Time spent in read calls
+	pub read_time: Duration,
+	/// Bytes accepted by writes
+	pub bytes_written: u64,
+	/// Number of write calls to `libhdfs`, including ones that failed
+	pub write_calls: u64,
+	/// Time spent in write calls
+	pub write_time: Duration,
+	/// Number of `flush` and `sync` calls
+	pub sync_calls: u64,
+	/// Time spent in `flush` and `sync` calls
+	pub sync_time: Duration,
+	/// Time since the file was opened
+	pub elapsed: Duration,
+}
+
+impl IoStats {
+	/// Average read throughput while in read calls, in bytes per second.
+	pub fn read_throughput(&self) -> f64 {
+		throughput(self.bytes_read, self.read_time)
+	}
+	
+	/// Average write throughput while in write calls, in bytes per second.
+	pub fn write_throughput(&self) -> f64 {
+		throughput(self.bytes_written, self.write_time)
+	}
+	
+	/// Time not spent in calls to `libhdfs`, ex. waiting on the code reading or writing the file.
+	pub fn idle_time(&self) -> Duration {
+		self.elapsed.saturating_sub(self.read_time + self.write_time + self.sync_time)
+	}
+}
+
+fn throughput(bytes: u64, time: Duration) -> f64 {
+	if time.as_secs_f64() == 0.0 { 0.0 } else { bytes as f64 / time.as_secs_f64() }
+}
+
+/// Block of a file, returned by `HdfsConnection::get_block_locations`.
+#[derive(Debug,Clone)]
+pub struct BlockLocation {
+	/// Offset of the start of the block in the file
+	pub offset: u64,
+	/// Length of the block
+	pub length: u64,
+	/// Hostnames of the datanodes storing replicas of the block
+	pub hosts: Vec<String>,
+}
+
 /// What type of file an HDFS entry can be.
 #[derive(Debug,Clone,Copy)]
 #[repr(u8)]
@@ -0,0 +1,170 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Reading the lines of a text file in splits, like Hadoop's `LineRecordReader`.
+//! 
+//! A split's byte range rarely starts or ends at a line boundary, so each reader skips the partial line it
+//! starts in, which belongs to the split before it, and reads past its end to finish its last line. A line is
+//! read by the split its first byte is in, or by the one before if it starts exactly at a split boundary, so
+//! splits that cover a file together read every line exactly once.
+
+use crate::split::Split;
+use crate::{HdfsConnection, HdfsFile};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+/// Line read by `LineReader`, without its line ending.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Line {
+	/// Offset of the start of the line in the file
+	pub offset: u64,
+	/// Contents of the line, with `\n` or `\r\n` removed
+	pub data: Vec<u8>,
+}
+impl Line {
+	/// Gets the line as a string, or an `InvalidData` error if it isn't UTF-8.
+	pub fn as_str(&self) -> io::Result<&str> {
+		std::str::from_utf8(&self.data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+	}
+}
+
+/// Iterator over the lines of one split of a file; see the module docs for which lines it reads.
+pub struct LineReader<R> {
+	reader: BufReader<R>,
+	// Offset in the file of the next byte to read
+	pos: u64,
+	end: u64,
+}
+
+impl<R: Read + Seek> LineReader<R> {
+	/// Reads the lines of `reader` that belong to the byte range `start..end`.
+	pub fn new(mut reader: R, start: u64, end: u64) -> io::Result<Self> {
+		reader.seek(SeekFrom::Start(start))?;
+		let mut lines = Self { reader: BufReader::new(reader), pos: start, end };
+		if start != 0 {
+			// The partial line belongs to the previous split, or if this starts on a line boundary, the whole line
+			// does, since the previous split reads the line starting at its end
+			lines.read_line()?;
+		}
+		Ok(lines)
+	}
+}
+
+impl<'a> LineReader<HdfsFile<'a>> {
+	/// Opens the file of a split from `HdfsConnection::split_plan`, and reads the lines that belong to it.
+	pub fn open(fs: &'a HdfsConnection, split: &Split) -> io::Result<Self> {
+		Self::new(fs.open_read(&split.path)?, split.offset, split.offset + split.length)
+	}
+}
+
+impl<R: Read> LineReader<R> {
+	/// Reads up to and including the next `\n`, returning `None` at the end of the file.
+	fn read_line(&mut self) -> io::Result<Option<Line>> {
+		let offset = self.pos;
+		let mut data = vec![];
+		let len = self.reader.read_until(b'\n', &mut data)?;
+		if len == 0 {
+			return Ok(None);
+		}
+		self.pos += len as u64;
+		if data.last() == Some(&b'\n') {
+			data.pop();
+			if data.last() == Some(&b'\r') {
+				data.pop();
+			}
+		}
+		Ok(Some(Line { offset, data }))
+	}
+}
+
+impl<R: Read> Iterator for LineReader<R> {
+	type Item = io::Result<Line>;
+	
+	fn next(&mut self) -> Option<io::Result<Line>> {
+		// A line starting exactly at the end is still this split's
+		if self.pos > self.end {
+			return None;
+		}
+		self.read_line().transpose()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Cursor;
+	
+	fn read_split(data: &[u8], start: u64, end: u64) -> Vec<(u64, String)> {
+		LineReader::new(Cursor::new(data), start, end).unwrap()
+			.map(|line| {
+				let line = line.unwrap();
+				(line.offset, line.as_str().unwrap().to_string())
+			})
+			.collect()
+	}
+	
+	fn owned(lines: &[(u64, &str)]) -> Vec<(u64, String)> {
+		lines.iter().map(|(offset, line)| (*offset, line.to_string())).collect()
+	}
+	
+	#[test]
+	fn splits_on_newlines() {
+		let data = b"one\ntwo\nthree\n";
+		assert_eq!(read_split(data, 0, data.len() as u64), owned(&[(0, "one"), (4, "two"), (8, "three")]));
+		// Ending inside a line finishes it, and starting inside one skips it
+		assert_eq!(read_split(data, 0, 5), owned(&[(0, "one"), (4, "two")]));
+		assert_eq!(read_split(data, 5, data.len() as u64), owned(&[(8, "three")]));
+		// A line starting exactly at the end belongs to the split before the boundary
+		assert_eq!(read_split(data, 0, 4), owned(&[(0, "one"), (4, "two")]));
+		assert_eq!(read_split(data, 4, data.len() as u64), owned(&[(8, "three")]));
+	}
+	
+	#[test]
+	fn crlf_straddling_the_end() {
+		let data = b"ab\r\ncd\r\n";
+		// The split ends between `\r` and `\n`
+		assert_eq!(read_split(data, 0, 3), owned(&[(0, "ab")]));
+		assert_eq!(read_split(data, 3, data.len() as u64), owned(&[(4, "cd")]));
+	}
+	
+	#[test]
+	fn last_line_without_newline() {
+		let data = b"one\ntwo";
+		assert_eq!(read_split(data, 0, data.len() as u64), owned(&[(0, "one"), (4, "two")]));
+		assert_eq!(read_split(data, 2, data.len() as u64), owned(&[(4, "two")]));
+		assert_eq!(read_split(data, 5, data.len() as u64), vec![]);
+	}
+	
+	#[test]
+	fn splits_read_every_line_once() {
+		let data = b"alpha\r\n\nbeta\ngamma delta\r\n\r\nepsilon";
+		let all = read_split(data, 0, data.len() as u64);
+		assert_eq!(all.len(), 6);
+		for split_size in 1..=data.len() as u64 + 1 {
+			let mut lines = vec![];
+			let mut start = 0;
+			while start < data.len() as u64 {
+				let end = (start + split_size).min(data.len() as u64);
+				lines.extend(read_split(data, start, end));
+				start = end;
+			}
+			assert_eq!(lines, all, "split size {}", split_size);
+		}
+	}
+}
@@ -0,0 +1,140 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Sorting, filtering, and limiting directory listings.
+
+use crate::path::AsHdfsPath;
+use crate::{HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::cmp::Ordering;
+use std::io;
+
+/// What `ListOptions` sorts entries by.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum SortBy {
+	/// Name, in byte order
+	Name,
+	/// Modification time, oldest first
+	Modified,
+	/// Size, smallest first
+	Size,
+}
+
+/// Options for `HdfsConnection::list_dir_opts`. By default, entries are returned unsorted, unfiltered, and
+/// unlimited, like `list_dir`.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct ListOptions {
+	sort: Option<SortBy>,
+	reverse: bool,
+	kind: Option<Kind>,
+	limit: Option<usize>,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+enum Kind {
+	Files,
+	Directories,
+}
+
+impl ListOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Sorts entries, breaking ties by name.
+	pub fn sort_by(mut self, sort: SortBy) -> Self {
+		self.sort = Some(sort);
+		self
+	}
+	
+	/// Reverses the sort order, ex. for newest or biggest first.
+	pub fn reverse(mut self) -> Self {
+		self.reverse = true;
+		self
+	}
+	
+	/// Only returns files.
+	pub fn files_only(mut self) -> Self {
+		self.kind = Some(Kind::Files);
+		self
+	}
+	
+	/// Only returns directories.
+	pub fn dirs_only(mut self) -> Self {
+		self.kind = Some(Kind::Directories);
+		self
+	}
+	
+	/// Returns at most `limit` entries: the first ones in sorted order, if sorting.
+	/// 
+	/// With a sort, this only sorts the entries that are kept, so picking the latest few of a big directory is
+	/// cheap.
+	pub fn limit(mut self, limit: usize) -> Self {
+		self.limit = Some(limit);
+		self
+	}
+	
+	/// Applies the options to a listing, ex. one from a `FileSystem`.
+	pub fn apply(&self, mut entries: Vec<HdfsDirectoryEntry>) -> Vec<HdfsDirectoryEntry> {
+		if let Some(kind) = self.kind {
+			entries.retain(|entry| match entry.kind {
+				HdfsDirectoryEntryKind::File => kind == Kind::Files,
+				HdfsDirectoryEntryKind::Directory => kind == Kind::Directories,
+				HdfsDirectoryEntryKind::Unrecognized(_) => false,
+			});
+		}
+		
+		let sort = match self.sort {
+			Some(sort) => sort,
+			None => {
+				entries.truncate(self.limit.unwrap_or(usize::MAX));
+				return entries;
+			},
+		};
+		let reverse = self.reverse;
+		let compare = move |a: &HdfsDirectoryEntry, b: &HdfsDirectoryEntry| {
+			let ordering = match sort {
+				SortBy::Name => Ordering::Equal,
+				SortBy::Modified => a.last_modified.cmp(&b.last_modified),
+				SortBy::Size => a.size.cmp(&b.size),
+			}.then_with(|| a.name.cmp(&b.name));
+			if reverse { ordering.reverse() } else { ordering }
+		};
+		match self.limit {
+			Some(0) => { entries.clear(); },
+			Some(limit) if limit < entries.len() => {
+				// Partition around the last kept entry, then only sort the ones before it
+				entries.select_nth_unstable_by(limit - 1, compare);
+				entries.truncate(limit);
+				entries.sort_unstable_by(compare);
+			},
+			_ => { entries.sort_unstable_by(compare); },
+		}
+		entries
+	}
+}
+
+impl HdfsConnection {
+	/// Lists the contents of a directory, sorted, filtered, and limited by `options`.
+	/// 
+	/// The namenode has no server-side sorting or filtering, so the whole directory is still fetched.
+	pub fn list_dir_opts<P: AsHdfsPath + ?Sized>(&self, path: &P, options: ListOptions) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		Ok(options.apply(self.list_dir(path)?))
+	}
+}
@@ -0,0 +1,276 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Manifests of directory trees, for checking that a backup or restore has everything it should.
+//! 
+//! A manifest is written as text, one line per entry, sorted by path so that manifests of the same tree are
+//! identical and can be diffed. The first line is `hdfs-rs manifest 1 <algorithm>`, and each entry is a line of
+//! fields separated by tabs, shown here as `\t`:
+//! 
+//! ```text
+//! <f or d>\t<size>\t<modification time, in milliseconds since the epoch>\t<permissions, in octal>\t<checksum, in hex, or - for directories>\t<path>
+//! ```
+//! 
+//! Paths are relative to the root of the tree, with backslashes, tabs, carriage returns, and newlines escaped as
+//! `\\`, `\t`, `\r`, and `\n`. The root itself is left out, unless it's a file, in which case it's the only entry
+//! and its path is empty.
+
+use crate::checksummed::checksum_reader;
+use crate::path::AsHdfsPath;
+use crate::{ChecksumAlgorithm, HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Header that manifests start with, followed by the checksum algorithm.
+const HEADER: &str = "hdfs-rs manifest 1";
+
+/// Manifest of a directory tree, created by `Manifest::build` or read with `Manifest::read_from`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Manifest {
+	pub algorithm: ChecksumAlgorithm,
+	/// Entries, sorted by path
+	pub entries: Vec<ManifestEntry>,
+}
+
+/// File or directory in a `Manifest`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ManifestEntry {
+	/// Path relative to the root of the tree
+	pub path: String,
+	pub is_dir: bool,
+	/// Size, or 0 for directories
+	pub size: u64,
+	pub last_modified: SystemTime,
+	pub permissions: u16,
+	/// Checksum of the file's contents, or empty for directories
+	pub checksum: Vec<u8>,
+}
+
+/// What `Manifest::verify` found wrong with an entry.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ManifestMismatchKind {
+	/// In the manifest, but not the tree
+	Missing,
+	/// In the tree, but not the manifest
+	Unexpected,
+	/// A file in one and a directory in the other
+	Kind,
+	Size,
+	/// Modification times differ by a second or more
+	Modified,
+	Permissions,
+	Checksum,
+}
+
+/// Difference found by `Manifest::verify`. An entry can have several.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ManifestMismatch {
+	pub kind: ManifestMismatchKind,
+	/// Path relative to the root of the tree
+	pub path: String,
+}
+
+impl Manifest {
+	/// Walks the tree at `root`, checksumming every file with `algorithm`.
+	/// 
+	/// `libhdfs` can't ask the datanodes for checksums, so this reads every file in the tree.
+	pub fn build<P: AsHdfsPath + ?Sized>(fs: &HdfsConnection, root: &P, algorithm: ChecksumAlgorithm) -> io::Result<Self> {
+		let mut entries = vec![];
+		for (path, entry) in index(fs, &root.as_str_path()?)? {
+			let is_dir = matches!(entry.kind, HdfsDirectoryEntryKind::Directory);
+			let checksum = if is_dir {
+				vec![]
+			} else {
+				checksum_reader(algorithm, &mut fs.open_read(&entry.name)?)?.0
+			};
+			entries.push(ManifestEntry {
+				path,
+				is_dir,
+				size: if is_dir { 0 } else { entry.size },
+				last_modified: entry.last_modified,
+				permissions: entry.permissions,
+				checksum,
+			});
+		}
+		Ok(Self { algorithm, entries })
+	}
+	
+	/// Checks the tree at `root` against the manifest, returning what differs, sorted by path.
+	/// 
+	/// Everything is compared, and files whose sizes match are read to compare checksums. Filter out
+	/// `Modified` or `Permissions` mismatches if the tree was restored without keeping those.
+	pub fn verify<P: AsHdfsPath + ?Sized>(&self, fs: &HdfsConnection, root: &P) -> io::Result<Vec<ManifestMismatch>> {
+		let mut live = index(fs, &root.as_str_path()?)?;
+		let mut mismatches = vec![];
+		for expected in self.entries.iter() {
+			let mut mismatch = |kind| mismatches.push(ManifestMismatch { kind, path: expected.path.clone() });
+			let entry = match live.remove(&expected.path) {
+				Some(entry) => entry,
+				None => {
+					mismatch(ManifestMismatchKind::Missing);
+					continue;
+				},
+			};
+			if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) != expected.is_dir {
+				mismatch(ManifestMismatchKind::Kind);
+				continue;
+			}
+			if epoch_millis(entry.last_modified) / 1000 != epoch_millis(expected.last_modified) / 1000 {
+				mismatch(ManifestMismatchKind::Modified);
+			}
+			if entry.permissions != expected.permissions {
+				mismatch(ManifestMismatchKind::Permissions);
+			}
+			if expected.is_dir {
+				continue;
+			}
+			if entry.size != expected.size {
+				mismatch(ManifestMismatchKind::Size);
+			} else if checksum_reader(self.algorithm, &mut fs.open_read(&entry.name)?)?.0 != expected.checksum {
+				mismatch(ManifestMismatchKind::Checksum);
+			}
+		}
+		mismatches.extend(live.into_keys().map(|path| ManifestMismatch { kind: ManifestMismatchKind::Unexpected, path }));
+		mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+		Ok(mismatches)
+	}
+	
+	/// Writes the manifest in the format described in the module documentation.
+	pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+		writeln!(writer, "{} {}", HEADER, algorithm_name(self.algorithm))?;
+		for entry in self.entries.iter() {
+			let checksum = if entry.is_dir {
+				"-".to_string()
+			} else {
+				entry.checksum.iter().map(|b| format!("{:02x}", b)).collect()
+			};
+			writeln!(writer, "{}\t{}\t{}\t{:o}\t{}\t{}",
+				if entry.is_dir { "d" } else { "f" }, entry.size, epoch_millis(entry.last_modified),
+				entry.permissions, checksum, escape(&entry.path))?;
+		}
+		Ok(())
+	}
+	
+	/// Reads a manifest written by `write_to`. Fails with `InvalidData` if it's malformed.
+	pub fn read_from<R: BufRead + ?Sized>(reader: &mut R) -> io::Result<Self> {
+		let mut lines = reader.lines();
+		let header = lines.next().unwrap_or_else(|| Ok(String::new()))?;
+		let algorithm = match header.strip_prefix(HEADER).map(str::trim) {
+			Some("crc32c") => ChecksumAlgorithm::Crc32c,
+			Some("md5") => ChecksumAlgorithm::Md5,
+			_ => { return Err(invalid(1, "not a manifest")); },
+		};
+		let mut entries = vec![];
+		for (i, line) in lines.enumerate() {
+			let line = line?;
+			entries.push(parse_entry(&line).ok_or_else(|| invalid(i + 2, "malformed entry"))?);
+		}
+		entries.sort_by(|a, b| a.path.cmp(&b.path));
+		Ok(Self { algorithm, entries })
+	}
+}
+
+fn parse_entry(line: &str) -> Option<ManifestEntry> {
+	let mut fields = line.splitn(6, '\t');
+	let is_dir = match fields.next()? {
+		"d" => true,
+		"f" => false,
+		_ => { return None; },
+	};
+	let size = fields.next()?.parse().ok()?;
+	let last_modified = UNIX_EPOCH + Duration::from_millis(fields.next()?.parse().ok()?);
+	let permissions = u16::from_str_radix(fields.next()?, 8).ok()?;
+	let checksum = match fields.next()? {
+		"-" if is_dir => vec![],
+		hex if hex.len() % 2 == 0 && hex.is_ascii() => (0..hex.len()).step_by(2)
+			.map(|i| u8::from_str_radix(&hex[i..i+2], 16).ok())
+			.collect::<Option<Vec<u8>>>()?,
+		_ => { return None; },
+	};
+	let path = unescape(fields.next()?)?;
+	Some(ManifestEntry { path, is_dir, size, last_modified, permissions, checksum })
+}
+
+fn invalid(line: usize, msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("manifest line {}: {}", line, msg))
+}
+
+fn algorithm_name(algorithm: ChecksumAlgorithm) -> &'static str {
+	match algorithm {
+		ChecksumAlgorithm::Crc32c => "crc32c",
+		ChecksumAlgorithm::Md5 => "md5",
+	}
+}
+
+fn escape(path: &str) -> String {
+	let mut escaped = String::with_capacity(path.len());
+	for c in path.chars() {
+		match c {
+			'\\' => escaped.push_str("\\\\"),
+			'\t' => escaped.push_str("\\t"),
+			'\r' => escaped.push_str("\\r"),
+			'\n' => escaped.push_str("\\n"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+fn unescape(escaped: &str) -> Option<String> {
+	let mut path = String::with_capacity(escaped.len());
+	let mut chars = escaped.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			path.push(c);
+			continue;
+		}
+		path.push(match chars.next()? {
+			'\\' => '\\',
+			't' => '\t',
+			'r' => '\r',
+			'n' => '\n',
+			_ => { return None; },
+		});
+	}
+	Some(path)
+}
+
+fn epoch_millis(time: SystemTime) -> u128 {
+	time.duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0)
+}
+
+/// Walks a tree, keyed by path relative to its root. The root is left out if it's a directory.
+fn index(fs: &HdfsConnection, root: &str) -> io::Result<BTreeMap<String, HdfsDirectoryEntry>> {
+	let root = fs.stat(root)?;
+	let mut index = BTreeMap::new();
+	for item in fs.walk(&root.name) {
+		let entry = item?.entry;
+		let path = entry.name.strip_prefix(root.name.as_str())
+			.unwrap_or(&entry.name)
+			.trim_start_matches('/')
+			.to_string();
+		if path.is_empty() && matches!(entry.kind, HdfsDirectoryEntryKind::Directory) {
+			continue;
+		}
+		index.insert(path, entry);
+	}
+	Ok(index)
+}
@@ -0,0 +1,372 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! An in-memory `FileSystem`, for testing code without a cluster.
+//! 
+//! It follows HDFS's semantics rather than POSIX's where they differ: creating a file or directory creates its
+//! parents, renaming onto an existing directory moves the source into it, and listing a file returns the file.
+
+use crate::filesystem::{FileReader, FileSystem, FileWriter};
+use crate::path::{base_name, join, parent, uri_path};
+use crate::{HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// Block size reported for files, which is HDFS's default.
+const BLOCK_SIZE: u64 = 128 * 1024 * 1024;
+
+/// Filesystem that keeps everything in memory. Starts out with an empty root directory.
+pub struct MemoryFs {
+	nodes: Mutex<BTreeMap<String, Node>>,
+	user: String,
+}
+
+#[derive(Debug,Clone)]
+struct Node {
+	/// `None` for directories
+	data: Option<Vec<u8>>,
+	modified: SystemTime,
+	accessed: SystemTime,
+	owner: String,
+	group: String,
+	permissions: u16,
+}
+
+impl MemoryFs {
+	/// Creates an empty filesystem, with new files owned by `hdfs`.
+	pub fn new() -> Self {
+		Self::with_user("hdfs")
+	}
+	
+	/// Creates an empty filesystem, with new files owned by `user`.
+	pub fn with_user(user: &str) -> Self {
+		let fs = Self {
+			nodes: Mutex::new(BTreeMap::new()),
+			user: user.to_string(),
+		};
+		let root = fs.new_node(None);
+		fs.lock().insert("/".to_string(), root);
+		fs
+	}
+	
+	fn lock(&self) -> MutexGuard<'_, BTreeMap<String, Node>> {
+		self.nodes.lock().unwrap_or_else(|e| e.into_inner())
+	}
+	
+	fn new_node(&self, data: Option<Vec<u8>>) -> Node {
+		let now = SystemTime::now();
+		Node {
+			permissions: if data.is_some() { 0o644 } else { 0o755 },
+			data,
+			modified: now,
+			accessed: now,
+			owner: self.user.clone(),
+			group: "supergroup".to_string(),
+		}
+	}
+	
+	/// Creates the directories above `path`, failing if one of them is a file.
+	fn create_parents(&self, nodes: &mut BTreeMap<String, Node>, path: &str) -> io::Result<()> {
+		let mut missing = vec![];
+		let mut dir = parent(path);
+		while let Some(current) = dir {
+			match nodes.get(current) {
+				Some(node) if node.data.is_some() => { return Err(not_a_directory(current)); },
+				Some(_) => { break; },
+				None => { missing.push(current.to_string()); },
+			}
+			dir = parent(current);
+		}
+		for dir in missing {
+			nodes.insert(dir, self.new_node(None));
+		}
+		Ok(())
+	}
+	
+	/// Opens a writer on an existing file, optionally emptying it first.
+	fn writer(&self, nodes: &mut BTreeMap<String, Node>, path: String, truncate: bool) -> io::Result<Box<dyn FileWriter + '_>> {
+		let node = nodes.get_mut(&path).ok_or_else(|| not_found(&path))?;
+		let data = node.data.as_mut().ok_or_else(|| is_a_directory(&path))?;
+		if truncate {
+			data.clear();
+			node.modified = SystemTime::now();
+		}
+		Ok(Box::new(MemoryWriter { fs: self, path }))
+	}
+}
+impl Default for MemoryFs {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl FileSystem for MemoryFs {
+	fn stat(&self, path: &str) -> io::Result<HdfsDirectoryEntry> {
+		let path = normalize(path);
+		let nodes = self.lock();
+		let node = nodes.get(&path).ok_or_else(|| not_found(&path))?;
+		Ok(entry(&path, node))
+	}
+	
+	fn exists(&self, path: &str) -> io::Result<bool> {
+		Ok(self.lock().contains_key(&normalize(path)))
+	}
+	
+	fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		let path = normalize(path);
+		let nodes = self.lock();
+		let node = nodes.get(&path).ok_or_else(|| not_found(&path))?;
+		if node.data.is_some() {
+			return Ok(vec![entry(&path, node)]);
+		}
+		Ok(children(&nodes, &path)
+			.map(|(child, node)| entry(child, node))
+			.collect())
+	}
+	
+	fn create_dir(&self, path: &str) -> io::Result<()> {
+		let path = normalize(path);
+		let mut nodes = self.lock();
+		match nodes.get(&path) {
+			Some(node) if node.data.is_some() => { return Err(not_a_directory(&path)); },
+			Some(_) => { return Ok(()); },
+			None => {},
+		}
+		self.create_parents(&mut nodes, &path)?;
+		nodes.insert(path, self.new_node(None));
+		Ok(())
+	}
+	
+	fn delete(&self, path: &str, recursive: bool) -> io::Result<()> {
+		let path = normalize(path);
+		let mut nodes = self.lock();
+		if !nodes.contains_key(&path) {
+			return Err(not_found(&path));
+		}
+		let descendants: Vec<String> = descendants(&nodes, &path).map(|(child, _)| child.clone()).collect();
+		if !descendants.is_empty() && !recursive {
+			return Err(io::Error::new(io::ErrorKind::Other, format!("{} is non empty", path)));
+		}
+		if path == "/" {
+			return Err(io::Error::new(io::ErrorKind::PermissionDenied, "cannot delete the root directory"));
+		}
+		for child in descendants {
+			nodes.remove(&child);
+		}
+		nodes.remove(&path);
+		Ok(())
+	}
+	
+	fn rename(&self, src: &str, dest: &str) -> io::Result<()> {
+		let src = normalize(src);
+		let mut dest = normalize(dest);
+		let mut nodes = self.lock();
+		if !nodes.contains_key(&src) || src == "/" {
+			return Err(not_found(&src));
+		}
+		if let Some(node) = nodes.get(&dest) {
+			if node.data.is_some() {
+				if dest == src {
+					return Ok(());
+				}
+				return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", dest)));
+			}
+			dest = join(&dest, base_name(&src));
+			if nodes.contains_key(&dest) {
+				if dest == src {
+					return Ok(());
+				}
+				return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", dest)));
+			}
+		}
+		if dest.starts_with(&format!("{}/", src.trim_end_matches('/'))) || src == dest {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot move {} into itself", src)));
+		}
+		match parent(&dest).and_then(|dir| nodes.get(dir)) {
+			Some(node) if node.data.is_none() => {},
+			_ => { return Err(not_found(parent(&dest).unwrap_or("/"))); },
+		}
+		
+		let mut moved: Vec<(String, Node)> = descendants(&nodes, &src)
+			.map(|(child, _)| (format!("{}{}", dest, &child[src.len()..]), nodes[child].clone()))
+			.collect();
+		moved.push((dest, nodes[&src].clone()));
+		for child in descendants(&nodes, &src).map(|(child, _)| child.clone()).collect::<Vec<_>>() {
+			nodes.remove(&child);
+		}
+		nodes.remove(&src);
+		nodes.extend(moved);
+		Ok(())
+	}
+	
+	fn chmod(&self, path: &str, mode: u16) -> io::Result<()> {
+		let path = normalize(path);
+		let mut nodes = self.lock();
+		let node = nodes.get_mut(&path).ok_or_else(|| not_found(&path))?;
+		node.permissions = mode & 0o7777;
+		Ok(())
+	}
+	
+	fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> io::Result<()> {
+		let path = normalize(path);
+		let mut nodes = self.lock();
+		let node = nodes.get_mut(&path).ok_or_else(|| not_found(&path))?;
+		if let Some(owner) = owner {
+			node.owner = owner.to_string();
+		}
+		if let Some(group) = group {
+			node.group = group.to_string();
+		}
+		Ok(())
+	}
+	
+	fn set_times(&self, path: &str, modified: Option<SystemTime>, accessed: Option<SystemTime>) -> io::Result<()> {
+		let path = normalize(path);
+		let mut nodes = self.lock();
+		let node = nodes.get_mut(&path).ok_or_else(|| not_found(&path))?;
+		if let Some(modified) = modified {
+			node.modified = modified;
+		}
+		if let Some(accessed) = accessed {
+			node.accessed = accessed;
+		}
+		Ok(())
+	}
+	
+	fn truncate(&self, path: &str, size: u64) -> io::Result<bool> {
+		let path = normalize(path);
+		let mut nodes = self.lock();
+		let node = nodes.get_mut(&path).ok_or_else(|| not_found(&path))?;
+		let data = node.data.as_mut().ok_or_else(|| is_a_directory(&path))?;
+		if size > data.len() as u64 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot truncate {} to a larger size", path)));
+		}
+		data.truncate(size as usize);
+		node.modified = SystemTime::now();
+		Ok(true)
+	}
+	
+	fn open_read(&self, path: &str) -> io::Result<Box<dyn FileReader + '_>> {
+		let path = normalize(path);
+		let mut nodes = self.lock();
+		let node = nodes.get_mut(&path).ok_or_else(|| not_found(&path))?;
+		let data = node.data.clone().ok_or_else(|| not_found(&path))?;
+		node.accessed = SystemTime::now();
+		Ok(Box::new(io::Cursor::new(data)))
+	}
+	
+	fn open_create(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		let path = normalize(path);
+		let mut nodes = self.lock();
+		if !nodes.contains_key(&path) {
+			self.create_parents(&mut nodes, &path)?;
+			nodes.insert(path.clone(), self.new_node(Some(vec![])));
+		}
+		self.writer(&mut nodes, path, true)
+	}
+	
+	fn open_append(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		let path = normalize(path);
+		let mut nodes = self.lock();
+		self.writer(&mut nodes, path, false)
+	}
+}
+
+/// File opened for writing by `MemoryFs`. Writes go straight to the file, so they're visible to readers
+/// opened afterwards.
+struct MemoryWriter<'a> {
+	fs: &'a MemoryFs,
+	path: String,
+}
+impl<'a> io::Write for MemoryWriter<'a> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let mut nodes = self.fs.lock();
+		let node = nodes.get_mut(&self.path).ok_or_else(|| not_found(&self.path))?;
+		let data = node.data.as_mut().ok_or_else(|| is_a_directory(&self.path))?;
+		data.extend_from_slice(buf);
+		node.modified = SystemTime::now();
+		Ok(buf.len())
+	}
+	
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+impl<'a> FileWriter for MemoryWriter<'a> {
+	fn sync(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+fn entry(path: &str, node: &Node) -> HdfsDirectoryEntry {
+	let size = node.data.as_ref().map(|data| data.len() as u64);
+	HdfsDirectoryEntry {
+		kind: if size.is_some() { HdfsDirectoryEntryKind::File } else { HdfsDirectoryEntryKind::Directory },
+		name: path.to_string(),
+		last_modified: node.modified,
+		size: size.unwrap_or(0),
+		replication: if size.is_some() { 1 } else { 0 },
+		block_size: if size.is_some() { BLOCK_SIZE } else { 0 },
+		owner: node.owner.clone(),
+		group: node.group.clone(),
+		permissions: node.permissions,
+		last_access: node.accessed,
+	}
+}
+
+/// Everything under a directory, at any depth.
+fn descendants<'a>(nodes: &'a BTreeMap<String, Node>, dir: &str) -> impl Iterator<Item = (&'a String, &'a Node)> {
+	let prefix = if dir == "/" { dir.to_string() } else { format!("{}/", dir) };
+	nodes.range(prefix.clone()..)
+		.take_while(move |(path, _)| path.starts_with(&prefix))
+		.filter(|(path, _)| path.as_str() != "/")
+}
+
+/// The entries directly in a directory.
+fn children<'a>(nodes: &'a BTreeMap<String, Node>, dir: &'a str) -> impl Iterator<Item = (&'a String, &'a Node)> {
+	descendants(nodes, dir).filter(move |(path, _)| parent(path) == Some(dir))
+}
+
+/// Turns a path or URI into an absolute path without `.`, `..`, or repeated or trailing slashes.
+fn normalize(path: &str) -> String {
+	let mut parts: Vec<&str> = vec![];
+	for part in uri_path(path).split('/') {
+		match part {
+			"" | "." => {},
+			".." => { parts.pop(); },
+			part => { parts.push(part); },
+		}
+	}
+	format!("/{}", parts.join("/"))
+}
+
+fn not_found(path: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::NotFound, format!("{} does not exist", path))
+}
+
+fn not_a_directory(path: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is a file, not a directory", path))
+}
+
+fn is_a_directory(path: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is a directory", path))
+}
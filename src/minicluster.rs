@@ -0,0 +1,225 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Single-node HDFS cluster for integration tests, with the `integration` feature.
+
+use crate::{init_classpath, HdfsBuilder, HdfsConnection};
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the cluster to come up. The first start is slow, since it formats the namenode.
+const START_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Runs the cluster, and stops it when stdin is closed: when `MiniCluster` is dropped, or the test process exits
+/// without dropping it, like when it's in a `static`. `mapred` execs the JVM, so killing `$child` stops it.
+const SUPERVISOR: &str = r#"exec 3<&0; "$@" & child=$!; (cat <&3 >/dev/null; kill $child 2>/dev/null) & wait $child"#;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A running single-node HDFS cluster, stopped when dropped.
+/// 
+/// This runs Hadoop's `mapred minicluster`, which starts a `MiniDFSCluster` in its own JVM, so it needs a
+/// Hadoop installation, found from, in order:
+/// 
+/// * `RSHDFS_TEST_HADOOP_HOME`
+/// * `HADOOP_HOME`
+/// * `RSHDFS_TEST_HADOOP_VERSION`, a Hadoop release to download from the Apache archive with `curl`. It's
+///   extracted to `hdfs-rs-hadoop` in the temporary directory and reused from there.
+/// 
+/// `RSHDFS_TEST_HADOOP_CLASSPATH` is added to the cluster's classpath, for test jars that the installation is
+/// missing.
+/// 
+/// ```ignore
+/// let cluster = hdfs::MiniCluster::start()?;
+/// let fs = cluster.connect()?;
+/// fs.create_dir("/test")?;
+/// ```
+pub struct MiniCluster {
+	child: Child,
+	port: u16,
+	http_port: u16,
+	dir: PathBuf,
+}
+
+impl MiniCluster {
+	/// Starts a cluster on free ports, waiting until the namenode is up.
+	/// 
+	/// Also sets `CLASSPATH` for `libhdfs` from the cluster's Hadoop installation, if it isn't set.
+	pub fn start() -> io::Result<Self> {
+		let home = hadoop_home()?;
+		if env::var_os("HADOOP_HOME").is_none() {
+			env::set_var("HADOOP_HOME", &home);
+		}
+		init_classpath()?;
+		
+		let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+		let dir = env::temp_dir().join(format!("hdfs-rs-minicluster-{}-{}", std::process::id(), id));
+		fs::create_dir_all(&dir)?;
+		let port = free_port()?;
+		let http_port = free_port()?;
+		let details = dir.join("details.json");
+		let log = File::create(dir.join("minicluster.log"))?;
+		
+		let mut command = Command::new("sh");
+		command.arg("-c").arg(SUPERVISOR).arg("sh")
+			.arg(home.join("bin").join("mapred"))
+			.args(["minicluster", "-nomr", "-format"])
+			.arg("-nnport").arg(port.to_string())
+			.arg("-nnhttpport").arg(http_port.to_string())
+			.arg("-D").arg(format!("hdfs.minidfs.basedir={}", dir.join("data").display()))
+			.arg("-writeDetails").arg(&details)
+			.current_dir(&dir)
+			.stdin(Stdio::piped())
+			.stdout(log.try_clone()?)
+			.stderr(log);
+		if let Some(classpath) = env::var_os("RSHDFS_TEST_HADOOP_CLASSPATH") {
+			command.env("HADOOP_CLASSPATH", classpath);
+		}
+		let child = command.spawn()?;
+		let mut cluster = Self { child, port, http_port, dir };
+		
+		// `-writeDetails` is written once everything has started
+		let started = Instant::now();
+		while !details.exists() {
+			if let Some(status) = cluster.child.try_wait()? {
+				return Err(cluster.failure(&format!("minicluster exited with {}", status)));
+			}
+			if started.elapsed() > START_TIMEOUT {
+				return Err(cluster.failure("timed out waiting for minicluster to start"));
+			}
+			thread::sleep(Duration::from_millis(250));
+		}
+		Ok(cluster)
+	}
+	
+	/// Gets the namenode's RPC port.
+	pub fn port(&self) -> u16 {
+		self.port
+	}
+	
+	/// Gets the namenode's web UI and WebHDFS port.
+	pub fn http_port(&self) -> u16 {
+		self.http_port
+	}
+	
+	/// Gets the URI of the namenode, ex. `hdfs://localhost:40123`.
+	pub fn name_node(&self) -> String {
+		format!("hdfs://localhost:{}", self.port)
+	}
+	
+	/// Gets the directory holding the cluster's data and `minicluster.log`, which is deleted when it's stopped.
+	pub fn dir(&self) -> &Path {
+		&self.dir
+	}
+	
+	/// Gets a builder for connecting to the cluster.
+	/// 
+	/// It forces a new filesystem instance for each connection; see `HdfsBuilder::force_new_instance`.
+	pub fn builder(&self) -> HdfsBuilder {
+		let mut builder = HdfsBuilder::new();
+		builder.name_node(Some(&self.name_node()));
+		builder.force_new_instance();
+		builder
+	}
+	
+	/// Connects to the cluster.
+	pub fn connect(&self) -> io::Result<HdfsConnection> {
+		self.builder().connect()
+	}
+	
+	/// Makes an error including the end of the log, for when the cluster didn't start.
+	fn failure(&self, message: &str) -> io::Error {
+		io::Error::new(io::ErrorKind::Other, format!("{}:\n{}", message, log_tail(&self.dir.join("minicluster.log"))))
+	}
+}
+
+impl Drop for MiniCluster {
+	fn drop(&mut self) {
+		// Closing stdin makes the supervisor script stop the JVM
+		drop(self.child.stdin.take());
+		let _ = self.child.wait();
+		let _ = fs::remove_dir_all(&self.dir);
+	}
+}
+
+/// Gets the last lines of a log file, or nothing if it can't be read.
+pub(crate) fn log_tail(path: &Path) -> String {
+	let log = fs::read_to_string(path).unwrap_or_default();
+	let lines: Vec<&str> = log.lines().collect();
+	lines[lines.len().saturating_sub(20)..].join("\n")
+}
+
+/// Gets a port that's free now, by binding to port 0.
+pub(crate) fn free_port() -> io::Result<u16> {
+	Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+/// Finds or downloads the Hadoop installation to run the cluster from.
+fn hadoop_home() -> io::Result<PathBuf> {
+	for var in ["RSHDFS_TEST_HADOOP_HOME", "HADOOP_HOME"].iter() {
+		if let Some(home) = env::var_os(var).filter(|home| !home.is_empty()) {
+			return Ok(PathBuf::from(home));
+		}
+	}
+	match env::var("RSHDFS_TEST_HADOOP_VERSION") {
+		Ok(version) => download(&version),
+		Err(_) => Err(io::Error::new(io::ErrorKind::NotFound,
+			"no Hadoop installation to run the minicluster from; set RSHDFS_TEST_HADOOP_HOME, HADOOP_HOME, or \
+			RSHDFS_TEST_HADOOP_VERSION")),
+	}
+}
+
+/// Downloads and extracts a Hadoop release, unless it already has been. It's extracted to a directory of its own
+/// first, so another process never sees a partial installation.
+fn download(version: &str) -> io::Result<PathBuf> {
+	let cache = env::temp_dir().join("hdfs-rs-hadoop");
+	let home = cache.join(format!("hadoop-{}", version));
+	if home.join("bin").join("mapred").exists() {
+		return Ok(home);
+	}
+	
+	let staging = cache.join(format!("staging-{}", std::process::id()));
+	fs::create_dir_all(&staging)?;
+	let tarball = staging.join("hadoop.tar.gz");
+	let url = format!("https://archive.apache.org/dist/hadoop/common/hadoop-{0}/hadoop-{0}.tar.gz", version);
+	run(Command::new("curl").args(["-fsSL", "-o"]).arg(&tarball).arg(&url))?;
+	run(Command::new("tar").arg("-xzf").arg(&tarball).arg("-C").arg(&staging))?;
+	// Someone else may have finished first
+	if fs::rename(staging.join(format!("hadoop-{}", version)), &home).is_err() && !home.exists() {
+		return Err(io::Error::new(io::ErrorKind::Other, format!("could not move Hadoop to {}", home.display())));
+	}
+	let _ = fs::remove_dir_all(&staging);
+	Ok(home)
+}
+
+pub(crate) fn run(command: &mut Command) -> io::Result<()> {
+	let status = command.status()?;
+	if !status.success() {
+		return Err(io::Error::new(io::ErrorKind::Other, format!("{:?} failed with {}", command, status)));
+	}
+	Ok(())
+}
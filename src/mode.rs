@@ -0,0 +1,118 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Creating files and directories with specific permissions.
+//! 
+//! `libhdfs` always creates files with mode `0666` and directories with `0777`, minus the connection's umask,
+//! which is read from `fs.permissions.umask-mode` when connecting. `HdfsBuilder::umask` sets it for everything a
+//! connection creates. For a mode per call, the file or directory is created under a hidden temporary name next
+//! to where it's going, changed to the right mode, and then renamed into place, so that it never appears with
+//! the wrong permissions.
+
+use crate::path::{base_name, join, parent, AsHdfsPath};
+use crate::{HdfsBuilder, HdfsConnection, HdfsDirectoryEntryKind, HdfsFile};
+use std::io;
+
+impl HdfsBuilder {
+	/// Sets the umask applied to the permissions of every file and directory that the connection creates, ex.
+	/// `0o027`. The namenode applies it as part of creating them, so there's no window where they're more
+	/// permissive.
+	/// 
+	/// Hadoop caches connections by namenode and user, and reads the umask when one is created, so this also
+	/// calls `force_new_instance`.
+	pub fn umask(&mut self, umask: u16) -> io::Result<()> {
+		if umask > 0o777 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid umask {:o}", umask)));
+		}
+		self.conf_set("fs.permissions.umask-mode", &format!("{:03o}", umask))?;
+		self.force_new_instance();
+		Ok(())
+	}
+}
+
+impl HdfsConnection {
+	/// Creates a directory with the permission bits `mode`, along with any missing parent directories, which get
+	/// the connection's default permissions.
+	/// 
+	/// Succeeds without changing anything if the directory already exists. If someone else creates it while this
+	/// is creating it, fails with `AlreadyExists`, leaving theirs as it is.
+	pub fn create_dir_with_mode<P: AsHdfsPath + ?Sized>(&self, path: &P, mode: u16) -> io::Result<()> {
+		let path = &*path.as_str_path()?;
+		match self.stat(path) {
+			Ok(entry) if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) => { return Ok(()); },
+			Ok(_) => { return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is a file", path))); },
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+			Err(e) => { return Err(e); },
+		}
+		let dir = parent(path).unwrap_or(".");
+		self.create_dir(dir)?;
+		
+		// Creating a file reserves a unique name, which is then reused for the directory
+		let temp = self.create_temp_in(dir, &temp_prefix(path))?;
+		let temp_path = temp.path().to_string();
+		drop(temp);
+		self.create_dir(&temp_path)?;
+		let res = self.chmod(&temp_path, mode)
+			.and_then(|()| self.rename(&temp_path, path));
+		if let Err(e) = res {
+			let _ = self.delete(&temp_path, false);
+			// Someone else created it in the meantime
+			if self.is_dir(path).unwrap_or(false) {
+				return Ok(());
+			}
+			return Err(e);
+		}
+		// Renaming onto a directory that was created in the meantime moves the temporary directory into it
+		let moved = join(path, base_name(&temp_path));
+		if self.exists(&moved)? {
+			let _ = self.delete(&moved, false);
+			return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} was created concurrently", path)));
+		}
+		Ok(())
+	}
+	
+	/// Opens a file for writing with the permission bits `mode`, creating it if it doesn't exist, and creating any
+	/// missing parent directories.
+	/// 
+	/// Like `open_create`, an existing file is replaced. The new file is moved into place once its mode is set,
+	/// and HDFS can't rename over a file, so the old one is deleted just before; readers may briefly see neither.
+	pub fn open_create_with_mode<P: AsHdfsPath + ?Sized>(&self, path: &P, mode: u16) -> io::Result<HdfsFile> {
+		let path = &*path.as_str_path()?;
+		let dir = parent(path).unwrap_or(".");
+		self.create_dir(dir)?;
+		let temp = self.create_temp_in(dir, &temp_prefix(path))?;
+		self.chmod(temp.path(), mode)?;
+		
+		match self.stat(path) {
+			Ok(entry) if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) => {
+				return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is a directory", path)));
+			},
+			Ok(_) => { self.delete(path, false)?; },
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+			Err(e) => { return Err(e); },
+		}
+		temp.persist_open(path)
+	}
+}
+
+/// Prefix for the temporary name of `path`, which hides it from most listings.
+fn temp_prefix(path: &str) -> String {
+	format!(".{}.", base_name(path))
+}
@@ -0,0 +1,142 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Moves between paths that may be on different filesystems.
+//! 
+//! Within one filesystem, a move is a rename on the namenode. Otherwise, or when the namenode refuses the rename,
+//! ex. between encryption zones, the data has to be copied and the source deleted. `hdfsMove` does that without
+//! checking the copy, so this streams it instead, and only deletes the source once every file has been verified.
+
+use crate::copy::Verify;
+use crate::path::{split_uri, AsHdfsPath};
+use crate::{HdfsConnection, HdfsDirectoryEntryKind};
+use std::io;
+use std::ptr;
+
+/// Options for `HdfsConnection::move_to_with`. By default, an existing destination is an error, and copies are
+/// verified by size.
+#[derive(Debug,Clone,Copy)]
+pub struct MoveOptions {
+	overwrite: bool,
+	verify: Verify,
+	fallback: bool,
+}
+
+impl Default for MoveOptions {
+	fn default() -> Self {
+		Self {
+			overwrite: false,
+			verify: Verify::Size,
+			fallback: true,
+		}
+	}
+}
+
+/// How `HdfsConnection::move_to_with` moved something.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum MoveStrategy {
+	/// Renamed on the namenode, without touching the data
+	Rename,
+	/// Copied through this process, verified, then deleted from the source
+	CopyDelete {
+		/// Total size of the copied files
+		bytes: u64,
+	},
+}
+
+impl MoveOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Replaces an existing file at the destination. Directories are never replaced.
+	/// 
+	/// `libhdfs` can't rename over a file, so it's deleted first, and a failed move leaves neither.
+	pub fn overwrite(mut self) -> Self {
+		self.overwrite = true;
+		self
+	}
+	
+	/// Also checks that each copy reads back the same as what was written, which reads every file twice. Only
+	/// applies when falling back to copying.
+	pub fn verify_contents(mut self) -> Self {
+		self.verify = Verify::Contents;
+		self
+	}
+	
+	/// Fails instead of copying when the move can't be done with a rename.
+	pub fn rename_only(mut self) -> Self {
+		self.fallback = false;
+		self
+	}
+}
+
+impl HdfsConnection {
+	/// Moves a file or directory tree from this filesystem to `dest` on `dest_fs`, which may be the same
+	/// connection, returning how it was done.
+	/// 
+	/// Renames if both are the same filesystem. Otherwise, or if the rename fails for a reason other than the
+	/// paths, files are copied one at a time, checked, and the source is deleted once all of them are. If the copy
+	/// fails, whatever was copied is left at the destination, and the source is untouched.
+	pub fn move_to_with<S: AsHdfsPath + ?Sized, D: AsHdfsPath + ?Sized>(&self, src: &S, dest_fs: &HdfsConnection, dest: &D, options: MoveOptions) -> io::Result<MoveStrategy> {
+		let src = &*src.as_str_path()?;
+		let dest = &*dest.as_str_path()?;
+		let root = self.stat(src)?;
+		match dest_fs.stat(dest) {
+			Ok(ref existing) if options.overwrite && !matches!(existing.kind, HdfsDirectoryEntryKind::Directory) => {
+				dest_fs.delete(dest, false)?;
+			},
+			Ok(_) => { return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", dest))); },
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+			Err(e) => { return Err(e); },
+		}
+		
+		if self.same_filesystem(dest_fs)? {
+			match self.rename(&root.name, dest) {
+				Ok(()) => { return Ok(MoveStrategy::Rename); },
+				Err(e) if !options.fallback || is_path_error(&e) => { return Err(e); },
+				Err(_) => {},
+			}
+		} else if !options.fallback {
+			return Err(io::Error::new(io::ErrorKind::Unsupported, format!("cannot rename {} to another filesystem", src)));
+		}
+		
+		let bytes = self.copy_tree(&root, dest_fs, dest, options.verify)?;
+		self.delete(&root.name, true)?;
+		Ok(MoveStrategy::CopyDelete { bytes })
+	}
+	
+	/// Checks whether two connections are to the same filesystem, by the scheme and authority of their working
+	/// directories.
+	fn same_filesystem(&self, other: &HdfsConnection) -> io::Result<bool> {
+		if ptr::eq(self, other) {
+			return Ok(true);
+		}
+		let ours = self.working_directory()?;
+		let theirs = other.working_directory()?;
+		Ok(split_uri(&ours).0 == split_uri(&theirs).0)
+	}
+}
+
+/// Checks whether a rename failed because of the paths, which copying wouldn't fix.
+fn is_path_error(e: &io::Error) -> bool {
+	matches!(e.kind(),
+		io::ErrorKind::NotFound | io::ErrorKind::AlreadyExists | io::ErrorKind::PermissionDenied | io::ErrorKind::InvalidInput)
+}
@@ -0,0 +1,366 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Paths converted to C strings ahead of time, and manipulating them.
+//! 
+//! Every `HdfsConnection` method that takes a path has to pass it to `libhdfs` as a null-terminated string,
+//! which for a `&str` means allocating and copying it on every call. Code that calls `stat` in a loop, or
+//! otherwise reuses the same paths, can convert them once into an `HdfsPath` instead.
+//! 
+//! `HdfsPath` also has `join`, `parent`, `file_name` and `normalize`, which understand URIs like
+//! `hdfs://host:8020/a/b`, keeping the scheme and authority as they are.
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::{self, FromStr};
+use std::sync::Arc;
+
+/// Path that can be passed to `HdfsConnection` methods: string types like `str`, `String` and `Cow<str>`,
+/// `HdfsPath`, `CStr`, or a local `Path`, which is used as-is.
+/// 
+/// Strings and `Path`s are converted to a C string on every call, while `HdfsPath` and `CStr` are passed as-is.
+pub trait AsHdfsPath {
+	/// Gets the path as a C string, borrowing it if it already is one.
+	/// 
+	/// Fails with `InvalidInput` if the path contains a null byte, or isn't UTF-8.
+	fn as_c_path(&self) -> io::Result<Cow<'_, CStr>>;
+	
+	/// Gets the path as a `str`, for methods that work with its components.
+	/// 
+	/// Fails with `InvalidInput` if the path isn't UTF-8.
+	fn as_str_path(&self) -> io::Result<Cow<'_, str>>;
+}
+
+/// UTF-8 path stored as a C string, so it can be passed to `libhdfs` without copying it.
+/// 
+/// Paths can't contain control characters. HDFS accepts most of them in names, but they're almost always a
+/// mistake, and make listings hard to read or use.
+#[derive(Debug,Clone,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub struct HdfsPath {
+	c: CString,
+}
+
+impl HdfsPath {
+	/// Converts a path. Fails if it contains a control character, including a null byte.
+	pub fn new<S: Into<String>>(path: S) -> io::Result<Self> {
+		let path = path.into();
+		if let Some(c) = path.chars().find(|c| c.is_control()) {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput,
+				format!("path {:?} contains the control character {:?}", path, c)));
+		}
+		Ok(Self::from_valid(path))
+	}
+	
+	/// Converts a path that's already been checked, or that's part of one that has.
+	fn from_valid<S: Into<String>>(path: S) -> Self {
+		// Reuses the `String`'s allocation when it has room for the terminator
+		Self { c: CString::new(path.into()).unwrap() }
+	}
+	
+	/// Gets the path as a `str`.
+	pub fn as_str(&self) -> &str {
+		// Only ever created from a `String`, so it's valid UTF-8
+		unsafe { str::from_utf8_unchecked(self.c.as_bytes()) }
+	}
+	
+	/// Gets the path as a C string.
+	pub fn as_c_str(&self) -> &CStr {
+		&self.c
+	}
+	
+	/// Converts the path back into a `String`.
+	pub fn into_string(self) -> String {
+		unsafe { String::from_utf8_unchecked(self.c.into_bytes()) }
+	}
+	
+	/// Checks if the path starts at the root, or is a URI.
+	pub fn is_absolute(&self) -> bool {
+		is_absolute(self.as_str())
+	}
+	
+	/// Appends a path, adding a slash between them if needed. If `path` is absolute, it replaces this one, like
+	/// `std::path::Path::join`.
+	pub fn join(&self, path: &str) -> io::Result<Self> {
+		if is_absolute(path) {
+			return Self::new(path);
+		}
+		Self::new(join(self.as_str(), path))
+	}
+	
+	/// Gets the directory containing this path, ignoring trailing slashes. Returns `None` for the root, and for
+	/// relative paths with one component.
+	/// 
+	/// This doesn't resolve `..`, so normalize the path first if it might have any.
+	pub fn parent(&self) -> Option<Self> {
+		parent(self.as_str()).map(Self::from_valid)
+	}
+	
+	/// Gets the last component of the path, ignoring trailing slashes. Returns `None` if there isn't one, or if
+	/// it's `.` or `..`.
+	pub fn file_name(&self) -> Option<&str> {
+		match base_name(self.as_str()) {
+			"" | "." | ".." => None,
+			name => Some(name),
+		}
+	}
+	
+	/// Removes `.` components, repeated and trailing slashes, and `..` components along with the component
+	/// before them. This doesn't look at the filesystem, so `a/link/..` becomes `a` even if `link` is a symlink.
+	/// 
+	/// `..` at the root is dropped, like the namenode does. Relative paths keep leading `..` components, and
+	/// become `.` if nothing is left.
+	pub fn normalize(&self) -> Self {
+		Self::from_valid(normalize(self.as_str()))
+	}
+}
+
+impl fmt::Display for HdfsPath {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+impl FromStr for HdfsPath {
+	type Err = io::Error;
+	fn from_str(path: &str) -> io::Result<Self> {
+		Self::new(path)
+	}
+}
+impl<'a> TryFrom<&'a str> for HdfsPath {
+	type Error = io::Error;
+	fn try_from(path: &'a str) -> io::Result<Self> {
+		Self::new(path)
+	}
+}
+impl TryFrom<String> for HdfsPath {
+	type Error = io::Error;
+	fn try_from(path: String) -> io::Result<Self> {
+		Self::new(path)
+	}
+}
+
+impl AsHdfsPath for HdfsPath {
+	fn as_c_path(&self) -> io::Result<Cow<'_, CStr>> {
+		Ok(Cow::Borrowed(&self.c))
+	}
+	fn as_str_path(&self) -> io::Result<Cow<'_, str>> {
+		Ok(Cow::Borrowed(self.as_str()))
+	}
+}
+impl AsHdfsPath for CStr {
+	fn as_c_path(&self) -> io::Result<Cow<'_, CStr>> {
+		Ok(Cow::Borrowed(self))
+	}
+	fn as_str_path(&self) -> io::Result<Cow<'_, str>> {
+		self.to_str()
+			.map(Cow::Borrowed)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("path {:?} is not UTF-8", self)))
+	}
+}
+impl AsHdfsPath for str {
+	fn as_c_path(&self) -> io::Result<Cow<'_, CStr>> {
+		CString::new(self)
+			.map(Cow::Owned)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("path {:?} contains a null byte", self)))
+	}
+	fn as_str_path(&self) -> io::Result<Cow<'_, str>> {
+		Ok(Cow::Borrowed(self))
+	}
+}
+impl AsHdfsPath for Path {
+	fn as_c_path(&self) -> io::Result<Cow<'_, CStr>> {
+		path_to_str(self)?.as_c_path()
+	}
+	fn as_str_path(&self) -> io::Result<Cow<'_, str>> {
+		path_to_str(self).map(Cow::Borrowed)
+	}
+}
+
+fn path_to_str(path: &Path) -> io::Result<&str> {
+	path.to_str()
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("path {:?} is not UTF-8", path)))
+}
+
+/// Implements `AsHdfsPath` for an owned type by calling through to what it dereferences to.
+macro_rules! forward_as_hdfs_path {
+	($($owner:ty => $target:ty),*) => {$(
+		impl AsHdfsPath for $owner {
+			fn as_c_path(&self) -> io::Result<Cow<'_, CStr>> {
+				<$target as AsHdfsPath>::as_c_path(self)
+			}
+			fn as_str_path(&self) -> io::Result<Cow<'_, str>> {
+				<$target as AsHdfsPath>::as_str_path(self)
+			}
+		}
+	)*};
+}
+forward_as_hdfs_path!(
+	String => str, Box<str> => str, Rc<str> => str, Arc<str> => str, Cow<'_, str> => str,
+	CString => CStr, PathBuf => Path
+);
+
+impl<T: AsHdfsPath + ?Sized> AsHdfsPath for &T {
+	fn as_c_path(&self) -> io::Result<Cow<'_, CStr>> {
+		(**self).as_c_path()
+	}
+	fn as_str_path(&self) -> io::Result<Cow<'_, str>> {
+		(**self).as_str_path()
+	}
+}
+
+/// Splits a URI into its scheme and authority, ex. `hdfs://host:8020`, and its path. Plain paths have an empty
+/// prefix.
+pub(crate) fn split_uri(name: &str) -> (&str, &str) {
+	let i = match name.find(':') {
+		Some(i) if !name[..i].contains('/') => i,
+		_ => { return ("", name); },
+	};
+	match name[i+1..].strip_prefix("//") {
+		Some(authority_and_path) => {
+			let end = authority_and_path.find('/').map(|j| i + 3 + j).unwrap_or(name.len());
+			name.split_at(end)
+		},
+		None => name.split_at(i + 1),
+	}
+}
+
+/// Strips the scheme and authority from a URI, ex. `hdfs://host:8020/a/b` to `/a/b`.
+pub(crate) fn uri_path(name: &str) -> &str {
+	match split_uri(name) {
+		(prefix, "") if prefix.contains("//") => "/",
+		(_, path) => path,
+	}
+}
+
+fn is_absolute(path: &str) -> bool {
+	let (prefix, path) = split_uri(path);
+	!prefix.is_empty() || path.starts_with('/')
+}
+
+/// Gets the last component of a path or URI, ignoring trailing slashes.
+pub(crate) fn base_name(name: &str) -> &str {
+	split_uri(name).1.trim_end_matches('/').rsplit('/').next().unwrap_or("")
+}
+
+/// Gets the parent directory of a path or URI, or `None` for the root or a relative path with one component.
+pub(crate) fn parent(path: &str) -> Option<&str> {
+	let (prefix, rest) = split_uri(path);
+	let rest = rest.trim_end_matches('/');
+	let i = rest.rfind('/')?;
+	let dir = rest[..i].trim_end_matches('/');
+	Some(&path[..prefix.len() + dir.len().max(1)])
+}
+
+/// Joins a name onto a directory path. An empty directory is treated as the current one.
+pub(crate) fn join(dir: &str, name: &str) -> String {
+	if dir.is_empty() {
+		name.to_string()
+	} else {
+		format!("{}/{}", dir.trim_end_matches('/'), name)
+	}
+}
+
+fn normalize(path: &str) -> String {
+	let (prefix, rest) = split_uri(path);
+	let absolute = !prefix.is_empty() || rest.starts_with('/');
+	let mut parts: Vec<&str> = vec![];
+	for part in rest.split('/') {
+		match part {
+			"" | "." => {},
+			".." if matches!(parts.last(), Some(last) if *last != "..") => { parts.pop(); },
+			".." if absolute => {},
+			part => { parts.push(part); },
+		}
+	}
+	if absolute {
+		format!("{}/{}", prefix, parts.join("/"))
+	} else if parts.is_empty() {
+		".".to_string()
+	} else {
+		parts.join("/")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn path(s: &str) -> HdfsPath {
+		HdfsPath::new(s).unwrap()
+	}
+	
+	#[test]
+	fn normalize_absolute() {
+		assert_eq!(path("/a/./b//c/").normalize().as_str(), "/a/b/c");
+		assert_eq!(path("/a/b/../c").normalize().as_str(), "/a/c");
+		assert_eq!(path("/../a").normalize().as_str(), "/a");
+		assert_eq!(path("/a/..").normalize().as_str(), "/");
+		assert_eq!(path("/").normalize().as_str(), "/");
+		assert_eq!(path("hdfs://nn:8020/a/../b/").normalize().as_str(), "hdfs://nn:8020/b");
+		assert_eq!(path("hdfs://nn:8020").normalize().as_str(), "hdfs://nn:8020/");
+	}
+	
+	#[test]
+	fn normalize_relative() {
+		assert_eq!(path("a/./b/").normalize().as_str(), "a/b");
+		assert_eq!(path("a/..").normalize().as_str(), ".");
+		assert_eq!(path("./").normalize().as_str(), ".");
+		assert_eq!(path("a/../..").normalize().as_str(), "..");
+		assert_eq!(path("../a/../b").normalize().as_str(), "../b");
+	}
+	
+	#[test]
+	fn parent_and_file_name() {
+		let parent_of = |s: &str| path(s).parent().map(HdfsPath::into_string);
+		assert_eq!(parent_of("/a/b"), Some("/a".to_string()));
+		assert_eq!(parent_of("/a/b/"), Some("/a".to_string()));
+		assert_eq!(parent_of("/a//b"), Some("/a".to_string()));
+		assert_eq!(parent_of("/a"), Some("/".to_string()));
+		assert_eq!(parent_of("/"), None);
+		assert_eq!(parent_of("a/b"), Some("a".to_string()));
+		assert_eq!(parent_of("a"), None);
+		assert_eq!(parent_of("hdfs://nn/a/b"), Some("hdfs://nn/a".to_string()));
+		assert_eq!(parent_of("hdfs://nn/a"), Some("hdfs://nn/".to_string()));
+		// `..` isn't resolved
+		assert_eq!(parent_of("a/.."), Some("a".to_string()));
+		
+		assert_eq!(path("/a/b/").file_name(), Some("b"));
+		assert_eq!(path("hdfs://nn/a").file_name(), Some("a"));
+		assert_eq!(path("/").file_name(), None);
+		assert_eq!(path("a/..").file_name(), None);
+	}
+	
+	#[test]
+	fn join_and_validation() {
+		assert_eq!(path("/a").join("b").unwrap().as_str(), "/a/b");
+		assert_eq!(path("/a/").join("b").unwrap().as_str(), "/a/b");
+		assert_eq!(path("/a").join("/c").unwrap().as_str(), "/c");
+		assert_eq!(path("/a").join("hdfs://nn/c").unwrap().as_str(), "hdfs://nn/c");
+		assert!(HdfsPath::new("/a\nb").is_err());
+		assert!(path("/a").join("b\0").is_err());
+		assert_eq!(uri_path("hdfs://nn:8020"), "/");
+		assert_eq!(uri_path("hdfs://nn:8020/a/b"), "/a/b");
+	}
+}
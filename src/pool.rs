@@ -0,0 +1,110 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Reusable I/O buffers.
+//! 
+//! Copies between HDFS files want buffers of a megabyte or more, and copying many files in parallel would
+//! otherwise allocate and zero a fresh one for every file. `BufferPool` keeps buffers that have been returned
+//! so the next copy can reuse them.
+
+use std::io::{self, Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+/// Pool of equally sized buffers, shareable between threads.
+pub struct BufferPool {
+	buffer_size: usize,
+	max_idle: usize,
+	idle: Mutex<Vec<Vec<u8>>>,
+}
+
+/// Buffer borrowed from a `BufferPool` by `BufferPool::get`, which goes back to the pool when dropped.
+/// 
+/// Its contents are whatever the last user left in it.
+pub struct PooledBuffer<'a> {
+	pool: &'a BufferPool,
+	buf: Vec<u8>,
+}
+
+impl BufferPool {
+	/// Creates an empty pool of `buffer_size` byte buffers, which keeps up to 16 buffers that aren't in use.
+	pub fn new(buffer_size: usize) -> Self {
+		Self {
+			buffer_size: buffer_size.max(1),
+			max_idle: 16,
+			idle: Mutex::new(vec![]),
+		}
+	}
+	
+	/// Sets how many unused buffers to keep. Buffers returned beyond that are freed.
+	pub fn max_idle(mut self, max_idle: usize) -> Self {
+		self.max_idle = max_idle;
+		self
+	}
+	
+	/// Gets the size of the buffers.
+	pub fn buffer_size(&self) -> usize {
+		self.buffer_size
+	}
+	
+	/// Takes a buffer from the pool, allocating one if none are free.
+	pub fn get(&self) -> PooledBuffer<'_> {
+		let buf = self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop()
+			.unwrap_or_else(|| vec![0; self.buffer_size]);
+		PooledBuffer { pool: self, buf }
+	}
+	
+	/// Copies everything from `reader` to `writer` through a buffer from the pool, like `io::copy`, returning
+	/// the number of bytes copied.
+	pub fn copy<R: Read + ?Sized, W: Write + ?Sized>(&self, reader: &mut R, writer: &mut W) -> io::Result<u64> {
+		let mut buf = self.get();
+		let mut copied = 0;
+		loop {
+			let len = match reader.read(&mut buf) {
+				Ok(0) => { return Ok(copied); },
+				Ok(len) => len,
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => { continue; },
+				Err(e) => { return Err(e); },
+			};
+			writer.write_all(&buf[..len])?;
+			copied += len as u64;
+		}
+	}
+}
+
+impl<'a> Deref for PooledBuffer<'a> {
+	type Target = [u8];
+	fn deref(&self) -> &[u8] {
+		&self.buf
+	}
+}
+impl<'a> DerefMut for PooledBuffer<'a> {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		&mut self.buf
+	}
+}
+impl<'a> Drop for PooledBuffer<'a> {
+	fn drop(&mut self) {
+		let mut idle = self.pool.idle.lock().unwrap_or_else(|e| e.into_inner());
+		if idle.len() < self.pool.max_idle {
+			idle.push(std::mem::take(&mut self.buf));
+		}
+	}
+}
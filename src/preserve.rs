@@ -0,0 +1,168 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Copying attributes along with contents, like `distcp -p`.
+//! 
+//! Block size can only be chosen when a file is created, so it's applied to the stream builder; everything else
+//! is set on the destination once it's been written. Extended attributes, ACLs, and checksum types can't be
+//! read or set through `libhdfs`, so they can't be preserved.
+
+use crate::path::AsHdfsPath;
+use crate::{HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind, HdfsStreamBuilder};
+use std::io;
+use std::str::FromStr;
+
+/// Attributes that a copy takes from its source. By default, nothing is preserved.
+/// 
+/// Parses from `distcp`'s `-p` letters, ex. `"ugpt"`.
+#[derive(Debug,Clone,Copy,Default,PartialEq,Eq)]
+pub struct Preserve {
+	replication: bool,
+	block_size: bool,
+	user: bool,
+	group: bool,
+	permissions: bool,
+	times: bool,
+}
+
+impl Preserve {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Preserves everything that can be, like `distcp -p` with no letters.
+	pub fn all() -> Self {
+		Self {
+			replication: true,
+			block_size: true,
+			user: true,
+			group: true,
+			permissions: true,
+			times: true,
+		}
+	}
+	
+	/// Preserves the replication factor of files.
+	pub fn replication(mut self) -> Self {
+		self.replication = true;
+		self
+	}
+	
+	/// Preserves the block size of files, which also keeps their checksums comparable.
+	pub fn block_size(mut self) -> Self {
+		self.block_size = true;
+		self
+	}
+	
+	/// Preserves the owner. Changing it requires being an HDFS superuser.
+	pub fn user(mut self) -> Self {
+		self.user = true;
+		self
+	}
+	
+	/// Preserves the group. Unless a superuser, the connection's user has to be in the group.
+	pub fn group(mut self) -> Self {
+		self.group = true;
+		self
+	}
+	
+	/// Preserves the permission bits.
+	pub fn permissions(mut self) -> Self {
+		self.permissions = true;
+		self
+	}
+	
+	/// Preserves the modification and access times.
+	pub fn times(mut self) -> Self {
+		self.times = true;
+		self
+	}
+	
+	/// Checks whether anything is preserved.
+	pub fn is_empty(&self) -> bool {
+		*self == Self::default()
+	}
+	
+	/// Sets up the creation of a copy of `src`, for the attributes that can't be changed afterwards.
+	pub fn apply_to_builder(&self, builder: &mut HdfsStreamBuilder, src: &HdfsDirectoryEntry) -> io::Result<()> {
+		if self.block_size {
+			builder.default_block_size(src.block_size as i64)?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for Preserve {
+	type Err = io::Error;
+	
+	/// Parses `distcp`'s letters: `r` replication, `b` block size, `u` user, `g` group, `p` permissions, and `t`
+	/// times. An empty string preserves everything.
+	/// 
+	/// `c` (checksum type), `a` (ACLs), and `x` (extended attributes) fail with `Unsupported`.
+	fn from_str(flags: &str) -> io::Result<Self> {
+		if flags.is_empty() {
+			return Ok(Self::all());
+		}
+		let mut preserve = Self::new();
+		for flag in flags.chars() {
+			preserve = match flag {
+				'r' => preserve.replication(),
+				'b' => preserve.block_size(),
+				'u' => preserve.user(),
+				'g' => preserve.group(),
+				'p' => preserve.permissions(),
+				't' => preserve.times(),
+				'c' | 'a' | 'x' => {
+					return Err(io::Error::new(io::ErrorKind::Unsupported, format!("cannot preserve '{}': not available through libhdfs", flag)));
+				},
+				_ => {
+					return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown attribute '{}', expected some of rbugpt", flag)));
+				},
+			};
+		}
+		Ok(preserve)
+	}
+}
+
+impl HdfsConnection {
+	/// Gives `dest` the attributes of `src` that `preserve` selects, other than the block size. Call it once
+	/// `dest` has been written and closed, since writing changes the modification time, and for directories,
+	/// once everything has been copied into them.
+	pub fn preserve_attributes<P: AsHdfsPath + ?Sized>(&self, dest: &P, src: &HdfsDirectoryEntry, preserve: Preserve) -> io::Result<()> {
+		let dest = &*dest.as_c_path()?;
+		if preserve.replication && matches!(src.kind, HdfsDirectoryEntryKind::File) {
+			self.set_replication(dest, src.replication as i16)?;
+		}
+		if preserve.user || preserve.group {
+			self.chown(
+				dest,
+				Some(src.owner.as_str()).filter(|_| preserve.user),
+				Some(src.group.as_str()).filter(|_| preserve.group),
+			)?;
+		}
+		if preserve.permissions {
+			self.chmod(dest, src.permissions)?;
+		}
+		if preserve.times {
+			self.set_times(dest, Some(src.last_modified), Some(src.last_access))?;
+		}
+		Ok(())
+	}
+}
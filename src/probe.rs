@@ -0,0 +1,91 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Measuring namenode round-trip latency.
+
+use crate::HdfsConnection;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Path that's looked up by `HdfsConnection::probe`. It always exists, and looking it up is a single namenode
+/// RPC that doesn't touch any datanode.
+const PROBE_PATH: &str = "/";
+
+/// Round-trip times measured by `HdfsConnection::probe`.
+#[derive(Debug,Clone)]
+pub struct Probe {
+	// Sorted, and never empty
+	samples: Vec<Duration>,
+}
+
+impl Probe {
+	/// Gets every measured time, from fastest to slowest.
+	pub fn samples(&self) -> &[Duration] {
+		&self.samples
+	}
+	
+	/// Gets the fastest time.
+	pub fn min(&self) -> Duration {
+		self.samples[0]
+	}
+	
+	/// Gets the slowest time.
+	pub fn max(&self) -> Duration {
+		self.samples[self.samples.len() - 1]
+	}
+	
+	/// Gets the mean time.
+	pub fn mean(&self) -> Duration {
+		self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+	}
+	
+	/// Gets the median time.
+	pub fn median(&self) -> Duration {
+		self.percentile(50.0)
+	}
+	
+	/// Gets a percentile, from 0 to 100, using the nearest-rank method, so it's always one of the samples.
+	pub fn percentile(&self, percentile: f64) -> Duration {
+		let percentile = percentile.clamp(0.0, 100.0);
+		let rank = (percentile / 100.0 * self.samples.len() as f64).ceil() as usize;
+		self.samples[rank.max(1) - 1]
+	}
+}
+
+impl HdfsConnection {
+	/// Measures the round-trip time of `count` trivial namenode calls, made one after another.
+	/// 
+	/// Stops at the first failed call and returns its error, since a namenode that's failing calls isn't one that
+	/// should be picked for being fast. The first call can be slower than the rest if the client had to
+	/// connect, so for a steady-state measurement, call this once with a small count first.
+	pub fn probe(&self, count: usize) -> io::Result<Probe> {
+		if count == 0 || count > u32::MAX as usize {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "probe count must be positive and fit in a u32"));
+		}
+		let mut samples = Vec::with_capacity(count);
+		for _ in 0..count {
+			let start = Instant::now();
+			self.stat(PROBE_PATH)?;
+			samples.push(start.elapsed());
+		}
+		samples.sort_unstable();
+		Ok(Probe { samples })
+	}
+}
@@ -0,0 +1,257 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Checking directory quotas and free space before and during long writes.
+
+use crate::filesystem::FileWriter;
+use crate::HdfsConnection;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Replication factor assumed when none is given, which is HDFS's default.
+const DEFAULT_REPLICATION: u16 = 3;
+
+/// What `QuotaWriter` checks, and how often.
+/// 
+/// `libhdfs` can't read directory quotas, so they're given with `dir_quota`, and usage under the directory is
+/// measured with `HdfsConnection::content_summary`, which walks the tree. Free space in the cluster is always
+/// checked.
+#[derive(Debug,Clone)]
+pub struct QuotaPolicy {
+	expected_len: Option<u64>,
+	replication: u16,
+	reserve: u64,
+	dir_quotas: Vec<(String, u64)>,
+	recheck_bytes: Option<u64>,
+	recheck_interval: Option<Duration>,
+}
+
+impl QuotaPolicy {
+	/// A policy that only checks free space in the cluster, once, before writing.
+	pub fn new() -> Self {
+		Self {
+			expected_len: None,
+			replication: DEFAULT_REPLICATION,
+			reserve: 0,
+			dir_quotas: vec![],
+			recheck_bytes: None,
+			recheck_interval: None,
+		}
+	}
+	
+	/// How many bytes will be written, so that the first check can fail before anything is written if there
+	/// isn't room for all of them.
+	pub fn expected_len(mut self, len: u64) -> Self {
+		self.expected_len = Some(len);
+		self
+	}
+	
+	/// Replication factor of the file, which multiplies how much space it uses. Defaults to 3.
+	pub fn replication(mut self, replication: u16) -> Self {
+		self.replication = replication.max(1);
+		self
+	}
+	
+	/// Leaves `bytes` of raw space free in the cluster.
+	pub fn reserve(mut self, bytes: u64) -> Self {
+		self.reserve = bytes;
+		self
+	}
+	
+	/// Checks against a space quota of `bytes` on `dir`, in raw bytes, like `hdfs dfsadmin -setSpaceQuota`.
+	pub fn dir_quota(mut self, dir: &str, bytes: u64) -> Self {
+		self.dir_quotas.push((dir.to_string(), bytes));
+		self
+	}
+	
+	/// Checks again once `bytes` have been written since the last check.
+	pub fn recheck_every_bytes(mut self, bytes: u64) -> Self {
+		self.recheck_bytes = Some(bytes.max(1));
+		self
+	}
+	
+	/// Checks again on the first write at least `interval` after the last check.
+	pub fn recheck_every(mut self, interval: Duration) -> Self {
+		self.recheck_interval = Some(interval);
+		self
+	}
+}
+
+impl Default for QuotaPolicy {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Error from `QuotaWriter` when a write wouldn't fit.
+/// 
+/// It's returned inside an `io::Error` of kind `QuotaExceeded`, or `StorageFull` for the cluster's free space,
+/// and can be gotten back with `QuotaExceeded::from_io`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct QuotaExceeded {
+	/// Directory whose quota would be exceeded, or `None` for the cluster's free space
+	pub dir: Option<String>,
+	/// Raw bytes needed
+	pub needed: u64,
+	/// Raw bytes available
+	pub available: u64,
+}
+
+impl QuotaExceeded {
+	/// Gets the `QuotaExceeded` inside an error returned by `QuotaWriter`.
+	pub fn from_io(e: &io::Error) -> Option<&Self> {
+		e.get_ref().and_then(|inner| inner.downcast_ref::<Self>())
+	}
+	
+	fn into_io(self) -> io::Error {
+		let kind = if self.dir.is_some() { io::ErrorKind::QuotaExceeded } else { io::ErrorKind::StorageFull };
+		io::Error::new(kind, self)
+	}
+}
+
+impl fmt::Display for QuotaExceeded {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.dir.as_ref() {
+			Some(dir) => write!(f, "space quota of {} exceeded", dir)?,
+			None => write!(f, "not enough free space in the cluster")?,
+		}
+		write!(f, ": {} bytes needed, {} available", self.needed, self.available)
+	}
+}
+
+impl Error for QuotaExceeded {}
+
+/// Wraps a `FileWriter`, like an `HdfsFile`, failing with `QuotaExceeded` as soon as a write won't fit in a
+/// directory quota or the cluster's free space, rather than partway through a long upload.
+/// 
+/// Space is checked when it's created, and again according to the `QuotaPolicy`; in between, what it writes is
+/// subtracted from what was available. Writes that don't fit are rechecked first, in case space was freed.
+/// Other clients can use space in between checks, so this narrows the window for failures rather than closing
+/// it.
+pub struct QuotaWriter<'a, W: FileWriter> {
+	fs: &'a HdfsConnection,
+	inner: W,
+	policy: QuotaPolicy,
+	written: u64,
+	// Raw bytes available as of the last check, minus what's been written since
+	headroom: u64,
+	since_check: u64,
+	last_check: Instant,
+}
+
+impl<'a, W: FileWriter> QuotaWriter<'a, W> {
+	/// Wraps a writer on a file in `fs`, checking for space right away. If the policy has an `expected_len`,
+	/// fails if there isn't room for all of it.
+	pub fn new(fs: &'a HdfsConnection, inner: W, policy: QuotaPolicy) -> io::Result<Self> {
+		let mut writer = Self {
+			fs,
+			inner,
+			policy,
+			written: 0,
+			headroom: 0,
+			since_check: 0,
+			last_check: Instant::now(),
+		};
+		writer.check(0)?;
+		Ok(writer)
+	}
+	
+	/// Gets the number of bytes written.
+	pub fn written(&self) -> u64 {
+		self.written
+	}
+	
+	pub fn get_ref(&self) -> &W {
+		&self.inner
+	}
+	
+	pub fn get_mut(&mut self) -> &mut W {
+		&mut self.inner
+	}
+	
+	/// Gets the wrapped writer back.
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+	
+	/// Measures the space available, failing if there isn't room for `len` more bytes, or for the rest of the
+	/// expected length.
+	fn check(&mut self, len: u64) -> io::Result<()> {
+		let remaining = self.policy.expected_len
+			.map(|expected| expected.saturating_sub(self.written))
+			.unwrap_or(0)
+			.max(len);
+		let needed = remaining.saturating_mul(self.policy.replication as u64);
+		
+		let free = self.fs.capacity()?
+			.saturating_sub(self.fs.used()?)
+			.saturating_sub(self.policy.reserve);
+		if needed > free {
+			return Err(QuotaExceeded { dir: None, needed, available: free }.into_io());
+		}
+		let mut headroom = free;
+		for (dir, quota) in self.policy.dir_quotas.iter() {
+			let available = quota.saturating_sub(self.fs.content_summary(dir)?.space_consumed);
+			if needed > available {
+				return Err(QuotaExceeded { dir: Some(dir.clone()), needed, available }.into_io());
+			}
+			headroom = headroom.min(available);
+		}
+		
+		self.headroom = headroom;
+		self.since_check = 0;
+		self.last_check = Instant::now();
+		Ok(())
+	}
+	
+	fn is_due(&self) -> bool {
+		self.policy.recheck_bytes.is_some_and(|bytes| self.since_check >= bytes)
+			|| self.policy.recheck_interval.is_some_and(|interval| self.last_check.elapsed() >= interval)
+	}
+}
+
+impl<'a, W: FileWriter> Write for QuotaWriter<'a, W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let raw = (buf.len() as u64).saturating_mul(self.policy.replication as u64);
+		if self.is_due() || raw > self.headroom {
+			self.check(buf.len() as u64)?;
+		}
+		let len = self.inner.write(buf)?;
+		self.written += len as u64;
+		self.since_check += len as u64;
+		self.headroom = self.headroom.saturating_sub(len as u64 * self.policy.replication as u64);
+		Ok(len)
+	}
+	
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+impl<'a, W: FileWriter> FileWriter for QuotaWriter<'a, W> {
+	fn sync(&mut self) -> io::Result<()> {
+		self.inner.sync()
+	}
+	fn close(self: Box<Self>) -> io::Result<()> {
+		Box::new(self.inner).close()
+	}
+}
@@ -0,0 +1,180 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Applying an operation to every file and directory in a tree.
+
+use crate::path::AsHdfsPath;
+use crate::{HdfsBuilder, HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// Options for `HdfsConnection::apply_recursive`, `chmod_recursive`, and `chown_recursive`.
+/// 
+/// By default, entries are processed one at a time on the calling thread, stopping at the first error.
+#[derive(Default)]
+pub struct RecursiveOptions<'a> {
+	builder: Option<&'a HdfsBuilder>,
+	parallel: usize,
+	keep_going: bool,
+}
+
+/// Result of `HdfsConnection::apply_recursive`.
+#[derive(Debug,Default)]
+pub struct RecursiveSummary {
+	/// Number of entries the operation succeeded on
+	pub applied: u64,
+	/// Paths that the operation or listing failed on, with the errors. Only filled in with
+	/// `RecursiveOptions::keep_going`.
+	pub errors: Vec<(String, io::Error)>,
+}
+
+impl<'a> RecursiveOptions<'a> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Processes up to `parallel` entries at once, each on a separate thread with a connection made by
+	/// `connect_isolated` on a clone of `builder`. Each worker needs a connection of its own, and that connection
+	/// needs its own filesystem instance, since a worker dropping a cached instance would close it for every other
+	/// connection to the cluster. The tree is still listed on the calling thread.
+	pub fn parallel(mut self, parallel: usize, builder: &'a HdfsBuilder) -> Self {
+		self.parallel = parallel;
+		self.builder = Some(builder);
+		self
+	}
+	
+	/// Carries on past errors, collecting them into `RecursiveSummary::errors`, instead of stopping and returning
+	/// the first one.
+	pub fn keep_going(mut self) -> Self {
+		self.keep_going = true;
+		self
+	}
+}
+
+/// Work shared between the threads of `apply_recursive`.
+struct Shared<'f, F> {
+	f: &'f F,
+	keep_going: bool,
+	stop: AtomicBool,
+	summary: Mutex<RecursiveSummary>,
+	first_error: Mutex<Option<io::Error>>,
+}
+impl<'f, F: Fn(&HdfsConnection, &HdfsDirectoryEntry) -> io::Result<()>> Shared<'f, F> {
+	fn apply(&self, fs: &HdfsConnection, entry: &HdfsDirectoryEntry) {
+		match (self.f)(fs, entry) {
+			Ok(()) => { self.summary.lock().unwrap().applied += 1; },
+			Err(e) => { self.fail(&entry.name, e); },
+		}
+	}
+	
+	fn fail(&self, path: &str, err: io::Error) {
+		if self.keep_going {
+			self.summary.lock().unwrap().errors.push((path.to_string(), err));
+		} else {
+			self.first_error.lock().unwrap().get_or_insert(err);
+			self.stop.store(true, Ordering::SeqCst);
+		}
+	}
+}
+
+impl HdfsConnection {
+	/// Calls `f` on `path` and everything under it, directories before their contents.
+	/// 
+	/// `f` gets the connection to use, which is a different one for each thread when running in parallel.
+	pub fn apply_recursive<P, F>(&self, path: &P, options: RecursiveOptions<'_>, f: F) -> io::Result<RecursiveSummary>
+		where P: AsHdfsPath + ?Sized, F: Fn(&HdfsConnection, &HdfsDirectoryEntry) -> io::Result<()> + Sync
+	{
+		let path = &*path.as_str_path()?;
+		let shared = Shared {
+			f: &f,
+			keep_going: options.keep_going,
+			stop: AtomicBool::new(false),
+			summary: Mutex::new(RecursiveSummary::default()),
+			first_error: Mutex::new(None),
+		};
+		// Connect up front, so a failure doesn't leave the walk waiting on workers that never started
+		let workers = match options.builder {
+			Some(builder) if options.parallel > 1 => (0..options.parallel)
+				.map(|_| builder.clone().connect_isolated())
+				.collect::<io::Result<Vec<HdfsConnection>>>()?,
+			_ => vec![],
+		};
+		
+		let parallel = !workers.is_empty();
+		let (tx, rx) = mpsc::sync_channel::<HdfsDirectoryEntry>(workers.len() * 4);
+		let rx = Mutex::new(rx);
+		thread::scope(|scope| {
+			for fs in workers {
+				let (shared, rx) = (&shared, &rx);
+				scope.spawn(move || loop {
+					let entry = match rx.lock().unwrap().recv() {
+						Ok(entry) => entry,
+						Err(_) => { break; },
+					};
+					// Drain the channel without doing anything after an error
+					if !shared.stop.load(Ordering::SeqCst) {
+						shared.apply(&fs, &entry);
+					}
+				});
+			}
+			
+			// Walk errors are for listing the last directory yielded, or for the starting path
+			let mut last_dir = path.to_string();
+			for item in self.walk(path) {
+				if shared.stop.load(Ordering::SeqCst) {
+					break;
+				}
+				let entry = match item {
+					Ok(item) => item.entry,
+					Err(e) => {
+						shared.fail(&last_dir, e);
+						continue;
+					},
+				};
+				if let HdfsDirectoryEntryKind::Directory = entry.kind {
+					last_dir = entry.name.clone();
+				}
+				if !parallel {
+					shared.apply(self, &entry);
+				} else if tx.send(entry).is_err() {
+					break;
+				}
+			}
+			drop(tx);
+		});
+		
+		if let Some(err) = shared.first_error.into_inner().unwrap() {
+			return Err(err);
+		}
+		Ok(shared.summary.into_inner().unwrap())
+	}
+	
+	/// Changes the permission bits of `path` and everything under it.
+	pub fn chmod_recursive<P: AsHdfsPath + ?Sized>(&self, path: &P, mode: u16, options: RecursiveOptions<'_>) -> io::Result<RecursiveSummary> {
+		self.apply_recursive(path, options, |fs, entry| fs.chmod(&entry.name, mode))
+	}
+	
+	/// Changes the owner and group of `path` and everything under it. `None` leaves that part unchanged.
+	pub fn chown_recursive<P: AsHdfsPath + ?Sized>(&self, path: &P, owner: Option<&str>, group: Option<&str>, options: RecursiveOptions<'_>) -> io::Result<RecursiveSummary> {
+		self.apply_recursive(path, options, |fs, entry| fs.chown(&entry.name, owner, group))
+	}
+}
@@ -0,0 +1,107 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Tracking the files open on each connection.
+//! 
+//! A file opened for writing holds its lease until it's closed, which blocks every other writer. `HdfsFile`
+//! borrows its connection, so files can't normally outlive it, but one that's leaked, ex. with `mem::forget` or
+//! a reference cycle, is never closed, and its lease is held until the process exits.
+
+use crate::HdfsConnection;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// File open on a connection, returned by `HdfsConnection::open_files`.
+#[derive(Debug,Clone)]
+pub struct OpenFile {
+	/// Path the file was opened with, or `None` if it came from `HdfsFile::from_raw`
+	pub path: Option<String>,
+	/// Whether it was opened for writing or appending, so it holds the lease on the file. Always `false` for
+	/// files from `HdfsFile::from_raw`, since the mode isn't known.
+	pub writing: bool,
+	/// When it was opened
+	pub opened: Instant,
+}
+impl OpenFile {
+	/// Gets how long the file has been open.
+	pub fn open_for(&self) -> Duration {
+		self.opened.elapsed()
+	}
+}
+
+/// Open files of a connection, keyed by an ID held by each `HdfsFile`.
+#[derive(Debug,Default)]
+pub(crate) struct Registry {
+	files: RefCell<BTreeMap<u64, OpenFile>>,
+	next_id: Cell<u64>,
+	warn_on_drop: Cell<bool>,
+}
+impl Registry {
+	pub(crate) fn register(&self, path: Option<String>, writing: bool) -> u64 {
+		let id = self.next_id.get();
+		self.next_id.set(id + 1);
+		self.files.borrow_mut().insert(id, OpenFile { path, writing, opened: Instant::now() });
+		id
+	}
+	
+	pub(crate) fn path(&self, id: u64) -> Option<String> {
+		self.files.borrow().get(&id).and_then(|file| file.path.clone())
+	}
+	
+	pub(crate) fn unregister(&self, id: u64) {
+		self.files.borrow_mut().remove(&id);
+	}
+	
+	/// Warns about files still open, if enabled with `HdfsConnection::warn_on_open_files`. Called when the
+	/// connection is dropped.
+	pub(crate) fn warn_if_open(&self) {
+		let files = self.files.borrow();
+		if !self.warn_on_drop.get() || files.is_empty() {
+			return;
+		}
+		let list = files.values()
+			.map(|file| format!("{} ({}, open for {:?})",
+				file.path.as_deref().unwrap_or("<raw handle>"),
+				if file.writing { "writing" } else { "reading" },
+				file.open_for()))
+			.collect::<Vec<_>>()
+			.join(", ");
+		#[cfg(feature = "log")]
+		log::warn!(target: "hdfs", "connection dropped with {} files still open: {}", files.len(), list);
+		#[cfg(not(feature = "log"))]
+		eprintln!("hdfs: connection dropped with {} files still open: {}", files.len(), list);
+	}
+}
+
+impl HdfsConnection {
+	/// Lists the files open on this connection, oldest first.
+	/// 
+	/// Files given up with `HdfsFile::into_raw` aren't included, since the connection no longer owns them.
+	pub fn open_files(&self) -> Vec<OpenFile> {
+		self.files.files.borrow().values().cloned().collect()
+	}
+	
+	/// Sets whether dropping the connection warns about files that are still open, which can only happen if
+	/// they were leaked. The warning goes to the `log` crate with the `log` feature, or to stderr otherwise.
+	pub fn warn_on_open_files(&self, warn: bool) {
+		self.files.warn_on_drop.set(warn);
+	}
+}
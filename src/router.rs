@@ -0,0 +1,199 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Sending operations to one of several filesystems by path, so code can work with paths on several clusters
+//! without knowing which connection each one is on.
+
+use crate::filesystem::{FileReader, FileSystem, FileWriter};
+use crate::path::{split_uri, uri_path};
+use crate::{HdfsConnection, HdfsDirectoryEntry};
+use std::borrow::Cow;
+use std::io;
+use std::time::SystemTime;
+
+/// `FileSystem` that sends each operation to the filesystem routed to its path.
+/// 
+/// Routes are either URI prefixes, like `hdfs://prod`, or absolute path prefixes, like `/archive`, and a path
+/// goes to the route with the longest prefix that matches whole components of it. Paths that don't match any
+/// route, including relative paths, go to the default route, if there is one, or fail with `InvalidInput`.
+/// Routes aren't merged into listings: listing `/` doesn't show a route for `/archive`.
+/// 
+/// A URI route's prefix can be any name, since it's replaced: a path like `hdfs://prod/data` is passed to its
+/// filesystem as `/data`, and names returned by `stat` and `list_dir` are changed back to `hdfs://prod/data`, so
+/// they route to the same place. Names from a path route are returned as plain paths, and the default route's
+/// names are returned unchanged.
+/// 
+/// Renames between different routes fail with `Unsupported`; use `HdfsConnection::move_to_with` to copy
+/// between clusters.
+/// 
+/// ```ignore
+/// let router = HdfsRouter::new()
+///     .route("hdfs://prod", prod_conn)
+///     .route("hdfs://dr", dr_conn)
+///     .default_route(local_conn);
+/// let entries = router.list_dir("hdfs://dr/backups")?;
+/// ```
+pub struct HdfsRouter<F = HdfsConnection> {
+	// Longest prefix first
+	routes: Vec<Route<F>>,
+	default: Option<F>,
+}
+
+struct Route<F> {
+	// Without a trailing slash, so `/` is empty
+	prefix: String,
+	is_uri: bool,
+	fs: F,
+}
+
+impl<F: FileSystem> HdfsRouter<F> {
+	/// Starts with no routes.
+	pub fn new() -> Self {
+		Self { routes: vec![], default: None }
+	}
+	
+	/// Routes paths under `prefix` to `fs`. A route with the same prefix as an existing one replaces it.
+	pub fn route(mut self, prefix: &str, fs: F) -> Self {
+		let prefix = prefix.trim_end_matches('/').to_string();
+		let is_uri = !split_uri(&prefix).0.is_empty();
+		self.routes.retain(|route| route.prefix != prefix);
+		let i = self.routes.iter().position(|route| route.prefix.len() < prefix.len()).unwrap_or(self.routes.len());
+		self.routes.insert(i, Route { prefix, is_uri, fs });
+		self
+	}
+	
+	/// Sends paths that don't match any route to `fs`.
+	pub fn default_route(mut self, fs: F) -> Self {
+		self.default = Some(fs);
+		self
+	}
+	
+	/// Gets the filesystem that a path is routed to, and the path to use on it, for operations that
+	/// `FileSystem` doesn't have.
+	pub fn resolve<'p>(&self, path: &'p str) -> io::Result<(&F, Cow<'p, str>)> {
+		let (fs, path, _) = self.resolve_route(path)?;
+		Ok((fs, path))
+	}
+	
+	/// Resolves a path, also returning the index of its route, or `None` for the default route.
+	fn resolve_route<'p>(&self, path: &'p str) -> io::Result<(&F, Cow<'p, str>, Option<usize>)> {
+		let is_uri = !split_uri(path).0.is_empty();
+		for (i, route) in self.routes.iter().enumerate() {
+			if route.is_uri != is_uri || !under(path, &route.prefix) {
+				continue;
+			}
+			let inner = if route.is_uri {
+				match &path[route.prefix.len()..] {
+					"" => Cow::Borrowed("/"),
+					rest => Cow::Borrowed(rest),
+				}
+			} else {
+				Cow::Borrowed(path)
+			};
+			return Ok((&route.fs, inner, Some(i)));
+		}
+		match &self.default {
+			Some(fs) => Ok((fs, Cow::Borrowed(path), None)),
+			None => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("no route for {}", path))),
+		}
+	}
+	
+	/// Changes the name of an entry from a route's filesystem to one that routes back to it.
+	fn outer_entry(&self, route: Option<usize>, mut entry: HdfsDirectoryEntry) -> HdfsDirectoryEntry {
+		if let Some(route) = route.map(|i| &self.routes[i]) {
+			let path = uri_path(&entry.name);
+			entry.name = if route.is_uri { format!("{}{}", route.prefix, path) } else { path.to_string() };
+		}
+		entry
+	}
+}
+
+impl<F: FileSystem> Default for HdfsRouter<F> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Checks whether a path is `prefix` or under it. An empty prefix is the root, which every absolute path is under.
+fn under(path: &str, prefix: &str) -> bool {
+	match path.strip_prefix(prefix) {
+		Some(rest) => rest.is_empty() || rest.starts_with('/'),
+		None => false,
+	}
+}
+
+impl<F: FileSystem> FileSystem for HdfsRouter<F> {
+	fn stat(&self, path: &str) -> io::Result<HdfsDirectoryEntry> {
+		let (fs, path, route) = self.resolve_route(path)?;
+		Ok(self.outer_entry(route, fs.stat(&path)?))
+	}
+	fn exists(&self, path: &str) -> io::Result<bool> {
+		let (fs, path) = self.resolve(path)?;
+		fs.exists(&path)
+	}
+	fn list_dir(&self, path: &str) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		let (fs, path, route) = self.resolve_route(path)?;
+		Ok(fs.list_dir(&path)?.into_iter().map(|entry| self.outer_entry(route, entry)).collect())
+	}
+	fn create_dir(&self, path: &str) -> io::Result<()> {
+		let (fs, path) = self.resolve(path)?;
+		fs.create_dir(&path)
+	}
+	fn delete(&self, path: &str, recursive: bool) -> io::Result<()> {
+		let (fs, path) = self.resolve(path)?;
+		fs.delete(&path, recursive)
+	}
+	fn rename(&self, src: &str, dest: &str) -> io::Result<()> {
+		let (fs, inner_src, src_route) = self.resolve_route(src)?;
+		let (_, inner_dest, dest_route) = self.resolve_route(dest)?;
+		if src_route != dest_route {
+			return Err(io::Error::new(io::ErrorKind::Unsupported, format!("cannot rename {} to {}, which is on another filesystem", src, dest)));
+		}
+		fs.rename(&inner_src, &inner_dest)
+	}
+	fn chmod(&self, path: &str, mode: u16) -> io::Result<()> {
+		let (fs, path) = self.resolve(path)?;
+		fs.chmod(&path, mode)
+	}
+	fn chown(&self, path: &str, owner: Option<&str>, group: Option<&str>) -> io::Result<()> {
+		let (fs, path) = self.resolve(path)?;
+		fs.chown(&path, owner, group)
+	}
+	fn set_times(&self, path: &str, modified: Option<SystemTime>, accessed: Option<SystemTime>) -> io::Result<()> {
+		let (fs, path) = self.resolve(path)?;
+		fs.set_times(&path, modified, accessed)
+	}
+	fn truncate(&self, path: &str, size: u64) -> io::Result<bool> {
+		let (fs, path) = self.resolve(path)?;
+		fs.truncate(&path, size)
+	}
+	fn open_read(&self, path: &str) -> io::Result<Box<dyn FileReader + '_>> {
+		let (fs, path) = self.resolve(path)?;
+		fs.open_read(&path)
+	}
+	fn open_create(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		let (fs, path) = self.resolve(path)?;
+		fs.open_create(&path)
+	}
+	fn open_append(&self, path: &str) -> io::Result<Box<dyn FileWriter + '_>> {
+		let (fs, path) = self.resolve(path)?;
+		fs.open_append(&path)
+	}
+}
@@ -0,0 +1,120 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Checksumming every file in a tree, for integrity audits.
+
+use crate::checksummed::checksum_reader;
+use crate::path::AsHdfsPath;
+use crate::{ChecksumAlgorithm, HdfsBuilder, HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::io;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// Result for one file, passed to the callback of `HdfsConnection::checksum_tree`.
+#[derive(Debug)]
+pub struct TreeChecksum {
+	/// The file, or for errors listing the tree, the directory that couldn't be listed
+	pub path: String,
+	/// Size of the file when it was listed
+	pub size: u64,
+	/// The checksum, or why the file couldn't be read
+	pub checksum: io::Result<Vec<u8>>,
+}
+
+impl TreeChecksum {
+	fn of(fs: &HdfsConnection, entry: HdfsDirectoryEntry, algorithm: ChecksumAlgorithm) -> Self {
+		let checksum = fs.open_read(&entry.name)
+			.and_then(|mut file| checksum_reader(algorithm, &mut file))
+			.map(|(checksum, _)| checksum);
+		Self { path: entry.name, size: entry.size, checksum }
+	}
+}
+
+impl HdfsConnection {
+	/// Checksums every file under `path` with `algorithm`, calling `on_result` with each one as it finishes.
+	/// 
+	/// `libhdfs` can't ask the datanodes for checksums, so every file is read in full. Up to `concurrency` files
+	/// are read at once, each on a separate thread with a connection made by `connect_isolated` on a clone of
+	/// `builder`: one per thread, since a connection can't be used from several at once, and each with its own
+	/// filesystem instance, so that the workers finishing doesn't close this connection's. The tree is listed on the calling thread, which is also where `on_result`
+	/// is called, in the order files finish rather than in the order they're listed. Errors reading a file or
+	/// listing a directory are passed to `on_result` and the scan carries on; this only fails if connecting does.
+	pub fn checksum_tree<P, F>(&self, path: &P, algorithm: ChecksumAlgorithm, concurrency: usize, builder: &HdfsBuilder, mut on_result: F) -> io::Result<()>
+		where P: AsHdfsPath + ?Sized, F: FnMut(TreeChecksum)
+	{
+		let path = &*path.as_str_path()?;
+		let workers = if concurrency > 1 {
+			(0..concurrency).map(|_| builder.clone().connect_isolated()).collect::<io::Result<Vec<HdfsConnection>>>()?
+		} else {
+			vec![]
+		};
+		let parallel = !workers.is_empty();
+		
+		let (work_tx, work_rx) = mpsc::sync_channel::<HdfsDirectoryEntry>(workers.len() * 4);
+		let work_rx = Mutex::new(work_rx);
+		let (result_tx, result_rx) = mpsc::channel::<TreeChecksum>();
+		thread::scope(|scope| {
+			for fs in workers {
+				let (work_rx, result_tx) = (&work_rx, result_tx.clone());
+				scope.spawn(move || loop {
+					let entry = match work_rx.lock().unwrap().recv() {
+						Ok(entry) => entry,
+						Err(_) => { break; },
+					};
+					if result_tx.send(TreeChecksum::of(&fs, entry, algorithm)).is_err() {
+						break;
+					}
+				});
+			}
+			// Owned by this closure, so that the workers stop if `on_result` panics
+			let (work_tx, result_rx) = (work_tx, result_rx);
+			drop(result_tx);
+			
+			// Walk errors are for listing the last directory yielded, or for the starting path
+			let mut last_dir = path.to_string();
+			for item in self.walk(path) {
+				let entry = match item {
+					Ok(item) => item.entry,
+					Err(e) => {
+						on_result(TreeChecksum { path: last_dir.clone(), size: 0, checksum: Err(e) });
+						continue;
+					},
+				};
+				match entry.kind {
+					HdfsDirectoryEntryKind::Directory => { last_dir = entry.name; },
+					_ if !parallel => { on_result(TreeChecksum::of(self, entry, algorithm)); },
+					_ => {
+						if work_tx.send(entry).is_err() {
+							break;
+						}
+						while let Ok(result) = result_rx.try_recv() {
+							on_result(result);
+						}
+					},
+				}
+			}
+			drop(work_tx);
+			for result in result_rx.iter() {
+				on_result(result);
+			}
+		});
+		Ok(())
+	}
+}
@@ -0,0 +1,56 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Closing Hadoop's cached filesystems before exiting.
+//! 
+//! `libhdfs` has no call for this, so it's done through JNI.
+
+use crate::jni::{Env, Frame};
+use std::io;
+
+/// Closes every Hadoop filesystem in the process, flushing and closing files still open for writing, and stopping
+/// the client threads they started. Does nothing if no connection has been made yet.
+/// 
+/// This calls Hadoop's `FileSystem.closeAll`, which Hadoop also runs when the JVM shuts down normally. The JVM
+/// itself is left running, since a process can only ever start one, and `libhdfs` keeps threads attached to it.
+/// Connections and files that are still open afterwards fail with errors, and should be dropped; new connections
+/// can still be made.
+/// 
+/// For tests and short-lived programs, where lingering client threads and unflushed writes would otherwise hold
+/// up or be lost when the process exits.
+pub fn shutdown() -> io::Result<()> {
+	unsafe {
+		let env = match Env::current()? {
+			Some(env) => env,
+			None => { return Ok(()); },
+		};
+		let res = close_all(&env);
+		env.detach();
+		res
+	}
+}
+
+/// Calls `org.apache.hadoop.fs.FileSystem.closeAll()`.
+unsafe fn close_all(env: &Env) -> io::Result<()> {
+	let mut frame = Frame::new(env);
+	let class = frame.class(b"org/apache/hadoop/fs/FileSystem\0")?;
+	let method = frame.static_method(class, b"closeAll\0", b"()V\0")?;
+	frame.call_static_void(class, method, &[], "FileSystem.closeAll failed")
+}
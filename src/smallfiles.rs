@@ -0,0 +1,255 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Packing many small records into a few large container files.
+//! 
+//! Every HDFS file costs the namenode memory and a round trip to create, whatever its size, so millions of tiny
+//! files hurt the whole cluster. `SmallFileArchiver` appends records to container files of up to a configured
+//! size, each with an index file mapping keys to byte ranges, and `SmallFileArchive` reads them back by key.
+//! 
+//! A directory of containers holds `part-NNNNN.dat` data files and `part-NNNNN.idx` indexes. An index is written
+//! once its container is complete, under a temporary name that's renamed into place, so a container that was
+//! being written when a writer crashed has no index, and is ignored. Index entries are a big-endian `u32` key
+//! length, the key, and `u64` offset and length.
+
+use crate::path::{self, AsHdfsPath};
+use crate::{HdfsConnection, HdfsFile};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+
+/// Default `SmallFileArchiver::max_container_size`, one default HDFS block.
+pub const DEFAULT_CONTAINER_SIZE: u64 = 128 * 1024 * 1024;
+
+/// Where a record is, in a `SmallFileArchive`.
+#[derive(Debug,Clone,Copy)]
+struct Location {
+	part: u32,
+	offset: u64,
+	length: u64,
+}
+
+/// Writes records into container files, created by `SmallFileArchiver::new`.
+/// 
+/// Call `finish` when done, or the last container has no index and its records are lost.
+pub struct SmallFileArchiver<'a> {
+	fs: &'a HdfsConnection,
+	dir: String,
+	max_container_size: u64,
+	next_part: u32,
+	// Open container, its part number, and the index entries written to it
+	current: Option<(HdfsFile<'a>, u32)>,
+	index: Vec<(String, u64, u64)>,
+	written: u64,
+}
+
+impl<'a> SmallFileArchiver<'a> {
+	/// Archives into `dir`, creating it if needed. New containers are numbered after any already there, so
+	/// several runs can add to the same archive, but only one writer can use a directory at a time.
+	pub fn new<P: AsHdfsPath + ?Sized>(fs: &'a HdfsConnection, dir: &P) -> io::Result<Self> {
+		let dir = dir.as_str_path()?.into_owned();
+		fs.create_dir(&dir)?;
+		let next_part = fs.list_dir(&dir)?.iter()
+			.filter_map(|entry| part_number(path::base_name(&entry.name)))
+			.map(|part| part + 1)
+			.max()
+			.unwrap_or(0);
+		Ok(Self {
+			fs,
+			dir,
+			max_container_size: DEFAULT_CONTAINER_SIZE,
+			next_part,
+			current: None,
+			index: vec![],
+			written: 0,
+		})
+	}
+	
+	/// Sets the size at which a container is closed and a new one started. A record bigger than this gets a
+	/// container to itself.
+	pub fn max_container_size(mut self, bytes: u64) -> Self {
+		self.max_container_size = bytes;
+		self
+	}
+	
+	/// Adds a record. Keys should be unique; if one is added more than once, reads get the last.
+	pub fn add(&mut self, key: &str, data: &[u8]) -> io::Result<()> {
+		self.add_reader(key, &mut &data[..]).map(|_| ())
+	}
+	
+	/// Adds a record with the contents of a reader, returning its length.
+	pub fn add_reader<R: Read + ?Sized>(&mut self, key: &str, reader: &mut R) -> io::Result<u64> {
+		if key.len() > u32::MAX as usize {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "key is too long"));
+		}
+		if self.written >= self.max_container_size {
+			self.finish_container()?;
+		}
+		if self.current.is_none() {
+			let part = self.next_part;
+			let file = self.fs.open_create(&container_path(&self.dir, part, "dat"))?;
+			self.current = Some((file, part));
+			self.next_part += 1;
+		}
+		let (file, _) = self.current.as_mut().unwrap();
+		let length = io::copy(reader, file)?;
+		self.index.push((key.to_string(), self.written, length));
+		self.written += length;
+		Ok(length)
+	}
+	
+	/// Finishes the last container, writing its index.
+	pub fn finish(mut self) -> io::Result<()> {
+		self.finish_container()
+	}
+	
+	/// Closes the open container, if any, and writes its index.
+	fn finish_container(&mut self) -> io::Result<()> {
+		let (file, part) = match self.current.take() {
+			Some(current) => current,
+			None => { return Ok(()); },
+		};
+		file.close()?;
+		
+		let mut buf = vec![];
+		for (key, offset, length) in self.index.drain(..) {
+			buf.extend_from_slice(&(key.len() as u32).to_be_bytes());
+			buf.extend_from_slice(key.as_bytes());
+			buf.extend_from_slice(&offset.to_be_bytes());
+			buf.extend_from_slice(&length.to_be_bytes());
+		}
+		let index_path = container_path(&self.dir, part, "idx");
+		let temp_path = format!("{}.tmp", index_path);
+		let mut index = self.fs.open_create(&temp_path)?;
+		index.write_all(&buf)?;
+		index.close()?;
+		self.fs.rename(&temp_path, &index_path)?;
+		self.written = 0;
+		Ok(())
+	}
+}
+
+/// Records in a directory written by `SmallFileArchiver`, opened with `SmallFileArchive::open`.
+pub struct SmallFileArchive<'a> {
+	fs: &'a HdfsConnection,
+	dir: String,
+	index: BTreeMap<String, Location>,
+	// Containers opened so far
+	containers: RefCell<BTreeMap<u32, HdfsFile<'a>>>,
+}
+
+impl<'a> SmallFileArchive<'a> {
+	/// Reads the indexes of every complete container in `dir`.
+	pub fn open<P: AsHdfsPath + ?Sized>(fs: &'a HdfsConnection, dir: &P) -> io::Result<Self> {
+		let dir = dir.as_str_path()?.into_owned();
+		let mut parts: Vec<u32> = fs.list_dir(&dir)?.iter()
+			.filter_map(|entry| {
+				let name = path::base_name(&entry.name);
+				name.ends_with(".idx").then(|| part_number(name)).flatten()
+			})
+			.collect();
+		parts.sort_unstable();
+		
+		let mut index = BTreeMap::new();
+		for part in parts {
+			let mut buf = vec![];
+			fs.open_read(&container_path(&dir, part, "idx"))?.read_to_end(&mut buf)?;
+			let mut rest = &buf[..];
+			while !rest.is_empty() {
+				let key_len = u32::from_be_bytes(take(&mut rest)?) as usize;
+				if rest.len() < key_len {
+					return Err(corrupt(part));
+				}
+				let (key, after) = rest.split_at(key_len);
+				rest = after;
+				let key = String::from_utf8(key.to_vec()).map_err(|_| corrupt(part))?;
+				let offset = u64::from_be_bytes(take(&mut rest)?);
+				let length = u64::from_be_bytes(take(&mut rest)?);
+				index.insert(key, Location { part, offset, length });
+			}
+		}
+		Ok(Self { fs, dir, index, containers: RefCell::new(BTreeMap::new()) })
+	}
+	
+	/// Gets the keys of every record, in order.
+	pub fn keys(&self) -> impl Iterator<Item = &str> {
+		self.index.keys().map(|key| key.as_str())
+	}
+	
+	/// Gets the number of records.
+	pub fn len(&self) -> usize {
+		self.index.len()
+	}
+	
+	/// Checks whether there are no records.
+	pub fn is_empty(&self) -> bool {
+		self.index.is_empty()
+	}
+	
+	/// Checks whether there's a record with a key.
+	pub fn contains(&self, key: &str) -> bool {
+		self.index.contains_key(key)
+	}
+	
+	/// Reads a record, returning a `NotFound` error if there's none with the key.
+	pub fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+		let location = *self.index.get(key)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no record {} in {}", key, self.dir)))?;
+		let mut containers = self.containers.borrow_mut();
+		let file = match containers.get(&location.part) {
+			Some(file) => file,
+			None => {
+				let file = self.fs.open_read(&container_path(&self.dir, location.part, "dat"))?;
+				containers.entry(location.part).or_insert(file)
+			},
+		};
+		let mut buf = vec![0; location.length as usize];
+		file.read_exact_at(location.offset, &mut buf)?;
+		Ok(buf)
+	}
+}
+
+fn container_path(dir: &str, part: u32, extension: &str) -> String {
+	path::join(dir, &format!("part-{:05}.{}", part, extension))
+}
+
+/// Gets the part number from a container or index name.
+fn part_number(name: &str) -> Option<u32> {
+	let (stem, extension) = name.strip_prefix("part-")?.split_once('.')?;
+	if extension != "dat" && extension != "idx" {
+		return None;
+	}
+	stem.parse().ok()
+}
+
+/// Takes a big-endian integer's bytes from the front of an index.
+fn take<const N: usize>(rest: &mut &[u8]) -> io::Result<[u8; N]> {
+	if rest.len() < N {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated index entry"));
+	}
+	let (bytes, after) = rest.split_at(N);
+	*rest = after;
+	Ok(bytes.try_into().unwrap())
+}
+
+fn corrupt(part: u32) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("corrupt index for part {}", part))
+}
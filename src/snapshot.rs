@@ -0,0 +1,175 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Reading HDFS snapshots.
+//! 
+//! Snapshots of a snapshottable directory are exposed as read-only directories under `<dir>/.snapshot`, which
+//! is all listing and diffing use. `libhdfs` does not expose the namenode calls for creating, deleting, or
+//! diffing snapshots, so creating and deleting go through JNI, and diffs are computed client-side by walking both
+//! trees.
+
+use crate::jni::{Frame, JValue};
+use crate::path::AsHdfsPath;
+use crate::{HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::collections::BTreeMap;
+use std::io;
+use std::os::raw::c_void;
+use std::ptr;
+use std::time::SystemTime;
+
+/// Name to pass to `HdfsConnection::snapshot_diff` to refer to the current state of the directory, rather than
+/// a snapshot. Same as `hdfs snapshotDiff`.
+pub const CURRENT_STATE: &str = ".";
+
+/// Kind of change reported by `HdfsConnection::snapshot_diff`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum SnapshotDiffKind {
+	/// Exists in the later snapshot only
+	Created,
+	/// Exists in the earlier snapshot only
+	Deleted,
+	/// A file whose size or modification time differs
+	Modified,
+}
+
+/// Entry returned by `HdfsConnection::snapshot_diff`.
+#[derive(Debug,Clone)]
+pub struct SnapshotDiffEntry {
+	pub kind: SnapshotDiffKind,
+	/// Path relative to the snapshottable directory
+	pub path: String,
+}
+
+/// What's compared to find modified files.
+#[derive(PartialEq,Eq)]
+struct State {
+	is_dir: bool,
+	size: u64,
+	modified: SystemTime,
+}
+
+impl HdfsConnection {
+	/// Gets the path of a snapshot of a directory, ex. `/data/.snapshot/s1`.
+	pub fn snapshot_path(dir: &str, snapshot: &str) -> String {
+		if snapshot == CURRENT_STATE {
+			return dir.to_string();
+		}
+		format!("{}/.snapshot/{}", dir.trim_end_matches('/'), snapshot)
+	}
+	
+	/// Creates a snapshot of a snapshottable directory, returning its path. Without a name, the namenode names it
+	/// after the current time, ex. `s20200131-120000.000`.
+	/// 
+	/// Goes through JNI with `FileSystem.createSnapshot`, so it needs the JNI `libhdfs`.
+	pub fn create_snapshot<P: AsHdfsPath + ?Sized>(&self, dir: &P, name: Option<&str>) -> io::Result<String> {
+		let dir = dir.as_str_path()?;
+		self.with_java_fs(|frame, fs| unsafe { create_snapshot_in(frame, fs, &dir, name) })
+	}
+	
+	/// Deletes a snapshot of a snapshottable directory.
+	/// 
+	/// Goes through JNI with `FileSystem.deleteSnapshot`, so it needs the JNI `libhdfs`.
+	pub fn delete_snapshot<P: AsHdfsPath + ?Sized>(&self, dir: &P, name: &str) -> io::Result<()> {
+		let dir = dir.as_str_path()?;
+		self.with_java_fs(|frame, fs| unsafe { delete_snapshot_in(frame, fs, &dir, name) })
+	}
+	
+	/// Lists the snapshots of a snapshottable directory.
+	pub fn list_snapshots<P: AsHdfsPath + ?Sized>(&self, dir: &P) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		self.list_dir(&format!("{}/.snapshot", dir.as_str_path()?.trim_end_matches('/')))
+	}
+	
+	/// Compares two snapshots of a directory, returning changes sorted by path.
+	/// 
+	/// Either snapshot may be `CURRENT_STATE` to compare against the directory as it is now. Unlike the namenode's
+	/// snapshot diff report, renames are reported as a deletion and a creation.
+	pub fn snapshot_diff<P: AsHdfsPath + ?Sized>(&self, dir: &P, from: &str, to: &str) -> io::Result<Vec<SnapshotDiffEntry>> {
+		let dir = &*dir.as_str_path()?;
+		let mut before = self.snapshot_index(&Self::snapshot_path(dir, from))?;
+		let after = self.snapshot_index(&Self::snapshot_path(dir, to))?;
+		
+		let mut diff = vec![];
+		for (path, state) in after.into_iter() {
+			let kind = match before.remove(&path) {
+				None => SnapshotDiffKind::Created,
+				Some(ref old) if old.is_dir != state.is_dir => {
+					diff.push(SnapshotDiffEntry { kind: SnapshotDiffKind::Deleted, path: path.clone() });
+					SnapshotDiffKind::Created
+				},
+				Some(ref old) if !state.is_dir && *old != state => SnapshotDiffKind::Modified,
+				Some(_) => { continue; },
+			};
+			diff.push(SnapshotDiffEntry { kind, path });
+		}
+		diff.extend(before.into_keys().map(|path| SnapshotDiffEntry { kind: SnapshotDiffKind::Deleted, path }));
+		diff.sort_by(|a, b| a.path.cmp(&b.path));
+		Ok(diff)
+	}
+	
+	/// Walks a snapshot, keyed by path relative to its root. The root itself is left out.
+	fn snapshot_index(&self, root: &str) -> io::Result<BTreeMap<String, State>> {
+		let root = self.stat(root)?;
+		let mut index = BTreeMap::new();
+		for item in self.walk(&root.name).skip(1) {
+			let entry = item?.entry;
+			let path = entry.name.strip_prefix(root.name.as_str())
+				.unwrap_or(&entry.name)
+				.trim_start_matches('/')
+				.to_string();
+			// HDFS hides `.snapshot`, but other filesystems may not
+			if path == ".snapshot" || path.starts_with(".snapshot/") {
+				continue;
+			}
+			index.insert(path, State {
+				is_dir: matches!(entry.kind, HdfsDirectoryEntryKind::Directory),
+				size: entry.size,
+				modified: entry.last_modified,
+			});
+		}
+		Ok(index)
+	}
+}
+
+/// Creates a snapshot with `FileSystem.createSnapshot`, returning the path it gives back.
+unsafe fn create_snapshot_in(frame: &mut Frame, fs: *mut c_void, dir: &str, name: Option<&str>) -> io::Result<String> {
+	let fs_class = frame.class(b"org/apache/hadoop/fs/FileSystem\0")?;
+	let create = frame.method(fs_class, b"createSnapshot\0", b"(Lorg/apache/hadoop/fs/Path;Ljava/lang/String;)Lorg/apache/hadoop/fs/Path;\0")?;
+	let java_dir = frame.hadoop_path(dir)?;
+	// A null name has the namenode pick one
+	let java_name = match name {
+		Some(name) => frame.new_string(name)?,
+		None => ptr::null_mut(),
+	};
+	let path = frame.call_object(fs, create, &[JValue { l: java_dir }, JValue { l: java_name }], &format!("could not create a snapshot of {}", dir))?;
+	if path.is_null() {
+		return Err(io::Error::new(io::ErrorKind::Other, format!("no snapshot path returned for {}", dir)));
+	}
+	frame.env.describe(path)
+		.ok_or_else(|| io::Error::new(io::ErrorKind::Other, format!("could not get the path of the snapshot of {}", dir)))
+}
+
+/// Deletes a snapshot with `FileSystem.deleteSnapshot`.
+unsafe fn delete_snapshot_in(frame: &mut Frame, fs: *mut c_void, dir: &str, name: &str) -> io::Result<()> {
+	let fs_class = frame.class(b"org/apache/hadoop/fs/FileSystem\0")?;
+	let delete = frame.method(fs_class, b"deleteSnapshot\0", b"(Lorg/apache/hadoop/fs/Path;Ljava/lang/String;)V\0")?;
+	let java_dir = frame.hadoop_path(dir)?;
+	let java_name = frame.new_string(name)?;
+	frame.call_void(fs, delete, &[JValue { l: java_dir }, JValue { l: java_name }], &format!("could not delete snapshot {} of {}", name, dir))
+}
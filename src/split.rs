@@ -0,0 +1,169 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Planning read splits for distributed readers, like Hadoop's `FileInputFormat`.
+//! 
+//! Splits end on block boundaries when they're at least a block long, so each block is read by one reader, and
+//! list the datanodes holding most of their data first, so a scheduler can place readers next to it.
+
+use crate::path::AsHdfsPath;
+use crate::{BlockLocation, HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io;
+
+/// How much bigger than the split size the last split of a file can be, rather than leaving a small one. Same
+/// as Hadoop's `SPLIT_SLOP`.
+const SPLIT_SLOP: f64 = 1.1;
+
+/// Part of a file for one reader to process, returned by `HdfsConnection::split_plan`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Split {
+	/// File the split is in, as an absolute URI
+	pub path: String,
+	/// Offset of the start of the split in the file
+	pub offset: u64,
+	/// Length of the split. Zero only for empty files, which get a single split.
+	pub length: u64,
+	/// Datanodes storing replicas of the split's blocks, ordered by how much of the split they store, most first
+	pub hosts: Vec<String>,
+}
+
+impl HdfsConnection {
+	/// Divides a file, or every file under a directory, into splits of about `target_split_size` bytes.
+	/// 
+	/// A target of at least the block size is rounded down to a whole number of blocks; a smaller one is used as
+	/// is, so blocks are shared between splits. The last split of a file may be up to 10% bigger, rather than
+	/// leaving a tiny one. Files are listed in the order they're walked, and splits in order of offset.
+	pub fn split_plan<P: AsHdfsPath + ?Sized>(&self, path: &P, target_split_size: u64) -> io::Result<Vec<Split>> {
+		if target_split_size == 0 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "split size must not be 0"));
+		}
+		let path = &*path.as_str_path()?;
+		let mut splits = vec![];
+		for item in self.walk(path) {
+			let entry = item?.entry;
+			if matches!(entry.kind, HdfsDirectoryEntryKind::File) {
+				self.split_file(&entry, target_split_size, &mut splits)?;
+			}
+		}
+		Ok(splits)
+	}
+	
+	fn split_file(&self, entry: &HdfsDirectoryEntry, target_split_size: u64, splits: &mut Vec<Split>) -> io::Result<()> {
+		if entry.size == 0 {
+			splits.push(Split { path: entry.name.clone(), offset: 0, length: 0, hosts: vec![] });
+			return Ok(());
+		}
+		let blocks = self.get_block_locations(&entry.name, 0, entry.size)?;
+		for (offset, length) in split_ranges(entry.size, entry.block_size, target_split_size) {
+			splits.push(Split {
+				path: entry.name.clone(),
+				offset,
+				length,
+				hosts: hosts_by_bytes(&blocks, offset, length),
+			});
+		}
+		Ok(())
+	}
+}
+
+/// Divides a non-empty file into `(offset, length)` ranges, as described by `split_plan`.
+fn split_ranges(size: u64, block_size: u64, target_split_size: u64) -> Vec<(u64, u64)> {
+	let block_size = block_size.max(1);
+	let split_size = if target_split_size >= block_size {
+		target_split_size / block_size * block_size
+	} else {
+		target_split_size
+	};
+	let mut ranges = vec![];
+	let mut offset = 0;
+	while offset < size {
+		let remaining = size - offset;
+		let length = if remaining as f64 <= split_size as f64 * SPLIT_SLOP { remaining } else { split_size };
+		ranges.push((offset, length));
+		offset += length;
+	}
+	ranges
+}
+
+/// Ranks the hosts of the blocks overlapping `offset..offset+length` by how many of those bytes they store.
+fn hosts_by_bytes(blocks: &[BlockLocation], offset: u64, length: u64) -> Vec<String> {
+	let end = offset + length;
+	let mut bytes: BTreeMap<&str, u64> = BTreeMap::new();
+	for block in blocks {
+		let overlap = end.min(block.offset + block.length).saturating_sub(offset.max(block.offset));
+		if overlap == 0 {
+			continue;
+		}
+		for host in block.hosts.iter() {
+			*bytes.entry(host.as_str()).or_insert(0) += overlap;
+		}
+	}
+	let mut hosts: Vec<(&str, u64)> = bytes.into_iter().collect();
+	// Stable, so ties stay in name order
+	hosts.sort_by_key(|&(_, bytes)| Reverse(bytes));
+	hosts.into_iter().map(|(host, _)| host.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const MB: u64 = 1024 * 1024;
+	
+	fn block(offset: u64, length: u64, hosts: &[&str]) -> BlockLocation {
+		BlockLocation { offset, length, hosts: hosts.iter().map(|host| host.to_string()).collect() }
+	}
+	
+	#[test]
+	fn splits_on_block_boundaries() {
+		// A target between one and two blocks rounds down to one
+		assert_eq!(split_ranges(3 * 128 * MB, 128 * MB, 200 * MB), vec![(0, 128 * MB), (128 * MB, 128 * MB), (256 * MB, 128 * MB)]);
+		assert_eq!(split_ranges(4 * 128 * MB, 128 * MB, 256 * MB), vec![(0, 256 * MB), (256 * MB, 256 * MB)]);
+		// Smaller targets share blocks
+		assert_eq!(split_ranges(100, 128 * MB, 40), vec![(0, 40), (40, 40), (80, 20)]);
+	}
+	
+	#[test]
+	fn last_split_absorbs_slop() {
+		// Up to 10% over, the remainder joins the last split rather than making a tiny one
+		assert_eq!(split_ranges(110, 1000, 100), vec![(0, 110)]);
+		assert_eq!(split_ranges(111, 1000, 100), vec![(0, 100), (100, 11)]);
+		assert_eq!(split_ranges(5, 1000, 100), vec![(0, 5)]);
+		assert_eq!(split_ranges(84, 1000, 40), vec![(0, 40), (40, 44)]);
+		// A zero block size is treated as one byte
+		assert_eq!(split_ranges(10, 0, 4), vec![(0, 4), (4, 4), (8, 2)]);
+	}
+	
+	#[test]
+	fn hosts_ranked_by_bytes() {
+		let blocks = vec![
+			block(0, 100, &["a", "b"]),
+			block(100, 100, &["b", "c"]),
+			block(200, 50, &["c", "d"]),
+		];
+		assert_eq!(hosts_by_bytes(&blocks, 0, 250), vec!["b", "c", "a", "d"]);
+		// Only the overlapping bytes count
+		assert_eq!(hosts_by_bytes(&blocks, 90, 20), vec!["b", "a", "c"]);
+		assert_eq!(hosts_by_bytes(&blocks, 150, 100), vec!["c", "b", "d"]);
+		assert_eq!(hosts_by_bytes(&blocks, 250, 10), Vec::<String>::new());
+	}
+}
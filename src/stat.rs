@@ -0,0 +1,85 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Getting the metadata of many paths at once.
+
+use crate::path::AsHdfsPath;
+use crate::{HdfsBuilder, HdfsConnection, HdfsDirectoryEntry};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+impl HdfsConnection {
+	/// Stats every path in `paths`, returning the results in the same order.
+	/// 
+	/// Each stat is a round trip to the namenode, so they're spread over `parallel` threads: this connection on the
+	/// calling thread, and others each with a connection made by `connect_isolated` on a clone of `builder`.
+	/// Connections can't be used by several threads at once, and a worker's connection has to be its own
+	/// filesystem instance, since dropping it when the worker finishes would otherwise close the instance under
+	/// this connection. If connecting fails, the work is spread over the connections that could be made.
+	pub fn stat_many<P>(&self, paths: &[P], parallel: usize, builder: &HdfsBuilder) -> Vec<io::Result<HdfsDirectoryEntry>>
+		where P: AsHdfsPath + Sync
+	{
+		self.for_each_parallel(Some(builder), parallel, paths.len(), |fs, i| fs.stat(&paths[i]))
+	}
+	
+	/// Calls `f` with each index in `0..n`, spread over up to `parallel` threads: this connection on the calling
+	/// thread, and others each with a connection made by `connect_isolated` on a clone of `builder`, if there is one.
+	/// Returns the results in index order.
+	pub(crate) fn for_each_parallel<T, F>(&self, builder: Option<&HdfsBuilder>, parallel: usize, n: usize, f: F) -> Vec<T>
+		where T: Send, F: Fn(&HdfsConnection, usize) -> T + Sync
+	{
+		let workers: Vec<HdfsConnection> = match builder {
+			Some(builder) => (1..parallel.min(n))
+				.map_while(|_| builder.clone().connect_isolated().ok())
+				.collect(),
+			None => vec![],
+		};
+		let next = AtomicUsize::new(0);
+		let run_next = |fs: &HdfsConnection| {
+			let mut results = vec![];
+			loop {
+				let i = next.fetch_add(1, Ordering::Relaxed);
+				if i >= n {
+					return results;
+				}
+				results.push((i, f(fs, i)));
+			}
+		};
+		
+		let mut results: Vec<Option<T>> = (0..n).map(|_| None).collect();
+		thread::scope(|scope| {
+			let handles: Vec<_> = workers.into_iter()
+				.map(|fs| {
+					let run_next = &run_next;
+					scope.spawn(move || run_next(&fs))
+				})
+				.collect();
+			let mut done = run_next(self);
+			for handle in handles {
+				done.extend(handle.join().unwrap());
+			}
+			for (i, res) in done {
+				results[i] = Some(res);
+			}
+		});
+		results.into_iter().map(|res| res.unwrap()).collect()
+	}
+}
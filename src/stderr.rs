@@ -0,0 +1,168 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Capturing what `libhdfs` and the JVM write to stderr.
+//! 
+//! `libhdfs` prints Java exceptions straight to stderr, and Hadoop's log4j configuration logs warnings there,
+//! neither of which can be turned off from Rust. The only way to catch them is to point file descriptor 2 at a
+//! pipe, which catches everything else written to stderr too, including `eprintln!`; `StderrCapture::original`
+//! is the real stderr, for output that should still go there.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::os::raw::c_int;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+extern "C" {
+	fn pipe(fds: *mut c_int) -> c_int;
+	fn dup(fd: c_int) -> c_int;
+	fn dup2(fd: c_int, new_fd: c_int) -> c_int;
+}
+
+const STDERR_FD: c_int = 2;
+
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+
+/// Where `capture_stderr` sends what's written to stderr.
+pub enum StderrTarget {
+	/// Discards it.
+	Silence,
+	/// Logs each line with the `log` crate, with the target `hdfs::jvm`. Lines from log4j are logged at the level
+	/// they were written with, and anything else, like exceptions and stack traces, as warnings.
+	#[cfg(feature = "log")]
+	Log,
+	/// Calls a function with each line, without the line ending.
+	Lines(Box<dyn FnMut(&str) + Send>),
+}
+
+/// Redirects stderr until it's dropped, created by `capture_stderr`.
+pub struct StderrCapture {
+	original: File,
+	reader: Option<JoinHandle<()>>,
+}
+
+impl StderrCapture {
+	/// Gets the stderr that was replaced, to write to it directly.
+	pub fn original(&self) -> &File {
+		&self.original
+	}
+}
+
+impl Drop for StderrCapture {
+	fn drop(&mut self) {
+		// Replacing descriptor 2 closes the pipe's last write end, so the reader gets to the end and stops
+		unsafe { dup2(self.original.as_raw_fd(), STDERR_FD); }
+		if let Some(reader) = self.reader.take() {
+			let _ = reader.join();
+		}
+		CAPTURING.store(false, Ordering::SeqCst);
+	}
+}
+
+/// Redirects stderr, for the whole process, to `target`, until the returned guard is dropped. Only one capture
+/// can be active at once.
+/// 
+/// Call this before connecting, so that warnings from starting the JVM are captured too. Lines are handled on a
+/// background thread, so they may arrive a little after they're written.
+/// 
+/// Only available on Unix.
+pub fn capture_stderr(target: StderrTarget) -> io::Result<StderrCapture> {
+	if CAPTURING.swap(true, Ordering::SeqCst) {
+		return Err(io::Error::new(io::ErrorKind::AlreadyExists, "stderr is already being captured"));
+	}
+	let res = redirect(target);
+	if res.is_err() {
+		CAPTURING.store(false, Ordering::SeqCst);
+	}
+	res
+}
+
+fn redirect(mut target: StderrTarget) -> io::Result<StderrCapture> {
+	let mut fds: [c_int; 2] = [-1, -1];
+	if unsafe { pipe(fds.as_mut_ptr()) } != 0 {
+		return Err(io::Error::last_os_error());
+	}
+	let (read, write) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+	let original = unsafe { dup(STDERR_FD) };
+	if original < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	let original = unsafe { File::from_raw_fd(original) };
+	if unsafe { dup2(write.as_raw_fd(), STDERR_FD) } < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	drop(write);
+	
+	let reader = thread::Builder::new()
+		.name("hdfs-stderr".to_string())
+		.spawn(move || {
+			let mut read = BufReader::new(read);
+			let mut line = vec![];
+			loop {
+				line.clear();
+				match read.read_until(b'\n', &mut line) {
+					Ok(0) | Err(_) => { break; },
+					Ok(_) => {},
+				}
+				let text = String::from_utf8_lossy(&line);
+				target.line(text.trim_end_matches(&['\r', '\n'][..]));
+			}
+		});
+	let reader = match reader {
+		Ok(reader) => reader,
+		Err(e) => {
+			unsafe { dup2(original.as_raw_fd(), STDERR_FD); }
+			return Err(e);
+		},
+	};
+	Ok(StderrCapture {
+		original,
+		reader: Some(reader),
+	})
+}
+
+impl StderrTarget {
+	fn line(&mut self, line: &str) {
+		match self {
+			StderrTarget::Silence => {},
+			#[cfg(feature = "log")]
+			StderrTarget::Log => { log::log!(target: "hdfs::jvm", log4j_level(line), "{}", line); },
+			StderrTarget::Lines(f) => { f(line); },
+		}
+	}
+}
+
+/// Finds the level of a line logged by log4j, ex. `2020-01-02 03:04:05,678 WARN util.NativeCodeLoader: ...`.
+#[cfg(feature = "log")]
+fn log4j_level(line: &str) -> log::Level {
+	for word in line.split_whitespace().take(4) {
+		match word {
+			"FATAL" | "ERROR" => { return log::Level::Error; },
+			"WARN" => { return log::Level::Warn; },
+			"INFO" => { return log::Level::Info; },
+			"DEBUG" => { return log::Level::Debug; },
+			"TRACE" => { return log::Level::Trace; },
+			_ => {},
+		}
+	}
+	log::Level::Warn
+}
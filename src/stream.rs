@@ -0,0 +1,126 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Settings for individual streams, so that a few files can be opened differently from the rest without a
+//! connection of their own.
+//! 
+//! Only what `libhdfs` takes per stream can be changed this way. The rest of the client configuration, like
+//! timeouts, is fixed when the filesystem instance is created; to change it, connect separately with
+//! `HdfsBuilder::connect_isolated`.
+
+use crate::path::AsHdfsPath;
+use crate::{DirtyDrop, HdfsConnection, HdfsFile, HdfsStreamBuilder};
+use std::io;
+
+/// Settings that override the connection's defaults for one stream, for the `HdfsConnection::open_*_with`
+/// methods. Anything that isn't set uses the connection's configuration.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct StreamOptions {
+	buffer_size: Option<i32>,
+	block_size: Option<i64>,
+	replication: Option<i16>,
+	dirty_drop: Option<DirtyDrop>,
+	skip_checksums: bool,
+}
+
+impl StreamOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Sets the client-side buffer size, `io.file.buffer.size`.
+	pub fn buffer_size(mut self, size: i32) -> Self {
+		self.buffer_size = Some(size);
+		self
+	}
+	
+	/// Sets the block size of new files, `dfs.blocksize`. Ignored when reading or appending.
+	pub fn block_size(mut self, size: i64) -> Self {
+		self.block_size = Some(size);
+		self
+	}
+	
+	/// Sets the replication factor of new files, `dfs.replication`. Ignored when reading or appending.
+	pub fn replication(mut self, replication: i16) -> Self {
+		self.replication = Some(replication);
+		self
+	}
+	
+	/// Sets what happens if the file is dropped with unsynced writes. Ignored when reading.
+	pub fn dirty_drop(mut self, policy: DirtyDrop) -> Self {
+		self.dirty_drop = Some(policy);
+		self
+	}
+	
+	/// Reads without verifying checksums. Ignored when writing.
+	/// 
+	/// **Corrupt data is returned as-is**, and only some reads can skip checksums; read the warnings on
+	/// `HdfsStreamBuilder::skip_checksums` before using this.
+	pub fn skip_checksums(mut self) -> Self {
+		self.skip_checksums = true;
+		self
+	}
+	
+	/// Sets everything that was set here on a stream builder, skipping what doesn't apply to it.
+	pub fn apply(&self, builder: &mut HdfsStreamBuilder) -> io::Result<()> {
+		if let Some(size) = self.buffer_size {
+			builder.buffer_size(size)?;
+		}
+		if !builder.writing {
+			if self.skip_checksums {
+				builder.skip_checksums()?;
+			}
+			return Ok(());
+		}
+		if let Some(size) = self.block_size {
+			builder.default_block_size(size)?;
+		}
+		if let Some(replication) = self.replication {
+			builder.replication(replication)?;
+		}
+		if let Some(policy) = self.dirty_drop {
+			builder.dirty_drop(policy);
+		}
+		Ok(())
+	}
+}
+
+impl HdfsConnection {
+	/// Opens a file for reading with settings for just this stream.
+	pub fn open_read_with<P: AsHdfsPath + ?Sized>(&self, path: &P, options: StreamOptions) -> io::Result<HdfsFile> {
+		let mut builder = self.open_read_builder(path)?;
+		options.apply(&mut builder)?;
+		builder.build()
+	}
+	
+	/// Opens a file for writing, creating it if it doesn't exist, with settings for just this stream.
+	pub fn open_create_with<P: AsHdfsPath + ?Sized>(&self, path: &P, options: StreamOptions) -> io::Result<HdfsFile> {
+		let mut builder = self.open_create_builder(path)?;
+		options.apply(&mut builder)?;
+		builder.build()
+	}
+	
+	/// Opens a file for appending, creating it if it doesn't exist, with settings for just this stream.
+	pub fn open_append_with<P: AsHdfsPath + ?Sized>(&self, path: &P, options: StreamOptions) -> io::Result<HdfsFile> {
+		let mut builder = self.open_append_builder(path)?;
+		options.apply(&mut builder)?;
+		builder.build()
+	}
+}
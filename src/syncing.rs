@@ -0,0 +1,126 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Writers that sync periodically, bounding how much data a crash can lose.
+
+use crate::filesystem::FileWriter;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// When `SyncingWriter` syncs. Syncs happen when either limit is reached, whichever comes first.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct SyncPolicy {
+	bytes: Option<u64>,
+	interval: Option<Duration>,
+}
+
+impl SyncPolicy {
+	/// A policy that never syncs on its own, to add limits to.
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Syncs once `bytes` bytes have been written since the last sync.
+	pub fn every_bytes(mut self, bytes: u64) -> Self {
+		self.bytes = Some(bytes.max(1));
+		self
+	}
+	
+	/// Syncs on the first write at least `interval` after the last sync.
+	pub fn every(mut self, interval: Duration) -> Self {
+		self.interval = Some(interval);
+		self
+	}
+}
+
+/// Wraps a `FileWriter`, like an `HdfsFile`, calling `sync` according to a `SyncPolicy`.
+/// 
+/// Time limits are only checked when writing, so data written just before a pause stays unsynced until the
+/// next write; call `sync` before going idle.
+pub struct SyncingWriter<W: FileWriter> {
+	inner: W,
+	policy: SyncPolicy,
+	unsynced: u64,
+	last_sync: Instant,
+}
+
+impl<W: FileWriter> SyncingWriter<W> {
+	pub fn new(inner: W, policy: SyncPolicy) -> Self {
+		Self {
+			inner,
+			policy,
+			unsynced: 0,
+			last_sync: Instant::now(),
+		}
+	}
+	
+	/// Gets the number of bytes written since the last sync.
+	pub fn unsynced_bytes(&self) -> u64 {
+		self.unsynced
+	}
+	
+	pub fn get_ref(&self) -> &W {
+		&self.inner
+	}
+	
+	pub fn get_mut(&mut self) -> &mut W {
+		&mut self.inner
+	}
+	
+	/// Gets the wrapped writer back, without syncing it.
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+	
+	fn is_due(&self) -> bool {
+		if self.unsynced == 0 {
+			return false;
+		}
+		self.policy.bytes.is_some_and(|bytes| self.unsynced >= bytes)
+			|| self.policy.interval.is_some_and(|interval| self.last_sync.elapsed() >= interval)
+	}
+}
+
+impl<W: FileWriter> Write for SyncingWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let len = self.inner.write(buf)?;
+		self.unsynced += len as u64;
+		if self.is_due() {
+			self.sync()?;
+		}
+		Ok(len)
+	}
+	
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+impl<W: FileWriter> FileWriter for SyncingWriter<W> {
+	fn sync(&mut self) -> io::Result<()> {
+		self.inner.sync()?;
+		self.unsynced = 0;
+		self.last_sync = Instant::now();
+		Ok(())
+	}
+	fn close(self: Box<Self>) -> io::Result<()> {
+		Box::new(self.inner).close()
+	}
+}
@@ -0,0 +1,143 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Streaming directories to and from tar archives, without staging them on local disk.
+//! 
+//! Files are streamed one at a time, so memory use doesn't depend on their sizes. Compression can be added by
+//! wrapping the writer or reader, ex. with a gzip encoder.
+
+use crate::path::{self, AsHdfsPath};
+use crate::{HdfsConnection, HdfsDirectoryEntryKind};
+use std::io::{self, Read, Write};
+use std::path::Component;
+use std::time::{Duration, UNIX_EPOCH};
+use tar::{Archive, Builder, EntryType, Header};
+
+impl HdfsConnection {
+	/// Writes a tar archive of `dir` and everything under it to `writer`, with paths relative to `dir`, and
+	/// returns the writer once the archive is finished. If `dir` is a file, the archive has just that file.
+	/// 
+	/// Entries keep their permission bits, modification times, and owner and group names.
+	pub fn write_tar<P: AsHdfsPath + ?Sized, W: Write>(&self, dir: &P, writer: W) -> io::Result<W> {
+		let dir = &*dir.as_str_path()?;
+		let mut builder = Builder::new(writer);
+		let mut root = None;
+		for item in self.walk(dir) {
+			let entry = item?.entry;
+			let root = root.get_or_insert_with(|| entry.name.clone());
+			let relative = match entry.name.strip_prefix(root.as_str()).map(|name| name.trim_start_matches('/')) {
+				Some("") if matches!(entry.kind, HdfsDirectoryEntryKind::Directory) => { continue; },
+				Some("") => path::base_name(&entry.name),
+				Some(relative) => relative,
+				None => { return Err(io::Error::new(io::ErrorKind::Other, format!("{} is not under {}", entry.name, root))); },
+			};
+			
+			let mut header = Header::new_gnu();
+			header.set_mode(entry.permissions as u32);
+			header.set_mtime(entry.last_modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0));
+			header.set_username(&entry.owner)?;
+			header.set_groupname(&entry.group)?;
+			match entry.kind {
+				HdfsDirectoryEntryKind::Directory => {
+					header.set_entry_type(EntryType::Directory);
+					header.set_size(0);
+					builder.append_data(&mut header, relative, io::empty())?;
+				},
+				_ => {
+					header.set_entry_type(EntryType::Regular);
+					header.set_size(entry.size);
+					let file = self.open_read(&entry.name)?;
+					builder.append_data(&mut header, relative, ExactReader { inner: file.take(entry.size), remaining: entry.size })?;
+				},
+			}
+		}
+		builder.into_inner()
+	}
+	
+	/// Unpacks a tar archive from `reader` into `dir`, creating it if needed, and returns the number of files
+	/// unpacked. Existing files are replaced.
+	/// 
+	/// Files and directories get the permission bits and modification times from the archive, but not the owners,
+	/// which only a superuser could set. Links and other special entries are skipped. Paths that are absolute or
+	/// contain `..` are an `InvalidData` error, so an archive can't write outside of `dir`.
+	pub fn unpack_tar<R: Read, P: AsHdfsPath + ?Sized>(&self, reader: R, dir: &P) -> io::Result<u64> {
+		let dir = &*dir.as_str_path()?;
+		self.create_dir(dir)?;
+		let mut archive = Archive::new(reader);
+		let mut files = 0;
+		for item in archive.entries()? {
+			let mut item = item?;
+			let relative = relative_path(&item.path()?)?;
+			let dest = if relative.is_empty() { dir.to_string() } else { path::join(dir, &relative) };
+			let header = item.header();
+			let (mode, mtime) = (header.mode()?, header.mtime()?);
+			match header.entry_type() {
+				EntryType::Directory => { self.create_dir(&dest)?; },
+				EntryType::Regular | EntryType::Continuous => {
+					let mut file = self.open_create(&dest)?;
+					io::copy(&mut item, &mut file)?;
+					file.close()?;
+					files += 1;
+				},
+				_ => { continue; },
+			}
+			self.chmod(&dest, (mode & 0o7777) as u16)?;
+			self.set_times(&dest, Some(UNIX_EPOCH + Duration::from_secs(mtime)), None)?;
+		}
+		Ok(files)
+	}
+}
+
+/// Turns an archive path into a relative HDFS path, rejecting ones that could escape the destination.
+fn relative_path(name: &std::path::Path) -> io::Result<String> {
+	let mut parts = vec![];
+	for component in name.components() {
+		match component {
+			Component::Normal(part) => {
+				let part = part.to_str()
+					.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} is not UTF-8", name.display())))?;
+				parts.push(part);
+			},
+			Component::CurDir => {},
+			_ => { return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is outside the archive's directory", name.display()))); },
+		}
+	}
+	Ok(parts.join("/"))
+}
+
+/// Reads exactly `remaining` bytes, failing instead of ending early, since a short file would corrupt the rest
+/// of the archive.
+struct ExactReader<R> {
+	inner: R,
+	remaining: u64,
+}
+impl<R: Read> Read for ExactReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.remaining == 0 {
+			return Ok(0);
+		}
+		let len = self.inner.read(buf)?;
+		if len == 0 {
+			return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file was truncated while archiving it"));
+		}
+		self.remaining -= len as u64;
+		Ok(len)
+	}
+}
@@ -0,0 +1,128 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Temporary files that are deleted unless they're kept, for writing files atomically.
+
+use crate::path::AsHdfsPath;
+use crate::{HdfsConnection, HdfsFile};
+use std::io::{self, Write};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many names to try before giving up.
+const MAX_ATTEMPTS: usize = 100;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Temporary file open for writing, created by `HdfsConnection::create_temp_in`.
+/// 
+/// The file is deleted when this is dropped, unless `persist` moves it into place first.
+pub struct TempFile<'a> {
+	fs: &'a HdfsConnection,
+	path: String,
+	file: Option<HdfsFile<'a>>,
+}
+
+impl<'a> TempFile<'a> {
+	/// Gets the path of the temporary file.
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+	
+	/// Gets the open file.
+	pub fn file(&mut self) -> &mut HdfsFile<'a> {
+		self.file.as_mut().unwrap()
+	}
+	
+	/// Closes the file and renames it to `dest`, so that it appears there complete or not at all.
+	/// 
+	/// Like `HdfsConnection::rename`, this fails if `dest` exists. The temporary file is deleted if it can't be
+	/// renamed.
+	pub fn persist<P: AsHdfsPath + ?Sized>(mut self, dest: &P) -> io::Result<()> {
+		self.file.take().unwrap().close()?;
+		self.fs.rename(&self.path, dest)?;
+		self.path.clear();
+		Ok(())
+	}
+	
+	/// Renames the file to `dest` without closing it, returning it to keep writing. HDFS leases follow the file,
+	/// so it can still be written after being renamed.
+	pub(crate) fn persist_open(mut self, dest: &str) -> io::Result<HdfsFile<'a>> {
+		self.fs.rename(&self.path, dest)?;
+		self.path.clear();
+		Ok(self.file.take().unwrap())
+	}
+}
+
+impl<'a> Write for TempFile<'a> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.file().write(buf)
+	}
+	
+	fn flush(&mut self) -> io::Result<()> {
+		self.file().flush()
+	}
+}
+
+impl<'a> Drop for TempFile<'a> {
+	fn drop(&mut self) {
+		// Close before deleting, so the namenode doesn't keep a lease on the deleted file. Closing explicitly keeps
+		// the dirty-drop policy, which is for lost writes, from applying to a file that's being thrown away.
+		if let Some(file) = self.file.take() {
+			let _ = file.close();
+		}
+		if !self.path.is_empty() {
+			let _ = self.fs.delete(&self.path, false);
+		}
+	}
+}
+
+impl HdfsConnection {
+	/// Creates a file with a unique name in `dir`, starting with `prefix`, that's deleted when the returned guard is
+	/// dropped unless `TempFile::persist` renames it.
+	/// 
+	/// `libhdfs` can't create a file only if it doesn't exist, so a name is checked with `exists` before creating
+	/// it, and a new one is tried if it's taken. Names include the process ID, time and a counter, so collisions
+	/// between the check and the create are unlikely.
+	pub fn create_temp_in<P: AsHdfsPath + ?Sized>(&self, dir: &P, prefix: &str) -> io::Result<TempFile> {
+		let dir = dir.as_str_path()?;
+		let dir = dir.trim_end_matches('/');
+		for _ in 0..MAX_ATTEMPTS {
+			let path = format!("{}/{}{}", dir, prefix, unique_suffix());
+			if self.exists(&path)? {
+				continue;
+			}
+			let file = self.open_create(&path)?;
+			return Ok(TempFile {
+				fs: self,
+				path,
+				file: Some(file),
+			});
+		}
+		return Err(io::Error::new(io::ErrorKind::AlreadyExists,
+			format!("could not find an unused temporary file name in {}", dir)));
+	}
+}
+
+fn unique_suffix() -> String {
+	let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+	format!("{:x}-{:x}-{}", process::id(), nanos, NEXT_ID.fetch_add(1, Ordering::SeqCst))
+}
@@ -0,0 +1,359 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Copying directory trees between the local filesystem and HDFS.
+//! 
+//! HDFS writers can't seek forward, so the zero runs of sparse local files, ex. VM images, can't be skipped; they're
+//! uploaded in full. Compressing them first, or storing only their allocated extents, is up to the caller.
+
+use crate::glob::GlobPattern;
+use crate::path::{self, AsHdfsPath};
+use crate::copy::{copy, copy_out};
+use crate::{HdfsBuilder, HdfsConnection, HdfsDirectoryEntryKind};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+
+/// What to do with files that already exist at the destination.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum Collision {
+	/// Replace them
+	Overwrite,
+	/// Leave them, counting them in `TransferSummary::skipped`
+	Skip,
+	/// Leave them, recording an `AlreadyExists` failure for each
+	#[default]
+	Fail,
+}
+
+/// Options for `HdfsConnection::upload_tree` and `download_tree`.
+/// 
+/// By default, everything is transferred one file at a time on the calling thread, and existing files are
+/// failures.
+#[derive(Default)]
+pub struct TransferOptions<'a> {
+	builder: Option<&'a HdfsBuilder>,
+	parallel: usize,
+	include: Vec<Filter>,
+	exclude: Vec<Filter>,
+	collision: Collision,
+}
+
+/// What `HdfsConnection::upload_tree` or `download_tree` transferred.
+#[derive(Debug,Default)]
+pub struct TransferSummary {
+	/// Number of files transferred
+	pub files: u64,
+	/// Total size of the transferred files
+	pub bytes: u64,
+	/// Number of directories created or already there
+	pub directories: u64,
+	/// Number of existing files left alone, with `Collision::Skip`
+	pub skipped: u64,
+	/// Source paths that couldn't be listed or transferred, with the errors
+	pub failures: Vec<(String, io::Error)>,
+}
+
+/// Pattern from `TransferOptions::include` or `exclude`.
+struct Filter {
+	pattern: GlobPattern,
+	/// Whether it matches the whole relative path, rather than just the name
+	whole_path: bool,
+}
+impl Filter {
+	fn new(pattern: &str) -> io::Result<Self> {
+		Ok(Self {
+			pattern: GlobPattern::new(pattern.trim_start_matches('/'))?,
+			whole_path: pattern.contains('/'),
+		})
+	}
+	
+	fn matches(&self, relative: &str) -> bool {
+		if self.whole_path {
+			self.pattern.matches(relative)
+		} else {
+			self.pattern.matches(path::base_name(relative))
+		}
+	}
+}
+
+/// A file to transfer, with its path relative to the root of the tree.
+struct Task {
+	relative: String,
+	size: u64,
+}
+
+impl<'a> TransferOptions<'a> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Transfers up to `parallel` files at once: one on the calling thread, and the rest each on a separate
+	/// thread with a connection made by `connect_isolated` on a clone of `builder`. A worker can't use this
+	/// connection from its own thread, and its connection must not share this one's filesystem instance, or
+	/// dropping it at the end of the transfer would close that instance.
+	pub fn parallel(mut self, parallel: usize, builder: &'a HdfsBuilder) -> Self {
+		self.parallel = parallel;
+		self.builder = Some(builder);
+		self
+	}
+	
+	/// Only transfers files matching a glob pattern; see `GlobPattern` for the syntax. Can be called more than
+	/// once to include files matching any of them.
+	/// 
+	/// Patterns without a `/` match file names, and others match paths relative to the root of the tree, ex.
+	/// `logs/*.gz`. Directories are always created, even if nothing in them is included.
+	pub fn include(mut self, pattern: &str) -> io::Result<Self> {
+		self.include.push(Filter::new(pattern)?);
+		Ok(self)
+	}
+	
+	/// Skips files and directories matching a glob pattern, which is matched like `include`. Excluding a
+	/// directory skips everything in it. Exclusions win over inclusions.
+	pub fn exclude(mut self, pattern: &str) -> io::Result<Self> {
+		self.exclude.push(Filter::new(pattern)?);
+		Ok(self)
+	}
+	
+	/// Sets what to do with files that already exist at the destination. Existing directories are always
+	/// merged into.
+	pub fn collision(mut self, collision: Collision) -> Self {
+		self.collision = collision;
+		self
+	}
+	
+	fn is_excluded(&self, relative: &str) -> bool {
+		self.exclude.iter().any(|filter| filter.matches(relative))
+	}
+	
+	fn is_included(&self, relative: &str) -> bool {
+		self.include.is_empty() || self.include.iter().any(|filter| filter.matches(relative))
+	}
+	
+	/// Connects the extra workers for `parallel`.
+	fn workers(&self) -> io::Result<Vec<HdfsConnection>> {
+		match self.builder {
+			Some(builder) => (1..self.parallel).map(|_| builder.clone().connect_isolated()).collect(),
+			None => Ok(vec![]),
+		}
+	}
+}
+
+impl HdfsConnection {
+	/// Copies the local directory `local_dir` and everything under it to `hdfs_dir`, creating it if needed.
+	/// 
+	/// Failures with individual files and directories are collected into the summary, rather than stopping the
+	/// rest of the upload. Symbolic links are followed, and other special files are skipped.
+	pub fn upload_tree<L, D>(&self, local_dir: &L, hdfs_dir: &D, options: &TransferOptions<'_>) -> io::Result<TransferSummary>
+		where L: AsRef<Path> + ?Sized, D: AsHdfsPath + ?Sized
+	{
+		let local_dir = local_dir.as_ref();
+		let hdfs_dir = &*hdfs_dir.as_str_path()?;
+		if !fs::metadata(local_dir)?.is_dir() {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a directory", local_dir.display())));
+		}
+		let workers = options.workers()?;
+		
+		// Create the directories up front, so the workers only have to deal with files
+		let mut summary = TransferSummary::default();
+		let mut tasks = vec![];
+		let mut dirs = vec![String::new()];
+		while let Some(relative) = dirs.pop() {
+			let dest = join(hdfs_dir, &relative);
+			if let Err(e) = self.create_dir(&dest) {
+				summary.failures.push((dest, e));
+				continue;
+			}
+			summary.directories += 1;
+			
+			let dir = local_dir.join(&relative);
+			for item in local_entries(&dir) {
+				let (name, metadata) = match item {
+					Ok(item) => item,
+					Err(e) => {
+						summary.failures.push((dir.display().to_string(), e));
+						continue;
+					},
+				};
+				let relative = join(&relative, &name);
+				if options.is_excluded(&relative) {
+					continue;
+				}
+				if metadata.is_dir() {
+					dirs.push(relative);
+				} else if metadata.is_file() && options.is_included(&relative) {
+					tasks.push(Task { relative, size: metadata.len() });
+				}
+			}
+		}
+		
+		let collision = options.collision;
+		run_tasks(self, workers, tasks, summary, |fs, task| {
+			let dest = join(hdfs_dir, &task.relative);
+			if collision != Collision::Overwrite && fs.exists(&dest)? {
+				return collided(collision, &dest);
+			}
+			let mut src = File::open(local_dir.join(&task.relative))?;
+			let mut out_file = fs.open_create(&dest)?;
+			let len = copy(&mut src, &mut out_file)?;
+			out_file.close()?;
+			Ok(Some(len))
+		}, |task| local_dir.join(&task.relative).display().to_string())
+	}
+}
+
+impl HdfsConnection {
+	/// Copies the HDFS directory `hdfs_dir` and everything under it to `local_dir`, creating it if needed. The
+	/// inverse of `upload_tree`, with the same handling of options and failures.
+	pub fn download_tree<D, L>(&self, hdfs_dir: &D, local_dir: &L, options: &TransferOptions<'_>) -> io::Result<TransferSummary>
+		where D: AsHdfsPath + ?Sized, L: AsRef<Path> + ?Sized
+	{
+		let hdfs_dir = &*hdfs_dir.as_str_path()?;
+		let local_dir = local_dir.as_ref();
+		if !matches!(self.stat(hdfs_dir)?.kind, HdfsDirectoryEntryKind::Directory) {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a directory", hdfs_dir)));
+		}
+		let workers = options.workers()?;
+		
+		let mut summary = TransferSummary::default();
+		let mut tasks = vec![];
+		let mut dirs = vec![String::new()];
+		while let Some(relative) = dirs.pop() {
+			let dest = local_dir.join(&relative);
+			if let Err(e) = fs::create_dir_all(&dest) {
+				summary.failures.push((dest.display().to_string(), e));
+				continue;
+			}
+			summary.directories += 1;
+			
+			let dir = join(hdfs_dir, &relative);
+			let mut entries = match self.list_dir(&dir) {
+				Ok(entries) => entries,
+				Err(e) => {
+					summary.failures.push((dir, e));
+					continue;
+				},
+			};
+			entries.sort_by(|a, b| a.name.cmp(&b.name));
+			for entry in entries {
+				let relative = join(&relative, path::base_name(&entry.name));
+				if options.is_excluded(&relative) {
+					continue;
+				}
+				match entry.kind {
+					HdfsDirectoryEntryKind::Directory => { dirs.push(relative); },
+					HdfsDirectoryEntryKind::File if options.is_included(&relative) => {
+						tasks.push(Task { relative, size: entry.size });
+					},
+					_ => {},
+				}
+			}
+		}
+		
+		let collision = options.collision;
+		run_tasks(self, workers, tasks, summary, |conn, task| {
+			let dest = local_dir.join(&task.relative);
+			if collision != Collision::Overwrite && fs::symlink_metadata(&dest).is_ok() {
+				return collided(collision, &dest.display().to_string());
+			}
+			let mut in_file = conn.open_read(&join(hdfs_dir, &task.relative))?;
+			let mut dest = File::create(&dest)?;
+			copy_out(&mut in_file, &mut dest).map(Some)
+		}, |task| join(hdfs_dir, &task.relative))
+	}
+}
+
+/// Lists a local directory, with names that aren't UTF-8 as errors, sorted by name.
+fn local_entries(dir: &Path) -> Vec<io::Result<(String, fs::Metadata)>> {
+	let read_dir = match fs::read_dir(dir) {
+		Ok(read_dir) => read_dir,
+		Err(e) => { return vec![Err(e)]; },
+	};
+	let mut entries: Vec<io::Result<(String, fs::Metadata)>> = read_dir
+		.map(|item| {
+			let item = item?;
+			let path = item.path();
+			let name = item.file_name().into_string()
+				.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("{} is not UTF-8", path.display())))?;
+			// Follows symbolic links
+			Ok((name, fs::metadata(&path)?))
+		})
+		.collect();
+	entries.sort_by(|a, b| match (a, b) {
+		(Ok(a), Ok(b)) => a.0.cmp(&b.0),
+		(a, b) => a.is_ok().cmp(&b.is_ok()),
+	});
+	entries
+}
+
+/// Handles a file that already exists, for any `Collision` but `Overwrite`.
+fn collided(collision: Collision, dest: &str) -> io::Result<Option<u64>> {
+	match collision {
+		Collision::Skip => Ok(None),
+		_ => Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", dest))),
+	}
+}
+
+/// Transfers files on this connection and `workers`, biggest first, so that one large file doesn't hold up
+/// the end. `transfer` returns the number of bytes transferred, or `None` if the file was skipped.
+fn run_tasks<T, N>(fs: &HdfsConnection, workers: Vec<HdfsConnection>, mut tasks: Vec<Task>, summary: TransferSummary, transfer: T, name: N) -> io::Result<TransferSummary>
+	where
+		T: Fn(&HdfsConnection, &Task) -> io::Result<Option<u64>> + Sync,
+		N: Fn(&Task) -> String + Sync,
+{
+	tasks.sort_by_key(|task| task.size);
+	let tasks = Mutex::new(tasks);
+	let summary = Mutex::new(summary);
+	let work = |fs: &HdfsConnection| loop {
+		let task = match tasks.lock().unwrap().pop() {
+			Some(task) => task,
+			None => { break; },
+		};
+		let res = transfer(fs, &task);
+		let mut summary = summary.lock().unwrap();
+		match res {
+			Ok(Some(bytes)) => {
+				summary.files += 1;
+				summary.bytes += bytes;
+			},
+			Ok(None) => { summary.skipped += 1; },
+			Err(e) => { summary.failures.push((name(&task), e)); },
+		}
+	};
+	thread::scope(|scope| {
+		for worker in workers {
+			let work = &work;
+			scope.spawn(move || work(&worker));
+		}
+		work(fs);
+	});
+	Ok(summary.into_inner().unwrap())
+}
+
+/// Joins a relative path onto a root, or returns the root for an empty one.
+fn join(root: &str, relative: &str) -> String {
+	if relative.is_empty() {
+		root.to_string()
+	} else {
+		path::join(root, relative)
+	}
+}
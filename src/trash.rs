@@ -0,0 +1,339 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Hadoop's trash directory.
+//! 
+//! `libhdfs` deletes files immediately, so this reimplements the layout used by Hadoop's `TrashPolicyDefault`:
+//! trashed paths are moved under `<root>/Current`, keeping their absolute path, and `Current` is periodically
+//! renamed to a checkpoint named after the time (`yyMMddHHmmss`), which is deleted once it's old enough.
+//! Like Hadoop, checkpoint names are in the local time zone, so clients should share the namenode's time zone;
+//! on platforms other than Unix they're in UTC.
+
+use crate::path::{base_name, parent, uri_path, AsHdfsPath};
+use crate::{HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::io;
+#[cfg(unix)]
+use std::os::raw::{c_char, c_int, c_long};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Name of the checkpoint that paths are moved into.
+pub const CURRENT_CHECKPOINT: &str = "Current";
+
+/// Trash directory of a user, created by `Trash::new`.
+pub struct Trash<'a> {
+	fs: &'a HdfsConnection,
+	root: String,
+}
+
+/// Item in the trash, returned by `Trash::list`.
+#[derive(Debug,Clone)]
+pub struct TrashEntry {
+	/// The trashed file or directory
+	pub entry: HdfsDirectoryEntry,
+	/// Name of the checkpoint it's in, ex. `Current` or `200102030405`
+	pub checkpoint: String,
+	/// Path it was moved from
+	pub original_path: String,
+}
+
+impl<'a> Trash<'a> {
+	/// Uses the trash of `user`, which is at `/user/<user>/.Trash`.
+	pub fn new(fs: &'a HdfsConnection, user: &str) -> Self {
+		Self::with_root(fs, &format!("/user/{}/.Trash", user))
+	}
+	
+	/// Uses a trash directory at a custom path.
+	pub fn with_root(fs: &'a HdfsConnection, root: &str) -> Self {
+		Self {
+			fs,
+			root: root.trim_end_matches('/').to_string(),
+		}
+	}
+	
+	/// Gets the path of the trash directory.
+	pub fn root(&self) -> &str {
+		&self.root
+	}
+	
+	/// Moves a file or directory into the current checkpoint, returning its path in the trash.
+	/// 
+	/// If something was already trashed at the same path, the time is appended to the name, like Hadoop does.
+	pub fn move_to_trash<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<String> {
+		let entry = self.fs.stat(path)?;
+		let absolute = uri_path(&entry.name);
+		let root = format!("{}/", self.root);
+		if absolute == self.root || absolute.starts_with(&root) || root.starts_with(&format!("{}/", absolute.trim_end_matches('/'))) {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot move {} to the trash, as it contains or is in the trash", absolute)));
+		}
+		
+		let mut dest = format!("{}/{}{}", self.root, CURRENT_CHECKPOINT, absolute);
+		if let Some(parent) = parent(&dest) {
+			self.fs.create_dir(parent)?;
+		}
+		if self.fs.exists(&dest)? {
+			dest = format!("{}{}", dest, epoch_millis(SystemTime::now()));
+		}
+		self.fs.rename(&entry.name, &dest)?;
+		Ok(dest)
+	}
+	
+	/// Lists the items in every checkpoint.
+	/// 
+	/// Parent directories that were created to hold trashed paths are descended into rather than listed, as long
+	/// as the original directory still exists.
+	pub fn list(&self) -> io::Result<Vec<TrashEntry>> {
+		let mut items = vec![];
+		for checkpoint in self.checkpoints()? {
+			let name = base_name(&checkpoint.name).to_string();
+			self.list_into(&checkpoint, &name, "", &mut items)?;
+		}
+		Ok(items)
+	}
+	
+	fn list_into(&self, dir: &HdfsDirectoryEntry, checkpoint: &str, original_dir: &str, items: &mut Vec<TrashEntry>) -> io::Result<()> {
+		for entry in self.fs.list_dir(&dir.name)? {
+			let original_path = format!("{}/{}", original_dir, base_name(&entry.name));
+			let is_dir = matches!(entry.kind, HdfsDirectoryEntryKind::Directory);
+			let live_dir = is_dir && match self.fs.stat(&original_path) {
+				Ok(live) => matches!(live.kind, HdfsDirectoryEntryKind::Directory),
+				Err(ref e) if e.kind() == io::ErrorKind::NotFound => false,
+				Err(e) => { return Err(e); },
+			};
+			if live_dir {
+				self.list_into(&entry, checkpoint, &original_path, items)?;
+			} else {
+				items.push(TrashEntry {
+					entry,
+					checkpoint: checkpoint.to_string(),
+					original_path,
+				});
+			}
+		}
+		Ok(())
+	}
+	
+	/// Moves an item back to where it was trashed from. Fails if something else exists there now.
+	pub fn restore(&self, item: &TrashEntry) -> io::Result<()> {
+		if self.fs.exists(&item.original_path)? {
+			return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", item.original_path)));
+		}
+		if let Some(parent) = parent(&item.original_path) {
+			self.fs.create_dir(parent)?;
+		}
+		self.fs.rename(&item.entry.name, &item.original_path)
+	}
+	
+	/// Renames the current checkpoint after the current time, so that it can expire. Returns the new
+	/// checkpoint's name, or `None` if there was nothing to checkpoint.
+	pub fn checkpoint(&self) -> io::Result<Option<String>> {
+		let current = format!("{}/{}", self.root, CURRENT_CHECKPOINT);
+		if !self.fs.exists(&current)? {
+			return Ok(None);
+		}
+		let base = checkpoint_name(SystemTime::now());
+		let mut name = base.clone();
+		let mut attempt = 0;
+		while self.fs.exists(&format!("{}/{}", self.root, name))? {
+			attempt += 1;
+			name = format!("{}-{}", base, attempt);
+		}
+		self.fs.rename(&current, &format!("{}/{}", self.root, name))?;
+		Ok(Some(name))
+	}
+	
+	/// Checkpoints the current checkpoint, then deletes checkpoints older than `older_than`, returning how many
+	/// were deleted.
+	/// 
+	/// If `older_than` is `None`, everything in the trash is deleted, including the current checkpoint.
+	pub fn expunge(&self, older_than: Option<Duration>) -> io::Result<usize> {
+		let cutoff = match older_than {
+			Some(older_than) => {
+				self.checkpoint()?;
+				Some(SystemTime::now() - older_than)
+			},
+			None => None,
+		};
+		let mut deleted = 0;
+		for checkpoint in self.checkpoints()? {
+			let name = base_name(&checkpoint.name);
+			if let Some(cutoff) = cutoff {
+				// Not a checkpoint, or too new
+				match parse_checkpoint_name(name) {
+					Some(time) if time < cutoff => {},
+					_ => { continue; },
+				}
+			}
+			self.fs.delete(&checkpoint.name, true)?;
+			deleted += 1;
+		}
+		Ok(deleted)
+	}
+	
+	/// Lists the checkpoint directories. Returns an empty list if the trash doesn't exist.
+	fn checkpoints(&self) -> io::Result<Vec<HdfsDirectoryEntry>> {
+		match self.fs.list_dir(&self.root) {
+			Ok(entries) => Ok(entries.into_iter()
+				.filter(|entry| matches!(entry.kind, HdfsDirectoryEntryKind::Directory))
+				.collect()),
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+			Err(e) => Err(e),
+		}
+	}
+}
+
+fn epoch_millis(time: SystemTime) -> u128 {
+	time.duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0)
+}
+
+/// Formats a time as `yyMMddHHmmss`, in the local time zone.
+fn checkpoint_name(time: SystemTime) -> String {
+	let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+	let (year, month, day, hour, minute, second) = to_local(secs as i64);
+	format!("{:02}{:02}{:02}{:02}{:02}{:02}", year.rem_euclid(100), month, day, hour, minute, second)
+}
+
+/// Parses a name created by `checkpoint_name`, ignoring any `-N` suffix.
+fn parse_checkpoint_name(name: &str) -> Option<SystemTime> {
+	let digits = name.split('-').next()?;
+	if digits.len() != 12 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+		return None;
+	}
+	let field = |i: usize| digits[i..i+2].parse::<u32>().ok();
+	let (year, month, day) = (2000 + field(0)? as i64, field(2)?, field(4)?);
+	let (hour, minute, second) = (field(6)?, field(8)?, field(10)?);
+	if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+		return None;
+	}
+	let secs = from_local(year, month, day, hour, minute, second)?;
+	if secs < 0 {
+		return None;
+	}
+	Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+}
+
+/// `struct tm`, with the fields that glibc, musl, and the BSDs add at the end.
+#[cfg(unix)]
+#[repr(C)]
+struct Tm {
+	tm_sec: c_int,
+	tm_min: c_int,
+	tm_hour: c_int,
+	tm_mday: c_int,
+	tm_mon: c_int,
+	tm_year: c_int,
+	tm_wday: c_int,
+	tm_yday: c_int,
+	tm_isdst: c_int,
+	tm_gmtoff: c_long,
+	tm_zone: *const c_char,
+}
+
+#[cfg(unix)]
+extern "C" {
+	fn localtime_r(time: *const c_long, result: *mut Tm) -> *mut Tm;
+	fn mktime(tm: *mut Tm) -> c_long;
+}
+
+/// Converts seconds since the Unix epoch to a local (year, month, day, hour, minute, second).
+#[cfg(unix)]
+fn to_local(secs: i64) -> (i64, u32, u32, u32, u32, u32) {
+	let time = secs as c_long;
+	let mut tm: Tm = unsafe { std::mem::zeroed() };
+	if unsafe { localtime_r(&time, &mut tm) }.is_null() {
+		return to_utc(secs);
+	}
+	(tm.tm_year as i64 + 1900, tm.tm_mon as u32 + 1, tm.tm_mday as u32, tm.tm_hour as u32, tm.tm_min as u32, tm.tm_sec as u32)
+}
+
+#[cfg(not(unix))]
+fn to_local(secs: i64) -> (i64, u32, u32, u32, u32, u32) {
+	to_utc(secs)
+}
+
+/// Converts a local time to seconds since the Unix epoch.
+#[cfg(unix)]
+fn from_local(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> Option<i64> {
+	let mut tm: Tm = unsafe { std::mem::zeroed() };
+	tm.tm_year = (year - 1900) as c_int;
+	tm.tm_mon = month as c_int - 1;
+	tm.tm_mday = day as c_int;
+	tm.tm_hour = hour as c_int;
+	tm.tm_min = minute as c_int;
+	tm.tm_sec = second as c_int;
+	// Let `mktime` work out whether daylight saving time applies
+	tm.tm_isdst = -1;
+	match unsafe { mktime(&mut tm) } {
+		-1 => None,
+		secs => Some(secs as i64),
+	}
+}
+
+#[cfg(not(unix))]
+fn from_local(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> Option<i64> {
+	Some(days_from_civil(year, month, day) * 86400 + (hour * 3600 + minute * 60 + second) as i64)
+}
+
+fn to_utc(secs: i64) -> (i64, u32, u32, u32, u32, u32) {
+	let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+	let secs_of_day = secs.rem_euclid(86400) as u32;
+	(year, month, day, secs_of_day / 3600, secs_of_day / 60 % 60, secs_of_day % 60)
+}
+
+// Date conversions from <http://howardhinnant.github.io/date_algorithms.html>
+
+#[cfg(not(unix))]
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let month = month as i64;
+	let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let days = days + 719468;
+	let era = days.div_euclid(146097);
+	let day_of_era = days - era * 146097;
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let mp = (5 * day_of_year + 2) / 153;
+	let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+	let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+	let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+	(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn checkpoint_names_round_trip() {
+		let time = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+		let name = checkpoint_name(time);
+		assert_eq!(name.len(), 12);
+		assert_eq!(parse_checkpoint_name(&name), Some(time));
+		assert_eq!(parse_checkpoint_name(&format!("{}-2", name)), Some(time));
+		assert_eq!(parse_checkpoint_name("Current"), None);
+		assert_eq!(parse_checkpoint_name("201399000000"), None);
+	}
+}
@@ -0,0 +1,101 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Recursive directory traversal.
+
+use crate::{HdfsConnection, HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::io;
+
+/// Entry yielded by `Walk`.
+#[derive(Debug,Clone)]
+pub struct WalkEntry {
+	/// The file or directory
+	pub entry: HdfsDirectoryEntry,
+	/// How many levels below the starting path the entry is. The starting path has a depth of 0.
+	pub depth: usize,
+}
+
+/// Iterator over a directory tree, created by `HdfsConnection::walk`.
+/// 
+/// Entries are yielded depth-first, with a directory yielded before its contents. Errors listing a
+/// directory are yielded as they happen, and the walk continues with the next entry.
+pub struct Walk<'a> {
+	fs: &'a HdfsConnection,
+	// Path to start with, or why it couldn't be used. Only `Some` before the first call to `next`.
+	root: Option<io::Result<String>>,
+	// Entries still to visit, in reverse order
+	stack: Vec<WalkEntry>,
+	// Error listing the last yielded directory, to yield next
+	pending_error: Option<io::Error>,
+	max_depth: Option<usize>,
+}
+impl<'a> Walk<'a> {
+	pub(crate) fn new(fs: &'a HdfsConnection, path: io::Result<String>) -> Self {
+		Self {
+			fs,
+			root: Some(path),
+			stack: vec![],
+			pending_error: None,
+			max_depth: None,
+		}
+	}
+	
+	/// Sets the maximum depth to descend to. A depth of 0 yields only the starting path.
+	pub fn max_depth(mut self, depth: usize) -> Self {
+		self.max_depth = Some(depth);
+		self
+	}
+	
+	/// Lists a directory entry, pushing its children onto the stack.
+	fn descend(&mut self, dir: &WalkEntry) -> io::Result<()> {
+		if self.max_depth.map(|max| dir.depth >= max).unwrap_or(false) {
+			return Ok(());
+		}
+		let children = self.fs.list_dir(&dir.entry.name)?;
+		let depth = dir.depth + 1;
+		self.stack.extend(children.into_iter().rev().map(|entry| WalkEntry { entry, depth }));
+		return Ok(());
+	}
+}
+impl<'a> Iterator for Walk<'a> {
+	type Item = io::Result<WalkEntry>;
+	
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(root) = self.root.take() {
+			let entry = match root.and_then(|root| self.fs.stat(&root)) {
+				Ok(entry) => entry,
+				Err(err) => { return Some(Err(err)); },
+			};
+			self.stack.push(WalkEntry { entry, depth: 0 });
+		}
+		
+		if let Some(err) = self.pending_error.take() {
+			return Some(Err(err));
+		}
+		
+		let next = self.stack.pop()?;
+		if let HdfsDirectoryEntryKind::Directory = next.entry.kind {
+			if let Err(err) = self.descend(&next) {
+				self.pending_error = Some(err);
+			}
+		}
+		Some(Ok(next))
+	}
+}
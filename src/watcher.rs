@@ -0,0 +1,216 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Noticing changes to files by polling, since HDFS's inotify stream isn't available through `libhdfs`.
+
+use crate::filesystem::FileSystem;
+use crate::path::uri_path;
+use crate::{HdfsDirectoryEntry, HdfsDirectoryEntryKind};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Change noticed by a `DirWatcher`.
+#[derive(Debug,Clone)]
+pub enum WatchEvent {
+	/// An entry appeared.
+	Created(HdfsDirectoryEntry),
+	/// An entry's size, modification time, or type changed. Has the new metadata.
+	Modified(HdfsDirectoryEntry),
+	/// An entry went away. Has the last metadata seen.
+	Removed(HdfsDirectoryEntry),
+}
+
+impl WatchEvent {
+	/// Gets the metadata the event is about.
+	pub fn entry(&self) -> &HdfsDirectoryEntry {
+		match self {
+			WatchEvent::Created(entry) | WatchEvent::Modified(entry) | WatchEvent::Removed(entry) => entry,
+		}
+	}
+}
+
+/// Polls paths for changes, comparing what's there with the last poll.
+/// 
+/// Directories are watched by listing them, and other paths by statting them, so a path that doesn't exist yet
+/// can be watched for it to be created. Modifications are noticed from the size and modification time, so
+/// several writes between polls are one event, and changes that keep both the same aren't noticed; HDFS updates
+/// the modification time when a file is closed, not on every write.
+/// 
+/// The first poll only records what's there, without events.
+pub struct DirWatcher {
+	paths: Vec<String>,
+	interval: Duration,
+	recursive: bool,
+	// `None` before the first poll. Keyed by path without the scheme and authority.
+	known: Option<BTreeMap<String, HdfsDirectoryEntry>>,
+}
+
+impl DirWatcher {
+	/// Watches a directory, or another path.
+	pub fn new(path: &str) -> Self {
+		Self::with_paths(&[path])
+	}
+	
+	/// Watches several paths.
+	pub fn with_paths<S: AsRef<str>>(paths: &[S]) -> Self {
+		Self {
+			paths: paths.iter().map(|path| path.as_ref().to_string()).collect(),
+			interval: Duration::from_secs(5),
+			recursive: false,
+			known: None,
+		}
+	}
+	
+	/// Sets how often `spawn` polls. Defaults to 5 seconds.
+	pub fn interval(mut self, interval: Duration) -> Self {
+		self.interval = interval;
+		self
+	}
+	
+	/// Watches the contents of subdirectories too, not just directories' immediate children.
+	pub fn recursive(mut self) -> Self {
+		self.recursive = true;
+		self
+	}
+	
+	/// Gets the entries seen by the last poll.
+	pub fn entries(&self) -> impl Iterator<Item = &HdfsDirectoryEntry> {
+		self.known.iter().flat_map(|known| known.values())
+	}
+	
+	/// Lists the watched paths now, returning the changes since the last poll. If this fails, the next poll is
+	/// compared with the last one that succeeded.
+	pub fn poll<F: FileSystem + ?Sized>(&mut self, fs: &F) -> io::Result<Vec<WatchEvent>> {
+		let current = self.scan(fs)?;
+		let previous = match self.known.replace(current) {
+			Some(previous) => previous,
+			None => { return Ok(vec![]); },
+		};
+		let current = self.known.as_ref().unwrap();
+		
+		let mut events = vec![];
+		for (path, entry) in current.iter() {
+			match previous.get(path) {
+				None => { events.push(WatchEvent::Created(entry.clone())); },
+				Some(old) if changed(old, entry) => { events.push(WatchEvent::Modified(entry.clone())); },
+				Some(_) => {},
+			}
+		}
+		for (path, entry) in previous.into_iter() {
+			if !current.contains_key(&path) {
+				events.push(WatchEvent::Removed(entry));
+			}
+		}
+		Ok(events)
+	}
+	
+	/// Polls on a background thread with its own connection, sending changes to the returned handle. Errors are
+	/// sent too, and polling carries on after them, since they're often transient, ex. namenode failovers.
+	/// 
+	/// Polling stops when the handle is dropped.
+	pub fn spawn<F: FileSystem + Send + 'static>(mut self, fs: F) -> io::Result<WatchHandle> {
+		let (events_tx, events) = mpsc::channel();
+		let (stop, stop_rx) = mpsc::channel::<()>();
+		let thread = thread::Builder::new()
+			.name("hdfs-watcher".to_string())
+			.spawn(move || loop {
+				match self.poll(&fs) {
+					Ok(new_events) => {
+						for event in new_events {
+							if events_tx.send(Ok(event)).is_err() {
+								return;
+							}
+						}
+					},
+					Err(e) => {
+						if events_tx.send(Err(e)).is_err() {
+							return;
+						}
+					},
+				}
+				match stop_rx.recv_timeout(self.interval) {
+					Err(RecvTimeoutError::Timeout) => {},
+					_ => { return; },
+				}
+			})?;
+		Ok(WatchHandle { events, stop: Some(stop), thread: Some(thread) })
+	}
+	
+	fn scan<F: FileSystem + ?Sized>(&self, fs: &F) -> io::Result<BTreeMap<String, HdfsDirectoryEntry>> {
+		let mut entries = BTreeMap::new();
+		for path in self.paths.iter() {
+			let entry = match fs.stat(path) {
+				Ok(entry) => entry,
+				Err(ref e) if e.kind() == io::ErrorKind::NotFound => { continue; },
+				Err(e) => { return Err(e); },
+			};
+			if !is_dir(&entry) {
+				entries.insert(uri_path(&entry.name).to_string(), entry);
+				continue;
+			}
+			let mut dirs = vec![entry.name];
+			while let Some(dir) = dirs.pop() {
+				for entry in fs.list_dir(&dir)? {
+					if self.recursive && is_dir(&entry) {
+						dirs.push(entry.name.clone());
+					}
+					entries.insert(uri_path(&entry.name).to_string(), entry);
+				}
+			}
+		}
+		Ok(entries)
+	}
+}
+
+/// Background polling started by `DirWatcher::spawn`. Stops it when dropped.
+pub struct WatchHandle {
+	events: Receiver<io::Result<WatchEvent>>,
+	// Dropping this wakes the thread up to stop
+	stop: Option<Sender<()>>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+	/// Gets the channel that changes and errors are sent to.
+	pub fn events(&self) -> &Receiver<io::Result<WatchEvent>> {
+		&self.events
+	}
+}
+
+impl Drop for WatchHandle {
+	/// Stops polling, waiting for a poll in progress to finish.
+	fn drop(&mut self) {
+		drop(self.stop.take());
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+fn is_dir(entry: &HdfsDirectoryEntry) -> bool {
+	matches!(entry.kind, HdfsDirectoryEntryKind::Directory)
+}
+
+fn changed(old: &HdfsDirectoryEntry, new: &HdfsDirectoryEntry) -> bool {
+	is_dir(old) != is_dir(new) || old.size != new.size || old.last_modified != new.last_modified
+}
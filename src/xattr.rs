@@ -0,0 +1,222 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Extended attributes, and conventions for storing common metadata in them.
+//! 
+//! `libhdfs` has no calls for extended attributes, so they're read and written through JNI with the connection's
+//! Java `FileSystem`. That needs the JNI `libhdfs`; with libhdfs++ they fail with `NotConnected`.
+//! 
+//! The typed helpers store their values as text, so `hdfs dfs -getfattr -d` shows them readably.
+
+use crate::jni::{Frame, JValue};
+use crate::path::AsHdfsPath;
+use crate::HdfsConnection;
+use std::fmt::Write;
+use std::io;
+use std::os::raw::c_void;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Extended attribute for the checksum of a file's contents, as `<algorithm>:<hex digest>`, ex.
+/// `md5:d41d8cd98f00b204e9800998ecf8427e`.
+pub const XATTR_CONTENT_CHECKSUM: &str = "user.content-checksum";
+/// Extended attribute for the MIME type of a file's contents, ex. `application/json`.
+pub const XATTR_CONTENT_TYPE: &str = "user.content-type";
+/// Extended attribute for when a file may be deleted, as decimal seconds since the Unix epoch.
+pub const XATTR_RETAIN_UNTIL: &str = "user.retain-until";
+
+/// Checksum of a file's contents, stored in `XATTR_CONTENT_CHECKSUM`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ContentChecksum {
+	/// Name of the algorithm, ex. `md5` or `crc32c`. It can't contain `:`.
+	pub algorithm: String,
+	pub digest: Vec<u8>,
+}
+
+impl ContentChecksum {
+	fn encode(&self) -> io::Result<String> {
+		if self.algorithm.is_empty() || self.algorithm.contains(':') {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid checksum algorithm {:?}", self.algorithm)));
+		}
+		let mut value = format!("{}:", self.algorithm);
+		for byte in self.digest.iter() {
+			write!(value, "{:02x}", byte).unwrap();
+		}
+		Ok(value)
+	}
+	
+	fn decode(value: &str) -> Option<Self> {
+		let (algorithm, hex) = value.split_once(':')?;
+		if algorithm.is_empty() || hex.len() % 2 != 0 {
+			return None;
+		}
+		let digest = (0..hex.len()).step_by(2)
+			.map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+			.collect::<Option<Vec<u8>>>()?;
+		Some(Self { algorithm: algorithm.to_string(), digest })
+	}
+}
+
+impl HdfsConnection {
+	/// Gets the extended attribute `name` of `path`, or `None` if it isn't set. Names include their namespace,
+	/// ex. `user.origin`.
+	pub fn get_xattr<P: AsHdfsPath + ?Sized>(&self, path: &P, name: &str) -> io::Result<Option<Vec<u8>>> {
+		let path = path.as_str_path()?;
+		self.with_java_fs(|frame, fs| unsafe { get_xattr_in(frame, fs, &path, name) })
+	}
+	
+	/// Sets the extended attribute `name` of `path`, creating or replacing it. Names include their namespace,
+	/// ex. `user.origin`; the namenode limits values to `dfs.namenode.fs-limits.max-xattr-size` bytes, 16 KiB
+	/// by default.
+	pub fn set_xattr<P: AsHdfsPath + ?Sized>(&self, path: &P, name: &str, value: &[u8]) -> io::Result<()> {
+		let path = path.as_str_path()?;
+		self.with_java_fs(|frame, fs| unsafe { set_xattr_in(frame, fs, &path, name, value) })
+	}
+	
+	/// Removes the extended attribute `name` of `path`. HDFS fails if it isn't set.
+	pub fn remove_xattr<P: AsHdfsPath + ?Sized>(&self, path: &P, name: &str) -> io::Result<()> {
+		let path = path.as_str_path()?;
+		self.with_java_fs(|frame, fs| unsafe { remove_xattr_in(frame, fs, &path, name) })
+	}
+	
+	/// Gets the checksum stored in `XATTR_CONTENT_CHECKSUM`. Fails with `InvalidData` if it isn't a checksum.
+	pub fn content_checksum<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<Option<ContentChecksum>> {
+		let value = match self.get_text_xattr(path, XATTR_CONTENT_CHECKSUM)? {
+			Some(value) => value,
+			None => { return Ok(None); },
+		};
+		ContentChecksum::decode(&value)
+			.map(Some)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid content checksum {:?}", value)))
+	}
+	
+	/// Stores a checksum of the contents of `path` in `XATTR_CONTENT_CHECKSUM`. It isn't checked against the
+	/// contents.
+	pub fn set_content_checksum<P: AsHdfsPath + ?Sized>(&self, path: &P, checksum: &ContentChecksum) -> io::Result<()> {
+		self.set_xattr(path, XATTR_CONTENT_CHECKSUM, checksum.encode()?.as_bytes())
+	}
+	
+	/// Gets the MIME type stored in `XATTR_CONTENT_TYPE`.
+	pub fn content_type<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<Option<String>> {
+		self.get_text_xattr(path, XATTR_CONTENT_TYPE)
+	}
+	
+	/// Stores the MIME type of the contents of `path` in `XATTR_CONTENT_TYPE`.
+	pub fn set_content_type<P: AsHdfsPath + ?Sized>(&self, path: &P, content_type: &str) -> io::Result<()> {
+		self.set_xattr(path, XATTR_CONTENT_TYPE, content_type.as_bytes())
+	}
+	
+	/// Gets the time stored in `XATTR_RETAIN_UNTIL`. Fails with `InvalidData` if it isn't a time.
+	pub fn retain_until<P: AsHdfsPath + ?Sized>(&self, path: &P) -> io::Result<Option<SystemTime>> {
+		let value = match self.get_text_xattr(path, XATTR_RETAIN_UNTIL)? {
+			Some(value) => value,
+			None => { return Ok(None); },
+		};
+		value.parse::<u64>()
+			.ok()
+			.and_then(|secs| UNIX_EPOCH.checked_add(Duration::from_secs(secs)))
+			.map(Some)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid retention time {:?}", value)))
+	}
+	
+	/// Stores when `path` may be deleted in `XATTR_RETAIN_UNTIL`, to the second. HDFS doesn't enforce it; it's
+	/// for cleanup jobs to check.
+	pub fn set_retain_until<P: AsHdfsPath + ?Sized>(&self, path: &P, time: SystemTime) -> io::Result<()> {
+		let secs = time.duration_since(UNIX_EPOCH)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "retention time is before the Unix epoch"))?
+			.as_secs();
+		self.set_xattr(path, XATTR_RETAIN_UNTIL, secs.to_string().as_bytes())
+	}
+	
+	/// Gets an extended attribute that should be UTF-8.
+	fn get_text_xattr<P: AsHdfsPath + ?Sized>(&self, path: &P, name: &str) -> io::Result<Option<String>> {
+		match self.get_xattr(path, name)? {
+			Some(value) => String::from_utf8(value)
+				.map(Some)
+				.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("{} is not UTF-8", name))),
+			None => Ok(None),
+		}
+	}
+}
+
+/// Gets an extended attribute with `FileSystem.getXAttrs`, rather than `getXAttr`, which fails if it isn't set.
+pub(crate) unsafe fn get_xattr_in(frame: &mut Frame, fs: *mut c_void, path: &str, name: &str) -> io::Result<Option<Vec<u8>>> {
+	let fs_class = frame.class(b"org/apache/hadoop/fs/FileSystem\0")?;
+	let get_xattrs = frame.method(fs_class, b"getXAttrs\0", b"(Lorg/apache/hadoop/fs/Path;)Ljava/util/Map;\0")?;
+	let map_class = frame.class(b"java/util/Map\0")?;
+	let get = frame.method(map_class, b"get\0", b"(Ljava/lang/Object;)Ljava/lang/Object;\0")?;
+	
+	let java_path = frame.hadoop_path(path)?;
+	let java_name = frame.new_string(name)?;
+	let message = format!("could not get extended attributes of {}", path);
+	let xattrs = frame.call_object(fs, get_xattrs, &[JValue { l: java_path }], &message)?;
+	if xattrs.is_null() {
+		return Ok(None);
+	}
+	let value = frame.call_object(xattrs, get, &[JValue { l: java_name }], &message)?;
+	if value.is_null() {
+		return Ok(None);
+	}
+	frame.byte_array(value).map(Some)
+}
+
+/// Sets an extended attribute with `FileSystem.setXAttr`.
+pub(crate) unsafe fn set_xattr_in(frame: &mut Frame, fs: *mut c_void, path: &str, name: &str, value: &[u8]) -> io::Result<()> {
+	let fs_class = frame.class(b"org/apache/hadoop/fs/FileSystem\0")?;
+	let set_xattr = frame.method(fs_class, b"setXAttr\0", b"(Lorg/apache/hadoop/fs/Path;Ljava/lang/String;[B)V\0")?;
+	let java_path = frame.hadoop_path(path)?;
+	let java_name = frame.new_string(name)?;
+	let java_value = frame.new_byte_array(value)?;
+	frame.call_void(fs, set_xattr, &[JValue { l: java_path }, JValue { l: java_name }, JValue { l: java_value }],
+		&format!("could not set extended attribute {} of {}", name, path))
+}
+
+/// Removes an extended attribute with `FileSystem.removeXAttr`.
+pub(crate) unsafe fn remove_xattr_in(frame: &mut Frame, fs: *mut c_void, path: &str, name: &str) -> io::Result<()> {
+	let fs_class = frame.class(b"org/apache/hadoop/fs/FileSystem\0")?;
+	let remove_xattr = frame.method(fs_class, b"removeXAttr\0", b"(Lorg/apache/hadoop/fs/Path;Ljava/lang/String;)V\0")?;
+	let java_path = frame.hadoop_path(path)?;
+	let java_name = frame.new_string(name)?;
+	frame.call_void(fs, remove_xattr, &[JValue { l: java_path }, JValue { l: java_name }],
+		&format!("could not remove extended attribute {} of {}", name, path))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn content_checksum_round_trips() {
+		let checksum = ContentChecksum { algorithm: "md5".to_string(), digest: vec![0xd4, 0x1d, 0x8c, 0x00] };
+		let value = checksum.encode().unwrap();
+		assert_eq!(value, "md5:d41d8c00");
+		assert_eq!(ContentChecksum::decode(&value), Some(checksum));
+	}
+	
+	#[test]
+	fn content_checksum_rejects_bad_values() {
+		assert!(ContentChecksum { algorithm: "a:b".to_string(), digest: vec![] }.encode().is_err());
+		assert!(ContentChecksum { algorithm: String::new(), digest: vec![] }.encode().is_err());
+		assert_eq!(ContentChecksum::decode("d41d8c"), None);
+		assert_eq!(ContentChecksum::decode(":d41d8c"), None);
+		assert_eq!(ContentChecksum::decode("md5:d41"), None);
+		assert_eq!(ContentChecksum::decode("md5:zz"), None);
+		assert_eq!(ContentChecksum::decode("md5:\u{e9}"), None);
+	}
+}
@@ -0,0 +1,96 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Reads that skip client-side checksum verification, for `StreamOptions::skip_checksums`.
+//! 
+//! `libhdfs` has no equivalent of Java's `FileSystem.setVerifyChecksum(false)`. Its zero-copy read API is the
+//! only way to skip checksums for a single stream, so files opened with `skip_checksums` read through it, with a
+//! fallback buffer pool for data that can't be read zero-copy.
+
+use crate::{check_rt, last_error, str_to_cstr, HdfsStreamBuilder};
+use std::io;
+use std::ptr::{self, NonNull};
+
+/// Java class that `libhdfs` allocates buffers from when a read can't be zero-copy.
+const FALLBACK_POOL: &str = "org.apache.hadoop.io.ElasticByteBufferPool";
+
+impl<'a> HdfsStreamBuilder<'a> {
+	/// Reads the file without verifying checksums, for high-throughput reads where the data is verified some
+	/// other way and checksumming is what's using the CPU. Returns an `InvalidInput` error for write streams.
+	/// 
+	/// Warning
+	/// =======
+	/// **Corrupt data is returned as-is.** HDFS only notices a corrupt replica when a client verifies its checksums,
+	/// so reads like this also never report it to the namenode to be re-replicated from a good copy. Only use this
+	/// if something downstream checks the data end to end, ex. against a hash stored with it.
+	/// 
+	/// Checksums can only be skipped for data that `libhdfs` can read zero-copy, which is blocks on the local host
+	/// read with short-circuit reads (see `HdfsBuilder::short_circuit_reads`). Everything else is read through a
+	/// buffer pool and **still verified**, without any error or warning, so check
+	/// `ReadStatistics::total_zero_copy_bytes_read` with the `ext` feature to see whether it's having an effect.
+	/// Only `Read` calls and `read_uninit` skip checksums; `read_at` always verifies them.
+	/// 
+	/// Fails when the file is opened if `libhdfs` doesn't support zero-copy reads, like libhdfs++.
+	pub fn skip_checksums(&mut self) -> io::Result<()> {
+		if self.writing {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "checksums can only be skipped when reading"));
+		}
+		self.skip_checksums = true;
+		Ok(())
+	}
+}
+
+/// Zero-copy read options of a file opened with `skip_checksums`.
+pub(crate) struct ZeroCopy(NonNull<libhdfs_sys::hadoopRzOptions>);
+
+impl ZeroCopy {
+	pub(crate) fn new() -> io::Result<Self> {
+		let p = unsafe { libhdfs_sys::hadoopRzOptionsAlloc() };
+		let options = Self(NonNull::new(p).ok_or_else(last_error)?);
+		check_rt(unsafe { libhdfs_sys::hadoopRzOptionsSetSkipChecksum(options.0.as_ptr(), 1) })?;
+		let pool = str_to_cstr(FALLBACK_POOL);
+		check_rt(unsafe { libhdfs_sys::hadoopRzOptionsSetByteBufferPool(options.0.as_ptr(), pool.as_ptr()) })?;
+		Ok(options)
+	}
+	
+	/// Reads up to `len` bytes from `file` into `ptr`, returning the number of bytes read.
+	/// 
+	/// `ptr` must be valid for writes of `len` bytes, and `file` must be open for reading.
+	pub(crate) unsafe fn read(&self, file: libhdfs_sys::hdfsFile, ptr: *mut u8, len: usize) -> io::Result<usize> {
+		let max_len = len.min(i32::MAX as usize) as i32;
+		let buffer = libhdfs_sys::hadoopReadZero(file, self.0.as_ptr(), max_len);
+		if buffer.is_null() {
+			return Err(last_error());
+		}
+		// Zero length, with a null pointer, at the end of the file
+		let n = (libhdfs_sys::hadoopRzBufferLength(buffer).max(0) as usize).min(len);
+		if n > 0 {
+			ptr::copy_nonoverlapping(libhdfs_sys::hadoopRzBufferGet(buffer) as *const u8, ptr, n);
+		}
+		libhdfs_sys::hadoopRzBufferFree(file, buffer);
+		Ok(n)
+	}
+}
+
+impl Drop for ZeroCopy {
+	fn drop(&mut self) {
+		unsafe { libhdfs_sys::hadoopRzOptionsFree(self.0.as_ptr()); }
+	}
+}
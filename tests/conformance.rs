@@ -0,0 +1,64 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Runs the conformance suite against `MemoryFs`, and `CachedFs` over it, and against the local filesystem through libhdfs when the
+//! `integration` feature is also enabled. The run against the cluster is in `integration.rs`, to share its
+//! cluster. Run with `cargo test --features conformance`.
+
+use hdfs::{CachedFs, Conformance, Fault, FaultyFs, MemoryFs, Op};
+use std::io;
+use std::time::Duration;
+
+#[test]
+fn memory() {
+	if let Err(e) = Conformance::new("/conformance").run(&MemoryFs::new()) {
+		panic!("{}", e);
+	}
+}
+
+/// Everything goes through the cache, so it should never serve stale results.
+#[test]
+fn cached() {
+	let fs = CachedFs::new(MemoryFs::new(), Duration::from_secs(3600));
+	if let Err(e) = Conformance::new("/conformance").run(&fs) {
+		panic!("{}", e);
+	}
+}
+
+#[test]
+fn detects_divergence() {
+	let fs = FaultyFs::new(MemoryFs::new());
+	fs.inject(Fault::error(io::ErrorKind::Other).ops(&[Op::Rename]));
+	assert!(Conformance::new("/conformance").cases(256).run(&fs).is_err());
+}
+
+#[cfg(feature = "integration")]
+#[test]
+fn local() {
+	let root = std::env::temp_dir().join(format!("hdfs-rs-conformance-{}", std::process::id()));
+	let mut builder = hdfs::HdfsBuilder::new();
+	builder.name_node(None);
+	let fs = builder.connect().expect("Could not connect to the local filesystem");
+	let result = Conformance::new(root.to_str().unwrap()).run(&fs);
+	let _ = std::fs::remove_dir_all(&root);
+	if let Err(e) = result {
+		panic!("{}", e);
+	}
+}
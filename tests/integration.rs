@@ -0,0 +1,341 @@
+/* This file is part of hdfs-rs.
+ *
+ * Copyright © 2020 Datto, Inc.
+ * Author: Alex Parrill <aparrill@datto.com>
+ *
+ * Licensed under the Mozilla Public License Version 2.0
+ * Fedora-License-Identifier: MPLv2.0
+ * SPDX-2.0-License-Identifier: MPL-2.0
+ * SPDX-3.0-License-Identifier: MPL-2.0
+ *
+ * hdfs-rs is free software.
+ * For more information on the license, see LICENSE.
+ * For more information on free software, see <https://www.gnu.org/philosophy/free-sw.en.html>.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at <https://mozilla.org/MPL/2.0/>.
+ */
+
+
+//! Tests against a `MiniCluster`, or a `DockerCluster` if `RSHDFS_TEST_DOCKER` is set. Run with
+//! `cargo test --features integration`.
+
+use hdfs::{diff_trees, BulkDeleteOptions, DiffOptions, DockerCluster, HdfsBuilder, HdfsConnection, HdfsDirectoryEntryKind, MiniCluster, TreeDiffKind};
+use std::env;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::OnceLock;
+
+enum Cluster {
+	Mini(MiniCluster),
+	Docker(DockerCluster),
+}
+
+/// Gets a builder for the cluster shared by every test, since starting one takes a while. It's stopped when the
+/// test process exits.
+fn builder() -> HdfsBuilder {
+	static CLUSTER: OnceLock<Cluster> = OnceLock::new();
+	let cluster = CLUSTER.get_or_init(|| {
+		if env::var_os("RSHDFS_TEST_DOCKER").is_some() {
+			Cluster::Docker(DockerCluster::start().expect("Could not start the Docker cluster"))
+		} else {
+			Cluster::Mini(MiniCluster::start().expect("Could not start the minicluster"))
+		}
+	});
+	match cluster {
+		Cluster::Mini(cluster) => cluster.builder(),
+		Cluster::Docker(cluster) => cluster.builder(),
+	}
+}
+
+/// Connects to the shared cluster, with a filesystem instance of the test's own.
+fn connect() -> HdfsConnection {
+	builder().connect().expect("Could not connect to the cluster")
+}
+
+/// Creates an empty directory for a test to work in.
+fn test_dir(fs: &HdfsConnection, name: &str) -> String {
+	let dir = format!("/{}", name);
+	let _ = fs.delete(&dir, true);
+	fs.create_dir(&dir).unwrap();
+	dir
+}
+
+fn write_file(fs: &HdfsConnection, path: &str, data: &[u8]) {
+	let mut file = fs.open_create(path).unwrap();
+	file.write_all(data).unwrap();
+}
+
+fn read_file(fs: &HdfsConnection, path: &str) -> Vec<u8> {
+	let mut data = vec![];
+	fs.open_read(path).unwrap().read_to_end(&mut data).unwrap();
+	data
+}
+
+#[test]
+fn write_then_read() {
+	let fs = connect();
+	let dir = test_dir(&fs, "write_then_read");
+	let path = format!("{}/file", dir);
+	write_file(&fs, &path, b"hello, world");
+	
+	assert_eq!(read_file(&fs, &path), b"hello, world");
+	let entry = fs.stat(&path).unwrap();
+	assert!(matches!(entry.kind, HdfsDirectoryEntryKind::File));
+	assert_eq!(entry.size, 12);
+}
+
+#[test]
+fn append() {
+	let fs = connect();
+	let dir = test_dir(&fs, "append");
+	let path = format!("{}/file", dir);
+	write_file(&fs, &path, b"abc");
+	fs.open_append(&path).unwrap().write_all(b"def").unwrap();
+	
+	assert_eq!(read_file(&fs, &path), b"abcdef");
+}
+
+#[test]
+fn seek_and_read_at() {
+	let fs = connect();
+	let dir = test_dir(&fs, "seek_and_read_at");
+	let path = format!("{}/file", dir);
+	write_file(&fs, &path, b"0123456789");
+	
+	let mut file = fs.open_read(&path).unwrap();
+	assert_eq!(file.seek(SeekFrom::Start(4)).unwrap(), 4);
+	let mut buf = [0; 3];
+	file.read_exact(&mut buf).unwrap();
+	assert_eq!(&buf, b"456");
+	file.read_exact_at(1, &mut buf).unwrap();
+	assert_eq!(&buf, b"123");
+}
+
+#[test]
+fn list_dir() {
+	let fs = connect();
+	let dir = test_dir(&fs, "list_dir");
+	write_file(&fs, &format!("{}/b", dir), b"");
+	write_file(&fs, &format!("{}/a", dir), b"");
+	fs.create_dir(&format!("{}/c", dir)).unwrap();
+	
+	let mut names: Vec<(String, bool)> = fs.list_dir(&dir).unwrap().into_iter()
+		.map(|entry| {
+			let name = entry.name.rsplit('/').next().unwrap().to_string();
+			(name, matches!(entry.kind, HdfsDirectoryEntryKind::Directory))
+		})
+		.collect();
+	names.sort();
+	assert_eq!(names, vec![("a".into(), false), ("b".into(), false), ("c".into(), true)]);
+}
+
+#[test]
+fn write_across_chunks() {
+	let fs = connect();
+	let dir = test_dir(&fs, "write_across_chunks");
+	let path = format!("{}/file", dir);
+	let data: Vec<u8> = (0..hdfs::WRITE_CHUNK_SIZE + 1).map(|i| i as u8).collect();
+	let mut file = fs.open_create(&path).unwrap();
+	
+	// One write call takes all of it, in two calls to libhdfs
+	assert_eq!(file.write(&data).unwrap(), data.len());
+	assert_eq!(file.io_stats().write_calls, 2);
+	drop(file);
+	assert_eq!(read_file(&fs, &path), data);
+}
+
+#[test]
+fn rename() {
+	let fs = connect();
+	let dir = test_dir(&fs, "rename");
+	let src = format!("{}/src", dir);
+	let dest = format!("{}/dest", dir);
+	write_file(&fs, &src, b"data");
+	fs.rename(&src, &dest).unwrap();
+	
+	assert!(!fs.exists(&src).unwrap());
+	assert_eq!(read_file(&fs, &dest), b"data");
+}
+
+#[test]
+fn delete() {
+	let fs = connect();
+	let dir = test_dir(&fs, "delete");
+	let sub = format!("{}/sub", dir);
+	fs.create_dir(&sub).unwrap();
+	write_file(&fs, &format!("{}/file", sub), b"data");
+	
+	assert!(fs.delete(&sub, false).is_err());
+	fs.delete(&sub, true).unwrap();
+	assert!(!fs.exists(&sub).unwrap());
+}
+
+#[test]
+fn stat_missing() {
+	let fs = connect();
+	let err = fs.stat("/does/not/exist").unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn list_file_and_missing() {
+	let fs = connect();
+	let dir = test_dir(&fs, "list_file_and_missing");
+	let path = format!("{}/file", dir);
+	write_file(&fs, &path, b"data");
+	
+	let entries = fs.list_dir(&path).unwrap();
+	let entry = fs.stat(&path).unwrap();
+	assert_eq!(entries.len(), 1);
+	assert_eq!(entries[0].name, entry.name);
+	assert!(matches!(entries[0].kind, HdfsDirectoryEntryKind::File));
+	assert_eq!(entries[0].size, 4);
+	
+	let err = fs.list_dir(&format!("{}/missing", dir)).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn truncate() {
+	let fs = connect();
+	let dir = test_dir(&fs, "truncate");
+	let path = format!("{}/file", dir);
+	write_file(&fs, &path, b"0123456789");
+	
+	// Truncating in the middle of a block has to recover the last block first, which finishes asynchronously
+	let done = fs.truncate(&path, 4).unwrap();
+	if done {
+		assert_eq!(read_file(&fs, &path), b"0123");
+	}
+	assert_eq!(fs.stat(&path).unwrap().size, 4);
+}
+
+#[test]
+fn permissions_and_walk() {
+	let fs = connect();
+	let dir = test_dir(&fs, "permissions_and_walk");
+	let path = format!("{}/a/b/file", dir);
+	fs.create_dir(&format!("{}/a/b", dir)).unwrap();
+	write_file(&fs, &path, b"data");
+	fs.chmod(&path, 0o600).unwrap();
+	
+	assert_eq!(fs.stat(&path).unwrap().permissions & 0o777, 0o600);
+	let files: Vec<String> = fs.walk(&dir)
+		.map(|item| item.unwrap().entry)
+		.filter(|entry| matches!(entry.kind, HdfsDirectoryEntryKind::File))
+		.map(|entry| entry.name)
+		.collect();
+	assert_eq!(files.len(), 1);
+	assert!(files[0].ends_with("/a/b/file"));
+}
+
+#[test]
+fn diff_trees_changes() {
+	let fs = connect();
+	let dir = test_dir(&fs, "diff_trees_changes");
+	let (a, b) = (format!("{}/a", dir), format!("{}/b", dir));
+	for root in &[&a, &b] {
+		fs.create_dir(&format!("{}/same", root)).unwrap();
+		write_file(&fs, &format!("{}/same/file", root), b"data");
+	}
+	write_file(&fs, &format!("{}/resized", a), b"data");
+	write_file(&fs, &format!("{}/resized", b), b"more data");
+	write_file(&fs, &format!("{}/removed", a), b"data");
+	fs.create_dir(&format!("{}/added", b)).unwrap();
+	write_file(&fs, &format!("{}/added/file", b), b"data");
+	write_file(&fs, &format!("{}/retyped", a), b"data");
+	fs.create_dir(&format!("{}/retyped", b)).unwrap();
+	
+	let options = DiffOptions::new().compare_times(false);
+	let diff: Vec<(TreeDiffKind, String)> = diff_trees(&fs, &a, &fs, &b, &options).unwrap()
+		.into_iter()
+		.map(|entry| (entry.kind, entry.path))
+		.collect();
+	assert_eq!(diff, vec![
+		(TreeDiffKind::Added, "added".to_string()),
+		(TreeDiffKind::Added, "added/file".to_string()),
+		(TreeDiffKind::Removed, "removed".to_string()),
+		(TreeDiffKind::Changed, "resized".to_string()),
+		(TreeDiffKind::Removed, "retyped".to_string()),
+		(TreeDiffKind::Added, "retyped".to_string()),
+	]);
+	
+	let missing = diff_trees(&fs, &format!("{}/missing", dir), &fs, &a, &options).unwrap();
+	assert!(missing.iter().all(|entry| entry.kind == TreeDiffKind::Added));
+	assert_eq!(missing[0].path, "");
+}
+
+#[test]
+fn delete_many() {
+	let fs = connect();
+	let dir = test_dir(&fs, "delete_many");
+	let mut paths: Vec<String> = (0..10).map(|i| format!("{}/file{}", dir, i)).collect();
+	for path in paths.iter() {
+		write_file(&fs, path, b"data");
+	}
+	paths.push(format!("{}/missing", dir));
+	
+	let builder = builder();
+	let results = fs.delete_many(&paths, BulkDeleteOptions::new().parallel(3, &builder));
+	assert!(results[..10].iter().all(|res| res.is_ok()));
+	assert_eq!(results[10].as_ref().unwrap_err().kind(), io::ErrorKind::NotFound);
+	// The workers' connections are closed, but this one must still work
+	assert!(fs.list_dir(&dir).unwrap().is_empty());
+	
+	let results = fs.delete_many(&paths, BulkDeleteOptions::new().ignore_missing());
+	assert!(results.iter().all(|res| res.is_ok()));
+}
+
+#[cfg(feature = "checksum")]
+#[test]
+fn manifest_verify() {
+	use hdfs::{ChecksumAlgorithm, Manifest, ManifestMismatchKind};
+	
+	let fs = connect();
+	let dir = test_dir(&fs, "manifest_verify");
+	let path = format!("{}/sub/file", dir);
+	fs.create_dir(&format!("{}/sub", dir)).unwrap();
+	write_file(&fs, &path, b"data");
+	
+	let manifest = Manifest::build(&fs, &dir, ChecksumAlgorithm::Crc32c).unwrap();
+	let mut text = vec![];
+	manifest.write_to(&mut text).unwrap();
+	assert_eq!(Manifest::read_from(&mut &text[..]).unwrap(), manifest);
+	assert!(manifest.verify(&fs, &dir).unwrap().is_empty());
+	
+	write_file(&fs, &path, b"date");
+	let mismatches = manifest.verify(&fs, &dir).unwrap();
+	assert!(mismatches.iter().any(|m| m.kind == ManifestMismatchKind::Checksum && m.path == "sub/file"));
+}
+
+#[cfg(feature = "checksum")]
+#[test]
+fn checksum_tree_parallel() {
+	use hdfs::ChecksumAlgorithm;
+	
+	let fs = connect();
+	let dir = test_dir(&fs, "checksum_tree_parallel");
+	for i in 0..8 {
+		write_file(&fs, &format!("{}/file{}", dir, i), b"data");
+	}
+	
+	let mut results = vec![];
+	fs.checksum_tree(&dir, ChecksumAlgorithm::Crc32c, 3, &builder(), |result| results.push(result)).unwrap();
+	assert_eq!(results.len(), 8);
+	for result in results {
+		assert_eq!(result.size, 4);
+		assert_eq!(result.checksum.unwrap(), crc32c::crc32c(b"data").to_be_bytes().to_vec());
+	}
+	// The workers' connections are closed, but this one must still work
+	assert_eq!(fs.list_dir(&dir).unwrap().len(), 8);
+}
+
+#[cfg(feature = "conformance")]
+#[test]
+fn conformance() {
+	let fs = connect();
+	if let Err(e) = hdfs::Conformance::new("/conformance").run(&fs) {
+		panic!("{}", e);
+	}
+}